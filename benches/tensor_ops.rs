@@ -1,5 +1,5 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion, BenchmarkId};
-use ferroflow::compute::{CPUBackend, MetalBackend, ComputeBackend};
+use ferroflow::compute::{CPUBackend, ComputeBackend};
 use ferroflow::tensor::{Tensor, Shape};
 use std::sync::Arc;
 
@@ -52,6 +52,32 @@ fn bench_backend<B: ComputeBackend>(c: &mut Criterion, backend_name: &str) {
             })
         );
 
+        // In-place and output-reusing variants, which skip the result allocation
+        let out = Tensor::<B>::zeros(Arc::clone(&ctx), Shape::new(vec![size])).unwrap();
+        group.bench_function(
+            BenchmarkId::new("add_into", size),
+            |bencher| bencher.iter(|| {
+                let result = black_box(&a).add_into(black_box(&tensor_b), black_box(&out));
+                black_box(result)
+            })
+        );
+
+        group.bench_function(
+            BenchmarkId::new("add_", size),
+            |bencher| bencher.iter(|| {
+                let result = black_box(&out).add_(black_box(&tensor_b));
+                black_box(result)
+            })
+        );
+
+        group.bench_function(
+            BenchmarkId::new("scale_", size),
+            |bencher| bencher.iter(|| {
+                let result = black_box(&out).scale_(black_box(0.5));
+                black_box(result)
+            })
+        );
+
         // Memory transfer
         group.bench_function(
             BenchmarkId::new("read", size),
@@ -62,12 +88,36 @@ fn bench_backend<B: ComputeBackend>(c: &mut Criterion, backend_name: &str) {
         );
     }
 
+    for n in [64, 256] {
+        let ctx = B::new().unwrap();
+        let data: Vec<f32> = (0..n * n).map(|x| (x % 7) as f32).collect();
+        let a = Tensor::<B>::new(Arc::clone(&ctx), Shape::new(vec![n, n]), &data).unwrap();
+        let out = Tensor::<B>::zeros(Arc::clone(&ctx), Shape::new(vec![n, n])).unwrap();
+
+        group.bench_function(
+            BenchmarkId::new("matmul", n),
+            |bencher| bencher.iter(|| {
+                let result = black_box(&a).matmul(black_box(&a));
+                black_box(result)
+            })
+        );
+
+        group.bench_function(
+            BenchmarkId::new("matmul_into", n),
+            |bencher| bencher.iter(|| {
+                let result = black_box(&a).matmul_into(black_box(&a), black_box(&out));
+                black_box(result)
+            })
+        );
+    }
+
     group.finish();
 }
 
 fn bench_operations(c: &mut Criterion) {
     bench_backend::<CPUBackend>(c, "CPU");
-    bench_backend::<MetalBackend>(c, "GPU");
+    #[cfg(all(feature = "metal", target_os = "macos"))]
+    bench_backend::<ferroflow::compute::MetalBackend>(c, "GPU");
 }
 
 criterion_group!(benches, bench_operations);
@@ -1,18 +1,24 @@
-use ferroflow::{init_logging, compute::{MetalBackend, ComputeBackend}, tensor::{Tensor, Shape}};
+use ferroflow::{init_logging, compute::ComputeBackend, tensor::{Tensor, Shape}};
 use std::sync::Arc;
 
+// Run on the GPU when the Metal backend is available, otherwise fall back to the CPU
+#[cfg(all(feature = "metal", target_os = "macos"))]
+type Backend = ferroflow::compute::MetalBackend;
+#[cfg(not(all(feature = "metal", target_os = "macos")))]
+type Backend = ferroflow::compute::CPUBackend;
+
 fn main() {
     init_logging();
 
-    let ctx = MetalBackend::new().unwrap();
+    let ctx = Backend::new().unwrap();
     
-    let a = Tensor::<MetalBackend>::new(
+    let a = Tensor::<Backend>::new(
         Arc::clone(&ctx),
         Shape::new(vec![2, 2]),
         &[1.0, 2.0, 3.0, 4.0],
     ).unwrap();
     
-    let b = Tensor::<MetalBackend>::new(
+    let b = Tensor::<Backend>::new(
         Arc::clone(&ctx),
         Shape::new(vec![2, 2]),
         &[5.0, 6.0, 7.0, 8.0],
@@ -1,6 +1,6 @@
 use ferroflow::{
     init_logging,
-    compute::{CPUBackend, MetalBackend, ComputeBackend},
+    compute::{CPUBackend, ComputeBackend},
     tensor::{Tensor, Shape}
 };
 use std::sync::Arc;
@@ -316,5 +316,6 @@ fn main() {
     init_logging();
     
     test_batched_matmul::<CPUBackend>("CPU");
-    test_batched_matmul::<MetalBackend>("GPU");
+    #[cfg(all(feature = "metal", target_os = "macos"))]
+    test_batched_matmul::<ferroflow::compute::MetalBackend>("GPU");
 } 
\ No newline at end of file
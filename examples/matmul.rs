@@ -1,6 +1,6 @@
 use ferroflow::{
     init_logging,
-    compute::{CPUBackend, MetalBackend, ComputeBackend},
+    compute::{CPUBackend, ComputeBackend},
     tensor::{Tensor, Shape}
 };
 use std::sync::Arc;
@@ -174,6 +174,43 @@ fn test_matmul<B: ComputeBackend>(name: &str) {
         all_tests_passed &= test_passed;
     }
 
+    // Test 4: Large matrix (512x512 × 512x512), timed against the naive triple loop
+    {
+        println!("\nTest 4: Large matrix multiplication (512x512 × 512x512)");
+        let size = 512;
+        let data_a: Vec<f32> = (0..size * size).map(|x| ((x % 17) as f32 - 8.0) / 8.0).collect();
+        let data_b: Vec<f32> = (0..size * size).map(|x| ((x % 13) as f32 - 6.0) / 6.0).collect();
+
+        let start = Instant::now();
+        let expected = compute_expected_matmul(&data_a, &data_b, size, size, size);
+        let naive_duration = start.elapsed();
+
+        let a = Tensor::<B>::new(
+            Arc::clone(&ctx),
+            Shape::new(vec![size, size]),
+            &data_a,
+        ).unwrap();
+
+        let b = Tensor::<B>::new(
+            Arc::clone(&ctx),
+            Shape::new(vec![size, size]),
+            &data_b,
+        ).unwrap();
+
+        let start = Instant::now();
+        let c = a.matmul(&b).unwrap();
+        let duration = start.elapsed();
+
+        let result = c.data().unwrap();
+        println!("Naive triple loop took {:?}", naive_duration);
+        println!("{} matmul took {:?} ({:.1}x faster)", name, duration,
+            naive_duration.as_secs_f64() / duration.as_secs_f64());
+
+        let test_passed = verify_result(&result, &expected, 1e-2);
+        println!("{}", if test_passed { "✅ Test passed!" } else { "❌ Test failed!" });
+        all_tests_passed &= test_passed;
+    }
+
     println!("\n=== Overall Result ===");
     println!("{}", if all_tests_passed {
         "✅ All tests passed!"
@@ -186,5 +223,6 @@ fn main() {
     init_logging();
     
     test_matmul::<CPUBackend>("CPU");
-    test_matmul::<MetalBackend>("GPU");
+    #[cfg(all(feature = "metal", target_os = "macos"))]
+    test_matmul::<ferroflow::compute::MetalBackend>("GPU");
 } 
\ No newline at end of file
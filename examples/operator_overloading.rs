@@ -1,6 +1,6 @@
 use ferroflow::{
     init_logging,
-    compute::{CPUBackend, MetalBackend, ComputeBackend},
+    compute::{CPUBackend, ComputeBackend},
     tensor::{Tensor, Shape}
 };
 use std::sync::Arc;
@@ -29,5 +29,6 @@ fn test_operators<B: ComputeBackend>(name: &str) {
 fn main() {
     init_logging();
     test_operators::<CPUBackend>("CPU");
-    test_operators::<MetalBackend>("GPU");
+    #[cfg(all(feature = "metal", target_os = "macos"))]
+    test_operators::<ferroflow::compute::MetalBackend>("GPU");
 } 
\ No newline at end of file
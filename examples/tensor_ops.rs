@@ -1,6 +1,6 @@
 use ferroflow::{
     init_logging,
-    compute::{CPUBackend, MetalBackend, ComputeBackend},
+    compute::{CPUBackend, ComputeBackend},
     tensor::{Tensor, Shape}
 };
 use std::sync::Arc;
@@ -49,5 +49,6 @@ fn run_ops<B: ComputeBackend>(name: &str) {
 fn main() {
     init_logging();
     run_ops::<CPUBackend>("CPU");
-    run_ops::<MetalBackend>("GPU");
+    #[cfg(all(feature = "metal", target_os = "macos"))]
+    run_ops::<ferroflow::compute::MetalBackend>("GPU");
 } 
\ No newline at end of file
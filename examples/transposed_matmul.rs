@@ -1,6 +1,6 @@
 use ferroflow::{
     init_logging,
-    compute::{CPUBackend, MetalBackend, ComputeBackend},
+    compute::{CPUBackend, ComputeBackend},
     tensor::{Tensor, Shape}
 };
 use std::sync::Arc;
@@ -111,5 +111,6 @@ fn main() {
     init_logging();
     
     test_transposed_matmul::<CPUBackend>("CPU");
-    test_transposed_matmul::<MetalBackend>("GPU");
+    #[cfg(all(feature = "metal", target_os = "macos"))]
+    test_transposed_matmul::<ferroflow::compute::MetalBackend>("GPU");
 } 
\ No newline at end of file
@@ -0,0 +1,239 @@
+//! Reverse-mode automatic differentiation.
+//!
+//! Every tensor that requires gradients carries a [`Node`] in the computation graph. Leaf
+//! nodes (created with `Tensor::requires_grad`) accumulate their gradient; interior nodes
+//! remember the inputs they were computed from and a closure mapping the output gradient to
+//! input gradients. `Tensor::backward` walks the graph in reverse topological order.
+//!
+//! Every buffer counts the in-place writes made to it. Operations record the count of each
+//! tensor their backward rule reads, and the backward pass refuses to run a rule whose saved
+//! tensors were written since, as their gradients would silently be wrong.
+
+use crate::compute::ComputeBackend;
+use crate::error::{Result, FerroFlowError};
+use crate::tensor::Tensor;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use tracing::debug;
+
+thread_local! {
+    static GRAD_ENABLED: Cell<bool> = const { Cell::new(true) };
+}
+
+/// Returns whether operations on the current thread are being recorded for autograd.
+pub fn is_grad_enabled() -> bool {
+    GRAD_ENABLED.with(|enabled| enabled.get())
+}
+
+/// Runs `f` without recording any operations, e.g. for inference or parameter updates.
+pub fn no_grad<R>(f: impl FnOnce() -> R) -> R {
+    let _guard = NoGradGuard::new();
+    f()
+}
+
+/// Disables gradient recording on the current thread until dropped.
+pub struct NoGradGuard {
+    previous: bool,
+}
+
+impl NoGradGuard {
+    pub fn new() -> Self {
+        let previous = GRAD_ENABLED.with(|enabled| enabled.replace(false));
+        Self { previous }
+    }
+}
+
+impl Default for NoGradGuard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for NoGradGuard {
+    fn drop(&mut self) {
+        GRAD_ENABLED.with(|enabled| enabled.set(self.previous));
+    }
+}
+
+/// Maps the gradient of an operation's output to the gradients of its inputs,
+/// returning `None` for inputs that do not need one.
+pub(crate) type BackwardFn<B> = dyn Fn(&Tensor<B>) -> Result<Vec<Option<Tensor<B>>>> + Send + Sync;
+
+struct GradFn<B: ComputeBackend> {
+    name: &'static str,
+    inputs: Vec<Option<Arc<Node<B>>>>,
+    saved: Vec<SavedVersion>,
+    backward: Box<BackwardFn<B>>,
+}
+
+/// The write count of a buffer at the time an operation saved a tensor viewing it for backward,
+/// see `Tensor::saved_version`
+pub(crate) struct SavedVersion {
+    counter: Arc<AtomicUsize>,
+    version: usize,
+}
+
+impl SavedVersion {
+    pub(crate) fn new(counter: Arc<AtomicUsize>) -> Self {
+        let version = counter.load(Ordering::Relaxed);
+        Self { counter, version }
+    }
+
+    /// Fails if the buffer was written in place since it was saved for the backward of `name`
+    fn check(&self, name: &str) -> Result<()> {
+        let current = self.counter.load(Ordering::Relaxed);
+        if current != self.version {
+            return Err(FerroFlowError::InvalidOperation(format!(
+                "A tensor saved for the backward of {} was modified in place (version {}, now {}); \
+                 recompute the forward pass after in-place writes or optimizer steps",
+                name, self.version, current
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// A vertex of the computation graph
+pub struct Node<B: ComputeBackend> {
+    grad: Mutex<Option<Tensor<B>>>,
+    grad_fn: Option<GradFn<B>>,
+}
+
+impl<B: ComputeBackend> Node<B> {
+    pub(crate) fn leaf() -> Arc<Self> {
+        Arc::new(Self { grad: Mutex::new(None), grad_fn: None })
+    }
+
+    pub(crate) fn is_leaf(&self) -> bool {
+        self.grad_fn.is_none()
+    }
+
+    /// Locks the gradient slot, ignoring poisoning for the same reason as `Tensor::buffer`
+    fn grad_slot(&self) -> MutexGuard<'_, Option<Tensor<B>>> {
+        self.grad.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub(crate) fn grad(&self) -> Option<Tensor<B>> {
+        self.grad_slot().clone()
+    }
+
+    pub(crate) fn set_grad(&self, grad: Option<Tensor<B>>) {
+        *self.grad_slot() = grad;
+    }
+
+    fn accumulate_grad(&self, grad: Tensor<B>) -> Result<()> {
+        let mut slot = self.grad_slot();
+        let total = match slot.take() {
+            Some(existing) => existing.add(&grad)?,
+            None => grad,
+        };
+        *slot = Some(total);
+        Ok(())
+    }
+}
+
+/// Attaches a backward rule to `output` if gradients are enabled and any input needs them.
+/// Returns `output` unchanged otherwise. `saved` holds the versions of the tensors the rule
+/// reads, which must not be written in place before it runs.
+pub(crate) fn record<B: ComputeBackend>(
+    name: &'static str,
+    output: Tensor<B>,
+    inputs: &[&Tensor<B>],
+    saved: Vec<SavedVersion>,
+    backward: impl Fn(&Tensor<B>) -> Result<Vec<Option<Tensor<B>>>> + Send + Sync + 'static,
+) -> Tensor<B> {
+    if !is_grad_enabled() || !inputs.iter().any(|input| input.needs_grad()) {
+        return output;
+    }
+
+    let node = Arc::new(Node {
+        grad: Mutex::new(None),
+        grad_fn: Some(GradFn {
+            name,
+            inputs: inputs.iter().map(|input| input.autograd_node().cloned()).collect(),
+            saved,
+            backward: Box::new(backward),
+        }),
+    });
+    output.with_autograd_node(node)
+}
+
+/// Back-propagates `grad` from `root` through the graph, accumulating into leaf gradients.
+pub(crate) fn backward<B: ComputeBackend>(root: &Arc<Node<B>>, grad: Tensor<B>) -> Result<()> {
+    let order = topological_order(root);
+    debug!("Running backward pass over {} nodes", order.len());
+
+    let mut grads: HashMap<*const Node<B>, Tensor<B>> = HashMap::new();
+    grads.insert(Arc::as_ptr(root), grad);
+
+    no_grad(|| {
+        for node in order {
+            let Some(grad) = grads.remove(&Arc::as_ptr(&node)) else {
+                continue;
+            };
+
+            let Some(grad_fn) = &node.grad_fn else {
+                node.accumulate_grad(grad)?;
+                continue;
+            };
+
+            for saved in &grad_fn.saved {
+                saved.check(grad_fn.name)?;
+            }
+            let input_grads = (grad_fn.backward)(&grad)?;
+            if input_grads.len() != grad_fn.inputs.len() {
+                return Err(FerroFlowError::InvalidOperation(format!(
+                    "Backward of {} produced {} gradients for {} inputs",
+                    grad_fn.name, input_grads.len(), grad_fn.inputs.len()
+                )));
+            }
+
+            for (input, input_grad) in grad_fn.inputs.iter().zip(input_grads) {
+                let (Some(input), Some(input_grad)) = (input, input_grad) else {
+                    continue;
+                };
+                let key = Arc::as_ptr(input);
+                let total = match grads.remove(&key) {
+                    Some(existing) => existing.add(&input_grad)?,
+                    None => input_grad,
+                };
+                grads.insert(key, total);
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Orders the nodes reachable from `root` so every node precedes the inputs it was computed from.
+fn topological_order<B: ComputeBackend>(root: &Arc<Node<B>>) -> Vec<Arc<Node<B>>> {
+    let mut visited = std::collections::HashSet::new();
+    let mut post_order = Vec::new();
+    // Iterative DFS: the flag marks whether the node's inputs have already been pushed
+    let mut stack = vec![(Arc::clone(root), false)];
+
+    while let Some((node, expanded)) = stack.pop() {
+        if expanded {
+            post_order.push(node);
+            continue;
+        }
+        if !visited.insert(Arc::as_ptr(&node)) {
+            continue;
+        }
+        stack.push((Arc::clone(&node), true));
+        if let Some(grad_fn) = &node.grad_fn {
+            for input in grad_fn.inputs.iter().flatten() {
+                if !visited.contains(&Arc::as_ptr(input)) {
+                    stack.push((Arc::clone(input), false));
+                }
+            }
+        }
+    }
+
+    post_order.reverse();
+    post_order
+}
+
+#[cfg(test)]
+pub(crate) mod tests;
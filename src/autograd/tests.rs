@@ -0,0 +1,247 @@
+use super::*;
+use crate::compute::CPUBackend;
+use crate::tensor::{Conv2dOptions, Pool2dOptions, Shape};
+
+type T = Tensor<CPUBackend>;
+
+/// Returns a `dims` tensor of values in [-1, 1.2] that vary with `seed`
+pub(crate) fn tensor(ctx: &Arc<<CPUBackend as ComputeBackend>::Context>, dims: &[usize], seed: usize) -> T {
+    let size: usize = dims.iter().product();
+    let data: Vec<f32> = (0..size).map(|i| (((i + seed) * 7919) % 23) as f32 / 10.0 - 1.0).collect();
+    Tensor::new(Arc::clone(ctx), Shape::new(dims.to_vec()), &data).unwrap()
+}
+
+/// Asserts that each element of `actual` is within `tolerance`, relative to the magnitude of the
+/// expected value, of `expected`
+pub(crate) fn assert_close(actual: &[f32], expected: &[f32], tolerance: f32) {
+    assert_eq!(actual.len(), expected.len());
+    for (i, (a, e)) in actual.iter().zip(expected).enumerate() {
+        assert!((a - e).abs() <= tolerance * (1.0 + e.abs()), "index {}: {} vs {}", i, a, e);
+    }
+}
+
+fn sum_all(t: &T) -> Result<T> {
+    t.sum_all()
+}
+
+/// Compares autograd gradients of `f` against central finite differences
+pub(crate) fn check_gradients(inputs: &[T], f: impl Fn(&[T]) -> Result<T>) -> Result<()> {
+    let tracked: Vec<T> = inputs.iter().map(|t| t.detach().requires_grad()).collect();
+    sum_all(&f(&tracked)?)?.backward()?;
+
+    let eps = 1e-2;
+    for (index, input) in inputs.iter().enumerate() {
+        let grad = tracked[index].grad().expect("input should have a gradient").data()?;
+        let base = input.data()?;
+        assert_eq!(grad.len(), base.len());
+
+        for i in 0..base.len() {
+            let evaluate = |delta: f32| -> Result<f32> {
+                let mut data = base.clone();
+                data[i] += delta;
+                let mut perturbed: Vec<T> = inputs.to_vec();
+                perturbed[index] = Tensor::new(Arc::new(crate::compute::CPUContext::new()), input.shape().clone(), &data)?;
+                Ok(sum_all(&f(&perturbed)?)?.data()?[0])
+            };
+            let numeric = (evaluate(eps)? - evaluate(-eps)?) / (2.0 * eps);
+            assert!(
+                (numeric - grad[i]).abs() < 2e-2 * (1.0 + numeric.abs()),
+                "input {} element {}: autograd {} vs numeric {}", index, i, grad[i], numeric
+            );
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_elementwise_gradients() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let a = tensor(&ctx, &[2, 3], 0);
+    let b = tensor(&ctx, &[2, 3], 5);
+    let bias = tensor(&ctx, &[3], 2);
+    let denominator = Tensor::full(Arc::clone(&ctx), Shape::new(vec![2, 1]), 1.5)?.add(&tensor(&ctx, &[2, 1], 1))?;
+
+    check_gradients(&[a.clone(), b.clone()], |t| t[0].add(&t[1])?.multiply(&t[1]))?;
+    check_gradients(&[a.clone(), bias], |t| t[0].sub(&t[1])?.multiply(&t[0]))?;
+    check_gradients(&[a.clone(), denominator], |t| t[0].divide(&t[1]))?;
+    check_gradients(&[a], |t| (-&t[0].scalar_multiply(3.0)?)?.multiply(&t[0]))?;
+
+    Ok(())
+}
+
+#[test]
+fn test_matmul_gradients() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let a = tensor(&ctx, &[2, 3], 0);
+    let b = tensor(&ctx, &[3, 4], 1);
+    let b_stored_transposed = tensor(&ctx, &[4, 3], 2);
+    let c_stored_transposed = tensor(&ctx, &[4, 2], 5);
+    let a_batched = tensor(&ctx, &[2, 3, 2], 3);
+    let b_batched = tensor(&ctx, &[2, 3, 4], 4);
+
+    check_gradients(&[a.clone(), b], |t| t[0].matmul(&t[1]))?;
+    check_gradients(&[a.clone(), b_stored_transposed], |t| t[0].matmul_transposed(&t[1], false, true))?;
+    check_gradients(&[a, c_stored_transposed], |t| t[0].matmul_transposed(&t[1], true, true))?;
+    check_gradients(&[a_batched, b_batched], |t| t[0].matmul_transposed(&t[1], true, false))?;
+
+    Ok(())
+}
+
+#[test]
+fn test_view_gradients() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let a = tensor(&ctx, &[2, 3, 4], 0);
+    let w = tensor(&ctx, &[3, 2], 1);
+
+    check_gradients(std::slice::from_ref(&a), |t| t[0].permute(&[2, 0, 1])?.contiguous()?.multiply(&t[0].permute(&[2, 0, 1])?))?;
+    check_gradients(std::slice::from_ref(&a), |t| t[0].slice(2, 1, 4, 2)?.reshape(Shape::new(vec![12])))?;
+    check_gradients(&[a, w], |t| t[0].narrow(0, 1, 1)?.reshape(Shape::new(vec![3, 4]))?.transpose(0, 1)?.matmul(&t[1]))?;
+
+    Ok(())
+}
+
+#[test]
+fn test_reduction_gradients() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let a = tensor(&ctx, &[2, 3, 4], 0);
+    let w = tensor(&ctx, &[4], 6);
+
+    check_gradients(std::slice::from_ref(&a), |t| t[0].sum(1, false)?.multiply(&t[0].mean(2, true)?.sum(1, false)?))?;
+    check_gradients(std::slice::from_ref(&a), |t| t[0].max(2, false)?.add(&t[0].min(2, false)?))?;
+    check_gradients(std::slice::from_ref(&a), |t| t[0].var(1, 1, true)?.add(&t[0].std(0, 0, false)?))?;
+    check_gradients(&[a.clone(), w], |t| t[0].broadcast_to(&Shape::new(vec![3, 2, 3, 4]))?.multiply(&t[1]))?;
+
+    // Includes a zero so the product gradient cannot divide by the element
+    let with_zero = Tensor::new(Arc::clone(&ctx), Shape::new(vec![2, 3]), &[0.5, 0.0, -1.5, 1.2, 0.8, -0.7])?;
+    check_gradients(&[with_zero], |t| t[0].prod(1, true))?;
+
+    Ok(())
+}
+
+#[test]
+fn test_unary_gradients() -> Result<()> {
+    use crate::compute::UnaryOp;
+    let ctx = CPUBackend::new()?;
+    // Shifted so no element sits on a kink of abs, relu or clamp
+    let a = tensor(&ctx, &[2, 3], 0).add(&Tensor::full(Arc::clone(&ctx), Shape::new(vec![1]), 0.05)?)?;
+    let positive = a.abs()?.add(&Tensor::full(Arc::clone(&ctx), Shape::new(vec![1]), 0.5)?)?;
+
+    for op in [
+        UnaryOp::Exp, UnaryOp::Abs, UnaryOp::Sin, UnaryOp::Cos, UnaryOp::Tanh, UnaryOp::Sigmoid,
+        UnaryOp::Relu, UnaryOp::Gelu, UnaryOp::GeluTanh, UnaryOp::Silu, UnaryOp::Softplus,
+        UnaryOp::Clamp { min: -0.5, max: 0.5 },
+    ] {
+        check_gradients(std::slice::from_ref(&a), |t| t[0].unary_op(op)?.multiply(&t[0]))?;
+    }
+    for op in [UnaryOp::Log, UnaryOp::Sqrt, UnaryOp::Rsqrt, UnaryOp::Pow(1.5)] {
+        check_gradients(std::slice::from_ref(&positive), |t| t[0].unary_op(op))?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_softmax_gradients() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let logits = tensor(&ctx, &[2, 3, 4], 0).scalar_multiply(3.0)?;
+    let weights = tensor(&ctx, &[2, 3, 4], 7);
+
+    check_gradients(&[logits.clone(), weights.clone()], |t| t[0].softmax(2)?.multiply(&t[1]))?;
+    check_gradients(&[logits.clone(), weights], |t| t[0].log_softmax(1)?.multiply(&t[1]))?;
+    check_gradients(std::slice::from_ref(&logits), |t| t[0].logsumexp(0, false))?;
+
+    Ok(())
+}
+
+#[test]
+fn test_grad_accumulation_and_no_grad() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let x = Tensor::<CPUBackend>::new(Arc::clone(&ctx), Shape::new(vec![1]), &[3.0])?.requires_grad();
+
+    // y = x * x + x, dy/dx = 2x + 1, with x used along several paths
+    let y = x.multiply(&x)?.add(&x)?;
+    assert!(!y.is_leaf());
+    y.backward()?;
+    assert_eq!(x.grad().unwrap().data()?, vec![7.0]);
+
+    // A second backward pass accumulates into the existing gradient
+    y.backward()?;
+    assert_eq!(x.grad().unwrap().data()?, vec![14.0]);
+    x.zero_grad();
+    assert!(x.grad().is_none());
+
+    let untracked = no_grad(|| x.scalar_multiply(2.0))?;
+    assert!(!untracked.needs_grad());
+    assert!(is_grad_enabled());
+    assert!(untracked.backward().is_err());
+
+    let detached = x.detach().scalar_multiply(2.0)?;
+    assert!(!detached.needs_grad());
+
+    let vector = x.add(&Tensor::full(Arc::clone(&ctx), Shape::new(vec![2]), 1.0)?)?;
+    assert!(vector.backward().is_err());
+    vector.backward_with_grad(&Tensor::new(Arc::clone(&ctx), Shape::new(vec![2]), &[1.0, 2.0])?)?;
+    assert_eq!(x.grad().unwrap().data()?, vec![3.0]);
+
+    Ok(())
+}
+
+#[test]
+fn test_conv_gradients() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let input = tensor(&ctx, &[2, 4, 5, 4], 0);
+    let weight = tensor(&ctx, &[6, 2, 3, 2], 3);
+    let bias = tensor(&ctx, &[6], 5);
+    let options = Conv2dOptions { stride: (2, 1), padding: (1, 1), dilation: (1, 2), groups: 2, output_padding: (1, 0) };
+
+    check_gradients(&[input.clone(), weight.clone(), bias.clone()], |t| {
+        t[0].conv2d(&t[1], Some(&t[2]), options)?.multiply(&t[0].conv2d(&t[1], None, options)?)
+    })?;
+
+    let transpose_weight = tensor(&ctx, &[4, 3, 3, 2], 11);
+    check_gradients(&[input, transpose_weight, bias], |t| {
+        let output = t[0].conv_transpose2d(&t[1], Some(&t[2]), options)?;
+        output.multiply(&output)
+    })?;
+
+    Ok(())
+}
+
+#[test]
+fn test_pooling_gradients() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    // Distinct values keep the maxima away from ties, where max pooling is not differentiable
+    let data: Vec<f32> = (0..2 * 3 * 5 * 6).map(|i| ((i * 37) % 181) as f32 / 20.0).collect();
+    let input = Tensor::new(Arc::clone(&ctx), Shape::new(vec![2, 3, 5, 6]), &data)?;
+    let weights = tensor(&ctx, &[2, 3, 3, 3], 1);
+    let options = Pool2dOptions { stride: (2, 2), padding: (1, 1), ..Pool2dOptions::new((3, 3)) };
+
+    check_gradients(&[input.clone(), weights.clone()], |t| t[0].max_pool2d(options)?.0.multiply(&t[1]))?;
+    check_gradients(&[input.clone(), weights.clone()], |t| t[0].avg_pool2d(options)?.multiply(&t[1]))?;
+    let excluded = Pool2dOptions { count_include_pad: false, ..options };
+    check_gradients(&[input.clone(), weights.clone()], |t| t[0].avg_pool2d(excluded)?.multiply(&t[1]))?;
+    check_gradients(&[input.clone(), weights.clone()], |t| t[0].adaptive_max_pool2d((3, 3))?.0.multiply(&t[1]))?;
+    check_gradients(&[input, weights], |t| t[0].adaptive_avg_pool2d((3, 3))?.multiply(&t[1]))?;
+
+    Ok(())
+}
+
+#[test]
+fn test_normalization_gradients() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let x = tensor(&ctx, &[2, 4, 3], 0).scalar_multiply(2.0)?;
+    let weights = tensor(&ctx, &[2, 4, 3], 5);
+    let (gamma, beta) = (tensor(&ctx, &[3], 2), tensor(&ctx, &[3], 9));
+    let (channel_gamma, channel_beta) = (tensor(&ctx, &[4], 1), tensor(&ctx, &[4], 4));
+
+    check_gradients(&[x.clone(), gamma.clone(), beta, weights.clone()], |t| {
+        t[0].layer_norm(&[3], Some(&t[1]), Some(&t[2]), 1e-5)?.multiply(&t[3])
+    })?;
+    check_gradients(&[x.clone(), weights.clone()], |t| t[0].layer_norm(&[4, 3], None, None, 1e-5)?.multiply(&t[1]))?;
+    check_gradients(&[x.clone(), gamma, weights.clone()], |t| t[0].rms_norm(&[3], Some(&t[1]), 1e-6)?.multiply(&t[2]))?;
+    check_gradients(&[x, channel_gamma, channel_beta, weights], |t| {
+        t[0].group_norm(2, Some(&t[1]), Some(&t[2]), 1e-5)?.multiply(&t[3])
+    })?;
+
+    Ok(())
+}
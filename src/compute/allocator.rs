@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use tracing::debug;
+
+/// Allocation statistics of a backend context, see [`ComputeBackend::memory_stats`](super::ComputeBackend::memory_stats).
+/// Byte counts include the rounding of requests up to their size bucket.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryStats {
+    /// Bytes held by buffers the allocator handed out that are still in use
+    pub live_bytes: usize,
+    /// Highest `live_bytes` since the context was created
+    pub peak_bytes: usize,
+    /// Bytes held by released buffers kept for reuse
+    pub cached_bytes: usize,
+    /// Buffers handed out, newly allocated or reused
+    pub allocations: usize,
+    /// Allocations served from the cache
+    pub reuses: usize,
+    /// Buffers handed back to the allocator
+    pub releases: usize,
+}
+
+/// Smallest size bucket in bytes
+const MIN_BUCKET: usize = 512;
+
+/// Rounds an allocation of `bytes` up to its size bucket: [`MIN_BUCKET`] for small requests,
+/// otherwise the next multiple of a quarter of the largest power of two not above `bytes`,
+/// which wastes at most 25%
+pub(crate) fn bucket_size(bytes: usize) -> usize {
+    if bytes <= MIN_BUCKET {
+        return MIN_BUCKET;
+    }
+    let step = 1 << (usize::BITS - 1 - bytes.leading_zeros() - 2);
+    bytes.div_ceil(step) * step
+}
+
+/// Keeps released buffers in per-size buckets and hands them out again for allocations of the
+/// same bucket, like a caching allocator. Buffers are identified by their address, so releasing
+/// a buffer the cache did not hand out leaves it alone.
+pub(crate) struct BufferCache<T> {
+    state: Mutex<CacheState<T>>,
+}
+
+struct CacheState<T> {
+    free: HashMap<usize, Vec<T>>,
+    live: HashMap<usize, usize>,
+    stats: MemoryStats,
+}
+
+impl<T> BufferCache<T> {
+    pub(crate) fn new() -> Self {
+        Self { state: Mutex::new(CacheState { free: HashMap::new(), live: HashMap::new(), stats: MemoryStats::default() }) }
+    }
+
+    /// Returns a cached buffer of `bytes`, a bucket size, or one made by `create`. Empty
+    /// buffers share a dangling address and are created without being tracked.
+    pub(crate) fn allocate(&self, bytes: usize, create: impl FnOnce() -> T, address: impl Fn(&T) -> usize) -> T {
+        if bytes == 0 {
+            return create();
+        }
+        let cached = {
+            let mut state = self.lock();
+            let cached = state.free.get_mut(&bytes).and_then(Vec::pop);
+            if cached.is_some() {
+                state.stats.cached_bytes -= bytes;
+                state.stats.reuses += 1;
+            }
+            cached
+        };
+        // Allocate outside the lock, other threads may be releasing meanwhile
+        let buffer = cached.unwrap_or_else(create);
+
+        let mut state = self.lock();
+        state.live.insert(address(&buffer), bytes);
+        let stats = &mut state.stats;
+        stats.allocations += 1;
+        stats.live_bytes += bytes;
+        stats.peak_bytes = stats.peak_bytes.max(stats.live_bytes);
+        buffer
+    }
+
+    /// Keeps `buffer`, found at `address`, for reuse if the cache handed it out and drops it otherwise.
+    /// A buffer the cache handed out but that was dropped or reallocated elsewhere is never
+    /// released and stays counted in `live_bytes`; the log of unknown addresses helps find those.
+    pub(crate) fn release(&self, buffer: T, address: usize) {
+        let mut state = self.lock();
+        let Some(bytes) = state.live.remove(&address) else {
+            debug!("Dropping released buffer at {:#x}, which the cache did not hand out", address);
+            return;
+        };
+        state.free.entry(bytes).or_default().push(buffer);
+        let stats = &mut state.stats;
+        stats.live_bytes -= bytes;
+        stats.cached_bytes += bytes;
+        stats.releases += 1;
+    }
+
+    pub(crate) fn stats(&self) -> MemoryStats {
+        self.lock().stats
+    }
+
+    /// Frees every cached buffer
+    pub(crate) fn clear(&self) {
+        let mut state = self.lock();
+        state.free.clear();
+        state.stats.cached_bytes = 0;
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CacheState<T>> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T> fmt::Debug for BufferCache<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BufferCache").field("stats", &self.stats()).finish()
+    }
+}
@@ -0,0 +1,180 @@
+//! Host implementation of 2D convolution (NCHW) via im2col and the blocked CPU GEMM.
+//!
+//! For each image and group, im2col unfolds the receptive field of every output pixel into a
+//! column, turning the convolution into one `(C_out/g x K) * (K x H_out*W_out)` matrix product
+//! with `K = C_in/g * kernel_h * kernel_w`. The backward passes reuse the same unfolding:
+//! the input gradient is `W^T * grad` folded back with col2im, and the weight gradient is
+//! `grad * cols^T` summed over the batch.
+
+use super::check_operand;
+use super::cpu::gemm::gemm;
+use crate::error::Result;
+
+/// Geometry of a 2D convolution over NCHW inputs with `[C_out, C_in / groups, kh, kw]` weights
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Conv2dParams {
+    pub batch_size: usize,
+    pub in_channels: usize,
+    pub out_channels: usize,
+    pub input_size: (usize, usize),
+    pub kernel_size: (usize, usize),
+    pub stride: (usize, usize),
+    pub padding: (usize, usize),
+    pub dilation: (usize, usize),
+    pub groups: usize,
+}
+
+impl Conv2dParams {
+    /// Returns the spatial size of the output, `(H_out, W_out)`.
+    /// Inputs smaller than the dilated kernel produce an empty output.
+    pub fn output_size(&self) -> (usize, usize) {
+        let out = |input: usize, kernel: usize, stride: usize, padding: usize, dilation: usize| {
+            let span = dilation * (kernel - 1) + 1;
+            (input + 2 * padding).checked_sub(span).map_or(0, |room| room / stride + 1)
+        };
+        (
+            out(self.input_size.0, self.kernel_size.0, self.stride.0, self.padding.0, self.dilation.0),
+            out(self.input_size.1, self.kernel_size.1, self.stride.1, self.padding.1, self.dilation.1),
+        )
+    }
+
+    pub fn input_len(&self) -> usize {
+        self.batch_size * self.in_channels * self.input_size.0 * self.input_size.1
+    }
+
+    pub fn weight_len(&self) -> usize {
+        self.out_channels * self.in_channels / self.groups * self.kernel_size.0 * self.kernel_size.1
+    }
+
+    pub fn output_len(&self) -> usize {
+        let (h, w) = self.output_size();
+        self.batch_size * self.out_channels * h * w
+    }
+
+    /// Rows of the unfolded column matrix of one group
+    fn column_rows(&self) -> usize {
+        self.in_channels / self.groups * self.kernel_size.0 * self.kernel_size.1
+    }
+
+    /// Calls `f(row, column, input_index)` for every in-bounds entry of the column matrix
+    /// of `group` in one image; padded positions are skipped.
+    fn for_each_column_entry(&self, group: usize, mut f: impl FnMut(usize, usize, usize)) {
+        let (h, w) = self.input_size;
+        let (kh, kw) = self.kernel_size;
+        let (out_h, out_w) = self.output_size();
+        let channels = self.in_channels / self.groups;
+
+        for c in 0..channels {
+            let channel_base = (group * channels + c) * h * w;
+            for ky in 0..kh {
+                for kx in 0..kw {
+                    let row = (c * kh + ky) * kw + kx;
+                    for oy in 0..out_h {
+                        let iy = (oy * self.stride.0 + ky * self.dilation.0) as isize - self.padding.0 as isize;
+                        if iy < 0 || iy >= h as isize {
+                            continue;
+                        }
+                        for ox in 0..out_w {
+                            let ix = (ox * self.stride.1 + kx * self.dilation.1) as isize - self.padding.1 as isize;
+                            if ix >= 0 && ix < w as isize {
+                                f(row, oy * out_w + ox, channel_base + iy as usize * w + ix as usize);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Unfolds one group of one image into a `column_rows x (H_out * W_out)` matrix
+    fn im2col(&self, image: &[f32], group: usize, columns: &mut [f32]) {
+        let (out_h, out_w) = self.output_size();
+        columns.fill(0.0);
+        self.for_each_column_entry(group, |row, column, index| {
+            columns[row * out_h * out_w + column] = image[index];
+        });
+    }
+
+    /// Adds a column matrix back onto the pixels it was unfolded from
+    fn col2im(&self, columns: &[f32], group: usize, image: &mut [f32]) {
+        let (out_h, out_w) = self.output_size();
+        self.for_each_column_entry(group, |row, column, index| {
+            image[index] += columns[row * out_h * out_w + column];
+        });
+    }
+}
+
+/// Forward convolution into `output`, which holds `[N, C_out, H_out, W_out]`
+pub(crate) fn conv2d(params: &Conv2dParams, input: &[f32], weight: &[f32], output: &mut [f32]) -> Result<()> {
+    check_operand("Convolution input", input, params.input_len())?;
+    check_operand("Convolution weight", weight, params.weight_len())?;
+    let (out_h, out_w) = params.output_size();
+    let pixels = out_h * out_w;
+    let (rows, out_per_group) = (params.column_rows(), params.out_channels / params.groups);
+    let image_len = params.in_channels * params.input_size.0 * params.input_size.1;
+
+    let mut columns = vec![0.0; rows * pixels];
+    for n in 0..params.batch_size {
+        let image = &input[n * image_len..(n + 1) * image_len];
+        for g in 0..params.groups {
+            params.im2col(image, g, &mut columns);
+            let weight_group = &weight[g * out_per_group * rows..(g + 1) * out_per_group * rows];
+            let start = (n * params.out_channels + g * out_per_group) * pixels;
+            gemm(weight_group, &columns, &mut output[start..start + out_per_group * pixels],
+                out_per_group, pixels, rows, false, false);
+        }
+    }
+    Ok(())
+}
+
+/// Adds the gradient of the convolution with respect to its input, `[N, C_in, H, W]`, to the
+/// zeroed `grad_input`. This is also the forward pass of the transposed convolution.
+pub(crate) fn conv2d_backward_input(params: &Conv2dParams, grad_output: &[f32], weight: &[f32], grad_input: &mut [f32]) -> Result<()> {
+    check_operand("Convolution output gradient", grad_output, params.output_len())?;
+    check_operand("Convolution weight", weight, params.weight_len())?;
+    let (out_h, out_w) = params.output_size();
+    let pixels = out_h * out_w;
+    let (rows, out_per_group) = (params.column_rows(), params.out_channels / params.groups);
+    let image_len = params.in_channels * params.input_size.0 * params.input_size.1;
+
+    let mut columns = vec![0.0; rows * pixels];
+    for n in 0..params.batch_size {
+        let image = &mut grad_input[n * image_len..(n + 1) * image_len];
+        for g in 0..params.groups {
+            let weight_group = &weight[g * out_per_group * rows..(g + 1) * out_per_group * rows];
+            let start = (n * params.out_channels + g * out_per_group) * pixels;
+            gemm(weight_group, &grad_output[start..start + out_per_group * pixels], &mut columns,
+                rows, pixels, out_per_group, true, false);
+            params.col2im(&columns, g, image);
+        }
+    }
+    Ok(())
+}
+
+/// Adds the gradient of the convolution with respect to its weight,
+/// `[C_out, C_in / groups, kh, kw]`, to the zeroed `grad_weight`
+pub(crate) fn conv2d_backward_weight(params: &Conv2dParams, input: &[f32], grad_output: &[f32], grad_weight: &mut [f32]) -> Result<()> {
+    check_operand("Convolution input", input, params.input_len())?;
+    check_operand("Convolution output gradient", grad_output, params.output_len())?;
+    let (out_h, out_w) = params.output_size();
+    let pixels = out_h * out_w;
+    let (rows, out_per_group) = (params.column_rows(), params.out_channels / params.groups);
+    let image_len = params.in_channels * params.input_size.0 * params.input_size.1;
+
+    let mut columns = vec![0.0; rows * pixels];
+    let mut partial = vec![0.0; out_per_group * rows];
+    for n in 0..params.batch_size {
+        let image = &input[n * image_len..(n + 1) * image_len];
+        for g in 0..params.groups {
+            params.im2col(image, g, &mut columns);
+            let start = (n * params.out_channels + g * out_per_group) * pixels;
+            gemm(&grad_output[start..start + out_per_group * pixels], &columns, &mut partial,
+                out_per_group, rows, pixels, false, true);
+            let group_grad = &mut grad_weight[g * out_per_group * rows..(g + 1) * out_per_group * rows];
+            for (total, value) in group_grad.iter_mut().zip(&partial) {
+                *total += value;
+            }
+        }
+    }
+    Ok(())
+}
@@ -1,20 +1,100 @@
-use super::ComputeBackend;
+use super::allocator::{self, BufferCache};
+use super::reduce;
+use super::{
+    check_operand, conv, loss, norm, optim, pool, softmax, storage_bytes, strided, BinaryOp, BufferView, ClassLossParams, ComputeBackend,
+    Conv2dParams, MemoryStats, NormOp, OptimizerUpdate, PointwiseLossOp, Pool2dParams, ReduceOp, SoftmaxOp, UnaryOp,
+};
+use crate::dtype::{DType, Element};
 use crate::error::{Result, FerroFlowError};
+use crate::io::Storage;
+use std::ops::Range;
 use std::sync::Arc;
 
+mod buffer;
+pub(super) mod gemm;
+mod simd;
+
+pub use buffer::{CPUBuffer, MappedBuffer};
+pub use simd::SimdLevel;
+
 #[derive(Debug)]
-pub struct CPUContext;
+pub struct CPUContext {
+    simd_level: SimdLevel,
+    buffers: BufferCache<Vec<f32>>,
+}
 
 impl CPUContext {
+    /// Creates a context using the widest SIMD instruction set the CPU supports
     pub fn new() -> Self {
-        Self
+        Self { simd_level: SimdLevel::detect(), buffers: BufferCache::new() }
+    }
+
+    /// Creates a context pinned to a specific SIMD code path, e.g. to verify it against
+    /// `SimdLevel::Scalar` on the same machine
+    pub fn with_simd_level(simd_level: SimdLevel) -> Result<Self> {
+        if !simd_level.is_supported() {
+            return Err(FerroFlowError::CPUError(
+                format!("SIMD level {:?} is not supported on this CPU", simd_level)
+            ));
+        }
+        Ok(Self { simd_level, buffers: BufferCache::new() })
+    }
+
+    /// Returns the SIMD code path used by the element-wise kernels
+    pub fn simd_level(&self) -> SimdLevel {
+        self.simd_level
+    }
+
+    /// Returns the statistics of the cache recycling the f32 buffers of op results
+    pub fn memory_stats(&self) -> MemoryStats {
+        self.buffers.stats()
+    }
+
+    /// Frees the f32 buffers kept for reuse
+    pub fn empty_cache(&self) {
+        self.buffers.clear()
+    }
+
+    /// Returns `len` zeroed elements, reusing the memory of a released buffer of the same
+    /// size bucket if one is cached
+    fn alloc_f32(&self, len: usize) -> Vec<f32> {
+        let bytes = allocator::bucket_size(len * std::mem::size_of::<f32>());
+        let capacity = bytes / std::mem::size_of::<f32>();
+        let mut values = self.buffers.allocate(bytes, || Vec::with_capacity(capacity), |values| values.as_ptr() as usize);
+        values.clear();
+        values.resize(len, 0.0);
+        values
+    }
+
+    /// Allocates an op result of `len` elements and lets `fill` write it, handing the memory
+    /// back to the cache if `fill` fails
+    fn output_buffer(&self, len: usize, fill: impl FnOnce(&mut [f32]) -> Result<()>) -> Result<CPUBuffer> {
+        let mut values = self.alloc_f32(len);
+        match fill(&mut values) {
+            Ok(()) => Ok(values.into()),
+            Err(e) => {
+                self.release_f32(values);
+                Err(e)
+            }
+        }
+    }
+
+    fn release_f32(&self, values: Vec<f32>) {
+        let address = values.as_ptr() as usize;
+        self.buffers.release(values, address);
+    }
+}
+
+impl Default for CPUContext {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 pub struct CPUBackend;
 
 impl ComputeBackend for CPUBackend {
-    type Buffer = Vec<f32>;
+    type Buffer = CPUBuffer;
     type Context = CPUContext;
 
     fn new() -> Result<Arc<Self::Context>> {
@@ -22,114 +102,635 @@ impl ComputeBackend for CPUBackend {
     }
 
     fn allocate_buffer(
-        _ctx: &Self::Context,
+        ctx: &Self::Context,
         size: usize,
         data: Option<&[f32]>
+    ) -> Result<Self::Buffer> {
+        ctx.output_buffer(size, |out| {
+            if let Some(data) = data {
+                check_operand("Data", data, size)?;
+                out.copy_from_slice(data);
+            }
+            Ok(())
+        })
+    }
+
+    fn allocate_typed_buffer(
+        ctx: &Self::Context,
+        dtype: DType,
+        size: usize,
+        data: Option<&[u8]>
     ) -> Result<Self::Buffer> {
         match data {
-            Some(data) => Ok(data.to_vec()),
-            None => Ok(vec![0.0; size])
+            Some(data) if data.len() != size * dtype.size_in_bytes() => Err(FerroFlowError::BufferError(format!(
+                "{} bytes don't match {} elements of {}", data.len(), size, dtype
+            ))),
+            Some(data) if dtype == DType::F32 => ctx.output_buffer(size, |out| {
+                for (value, bytes) in out.iter_mut().zip(data.chunks_exact(std::mem::size_of::<f32>())) {
+                    *value = f32::from_ne_slice(bytes);
+                }
+                Ok(())
+            }),
+            Some(data) => CPUBuffer::from_bytes(dtype, data),
+            None if dtype == DType::F32 => Self::allocate_buffer(ctx, size, None),
+            None => Ok(CPUBuffer::zeros(dtype, size)),
+        }
+    }
+
+    fn import_storage(ctx: &Self::Context, storage: &Arc<Storage>, range: Range<usize>, dtype: DType) -> Result<Self::Buffer> {
+        if let Some(mapped) = MappedBuffer::new(Arc::clone(storage), range.clone(), dtype) {
+            return Ok(CPUBuffer::Mapped(mapped));
         }
+        // Unaligned or boolean data can't be read in place
+        let bytes = storage_bytes(storage, range, dtype)?;
+        Self::allocate_typed_buffer(ctx, dtype, bytes.len() / dtype.size_in_bytes(), Some(bytes))
     }
 
     fn read_buffer(_ctx: &Self::Context, buffer: &Self::Buffer) -> Result<Vec<f32>> {
-        Ok(buffer.clone())
+        Ok(buffer.as_f32()?.to_vec())
     }
 
-    fn element_wise_add(
+    fn read_buffer_bytes(_ctx: &Self::Context, buffer: &Self::Buffer, dtype: DType) -> Result<Vec<u8>> {
+        check_dtype(buffer, dtype)?;
+        Ok(buffer.to_bytes())
+    }
+
+    fn buffer_len(buffer: &Self::Buffer, _dtype: DType) -> usize {
+        buffer.len()
+    }
+
+    fn release_buffer(ctx: &Self::Context, buffer: &mut Self::Buffer) {
+        if let CPUBuffer::F32(values) = buffer {
+            ctx.release_f32(std::mem::take(values));
+        }
+    }
+
+    fn memory_stats(ctx: &Self::Context) -> MemoryStats {
+        ctx.memory_stats()
+    }
+
+    fn empty_cache(ctx: &Self::Context) {
+        ctx.empty_cache()
+    }
+
+    fn copy_strided(
+        ctx: &Self::Context,
+        input: &Self::Buffer,
+        dtype: DType,
+        shape: &[usize],
+        strides: &[usize],
+        offset: usize
+    ) -> Result<Self::Buffer> {
+        check_dtype(input, dtype)?;
+        if dtype != DType::F32 {
+            return Ok(input.gather(shape, strides, offset));
+        }
+        let values = input.as_f32()?;
+        ctx.output_buffer(shape.iter().product(), |out| {
+            strided::gather_into(values, shape, strides, offset, out);
+            Ok(())
+        })
+    }
+
+    fn copy_strided_into(
         _ctx: &Self::Context,
+        input: &Self::Buffer,
+        dtype: DType,
+        shape: &[usize],
+        strides: &[usize],
+        offset: usize,
+        out: &mut Self::Buffer
+    ) -> Result<()> {
+        check_dtype(input, dtype)?;
+        check_dtype(out, dtype)?;
+        if out.len() != shape.iter().product::<usize>() {
+            return Err(FerroFlowError::ShapeMismatch(format!(
+                "Output buffer has {} elements, expected {}", out.len(), shape.iter().product::<usize>()
+            )));
+        }
+        input.gather_into(shape, strides, offset, out)
+    }
+
+    fn cast(_ctx: &Self::Context, input: &Self::Buffer, from: DType, to: DType, size: usize) -> Result<Self::Buffer> {
+        check_dtype(input, from)?;
+        if input.len() != size {
+            return Err(FerroFlowError::BufferError("Buffer size mismatch".into()));
+        }
+        Ok(input.cast(to))
+    }
+
+    fn element_wise_add(
+        ctx: &Self::Context,
         a: &Self::Buffer,
         b: &Self::Buffer,
         size: usize
     ) -> Result<Self::Buffer> {
+        let (a, b) = (a.as_f32()?, b.as_f32()?);
         if a.len() != size || b.len() != size {
             return Err(FerroFlowError::BufferError("Buffer size mismatch".into()));
         }
         
-        Ok(a.iter().zip(b.iter()).map(|(x, y)| x + y).collect())
+        ctx.output_buffer(size, |out| {
+            simd::add(ctx.simd_level, a, b, out);
+            Ok(())
+        })
     }
 
     fn element_wise_multiply(
-        _ctx: &Self::Context,
+        ctx: &Self::Context,
         a: &Self::Buffer,
         b: &Self::Buffer,
         size: usize
     ) -> Result<Self::Buffer> {
+        let (a, b) = (a.as_f32()?, b.as_f32()?);
         if a.len() != size || b.len() != size {
             return Err(FerroFlowError::BufferError("Buffer size mismatch".into()));
         }
         
-        Ok(a.iter().zip(b.iter()).map(|(x, y)| x * y).collect())
+        ctx.output_buffer(size, |out| {
+            simd::mul(ctx.simd_level, a, b, out);
+            Ok(())
+        })
     }
 
-    fn scalar_multiply(
+    fn binary_op(
+        ctx: &Self::Context,
+        op: BinaryOp,
+        a: BufferView<'_, Self::Buffer>,
+        b: BufferView<'_, Self::Buffer>,
+        shape: &[usize]
+    ) -> Result<Self::Buffer> {
+        ctx.output_buffer(shape.iter().product(), |out| binary_op_into_slice(ctx, op, a, b, shape, out))
+    }
+
+    fn binary_op_into(
+        ctx: &Self::Context,
+        op: BinaryOp,
+        a: BufferView<'_, Self::Buffer>,
+        b: BufferView<'_, Self::Buffer>,
+        shape: &[usize],
+        out: &mut Self::Buffer
+    ) -> Result<()> {
+        let out = out.as_f32_mut()?;
+        check_operand("Output buffer", out, shape.iter().product())?;
+        binary_op_into_slice(ctx, op, a, b, shape, out)
+    }
+
+    fn binary_op_assign(
+        ctx: &Self::Context,
+        op: BinaryOp,
+        target: &mut Self::Buffer,
+        b: BufferView<'_, Self::Buffer>,
+        shape: &[usize]
+    ) -> Result<()> {
+        let size: usize = shape.iter().product();
+        let (target, b_data) = (target.as_f32_mut()?, b.buffer.as_f32()?);
+        check_operand("Target buffer", target, size)?;
+        b.check_bounds(shape, b_data.len())?;
+
+        if b.is_dense(shape) && b_data.len() == size {
+            match op {
+                BinaryOp::Add => simd::add_assign(ctx.simd_level, target, b_data),
+                BinaryOp::Multiply => simd::mul_assign(ctx.simd_level, target, b_data),
+                _ => target.iter_mut().zip(b_data).for_each(|(x, &y)| *x = op.apply(*x, y)),
+            }
+            return Ok(());
+        }
+
+        let mut index = 0;
+        strided::for_each_offset(shape, b.strides, b.offset, |j| {
+            target[index] = op.apply(target[index], b_data[j]);
+            index += 1;
+        });
+        Ok(())
+    }
+
+    fn reduce(
+        ctx: &Self::Context,
+        op: ReduceOp,
+        input: &Self::Buffer,
+        outer: usize,
+        size: usize,
+        inner: usize
+    ) -> Result<Self::Buffer> {
+        let input = input.as_f32()?;
+        if input.len() != outer * size * inner {
+            return Err(FerroFlowError::BufferError("Buffer size mismatch".into()));
+        }
+
+        if op.output_dtype() == DType::I64 {
+            return Ok(reduce::arg_reduce(op, input, outer, size, inner).into());
+        }
+        ctx.output_buffer(outer * inner, |out| {
+            reduce::reduce(op, input, outer, size, inner, out);
+            Ok(())
+        })
+    }
+
+    fn softmax(
+        ctx: &Self::Context,
+        op: SoftmaxOp,
+        input: &Self::Buffer,
+        outer: usize,
+        size: usize,
+        inner: usize
+    ) -> Result<Self::Buffer> {
+        let input = input.as_f32()?;
+        if input.len() != outer * size * inner {
+            return Err(FerroFlowError::BufferError("Buffer size mismatch".into()));
+        }
+        ctx.output_buffer(op.output_len(input.len(), outer, inner), |out| {
+            softmax::softmax(op, input, outer, size, inner, out);
+            Ok(())
+        })
+    }
+
+    fn conv2d(ctx: &Self::Context, input: &Self::Buffer, weight: &Self::Buffer, params: &Conv2dParams) -> Result<Self::Buffer> {
+        let (input, weight) = (input.as_f32()?, weight.as_f32()?);
+        ctx.output_buffer(params.output_len(), |out| conv::conv2d(params, input, weight, out))
+    }
+
+    fn conv2d_backward_input(
+        ctx: &Self::Context,
+        grad_output: &Self::Buffer,
+        weight: &Self::Buffer,
+        params: &Conv2dParams
+    ) -> Result<Self::Buffer> {
+        let (grad_output, weight) = (grad_output.as_f32()?, weight.as_f32()?);
+        ctx.output_buffer(params.input_len(), |out| conv::conv2d_backward_input(params, grad_output, weight, out))
+    }
+
+    fn conv2d_backward_weight(
+        ctx: &Self::Context,
+        input: &Self::Buffer,
+        grad_output: &Self::Buffer,
+        params: &Conv2dParams
+    ) -> Result<Self::Buffer> {
+        let (input, grad_output) = (input.as_f32()?, grad_output.as_f32()?);
+        ctx.output_buffer(params.weight_len(), |out| conv::conv2d_backward_weight(params, input, grad_output, out))
+    }
+
+    fn max_pool2d(ctx: &Self::Context, input: &Self::Buffer, params: &Pool2dParams) -> Result<(Self::Buffer, Self::Buffer)> {
+        let input = input.as_f32()?;
+        let mut indices = Vec::new();
+        let values = ctx.output_buffer(params.output_len(), |out| {
+            indices = pool::max_pool2d(params, input, out)?;
+            Ok(())
+        })?;
+        Ok((values, indices.into()))
+    }
+
+    fn max_pool2d_backward(
+        ctx: &Self::Context,
+        grad_output: &Self::Buffer,
+        indices: &Self::Buffer,
+        params: &Pool2dParams
+    ) -> Result<Self::Buffer> {
+        let (grad_output, indices) = (grad_output.as_f32()?, indices.as_i64()?);
+        ctx.output_buffer(params.input_len(), |out| pool::max_pool2d_backward(params, grad_output, indices, out))
+    }
+
+    fn avg_pool2d(ctx: &Self::Context, input: &Self::Buffer, params: &Pool2dParams, count_include_pad: bool) -> Result<Self::Buffer> {
+        let input = input.as_f32()?;
+        ctx.output_buffer(params.output_len(), |out| pool::avg_pool2d(params, input, count_include_pad, out))
+    }
+
+    fn avg_pool2d_backward(
+        ctx: &Self::Context,
+        grad_output: &Self::Buffer,
+        params: &Pool2dParams,
+        count_include_pad: bool
+    ) -> Result<Self::Buffer> {
+        let grad_output = grad_output.as_f32()?;
+        ctx.output_buffer(params.input_len(), |out| pool::avg_pool2d_backward(params, grad_output, count_include_pad, out))
+    }
+
+    fn normalize(
+        ctx: &Self::Context,
+        op: NormOp,
+        input: &Self::Buffer,
+        rows: usize,
+        size: usize,
+        eps: f32
+    ) -> Result<(Self::Buffer, Self::Buffer)> {
+        let input = input.as_f32()?;
+        let mut rstd = ctx.alloc_f32(rows);
+        match ctx.output_buffer(rows * size, |out| norm::normalize(op, input, size, eps, out, &mut rstd)) {
+            Ok(output) => Ok((output, rstd.into())),
+            Err(e) => {
+                ctx.release_f32(rstd);
+                Err(e)
+            }
+        }
+    }
+
+    fn normalize_backward(
+        ctx: &Self::Context,
+        op: NormOp,
+        normalized: &Self::Buffer,
+        rstd: &Self::Buffer,
+        grad: &Self::Buffer,
+        size: usize
+    ) -> Result<Self::Buffer> {
+        let (normalized, rstd, grad) = (normalized.as_f32()?, rstd.as_f32()?, grad.as_f32()?);
+        ctx.output_buffer(normalized.len(), |out| norm::normalize_backward(op, normalized, rstd, grad, size, out))
+    }
+
+    fn class_loss(
+        ctx: &Self::Context,
+        input: &Self::Buffer,
+        targets: &Self::Buffer,
+        params: &ClassLossParams
+    ) -> Result<(Self::Buffer, usize)> {
+        let (input, targets) = (input.as_f32()?, targets.as_i64()?);
+        let mut counted = 0;
+        let losses = ctx.output_buffer(params.rows, |out| {
+            counted = loss::class_loss(params, input, targets, out)?;
+            Ok(())
+        })?;
+        Ok((losses, counted))
+    }
+
+    fn class_loss_backward(
+        ctx: &Self::Context,
+        input: &Self::Buffer,
+        targets: &Self::Buffer,
+        grad: &Self::Buffer,
+        params: &ClassLossParams
+    ) -> Result<Self::Buffer> {
+        let (input, targets, grad) = (input.as_f32()?, targets.as_i64()?, grad.as_f32()?);
+        ctx.output_buffer(params.rows * params.classes, |out| loss::class_loss_backward(params, input, targets, grad, out))
+    }
+
+    fn pointwise_loss(
+        ctx: &Self::Context,
+        op: PointwiseLossOp,
+        input: &Self::Buffer,
+        target: &Self::Buffer,
+        size: usize
+    ) -> Result<Self::Buffer> {
+        let (input, target) = (input.as_f32()?, target.as_f32()?);
+        ctx.output_buffer(size, |out| loss::pointwise_loss(op, input, target, out))
+    }
+
+    fn pointwise_loss_backward(
+        ctx: &Self::Context,
+        op: PointwiseLossOp,
+        input: &Self::Buffer,
+        target: &Self::Buffer,
+        grad: &Self::Buffer,
+        size: usize
+    ) -> Result<Self::Buffer> {
+        let (input, target, grad) = (input.as_f32()?, target.as_f32()?, grad.as_f32()?);
+        ctx.output_buffer(size, |out| loss::pointwise_loss_backward(op, input, target, grad, out))
+    }
+
+    fn optimizer_step(
         _ctx: &Self::Context,
+        update: &OptimizerUpdate,
+        param: &mut Self::Buffer,
+        grad: &Self::Buffer,
+        state: &mut [&mut Self::Buffer],
+        size: usize
+    ) -> Result<()> {
+        let (param, grad) = (param.as_f32_mut()?, grad.as_f32()?);
+        check_operand("Parameter", param, size)?;
+        check_operand("Gradient", grad, size)?;
+        if state.len() != update.state_len() {
+            return Err(FerroFlowError::BufferError(format!(
+                "{:?} needs {} state buffers, got {}", update, update.state_len(), state.len()
+            )));
+        }
+        let mut state = state.iter_mut().map(|buffer| buffer.as_f32_mut()).collect::<Result<Vec<_>>>()?;
+        for buffer in &state {
+            check_operand("Optimizer state", buffer, size)?;
+        }
+        optim::step(update, param, grad, &mut state);
+        Ok(())
+    }
+
+    fn unary_op(ctx: &Self::Context, op: UnaryOp, input: &Self::Buffer, size: usize) -> Result<Self::Buffer> {
+        let input = input.as_f32()?;
+        if input.len() != size {
+            return Err(FerroFlowError::BufferError("Buffer size mismatch".into()));
+        }
+        ctx.output_buffer(size, |out| {
+            for (o, &x) in out.iter_mut().zip(input) {
+                *o = op.apply(x);
+            }
+            Ok(())
+        })
+    }
+
+    fn unary_op_backward(
+        ctx: &Self::Context,
+        op: UnaryOp,
+        input: &Self::Buffer,
+        grad: &Self::Buffer,
+        size: usize
+    ) -> Result<Self::Buffer> {
+        let (input, grad) = (input.as_f32()?, grad.as_f32()?);
+        if input.len() != size || grad.len() != size {
+            return Err(FerroFlowError::BufferError("Buffer size mismatch".into()));
+        }
+        ctx.output_buffer(size, |out| {
+            for ((o, &x), &g) in out.iter_mut().zip(input).zip(grad) {
+                *o = g * op.derivative(x);
+            }
+            Ok(())
+        })
+    }
+
+    fn scalar_multiply(
+        ctx: &Self::Context,
         input: &Self::Buffer,
         scalar: f32,
         size: usize
     ) -> Result<Self::Buffer> {
+        let input = input.as_f32()?;
         if input.len() != size {
             return Err(FerroFlowError::BufferError("Buffer size mismatch".into()));
         }
         
-        Ok(input.iter().map(|x| x * scalar).collect())
+        ctx.output_buffer(size, |out| {
+            simd::scale(ctx.simd_level, input, scalar, out);
+            Ok(())
+        })
+    }
+
+    fn scalar_multiply_into(
+        ctx: &Self::Context,
+        input: &Self::Buffer,
+        scalar: f32,
+        size: usize,
+        out: &mut Self::Buffer
+    ) -> Result<()> {
+        let (input, out) = (input.as_f32()?, out.as_f32_mut()?);
+        check_operand("Input buffer", input, size)?;
+        check_operand("Output buffer", out, size)?;
+        simd::scale(ctx.simd_level, input, scalar, out);
+        Ok(())
+    }
+
+    fn scalar_multiply_assign(ctx: &Self::Context, target: &mut Self::Buffer, scalar: f32, size: usize) -> Result<()> {
+        let target = target.as_f32_mut()?;
+        check_operand("Target buffer", target, size)?;
+        simd::scale_assign(ctx.simd_level, target, scalar);
+        Ok(())
     }
 
     fn synchronize(_ctx: &Self::Context) -> Result<()> {
         Ok(())
     }
 
-    fn matmul(
-        _ctx: &Self::Context,
+    #[allow(clippy::too_many_arguments)]
+    fn matmul_transposed(
+        ctx: &Self::Context,
         a: &Self::Buffer,
         b: &Self::Buffer,
         m: usize,
         n: usize,
-        k: usize
+        k: usize,
+        transpose_a: bool,
+        transpose_b: bool
     ) -> Result<Self::Buffer> {
-        let mut c = vec![0.0; m * n];
-        
-        // Basic matrix multiplication
-        for i in 0..m {
-            for j in 0..n {
-                let mut sum = 0.0;
-                for kk in 0..k {
-                    sum += a[i * k + kk] * b[kk * n + j];
-                }
-                c[i * n + j] = sum;
+        let (a, b) = (a.as_f32()?, b.as_f32()?);
+        check_matmul_operands(a, b, 1, m, n, k)?;
+
+        ctx.output_buffer(m * n, |c| {
+            gemm::gemm(a, b, c, m, n, k, transpose_a, transpose_b);
+            Ok(())
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn matmul_transposed_batched(
+        ctx: &Self::Context,
+        a: &Self::Buffer,
+        b: &Self::Buffer,
+        batch_size: usize,
+        m: usize,
+        n: usize,
+        k: usize,
+        transpose_a: bool,
+        transpose_b: bool
+    ) -> Result<Self::Buffer> {
+        let (a, b) = (a.as_f32()?, b.as_f32()?);
+        check_matmul_operands(a, b, batch_size, m, n, k)?;
+
+        ctx.output_buffer(batch_size * m * n, |c| {
+            for batch in 0..batch_size {
+                gemm::gemm(
+                    &a[batch * m * k..(batch + 1) * m * k],
+                    &b[batch * k * n..(batch + 1) * k * n],
+                    &mut c[batch * m * n..(batch + 1) * m * n],
+                    m,
+                    n,
+                    k,
+                    transpose_a,
+                    transpose_b,
+                );
             }
-        }
-        
-        Ok(c)
+            Ok(())
+        })
     }
 
-    fn matmul_batched(
+    #[allow(clippy::too_many_arguments)]
+    fn matmul_into(
         _ctx: &Self::Context,
         a: &Self::Buffer,
         b: &Self::Buffer,
+        out: &mut Self::Buffer,
         batch_size: usize,
         m: usize,
         n: usize,
-        k: usize
-    ) -> Result<Self::Buffer> {
-        let mut c = vec![0.0; batch_size * m * n];
-        
+        k: usize,
+        transpose_a: bool,
+        transpose_b: bool
+    ) -> Result<()> {
+        let (a, b, c) = (a.as_f32()?, b.as_f32()?, out.as_f32_mut()?);
+        check_matmul_operands(a, b, batch_size, m, n, k)?;
+        check_operand("Output buffer", c, batch_size * m * n)?;
+
         for batch in 0..batch_size {
-            let batch_offset_a = batch * m * k;
-            let batch_offset_b = batch * k * n;
-            let batch_offset_c = batch * m * n;
-            
-            for i in 0..m {
-                for j in 0..n {
-                    let mut sum = 0.0;
-                    for kk in 0..k {
-                        sum += a[batch_offset_a + i * k + kk] * b[batch_offset_b + kk * n + j];
-                    }
-                    c[batch_offset_c + i * n + j] = sum;
+            gemm::gemm(
+                &a[batch * m * k..(batch + 1) * m * k],
+                &b[batch * k * n..(batch + 1) * k * n],
+                &mut c[batch * m * n..(batch + 1) * m * n],
+                m,
+                n,
+                k,
+                transpose_a,
+                transpose_b,
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Evaluates `binary_op` into `out`, which holds `shape`'s element count
+fn binary_op_into_slice(
+    ctx: &CPUContext,
+    op: BinaryOp,
+    a: BufferView<'_, CPUBuffer>,
+    b: BufferView<'_, CPUBuffer>,
+    shape: &[usize],
+    out: &mut [f32]
+) -> Result<()> {
+    let size = out.len();
+    let (a_data, b_data) = (a.buffer.as_f32()?, b.buffer.as_f32()?);
+    a.check_bounds(shape, a_data.len())?;
+    b.check_bounds(shape, b_data.len())?;
+
+    if a.is_dense(shape) && b.is_dense(shape) && a_data.len() == size && b_data.len() == size {
+        match op {
+            BinaryOp::Add => simd::add(ctx.simd_level, a_data, b_data, out),
+            BinaryOp::Multiply => simd::mul(ctx.simd_level, a_data, b_data, out),
+            _ => {
+                for ((o, &x), &y) in out.iter_mut().zip(a_data).zip(b_data) {
+                    *o = op.apply(x, y);
                 }
             }
         }
-        
-        Ok(c)
+        return Ok(());
+    }
+
+    // Broadcast operands are read in place through their (possibly zero) strides
+    let mut index = 0;
+    strided::for_each_offset_pair(shape, (a.strides, a.offset), (b.strides, b.offset), |i, j| {
+        out[index] = op.apply(a_data[i], b_data[j]);
+        index += 1;
+    });
+    Ok(())
+}
+
+/// Checks that a buffer handed to the backend holds the element type the caller expects
+fn check_dtype(buffer: &CPUBuffer, dtype: DType) -> Result<()> {
+    if buffer.dtype() != dtype {
+        return Err(FerroFlowError::DTypeMismatch(format!(
+            "expected a {} buffer, got {}", dtype, buffer.dtype()
+        )));
     }
-} 
\ No newline at end of file
+    Ok(())
+}
+
+/// Validates that `a` and `b` hold exactly `batch_size` (M x K) and (K x N) matrices.
+/// Transposition does not change the element count, so it does not affect the check.
+fn check_matmul_operands(a: &[f32], b: &[f32], batch_size: usize, m: usize, n: usize, k: usize) -> Result<()> {
+    if a.len() != batch_size * m * k {
+        return Err(FerroFlowError::ShapeMismatch(format!(
+            "Left matmul operand has {} elements, expected {} ({} x {} x {})",
+            a.len(), batch_size * m * k, batch_size, m, k
+        )));
+    }
+    if b.len() != batch_size * k * n {
+        return Err(FerroFlowError::ShapeMismatch(format!(
+            "Right matmul operand has {} elements, expected {} ({} x {} x {})",
+            b.len(), batch_size * k * n, batch_size, k, n
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,241 @@
+use crate::compute::strided;
+use crate::dtype::{self, DType, Element};
+use crate::error::{Result, FerroFlowError};
+use crate::io::Storage;
+use half::{bf16, f16};
+use std::fmt;
+use std::ops::Range;
+use std::sync::Arc;
+
+/// Host memory backing a CPU tensor, tagged with its element type.
+///
+/// The f32 buffers a `CPUContext` returns come from its buffer cache. Hand them back with
+/// `release_buffer` rather than taking the vector out, or `memory_stats` keeps counting them as live.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CPUBuffer {
+    F32(Vec<f32>),
+    F64(Vec<f64>),
+    F16(Vec<f16>),
+    BF16(Vec<bf16>),
+    I32(Vec<i32>),
+    I64(Vec<i64>),
+    U8(Vec<u8>),
+    Bool(Vec<bool>),
+    /// Elements read in place from a loaded file, copied into an owned buffer on first write
+    Mapped(MappedBuffer),
+}
+
+/// Native-endian elements borrowed from the bytes of a file shared by the tensors loaded from it
+#[derive(Clone)]
+pub struct MappedBuffer {
+    storage: Arc<Storage>,
+    range: Range<usize>,
+    dtype: DType,
+}
+
+/// Evaluates `$body` with `$v` bound to the typed vector inside the buffer
+macro_rules! dispatch {
+    ($buffer:expr, $v:ident => $body:expr) => {
+        match $buffer {
+            CPUBuffer::F32($v) => $body,
+            CPUBuffer::F64($v) => $body,
+            CPUBuffer::F16($v) => $body,
+            CPUBuffer::BF16($v) => $body,
+            CPUBuffer::I32($v) => $body,
+            CPUBuffer::I64($v) => $body,
+            CPUBuffer::U8($v) => $body,
+            CPUBuffer::Bool($v) => $body,
+            CPUBuffer::Mapped(mapped) => dtype::with_dtype!(mapped.dtype, T => {
+                let $v = mapped.elements::<T>();
+                $body
+            }),
+        }
+    };
+}
+
+macro_rules! impl_from_vec {
+    ($($ty:ty => $variant:ident),*) => {
+        $(impl From<Vec<$ty>> for CPUBuffer {
+            fn from(values: Vec<$ty>) -> Self {
+                CPUBuffer::$variant(values)
+            }
+        })*
+    };
+}
+
+impl_from_vec!(f32 => F32, f64 => F64, f16 => F16, bf16 => BF16, i32 => I32, i64 => I64, u8 => U8, bool => Bool);
+
+impl CPUBuffer {
+    /// Allocates `len` zeroed elements of `dtype`
+    pub fn zeros(dtype: DType, len: usize) -> Self {
+        dtype::with_dtype!(dtype, T => Self::from(vec![T::default(); len]))
+    }
+
+    /// Decodes native-endian bytes holding elements of `dtype`
+    pub fn from_bytes(dtype: DType, bytes: &[u8]) -> Result<Self> {
+        if !bytes.len().is_multiple_of(dtype.size_in_bytes()) {
+            return Err(FerroFlowError::BufferError(format!(
+                "{} bytes do not hold a whole number of {} elements", bytes.len(), dtype
+            )));
+        }
+        Ok(dtype::with_dtype!(dtype, T => Self::from(dtype::from_bytes::<T>(bytes))))
+    }
+
+    /// Returns the native-endian bytes of every element
+    pub fn to_bytes(&self) -> Vec<u8> {
+        dispatch!(self, v => dtype::to_bytes(v))
+    }
+
+    pub fn dtype(&self) -> DType {
+        dispatch!(self, v => element_dtype(v))
+    }
+
+    /// Returns the number of elements
+    pub fn len(&self) -> usize {
+        dispatch!(self, v => v.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Borrows the elements of an `f32` buffer, which is what the compute kernels operate on
+    pub fn as_f32(&self) -> Result<&[f32]> {
+        match self {
+            CPUBuffer::F32(values) => Ok(values),
+            CPUBuffer::Mapped(mapped) if mapped.dtype == DType::F32 => Ok(mapped.elements()),
+            other => Err(FerroFlowError::DTypeMismatch(format!(
+                "expected an f32 buffer, got {}", other.dtype()
+            ))),
+        }
+    }
+
+    /// Mutably borrows the elements of an `f32` buffer, for kernels that update in place.
+    /// A mapped buffer is copied into an owned one first.
+    pub fn as_f32_mut(&mut self) -> Result<&mut [f32]> {
+        self.make_owned();
+        match self {
+            CPUBuffer::F32(values) => Ok(values),
+            other => Err(FerroFlowError::DTypeMismatch(format!(
+                "expected an f32 buffer, got {}", other.dtype()
+            ))),
+        }
+    }
+
+    /// Borrows the elements of an `i64` buffer, such as the indices produced by arg reductions
+    pub fn as_i64(&self) -> Result<&[i64]> {
+        match self {
+            CPUBuffer::I64(values) => Ok(values),
+            CPUBuffer::Mapped(mapped) if mapped.dtype == DType::I64 => Ok(mapped.elements()),
+            other => Err(FerroFlowError::DTypeMismatch(format!(
+                "expected an i64 buffer, got {}", other.dtype()
+            ))),
+        }
+    }
+
+    /// Converts every element to `dtype`
+    pub fn cast(&self, dtype: DType) -> Self {
+        if self.dtype() == dtype {
+            return self.clone();
+        }
+        dispatch!(self, v => dtype::with_dtype!(dtype, T => Self::from(dtype::convert::<_, T>(v))))
+    }
+
+    /// Copies a strided view into a new contiguous buffer of the same type
+    pub(crate) fn gather(&self, shape: &[usize], strides: &[usize], offset: usize) -> Self {
+        dispatch!(self, v => Self::from(strided::gather(v, shape, strides, offset)))
+    }
+
+    /// Copies a strided view into `out`, a contiguous buffer of the same type and the view's size
+    pub(crate) fn gather_into(&self, shape: &[usize], strides: &[usize], offset: usize, out: &mut Self) -> Result<()> {
+        if let CPUBuffer::Mapped(mapped) = self {
+            return CPUBuffer::from(mapped).gather_into(shape, strides, offset, out);
+        }
+        out.make_owned();
+        macro_rules! gather_same_type {
+            ($($variant:ident),*) => {
+                match (self, out) {
+                    $((CPUBuffer::$variant(src), CPUBuffer::$variant(dst)) => {
+                        strided::gather_into(src, shape, strides, offset, dst);
+                        Ok(())
+                    })*
+                    (src, dst) => Err(FerroFlowError::DTypeMismatch(format!(
+                        "Cannot copy a {} buffer into a {} buffer", src.dtype(), dst.dtype()
+                    ))),
+                }
+            };
+        }
+        gather_same_type!(F32, F64, F16, BF16, I32, I64, U8, Bool)
+    }
+
+    /// Replaces a mapped buffer by an owned copy of its elements, so it can be written
+    fn make_owned(&mut self) {
+        if let CPUBuffer::Mapped(mapped) = self {
+            *self = CPUBuffer::from(&*mapped);
+        }
+    }
+}
+
+impl From<&MappedBuffer> for CPUBuffer {
+    /// Copies the elements of a mapped buffer
+    fn from(mapped: &MappedBuffer) -> Self {
+        dtype::with_dtype!(mapped.dtype, T => Self::from(mapped.elements::<T>().to_vec()))
+    }
+}
+
+impl MappedBuffer {
+    /// Borrows the `dtype` elements in `range` of `storage`. Returns `None` when they can't be
+    /// read in place: booleans, whose bytes may not be 0 or 1, and data that isn't aligned for
+    /// `dtype` or doesn't hold a whole number of elements.
+    pub(crate) fn new(storage: Arc<Storage>, range: Range<usize>, dtype: DType) -> Option<Self> {
+        let align = dtype::with_dtype!(dtype, T => std::mem::align_of::<T>());
+        let bytes = storage.get(range.clone())?;
+        let readable = dtype != DType::Bool
+            && (bytes.as_ptr() as usize).is_multiple_of(align)
+            && bytes.len().is_multiple_of(dtype.size_in_bytes());
+        readable.then_some(Self { storage, range, dtype })
+    }
+
+    pub fn dtype(&self) -> DType {
+        self.dtype
+    }
+
+    /// Returns the number of elements
+    pub fn len(&self) -> usize {
+        self.range.len() / self.dtype.size_in_bytes()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.range.is_empty()
+    }
+
+    /// Returns the native-endian bytes of the elements
+    pub(crate) fn bytes(&self) -> &[u8] {
+        &self.storage[self.range.clone()]
+    }
+
+    fn elements<T: Element>(&self) -> &[T] {
+        assert_eq!(T::DTYPE, self.dtype, "mapped elements read as the wrong type");
+        let bytes = self.bytes();
+        // Safety: `new` checked that the bytes are aligned for `T` and hold whole elements, every
+        // bit pattern is a valid non-boolean element, and the storage is immutable while borrowed
+        // (for a mapped file, by the contract of `Storage::map`)
+        unsafe { std::slice::from_raw_parts(bytes.as_ptr().cast(), self.len()) }
+    }
+}
+
+impl fmt::Debug for MappedBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MappedBuffer").field("dtype", &self.dtype).field("len", &self.len()).finish()
+    }
+}
+
+impl PartialEq for MappedBuffer {
+    fn eq(&self, other: &Self) -> bool {
+        self.dtype == other.dtype && self.bytes() == other.bytes()
+    }
+}
+
+fn element_dtype<T: Element>(_: &[T]) -> DType {
+    T::DTYPE
+}
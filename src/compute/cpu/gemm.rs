@@ -0,0 +1,226 @@
+//! Cache-blocked single-precision GEMM used by `CPUBackend`.
+//!
+//! Follows the usual BLIS-style loop nest: C is produced in NC-wide column blocks, the shared
+//! dimension is split into KC-deep slices whose B panel is packed once and reused by every
+//! MC-row block of A, and an MR x NR register micro-kernel computes the inner products.
+//! Transposition is folded into the packing step, so every variant runs the same kernel.
+//! Large problems pack each B block once on the calling thread, then split the rows of C across
+//! scoped threads that share the packed block and pack their own rows of A. All packing buffers
+//! are sized to the problem and kept by the calling thread, so batched matmul and grouped
+//! convolution reuse them across their many small products.
+
+use std::cell::RefCell;
+
+const MR: usize = 8;
+const NR: usize = 8;
+const MC: usize = 128;
+const KC: usize = 256;
+const NC: usize = 2048;
+
+/// Below this many multiply-adds the cost of spawning threads outweighs the speedup
+const PARALLEL_THRESHOLD: usize = 1 << 18;
+
+thread_local! {
+    /// Packed A blocks, one per worker, and the shared packed B block, grown on demand and
+    /// reused by every GEMM called from this thread
+    static PACKING: RefCell<(Vec<Vec<f32>>, Vec<f32>)> = const { RefCell::new((Vec::new(), Vec::new())) };
+}
+
+/// Read-only view of a row-major matrix, possibly transposed via its strides
+#[derive(Clone, Copy)]
+struct MatrixRef<'a> {
+    data: &'a [f32],
+    row_stride: usize,
+    col_stride: usize,
+}
+
+impl MatrixRef<'_> {
+    #[inline(always)]
+    fn get(&self, row: usize, col: usize) -> f32 {
+        self.data[row * self.row_stride + col * self.col_stride]
+    }
+}
+
+/// The packed (kc x nc) block of op(B) starting at row `pc` and column `jc`
+#[derive(Clone, Copy)]
+struct PackedB<'a> {
+    panels: &'a [f32],
+    pc: usize,
+    kc: usize,
+    jc: usize,
+    nc: usize,
+}
+
+/// Computes C = op(A) * op(B) where op(A) is (M x K) and op(B) is (K x N), overwriting `c`.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn gemm(
+    a: &[f32],
+    b: &[f32],
+    c: &mut [f32],
+    m: usize,
+    n: usize,
+    k: usize,
+    transpose_a: bool,
+    transpose_b: bool,
+) {
+    let threads = thread_count(m, n, k);
+    gemm_with_threads(a, b, c, m, n, k, transpose_a, transpose_b, threads);
+}
+
+/// Same as [`gemm`] but with an explicit thread count, so tests can exercise the parallel
+/// path regardless of the machine they run on.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn gemm_with_threads(
+    a: &[f32],
+    b: &[f32],
+    c: &mut [f32],
+    m: usize,
+    n: usize,
+    k: usize,
+    transpose_a: bool,
+    transpose_b: bool,
+    threads: usize,
+) {
+    c.fill(0.0);
+    if m == 0 || n == 0 || k == 0 {
+        return;
+    }
+
+    let a = MatrixRef {
+        data: a,
+        row_stride: if transpose_a { 1 } else { k },
+        col_stride: if transpose_a { m } else { 1 },
+    };
+    let b = MatrixRef {
+        data: b,
+        row_stride: if transpose_b { 1 } else { n },
+        col_stride: if transpose_b { k } else { 1 },
+    };
+
+    // Keep each worker's share a multiple of MR so no micro-tile straddles two workers
+    let rows_per_worker = if threads <= 1 { m } else { m.div_ceil(threads).next_multiple_of(MR) };
+    let workers = m.div_ceil(rows_per_worker);
+    // Packed panels are always whole MR rows / NR columns wide, so round the block sizes up
+    let a_len = MC.min(rows_per_worker).next_multiple_of(MR) * KC.min(k);
+    let b_len = NC.min(n).next_multiple_of(NR) * KC.min(k);
+    PACKING.with_borrow_mut(|(packed_a, packed_b)| {
+        if packed_a.len() < workers {
+            packed_a.resize_with(workers, Vec::new);
+        }
+        for buffer in &mut packed_a[..workers] {
+            if buffer.len() < a_len {
+                buffer.resize(a_len, 0.0);
+            }
+        }
+        if packed_b.len() < b_len {
+            packed_b.resize(b_len, 0.0);
+        }
+
+        for jc in (0..n).step_by(NC) {
+            let nc = NC.min(n - jc);
+            for pc in (0..k).step_by(KC) {
+                let kc = KC.min(k - pc);
+                pack_b(&b, packed_b, pc, kc, jc, nc);
+                let block = PackedB { panels: packed_b, pc, kc, jc, nc };
+
+                if workers == 1 {
+                    multiply_block(a, block, c, 0, n, &mut packed_a[0]);
+                    continue;
+                }
+                std::thread::scope(|scope| {
+                    let c_rows = c.chunks_mut(rows_per_worker * n);
+                    for (t, (c_rows, packed_a)) in c_rows.zip(packed_a.iter_mut()).enumerate() {
+                        scope.spawn(move || multiply_block(a, block, c_rows, t * rows_per_worker, n, packed_a));
+                    }
+                });
+            }
+        }
+    });
+}
+
+fn thread_count(m: usize, n: usize, k: usize) -> usize {
+    if m * n * k < PARALLEL_THRESHOLD {
+        return 1;
+    }
+    let available = std::thread::available_parallelism()
+        .map(|threads| threads.get())
+        .unwrap_or(1);
+    available.min(m.div_ceil(MR)).max(1)
+}
+
+/// Accumulates the product of rows `row_start..` of op(A) with the packed block of B into
+/// `c`, which holds those rows of C (N wide), packing A into `packed_a` one MC block at a time.
+fn multiply_block(a: MatrixRef, b: PackedB, c: &mut [f32], row_start: usize, n: usize, packed_a: &mut [f32]) {
+    let rows = c.len() / n;
+    for ic in (0..rows).step_by(MC) {
+        let mc = MC.min(rows - ic);
+        pack_a(&a, packed_a, row_start + ic, mc, b.pc, b.kc);
+
+        for jr in (0..b.nc).step_by(NR) {
+            let nr = NR.min(b.nc - jr);
+            let b_panel = &b.panels[jr * b.kc..(jr + NR) * b.kc];
+
+            for ir in (0..mc).step_by(MR) {
+                let mr = MR.min(mc - ir);
+                let a_panel = &packed_a[ir * b.kc..(ir + MR) * b.kc];
+                let acc = micro_kernel(a_panel, b_panel);
+
+                for (i, acc_row) in acc.iter().enumerate().take(mr) {
+                    let offset = (ic + ir + i) * n + b.jc + jr;
+                    for (dst, value) in c[offset..offset + nr].iter_mut().zip(acc_row) {
+                        *dst += value;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Packs an (mc x kc) block of A into MR-row panels laid out column by column,
+/// zero-padding the last panel so the micro-kernel never needs edge handling.
+fn pack_a(a: &MatrixRef, packed: &mut [f32], row0: usize, mc: usize, col0: usize, kc: usize) {
+    for ir in (0..mc).step_by(MR) {
+        let mr = MR.min(mc - ir);
+        let panel = &mut packed[ir * kc..(ir + MR) * kc];
+        for (p, column) in panel.chunks_exact_mut(MR).enumerate() {
+            for (i, dst) in column.iter_mut().enumerate() {
+                *dst = if i < mr { a.get(row0 + ir + i, col0 + p) } else { 0.0 };
+            }
+        }
+    }
+}
+
+/// Packs a (kc x nc) block of B into NR-column panels laid out row by row,
+/// zero-padding the last panel.
+fn pack_b(b: &MatrixRef, packed: &mut [f32], row0: usize, kc: usize, col0: usize, nc: usize) {
+    for jr in (0..nc).step_by(NR) {
+        let nr = NR.min(nc - jr);
+        let panel = &mut packed[jr * kc..(jr + NR) * kc];
+        for (p, row) in panel.chunks_exact_mut(NR).enumerate() {
+            for (j, dst) in row.iter_mut().enumerate() {
+                *dst = if j < nr { b.get(row0 + p, col0 + jr + j) } else { 0.0 };
+            }
+        }
+    }
+}
+
+/// MR x NR outer-product kernel over packed panels. The fixed-size accumulator stays in
+/// registers and the inner loop vectorizes across NR.
+#[inline(always)]
+fn micro_kernel(a_panel: &[f32], b_panel: &[f32]) -> [[f32; NR]; MR] {
+    let mut acc = [[0.0f32; NR]; MR];
+    for (a_col, b_row) in a_panel.chunks_exact(MR).zip(b_panel.chunks_exact(NR)) {
+        for (acc_row, &a_value) in acc.iter_mut().zip(a_col) {
+            for (acc_value, &b_value) in acc_row.iter_mut().zip(b_row) {
+                *acc_value += a_value * b_value;
+            }
+        }
+    }
+    acc
+}
+
+/// Lengths of this thread's packing buffers, so tests can check they fit the problem size
+#[cfg(test)]
+pub(crate) fn packing_lens() -> (Vec<usize>, usize) {
+    PACKING.with_borrow(|(packed_a, packed_b)| (packed_a.iter().map(Vec::len).collect(), packed_b.len()))
+}
@@ -0,0 +1,292 @@
+//! Vectorized element-wise kernels for `CPUBackend`.
+//!
+//! The instruction set is chosen at runtime (see [`SimdLevel::detect`]) and stored on the
+//! `CPUContext`, so a context can also be pinned to a specific path to test it against the
+//! portable scalar code on the same machine.
+
+#[cfg(target_arch = "aarch64")]
+use std::arch::aarch64::*;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::*;
+
+/// Instruction set used by the CPU element-wise kernels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimdLevel {
+    /// Portable scalar code, available everywhere
+    Scalar,
+    /// 256-bit AVX2 (x86_64)
+    Avx2,
+    /// 512-bit AVX-512F (x86_64)
+    Avx512,
+    /// 128-bit NEON (aarch64)
+    Neon,
+}
+
+impl SimdLevel {
+    /// Returns the widest instruction set supported by the running CPU
+    pub fn detect() -> Self {
+        [SimdLevel::Avx512, SimdLevel::Avx2, SimdLevel::Neon]
+            .into_iter()
+            .find(|level| level.is_supported())
+            .unwrap_or(SimdLevel::Scalar)
+    }
+
+    /// Whether the running CPU can execute this code path
+    pub fn is_supported(self) -> bool {
+        match self {
+            SimdLevel::Scalar => true,
+            #[cfg(target_arch = "x86_64")]
+            SimdLevel::Avx2 => is_x86_feature_detected!("avx2"),
+            #[cfg(target_arch = "x86_64")]
+            SimdLevel::Avx512 => is_x86_feature_detected!("avx512f"),
+            #[cfg(target_arch = "aarch64")]
+            SimdLevel::Neon => std::arch::is_aarch64_feature_detected!("neon"),
+            #[allow(unreachable_patterns)]
+            _ => false,
+        }
+    }
+}
+
+/// out[i] = a[i] + b[i]
+pub(crate) fn add(level: SimdLevel, a: &[f32], b: &[f32], out: &mut [f32]) {
+    debug_assert!(a.len() == out.len() && b.len() == out.len());
+    match level {
+        #[cfg(target_arch = "x86_64")]
+        // SAFETY: a context only carries a SIMD level that passed `is_supported`
+        SimdLevel::Avx512 => unsafe { add_avx512(a, b, out) },
+        #[cfg(target_arch = "x86_64")]
+        SimdLevel::Avx2 => unsafe { add_avx2(a, b, out) },
+        #[cfg(target_arch = "aarch64")]
+        SimdLevel::Neon => unsafe { add_neon(a, b, out) },
+        _ => add_scalar(a, b, out),
+    }
+}
+
+/// out[i] = a[i] * b[i]
+pub(crate) fn mul(level: SimdLevel, a: &[f32], b: &[f32], out: &mut [f32]) {
+    debug_assert!(a.len() == out.len() && b.len() == out.len());
+    match level {
+        #[cfg(target_arch = "x86_64")]
+        // SAFETY: a context only carries a SIMD level that passed `is_supported`
+        SimdLevel::Avx512 => unsafe { mul_avx512(a, b, out) },
+        #[cfg(target_arch = "x86_64")]
+        SimdLevel::Avx2 => unsafe { mul_avx2(a, b, out) },
+        #[cfg(target_arch = "aarch64")]
+        SimdLevel::Neon => unsafe { mul_neon(a, b, out) },
+        _ => mul_scalar(a, b, out),
+    }
+}
+
+/// out[i] = input[i] * scalar
+pub(crate) fn scale(level: SimdLevel, input: &[f32], scalar: f32, out: &mut [f32]) {
+    debug_assert_eq!(input.len(), out.len());
+    match level {
+        #[cfg(target_arch = "x86_64")]
+        // SAFETY: a context only carries a SIMD level that passed `is_supported`
+        SimdLevel::Avx512 => unsafe { scale_avx512(input, scalar, out) },
+        #[cfg(target_arch = "x86_64")]
+        SimdLevel::Avx2 => unsafe { scale_avx2(input, scalar, out) },
+        #[cfg(target_arch = "aarch64")]
+        SimdLevel::Neon => unsafe { scale_neon(input, scalar, out) },
+        _ => scale_scalar(input, scalar, out),
+    }
+}
+
+/// target[i] += b[i]
+pub(crate) fn add_assign(level: SimdLevel, target: &mut [f32], b: &[f32]) {
+    debug_assert_eq!(target.len(), b.len());
+    match level {
+        #[cfg(target_arch = "x86_64")]
+        // SAFETY: a context only carries a SIMD level that passed `is_supported`
+        SimdLevel::Avx512 => unsafe { add_assign_avx512(target, b) },
+        #[cfg(target_arch = "x86_64")]
+        SimdLevel::Avx2 => unsafe { add_assign_avx2(target, b) },
+        #[cfg(target_arch = "aarch64")]
+        SimdLevel::Neon => unsafe { add_assign_neon(target, b) },
+        _ => add_assign_scalar(target, b),
+    }
+}
+
+/// target[i] *= b[i]
+pub(crate) fn mul_assign(level: SimdLevel, target: &mut [f32], b: &[f32]) {
+    debug_assert_eq!(target.len(), b.len());
+    match level {
+        #[cfg(target_arch = "x86_64")]
+        // SAFETY: a context only carries a SIMD level that passed `is_supported`
+        SimdLevel::Avx512 => unsafe { mul_assign_avx512(target, b) },
+        #[cfg(target_arch = "x86_64")]
+        SimdLevel::Avx2 => unsafe { mul_assign_avx2(target, b) },
+        #[cfg(target_arch = "aarch64")]
+        SimdLevel::Neon => unsafe { mul_assign_neon(target, b) },
+        _ => mul_assign_scalar(target, b),
+    }
+}
+
+/// target[i] *= scalar
+pub(crate) fn scale_assign(level: SimdLevel, target: &mut [f32], scalar: f32) {
+    match level {
+        #[cfg(target_arch = "x86_64")]
+        // SAFETY: a context only carries a SIMD level that passed `is_supported`
+        SimdLevel::Avx512 => unsafe { scale_assign_avx512(target, scalar) },
+        #[cfg(target_arch = "x86_64")]
+        SimdLevel::Avx2 => unsafe { scale_assign_avx2(target, scalar) },
+        #[cfg(target_arch = "aarch64")]
+        SimdLevel::Neon => unsafe { scale_assign_neon(target, scalar) },
+        _ => scale_assign_scalar(target, scalar),
+    }
+}
+
+fn add_scalar(a: &[f32], b: &[f32], out: &mut [f32]) {
+    for ((o, x), y) in out.iter_mut().zip(a).zip(b) {
+        *o = x + y;
+    }
+}
+
+fn mul_scalar(a: &[f32], b: &[f32], out: &mut [f32]) {
+    for ((o, x), y) in out.iter_mut().zip(a).zip(b) {
+        *o = x * y;
+    }
+}
+
+fn scale_scalar(input: &[f32], scalar: f32, out: &mut [f32]) {
+    for (o, x) in out.iter_mut().zip(input) {
+        *o = x * scalar;
+    }
+}
+
+fn add_assign_scalar(target: &mut [f32], b: &[f32]) {
+    for (t, y) in target.iter_mut().zip(b) {
+        *t += y;
+    }
+}
+
+fn mul_assign_scalar(target: &mut [f32], b: &[f32]) {
+    for (t, y) in target.iter_mut().zip(b) {
+        *t *= y;
+    }
+}
+
+fn scale_assign_scalar(target: &mut [f32], scalar: f32) {
+    for t in target {
+        *t *= scalar;
+    }
+}
+
+/// Generates a vectorized binary kernel: full vectors with unaligned loads, then the scalar
+/// kernel for the remainder.
+macro_rules! binary_kernel {
+    ($name:ident, $feature:literal, $lanes:expr, $load:ident, $store:ident, $op:ident, $tail:ident) => {
+        #[target_feature(enable = $feature)]
+        unsafe fn $name(a: &[f32], b: &[f32], out: &mut [f32]) {
+            let len = out.len().min(a.len()).min(b.len());
+            let body = len - len % $lanes;
+            let mut i = 0;
+            while i < body {
+                let x = $load(a.as_ptr().add(i));
+                let y = $load(b.as_ptr().add(i));
+                $store(out.as_mut_ptr().add(i), $op(x, y));
+                i += $lanes;
+            }
+            $tail(&a[body..len], &b[body..len], &mut out[body..len]);
+        }
+    };
+}
+
+/// Generates a vectorized scalar-broadcast kernel
+macro_rules! scale_kernel {
+    ($name:ident, $feature:literal, $lanes:expr, $load:ident, $store:ident, $splat:ident, $op:ident) => {
+        #[target_feature(enable = $feature)]
+        unsafe fn $name(input: &[f32], scalar: f32, out: &mut [f32]) {
+            let len = out.len().min(input.len());
+            let body = len - len % $lanes;
+            let factor = $splat(scalar);
+            let mut i = 0;
+            while i < body {
+                let x = $load(input.as_ptr().add(i));
+                $store(out.as_mut_ptr().add(i), $op(x, factor));
+                i += $lanes;
+            }
+            scale_scalar(&input[body..len], scalar, &mut out[body..len]);
+        }
+    };
+}
+
+/// Generates a vectorized in-place binary kernel, `target[i] = op(target[i], b[i])`
+macro_rules! assign_kernel {
+    ($name:ident, $feature:literal, $lanes:expr, $load:ident, $store:ident, $op:ident, $tail:ident) => {
+        #[target_feature(enable = $feature)]
+        unsafe fn $name(target: &mut [f32], b: &[f32]) {
+            let len = target.len().min(b.len());
+            let body = len - len % $lanes;
+            let mut i = 0;
+            while i < body {
+                let x = $load(target.as_ptr().add(i));
+                let y = $load(b.as_ptr().add(i));
+                $store(target.as_mut_ptr().add(i), $op(x, y));
+                i += $lanes;
+            }
+            $tail(&mut target[body..len], &b[body..len]);
+        }
+    };
+}
+
+/// Generates a vectorized in-place scalar-broadcast kernel
+macro_rules! scale_assign_kernel {
+    ($name:ident, $feature:literal, $lanes:expr, $load:ident, $store:ident, $splat:ident, $op:ident) => {
+        #[target_feature(enable = $feature)]
+        unsafe fn $name(target: &mut [f32], scalar: f32) {
+            let len = target.len();
+            let body = len - len % $lanes;
+            let factor = $splat(scalar);
+            let mut i = 0;
+            while i < body {
+                let x = $load(target.as_ptr().add(i));
+                $store(target.as_mut_ptr().add(i), $op(x, factor));
+                i += $lanes;
+            }
+            scale_assign_scalar(&mut target[body..len], scalar);
+        }
+    };
+}
+
+#[cfg(target_arch = "x86_64")]
+binary_kernel!(add_avx2, "avx2", 8, _mm256_loadu_ps, _mm256_storeu_ps, _mm256_add_ps, add_scalar);
+#[cfg(target_arch = "x86_64")]
+binary_kernel!(mul_avx2, "avx2", 8, _mm256_loadu_ps, _mm256_storeu_ps, _mm256_mul_ps, mul_scalar);
+#[cfg(target_arch = "x86_64")]
+scale_kernel!(scale_avx2, "avx2", 8, _mm256_loadu_ps, _mm256_storeu_ps, _mm256_set1_ps, _mm256_mul_ps);
+
+#[cfg(target_arch = "x86_64")]
+binary_kernel!(add_avx512, "avx512f", 16, _mm512_loadu_ps, _mm512_storeu_ps, _mm512_add_ps, add_scalar);
+#[cfg(target_arch = "x86_64")]
+binary_kernel!(mul_avx512, "avx512f", 16, _mm512_loadu_ps, _mm512_storeu_ps, _mm512_mul_ps, mul_scalar);
+#[cfg(target_arch = "x86_64")]
+scale_kernel!(scale_avx512, "avx512f", 16, _mm512_loadu_ps, _mm512_storeu_ps, _mm512_set1_ps, _mm512_mul_ps);
+
+#[cfg(target_arch = "aarch64")]
+binary_kernel!(add_neon, "neon", 4, vld1q_f32, vst1q_f32, vaddq_f32, add_scalar);
+#[cfg(target_arch = "aarch64")]
+binary_kernel!(mul_neon, "neon", 4, vld1q_f32, vst1q_f32, vmulq_f32, mul_scalar);
+#[cfg(target_arch = "aarch64")]
+scale_kernel!(scale_neon, "neon", 4, vld1q_f32, vst1q_f32, vdupq_n_f32, vmulq_f32);
+
+#[cfg(target_arch = "x86_64")]
+assign_kernel!(add_assign_avx2, "avx2", 8, _mm256_loadu_ps, _mm256_storeu_ps, _mm256_add_ps, add_assign_scalar);
+#[cfg(target_arch = "x86_64")]
+assign_kernel!(mul_assign_avx2, "avx2", 8, _mm256_loadu_ps, _mm256_storeu_ps, _mm256_mul_ps, mul_assign_scalar);
+#[cfg(target_arch = "x86_64")]
+scale_assign_kernel!(scale_assign_avx2, "avx2", 8, _mm256_loadu_ps, _mm256_storeu_ps, _mm256_set1_ps, _mm256_mul_ps);
+
+#[cfg(target_arch = "x86_64")]
+assign_kernel!(add_assign_avx512, "avx512f", 16, _mm512_loadu_ps, _mm512_storeu_ps, _mm512_add_ps, add_assign_scalar);
+#[cfg(target_arch = "x86_64")]
+assign_kernel!(mul_assign_avx512, "avx512f", 16, _mm512_loadu_ps, _mm512_storeu_ps, _mm512_mul_ps, mul_assign_scalar);
+#[cfg(target_arch = "x86_64")]
+scale_assign_kernel!(scale_assign_avx512, "avx512f", 16, _mm512_loadu_ps, _mm512_storeu_ps, _mm512_set1_ps, _mm512_mul_ps);
+
+#[cfg(target_arch = "aarch64")]
+assign_kernel!(add_assign_neon, "neon", 4, vld1q_f32, vst1q_f32, vaddq_f32, add_assign_scalar);
+#[cfg(target_arch = "aarch64")]
+assign_kernel!(mul_assign_neon, "neon", 4, vld1q_f32, vst1q_f32, vmulq_f32, mul_assign_scalar);
+#[cfg(target_arch = "aarch64")]
+scale_assign_kernel!(scale_assign_neon, "neon", 4, vld1q_f32, vst1q_f32, vdupq_n_f32, vmulq_f32);
@@ -0,0 +1,671 @@
+use super::*;
+use crate::autograd::tests::assert_close;
+use crate::compute::{allocator, NormOp, PoolWindow};
+
+/// Straightforward triple-loop GEMM accumulating in f64, used as the oracle for the blocked kernel
+#[allow(clippy::too_many_arguments)]
+fn gemm_reference(a: &[f32], b: &[f32], m: usize, n: usize, k: usize, transpose_a: bool, transpose_b: bool) -> Vec<f32> {
+    let mut c = vec![0.0; m * n];
+    for i in 0..m {
+        for j in 0..n {
+            let mut sum = 0.0f64;
+            for kk in 0..k {
+                let a_idx = if transpose_a { kk * m + i } else { i * k + kk };
+                let b_idx = if transpose_b { j * k + kk } else { kk * n + j };
+                sum += a[a_idx] as f64 * b[b_idx] as f64;
+            }
+            c[i * n + j] = sum as f32;
+        }
+    }
+    c
+}
+
+fn pseudo_random(len: usize, seed: u32) -> Vec<f32> {
+    let mut state = seed.wrapping_mul(2654435761).max(1);
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            (state % 2000) as f32 / 1000.0 - 1.0
+        })
+        .collect()
+}
+
+
+#[test]
+fn test_blocked_gemm_matches_reference() {
+    // Sizes straddle the MR/NR/MC/KC block boundaries
+    for &(m, n, k) in &[(1, 1, 1), (7, 9, 5), (33, 17, 300), (130, 65, 257)] {
+        let a = pseudo_random(m * k, 1);
+        let b = pseudo_random(k * n, 2);
+        for &(transpose_a, transpose_b) in &[(false, false), (true, false), (false, true), (true, true)] {
+            let expected = gemm_reference(&a, &b, m, n, k, transpose_a, transpose_b);
+            for threads in [1, 3] {
+                let mut c = vec![f32::NAN; m * n];
+                gemm::gemm_with_threads(&a, &b, &mut c, m, n, k, transpose_a, transpose_b, threads);
+                assert_close(&c, &expected, 1e-4);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_gemm_packing_buffers_fit_the_problem() {
+    // A fresh thread starts with empty buffers, so the lengths reflect this product alone
+    std::thread::spawn(|| {
+        let mut c = vec![0.0; 4];
+        gemm::gemm(&[1.0, 2.0, 3.0, 4.0], &[5.0, 6.0, 7.0, 8.0], &mut c, 2, 2, 2, false, false);
+        assert_eq!(c, vec![19.0, 22.0, 43.0, 50.0]);
+        assert_eq!(gemm::packing_lens(), (vec![8 * 2], 8 * 2));
+
+        // Later products on the same thread reuse the buffers, growing them only as needed
+        let (m, n, k) = (9, 3, 5);
+        let mut c = vec![0.0; m * n];
+        gemm::gemm(&pseudo_random(m * k, 7), &pseudo_random(k * n, 8), &mut c, m, n, k, false, false);
+        assert_eq!(gemm::packing_lens(), (vec![16 * 5], 8 * 5));
+
+        // Parallel products share one packed B block and keep an A buffer per worker on the
+        // calling thread, so the spawned workers allocate nothing
+        let (m, n, k) = (40, 3, 300);
+        let (a, b) = (pseudo_random(m * k, 9), pseudo_random(k * n, 10));
+        let mut c = vec![0.0; m * n];
+        gemm::gemm_with_threads(&a, &b, &mut c, m, n, k, false, false, 3);
+        assert_eq!(gemm::packing_lens(), (vec![16 * 256; 3], 8 * 256));
+        let mut expected = vec![0.0; m * n];
+        gemm::gemm_with_threads(&a, &b, &mut expected, m, n, k, false, false, 1);
+        assert_eq!(c, expected);
+    })
+    .join()
+    .unwrap();
+}
+
+#[test]
+fn test_batched_transposed_matmul_matches_reference() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let (batch_size, m, n, k) = (3, 19, 23, 41);
+    let a = pseudo_random(batch_size * m * k, 3);
+    let b = pseudo_random(batch_size * k * n, 4);
+
+    let c = CPUBackend::matmul_transposed_batched(&ctx, &a.clone().into(), &b.clone().into(), batch_size, m, n, k, true, false)?;
+    let c = c.as_f32()?;
+
+    for batch in 0..batch_size {
+        let expected = gemm_reference(
+            &a[batch * m * k..(batch + 1) * m * k],
+            &b[batch * k * n..(batch + 1) * k * n],
+            m, n, k, true, false,
+        );
+        assert_close(&c[batch * m * n..(batch + 1) * m * n], &expected, 1e-4);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_simd_levels_match_scalar() -> Result<()> {
+    let scalar_ctx = CPUContext::with_simd_level(SimdLevel::Scalar)?;
+    // Odd length so every path exercises its remainder loop
+    let size = 1037;
+    let a = CPUBuffer::from(pseudo_random(size, 5));
+    let b = CPUBuffer::from(pseudo_random(size, 6));
+
+    let expected_add = CPUBackend::element_wise_add(&scalar_ctx, &a, &b, size)?;
+    let expected_mul = CPUBackend::element_wise_multiply(&scalar_ctx, &a, &b, size)?;
+    let expected_scale = CPUBackend::scalar_multiply(&scalar_ctx, &a, -1.5, size)?;
+    let view = |buffer| BufferView { buffer, strides: &[1], offset: 0 };
+
+    for level in [SimdLevel::Scalar, SimdLevel::Avx2, SimdLevel::Avx512, SimdLevel::Neon] {
+        if !level.is_supported() {
+            assert!(CPUContext::with_simd_level(level).is_err());
+            continue;
+        }
+        let ctx = CPUContext::with_simd_level(level)?;
+        assert_eq!(CPUBackend::element_wise_add(&ctx, &a, &b, size)?, expected_add, "{:?}", level);
+        assert_eq!(CPUBackend::element_wise_multiply(&ctx, &a, &b, size)?, expected_mul, "{:?}", level);
+        assert_eq!(CPUBackend::scalar_multiply(&ctx, &a, -1.5, size)?, expected_scale, "{:?}", level);
+
+        // The in-place and `_into` kernels match the allocating ones
+        let mut out = CPUBuffer::from(vec![0.0f32; size]);
+        CPUBackend::binary_op_into(&ctx, BinaryOp::Add, view(&a), view(&b), &[size], &mut out)?;
+        assert_eq!(out, expected_add, "{:?}", level);
+        CPUBackend::scalar_multiply_into(&ctx, &a, -1.5, size, &mut out)?;
+        assert_eq!(out, expected_scale, "{:?}", level);
+        let mut target = a.clone();
+        CPUBackend::binary_op_assign(&ctx, BinaryOp::Multiply, &mut target, view(&b), &[size])?;
+        assert_eq!(target, expected_mul, "{:?}", level);
+        let mut target = a.clone();
+        CPUBackend::scalar_multiply_assign(&ctx, &mut target, -1.5, size)?;
+        assert_eq!(target, expected_scale, "{:?}", level);
+        assert!(CPUBackend::binary_op_into(&ctx, BinaryOp::Add, view(&a), view(&b), &[size], &mut CPUBuffer::from(vec![0.0f32; 3])).is_err());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_binary_op_rejects_views_past_the_buffer() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let a = CPUBuffer::from(vec![1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0]);
+    let row = CPUBuffer::from(vec![10.0f32, 20.0, 30.0]);
+    fn view<'a>(buffer: &'a CPUBuffer, strides: &'a [usize], offset: usize) -> BufferView<'a, CPUBuffer> {
+        BufferView { buffer, strides, offset }
+    }
+
+    // A broadcast row and a view at an offset stay within their buffers
+    let sum = CPUBackend::binary_op(&ctx, BinaryOp::Add, view(&a, &[3, 1], 0), view(&row, &[0, 1], 0), &[2, 3])?;
+    assert_eq!(sum.as_f32()?, &[11.0, 22.0, 33.0, 14.0, 25.0, 36.0]);
+    let tail = CPUBackend::binary_op(&ctx, BinaryOp::Add, view(&a, &[1], 3), view(&row, &[1], 0), &[3])?;
+    assert_eq!(tail.as_f32()?, &[14.0, 25.0, 36.0]);
+
+    let malformed: [(&[usize], usize, &[usize]); 4] = [
+        (&[1], 4, &[3]),
+        (&[3, 1], 0, &[3, 3]),
+        (&[usize::MAX, 1], 0, &[2, 3]),
+        (&[1], 0, &[2, 3]),
+    ];
+    for (strides, offset, shape) in malformed {
+        let size = shape.iter().product();
+        assert!(CPUBackend::binary_op(&ctx, BinaryOp::Add, view(&a, strides, offset), view(&a, strides, 0), shape).is_err());
+        let (mut out, broadcast) = (CPUBuffer::from(vec![0.0f32; size]), vec![0; shape.len()]);
+        assert!(CPUBackend::binary_op_into(&ctx, BinaryOp::Add, view(&row, &broadcast, 0), view(&a, strides, offset), shape, &mut out).is_err());
+        assert!(CPUBackend::binary_op_assign(&ctx, BinaryOp::Add, &mut out, view(&a, strides, offset), shape).is_err());
+    }
+    Ok(())
+}
+
+/// Reduces one lane in f64, the oracle for the f32 reduction kernels
+fn reduce_reference(op: ReduceOp, lane: &[f32]) -> f64 {
+    let values: Vec<f64> = lane.iter().map(|&v| v as f64).collect();
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let squared_deviations: f64 = values.iter().map(|v| (v - mean) * (v - mean)).sum();
+    let position = |better: fn(f64, f64) -> bool| {
+        (0..values.len()).fold(0, |best, i| if better(values[i], values[best]) { i } else { best }) as f64
+    };
+    match op {
+        ReduceOp::Sum => values.iter().sum(),
+        ReduceOp::Mean => mean,
+        ReduceOp::Max => values.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+        ReduceOp::Min => values.iter().cloned().fold(f64::INFINITY, f64::min),
+        ReduceOp::Prod => values.iter().product(),
+        ReduceOp::ArgMax => position(|v, best| v > best),
+        ReduceOp::ArgMin => position(|v, best| v < best),
+        ReduceOp::Var { correction } => squared_deviations / (n - correction as f64),
+        ReduceOp::Std { correction } => (squared_deviations / (n - correction as f64)).sqrt(),
+    }
+}
+
+#[test]
+fn test_reductions_match_f64_reference() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let ops = [
+        ReduceOp::Sum, ReduceOp::Mean, ReduceOp::Max, ReduceOp::Min, ReduceOp::Prod,
+        ReduceOp::ArgMax, ReduceOp::ArgMin,
+        ReduceOp::Var { correction: 0 }, ReduceOp::Var { correction: 1 }, ReduceOp::Std { correction: 1 },
+    ];
+
+    for &(outer, size, inner) in &[(3, 7, 5), (1, 1000, 1), (4, 300, 2), (2, 1, 3)] {
+        let mut data = pseudo_random(outer * size * inner, 7);
+        // Keep products in a representable range
+        for v in data.iter_mut() {
+            *v = 1.0 + *v * 0.01;
+        }
+        let input = CPUBuffer::from(data.clone());
+
+        for op in ops {
+            if size == 1 && matches!(op, ReduceOp::Var { correction: 1 } | ReduceOp::Std { .. }) {
+                continue;
+            }
+            let result = CPUBackend::reduce(&ctx, op, &input, outer, size, inner)?;
+            assert_eq!(result.dtype(), op.output_dtype());
+            let actual: Vec<f64> = match &result {
+                CPUBuffer::F32(values) => values.iter().map(|&v| v as f64).collect(),
+                CPUBuffer::I64(indices) => indices.iter().map(|&v| v as f64).collect(),
+                other => panic!("unexpected output type {:?}", other.dtype()),
+            };
+            assert_eq!(actual.len(), outer * inner);
+
+            for o in 0..outer {
+                for i in 0..inner {
+                    let lane: Vec<f32> = (0..size).map(|r| data[(o * size + r) * inner + i]).collect();
+                    let expected = reduce_reference(op, &lane);
+                    let got = actual[o * inner + i];
+                    assert!(
+                        (got - expected).abs() <= 1e-5 * (1.0 + expected.abs()),
+                        "{:?} on ({}, {}, {}) lane ({}, {}): {} vs {}", op, outer, size, inner, o, i, got, expected
+                    );
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_pairwise_sum_is_accurate() {
+    // A running f32 total drifts by roughly 1e-2 relative over this many terms
+    let values = vec![0.1f32; 1 << 22];
+    let expected = values.len() as f64 * 0.1f32 as f64;
+    let actual = reduce::pairwise_sum(&values) as f64;
+    assert!((actual - expected).abs() / expected < 1e-6, "{} vs {}", actual, expected);
+}
+
+#[test]
+fn test_reductions_propagate_nan() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let input = CPUBuffer::from(vec![1.0, f32::NAN, 3.0]);
+    for op in [ReduceOp::Max, ReduceOp::Min, ReduceOp::Sum] {
+        assert!(CPUBackend::reduce(&ctx, op, &input, 1, 3, 1)?.as_f32()?[0].is_nan(), "{:?}", op);
+    }
+    assert_eq!(CPUBackend::reduce(&ctx, ReduceOp::ArgMax, &input, 1, 3, 1)?, CPUBuffer::from(vec![1i64]));
+    Ok(())
+}
+
+/// f64 formulas for the unary ops, independent of the kernels' f32 rewrites
+fn unary_reference(op: UnaryOp, x: f64) -> f64 {
+    let erf = |x: f64| {
+        // Maclaurin series, converges quickly for the small inputs used here
+        let mut term = x;
+        let mut sum = x;
+        for n in 1..60 {
+            term *= -x * x / n as f64;
+            sum += term / (2 * n + 1) as f64;
+        }
+        sum * 2.0 / std::f64::consts::PI.sqrt()
+    };
+    let sigmoid = |x: f64| 1.0 / (1.0 + (-x).exp());
+    match op {
+        UnaryOp::Exp => x.exp(),
+        UnaryOp::Log => x.ln(),
+        UnaryOp::Sqrt => x.sqrt(),
+        UnaryOp::Rsqrt => 1.0 / x.sqrt(),
+        UnaryOp::Abs => x.abs(),
+        UnaryOp::Sin => x.sin(),
+        UnaryOp::Cos => x.cos(),
+        UnaryOp::Tanh => x.tanh(),
+        UnaryOp::Sigmoid => sigmoid(x),
+        UnaryOp::Relu => x.max(0.0),
+        UnaryOp::Gelu => 0.5 * x * (1.0 + erf(x / std::f64::consts::SQRT_2)),
+        UnaryOp::GeluTanh => {
+            0.5 * x * (1.0 + ((2.0 / std::f64::consts::PI).sqrt() * (x + 0.044715 * x.powi(3))).tanh())
+        }
+        UnaryOp::Silu => x * sigmoid(x),
+        UnaryOp::Softplus => (1.0 + x.exp()).ln(),
+        UnaryOp::Pow(exponent) => x.powf(exponent as f64),
+        UnaryOp::Clamp { min, max } => x.clamp(min as f64, max as f64),
+    }
+}
+
+#[test]
+fn test_unary_ops_match_f64_reference() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let ops = [
+        UnaryOp::Exp, UnaryOp::Log, UnaryOp::Sqrt, UnaryOp::Rsqrt, UnaryOp::Abs, UnaryOp::Sin,
+        UnaryOp::Cos, UnaryOp::Tanh, UnaryOp::Sigmoid, UnaryOp::Relu, UnaryOp::Gelu, UnaryOp::GeluTanh,
+        UnaryOp::Silu, UnaryOp::Softplus, UnaryOp::Pow(2.5), UnaryOp::Clamp { min: -0.5, max: 1.0 },
+    ];
+    let signed: Vec<f32> = pseudo_random(257, 8).iter().map(|v| v * 3.0).collect();
+    let positive: Vec<f32> = signed.iter().map(|v| v.abs() + 0.1).collect();
+
+    for op in ops {
+        let data = match op {
+            UnaryOp::Log | UnaryOp::Sqrt | UnaryOp::Rsqrt | UnaryOp::Pow(_) => &positive,
+            _ => &signed,
+        };
+        let result = CPUBackend::unary_op(&ctx, op, &data.clone().into(), data.len())?;
+        for (&x, &y) in data.iter().zip(result.as_f32()?) {
+            let expected = unary_reference(op, x as f64);
+            assert!(
+                (y as f64 - expected).abs() <= 1e-5 * (1.0 + expected.abs()),
+                "{:?}({}): {} vs {}", op, x, y, expected
+            );
+        }
+    }
+
+    // Softplus must not overflow for large inputs
+    let large = CPUBackend::unary_op(&ctx, UnaryOp::Softplus, &vec![100.0f32, -100.0].into(), 2)?;
+    assert_eq!(large.as_f32()?[0], 100.0);
+    assert!(large.as_f32()?[1] > 0.0 && large.as_f32()?[1] < 1e-40);
+
+    Ok(())
+}
+
+#[test]
+fn test_softmax_matches_f64_reference() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let (outer, size, inner) = (3, 37, 4);
+    // Large logits would overflow a naive exp
+    let data: Vec<f32> = pseudo_random(outer * size * inner, 9).iter().map(|v| v * 50.0 + 1000.0).collect();
+    let input = CPUBuffer::from(data.clone());
+
+    let softmax = CPUBackend::softmax(&ctx, SoftmaxOp::Softmax, &input, outer, size, inner)?;
+    let log_softmax = CPUBackend::softmax(&ctx, SoftmaxOp::LogSoftmax, &input, outer, size, inner)?;
+    let logsumexp = CPUBackend::softmax(&ctx, SoftmaxOp::LogSumExp, &input, outer, size, inner)?;
+    assert_eq!(logsumexp.len(), outer * inner);
+
+    for o in 0..outer {
+        for i in 0..inner {
+            let index = |r: usize| (o * size + r) * inner + i;
+            let max = (0..size).map(|r| data[index(r)] as f64).fold(f64::NEG_INFINITY, f64::max);
+            let lse = max + (0..size).map(|r| (data[index(r)] as f64 - max).exp()).sum::<f64>().ln();
+
+            assert!((logsumexp.as_f32()?[o * inner + i] as f64 - lse).abs() < 1e-3);
+            for r in 0..size {
+                let log_prob = data[index(r)] as f64 - lse;
+                assert!((log_softmax.as_f32()?[index(r)] as f64 - log_prob).abs() < 1e-3);
+                assert!((softmax.as_f32()?[index(r)] as f64 - log_prob.exp()).abs() < 1e-6);
+            }
+        }
+    }
+
+    // Masked positions get zero probability; a fully masked lane has no finite normalizer
+    let masked = CPUBuffer::from(vec![0.0, f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY]);
+    let probs = CPUBackend::softmax(&ctx, SoftmaxOp::Softmax, &masked, 2, 2, 1)?;
+    assert_eq!(&probs.as_f32()?[..2], &[1.0, 0.0]);
+    let lse = CPUBackend::softmax(&ctx, SoftmaxOp::LogSumExp, &masked, 2, 2, 1)?;
+    assert_eq!(lse.as_f32()?, &[0.0, f32::NEG_INFINITY]);
+
+    Ok(())
+}
+
+/// Calls `f(output_index, input_index, weight_index)` for every multiply-add of a direct convolution
+fn for_each_conv_tap(p: &Conv2dParams, mut f: impl FnMut(usize, usize, usize)) {
+    let (h, w) = p.input_size;
+    let (kh, kw) = p.kernel_size;
+    let (out_h, out_w) = p.output_size();
+    let (in_per_group, out_per_group) = (p.in_channels / p.groups, p.out_channels / p.groups);
+    for n in 0..p.batch_size {
+        for oc in 0..p.out_channels {
+            let g = oc / out_per_group;
+            for oy in 0..out_h {
+                for ox in 0..out_w {
+                    for ic in 0..in_per_group {
+                        for ky in 0..kh {
+                            for kx in 0..kw {
+                                let iy = (oy * p.stride.0 + ky * p.dilation.0) as isize - p.padding.0 as isize;
+                                let ix = (ox * p.stride.1 + kx * p.dilation.1) as isize - p.padding.1 as isize;
+                                if iy < 0 || ix < 0 || iy >= h as isize || ix >= w as isize {
+                                    continue;
+                                }
+                                let channel = g * in_per_group + ic;
+                                f(
+                                    ((n * p.out_channels + oc) * out_h + oy) * out_w + ox,
+                                    ((n * p.in_channels + channel) * h + iy as usize) * w + ix as usize,
+                                    ((oc * in_per_group + ic) * kh + ky) * kw + kx,
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_conv2d_matches_direct_reference() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let base = Conv2dParams {
+        batch_size: 2,
+        in_channels: 4,
+        out_channels: 6,
+        input_size: (7, 6),
+        kernel_size: (3, 2),
+        stride: (1, 1),
+        padding: (0, 0),
+        dilation: (1, 1),
+        groups: 1,
+    };
+    let cases = [
+        base,
+        Conv2dParams { stride: (2, 3), ..base },
+        Conv2dParams { padding: (1, 2), ..base },
+        Conv2dParams { dilation: (2, 3), padding: (1, 0), ..base },
+        Conv2dParams { groups: 2, stride: (2, 1), padding: (1, 1), ..base },
+        // Depthwise
+        Conv2dParams { groups: 4, in_channels: 4, out_channels: 4, kernel_size: (3, 3), padding: (1, 1), ..base },
+    ];
+
+    for (seed, p) in cases.into_iter().enumerate() {
+        let input = pseudo_random(p.input_len(), seed as u32 * 3 + 1);
+        let weight = pseudo_random(p.weight_len(), seed as u32 * 3 + 2);
+        let grad_output = pseudo_random(p.output_len(), seed as u32 * 3 + 3);
+
+        let mut output = vec![0.0f64; p.output_len()];
+        let mut grad_input = vec![0.0f64; p.input_len()];
+        let mut grad_weight = vec![0.0f64; p.weight_len()];
+        for_each_conv_tap(&p, |o, i, k| {
+            output[o] += input[i] as f64 * weight[k] as f64;
+            grad_input[i] += grad_output[o] as f64 * weight[k] as f64;
+            grad_weight[k] += grad_output[o] as f64 * input[i] as f64;
+        });
+        let to_f32 = |v: Vec<f64>| v.into_iter().map(|x| x as f32).collect::<Vec<_>>();
+
+        let (input, weight, grad_output) = (CPUBuffer::from(input), CPUBuffer::from(weight), CPUBuffer::from(grad_output));
+        let actual = CPUBackend::conv2d(&ctx, &input, &weight, &p)?;
+        assert_close(actual.as_f32()?, &to_f32(output), 1e-5);
+        let actual = CPUBackend::conv2d_backward_input(&ctx, &grad_output, &weight, &p)?;
+        assert_close(actual.as_f32()?, &to_f32(grad_input), 1e-5);
+        let actual = CPUBackend::conv2d_backward_weight(&ctx, &input, &grad_output, &p)?;
+        assert_close(actual.as_f32()?, &to_f32(grad_weight), 1e-5);
+    }
+
+    let p = Conv2dParams { input_size: (1, 1), ..base };
+    assert!(CPUBackend::conv2d(&ctx, &vec![0.0f32; 3].into(), &vec![0.0f32; p.weight_len()].into(), &p).is_err());
+
+    Ok(())
+}
+
+/// Direct max/average pooling of one plane over the window `[y0, y1) x [x0, x1)` of a padded
+/// input; returns (max, flat index of the max, sum, number of in-bounds elements)
+fn pool_window_reference(plane: &[f32], (h, w): (usize, usize), rows: (isize, isize), cols: (isize, isize)) -> (f32, i64, f64, usize) {
+    let (mut max, mut index, mut sum, mut count) = (f32::NEG_INFINITY, -1, 0.0, 0);
+    for y in rows.0.max(0)..rows.1.min(h as isize) {
+        for x in cols.0.max(0)..cols.1.min(w as isize) {
+            let flat = y as usize * w + x as usize;
+            if index < 0 || plane[flat] > max {
+                (max, index) = (plane[flat], flat as i64);
+            }
+            sum += plane[flat] as f64;
+            count += 1;
+        }
+    }
+    (max, index, sum, count)
+}
+
+#[test]
+fn test_pooling_matches_direct_reference() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let (batch_size, channels, input_size) = (2, 3, (7, 6));
+    let fixed = |kernel_size, stride, padding| PoolWindow::Fixed { kernel_size, stride, padding };
+    let windows = [
+        fixed((2, 2), (2, 2), (0, 0)),
+        fixed((3, 2), (2, 1), (1, 1)),
+        fixed((3, 3), (1, 2), (1, 0)),
+        PoolWindow::Adaptive { output_size: (3, 4) },
+        PoolWindow::Adaptive { output_size: (1, 1) },
+        // More outputs than inputs repeats elements across windows
+        PoolWindow::Adaptive { output_size: (9, 6) },
+    ];
+
+    for (seed, window) in windows.into_iter().enumerate() {
+        let p = Pool2dParams { batch_size, channels, input_size, window };
+        let (out_h, out_w) = p.output_size();
+        let data = pseudo_random(p.input_len(), seed as u32 + 1);
+        let grad_output = pseudo_random(p.output_len(), seed as u32 + 11);
+        let input = CPUBuffer::from(data.clone());
+        let (values, indices) = CPUBackend::max_pool2d(&ctx, &input, &p)?;
+        let (values, indices) = (values.as_f32()?.to_vec(), indices.as_i64()?.to_vec());
+
+        for count_include_pad in [true, false] {
+            let averages = CPUBackend::avg_pool2d(&ctx, &input, &p, count_include_pad)?;
+            let grad_avg = CPUBackend::avg_pool2d_backward(&ctx, &grad_output.clone().into(), &p, count_include_pad)?;
+            let grad_max = CPUBackend::max_pool2d_backward(&ctx, &grad_output.clone().into(), &indices.clone().into(), &p)?;
+            let mut expected_grad_avg = vec![0.0f64; p.input_len()];
+            let mut expected_grad_max = vec![0.0f64; p.input_len()];
+
+            let plane_len = input_size.0 * input_size.1;
+            for plane in 0..batch_size * channels {
+                for oy in 0..out_h {
+                    for ox in 0..out_w {
+                        let (rows, cols, window_len) = match window {
+                            PoolWindow::Fixed { kernel_size: (kh, kw), stride: (sh, sw), padding: (ph, pw) } => {
+                                let (y0, x0) = ((oy * sh) as isize - ph as isize, (ox * sw) as isize - pw as isize);
+                                ((y0, y0 + kh as isize), (x0, x0 + kw as isize), kh * kw)
+                            }
+                            PoolWindow::Adaptive { .. } => {
+                                let (y0, y1) = (oy * input_size.0 / out_h, ((oy + 1) * input_size.0).div_ceil(out_h));
+                                let (x0, x1) = (ox * input_size.1 / out_w, ((ox + 1) * input_size.1).div_ceil(out_w));
+                                ((y0 as isize, y1 as isize), (x0 as isize, x1 as isize), (y1 - y0) * (x1 - x0))
+                            }
+                        };
+                        let image = &data[plane * plane_len..(plane + 1) * plane_len];
+                        let (max, index, sum, count) = pool_window_reference(image, input_size, rows, cols);
+                        let out = (plane * out_h + oy) * out_w + ox;
+                        assert_eq!((values[out], indices[out]), (max, index));
+
+                        let divisor = if count_include_pad { window_len } else { count };
+                        assert!((averages.as_f32()?[out] as f64 - sum / divisor as f64).abs() < 1e-5);
+                        expected_grad_max[plane * plane_len + index as usize] += grad_output[out] as f64;
+                        for y in rows.0.max(0)..rows.1.min(input_size.0 as isize) {
+                            for x in cols.0.max(0)..cols.1.min(input_size.1 as isize) {
+                                let flat = plane * plane_len + y as usize * input_size.1 + x as usize;
+                                expected_grad_avg[flat] += grad_output[out] as f64 / divisor as f64;
+                            }
+                        }
+                    }
+                }
+            }
+            let to_f32 = |v: Vec<f64>| v.into_iter().map(|x| x as f32).collect::<Vec<_>>();
+            assert_close(grad_avg.as_f32()?, &to_f32(expected_grad_avg), 1e-5);
+            assert_close(grad_max.as_f32()?, &to_f32(expected_grad_max), 1e-5);
+        }
+    }
+
+    // Indices that do not point into the plane are rejected
+    let p = Pool2dParams { batch_size: 1, channels: 1, input_size: (2, 2), window: PoolWindow::Adaptive { output_size: (1, 1) } };
+    assert!(CPUBackend::max_pool2d_backward(&ctx, &vec![1.0f32].into(), &vec![4i64].into(), &p).is_err());
+    // The host kernels behind every backend's fallback reject them too, along with short operands
+    let mut grad_input = vec![0.0; 4];
+    assert!(pool::max_pool2d_backward(&p, &[1.0], &[-1], &mut grad_input).is_err());
+    assert!(pool::max_pool2d_backward(&p, &[1.0], &[], &mut grad_input).is_err());
+    assert!(pool::avg_pool2d(&p, &[1.0; 3], true, &mut [0.0]).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_normalize_matches_f64_reference() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let (rows, size, eps) = (5, 300, 1e-5);
+    // A large common offset is where E[x^2] - E[x]^2 would cancel catastrophically
+    let data: Vec<f32> = pseudo_random(rows * size, 21).iter().map(|v| v + 1.0e4).collect();
+    let input = CPUBuffer::from(data.clone());
+
+    for op in [NormOp::LayerNorm, NormOp::RmsNorm] {
+        let (output, rstd) = CPUBackend::normalize(&ctx, op, &input, rows, size, eps)?;
+        assert_eq!(rstd.len(), rows);
+        for row in 0..rows {
+            let values: Vec<f64> = data[row * size..(row + 1) * size].iter().map(|&v| v as f64).collect();
+            let mean = values.iter().sum::<f64>() / size as f64;
+            let (center, spread) = match op {
+                NormOp::LayerNorm => (mean, values.iter().map(|v| (v - mean) * (v - mean)).sum::<f64>() / size as f64),
+                NormOp::RmsNorm => (0.0, values.iter().map(|v| v * v).sum::<f64>() / size as f64),
+            };
+            let inv = 1.0 / (spread + eps as f64).sqrt();
+            assert!((rstd.as_f32()?[row] as f64 - inv).abs() < 1e-5 * inv);
+            for (i, v) in values.iter().enumerate() {
+                let expected = (v - center) * inv;
+                assert!((output.as_f32()?[row * size + i] as f64 - expected).abs() < 2e-3, "{:?} row {} element {}", op, row, i);
+            }
+        }
+    }
+
+    assert!(CPUBackend::normalize(&ctx, NormOp::LayerNorm, &input, rows, size + 1, eps).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_caching_allocator_reuses_buffers() -> Result<()> {
+    let ctx = CPUContext::new();
+    assert_eq!(allocator::bucket_size(1), 512);
+    assert_eq!(allocator::bucket_size(4000), 4096);
+    assert_eq!(allocator::bucket_size(4097), 5120);
+
+    // 1000 and 900 elements share the 4 KiB bucket
+    let mut a = CPUBackend::allocate_buffer(&ctx, 1000, None)?;
+    let b = CPUBackend::allocate_buffer(&ctx, 1000, Some(&pseudo_random(1000, 1)))?;
+    let stats = ctx.memory_stats();
+    assert_eq!((stats.live_bytes, stats.peak_bytes, stats.allocations, stats.reuses), (8192, 8192, 2, 0));
+
+    CPUBackend::release_buffer(&ctx, &mut a);
+    assert_eq!(ctx.memory_stats().cached_bytes, 4096);
+    let _sum = CPUBackend::element_wise_add(&ctx, &b, &b, 1000)?;
+    let mut c = CPUBackend::allocate_buffer(&ctx, 900, None)?;
+    let stats = ctx.memory_stats();
+    assert_eq!((stats.live_bytes, stats.cached_bytes, stats.allocations, stats.reuses), (12288, 0, 4, 1));
+    // Reused memory is zeroed again
+    assert_eq!(c.as_f32()?, &[0.0; 900][..]);
+
+    // Buffers the cache didn't hand out are left alone
+    CPUBackend::release_buffer(&ctx, &mut CPUBuffer::from(vec![0.0f32; 1000]));
+    assert_eq!(ctx.memory_stats().releases, 1);
+
+    CPUBackend::release_buffer(&ctx, &mut c);
+    CPUBackend::empty_cache(&ctx);
+    let stats = ctx.memory_stats();
+    assert_eq!((stats.live_bytes, stats.peak_bytes, stats.cached_bytes, stats.releases), (8192, 12288, 0, 2));
+
+    Ok(())
+}
+
+#[test]
+fn test_mapped_buffer_copies_on_write() -> Result<()> {
+    // Place the elements at the first 4-byte aligned address of the allocation
+    let mut bytes = Vec::with_capacity(32);
+    let start = (bytes.as_ptr() as usize).next_multiple_of(4) - bytes.as_ptr() as usize;
+    bytes.resize(start, 0);
+    bytes.extend(crate::dtype::to_bytes(&[1.5f32, -2.0, 3.25]));
+    let storage = Arc::new(crate::io::Storage::Owned(bytes));
+
+    assert!(MappedBuffer::new(Arc::clone(&storage), start + 1..start + 9, DType::F32).is_none());
+    assert!(MappedBuffer::new(Arc::clone(&storage), start..start + 6, DType::F32).is_none());
+    assert!(MappedBuffer::new(Arc::clone(&storage), start..start + 4, DType::Bool).is_none());
+    assert!(MappedBuffer::new(Arc::clone(&storage), start..start + 64, DType::F32).is_none());
+
+    let mapped = MappedBuffer::new(Arc::clone(&storage), start..start + 12, DType::F32).expect("aligned f32 data");
+    let shared = CPUBuffer::Mapped(mapped);
+    let mut written = shared.clone();
+    assert_eq!((written.dtype(), written.len()), (DType::F32, 3));
+    assert!(written.as_i64().is_err());
+
+    // Writing takes an owned copy, the shared elements keep their values
+    written.as_f32_mut()?[0] = 0.0;
+    assert!(matches!(written, CPUBuffer::F32(_)));
+    assert_eq!(written.as_f32()?, &[0.0, -2.0, 3.25]);
+    assert_eq!(shared.as_f32()?, &[1.5, -2.0, 3.25]);
+    assert_eq!(shared.cast(DType::F64), CPUBuffer::from(vec![1.5f64, -2.0, 3.25]));
+    Ok(())
+}
+
+#[test]
+fn test_import_storage_reads_aligned_data_in_place() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let mut bytes = Vec::with_capacity(32);
+    let start = (bytes.as_ptr() as usize).next_multiple_of(4) - bytes.as_ptr() as usize;
+    bytes.resize(start + 1, 0);
+    bytes.extend(crate::dtype::to_bytes(&[1.5f32, -2.0]));
+    let storage = Arc::new(crate::io::Storage::Owned(bytes));
+
+    let aligned = CPUBackend::import_storage(&ctx, &storage, start..start + 4, DType::I32)?;
+    assert!(matches!(aligned, CPUBuffer::Mapped(_)));
+    // Unaligned data falls back to a copy
+    let unaligned = CPUBackend::import_storage(&ctx, &storage, start + 1..start + 9, DType::F32)?;
+    assert_eq!(unaligned, CPUBuffer::from(vec![1.5f32, -2.0]));
+
+    assert!(CPUBackend::import_storage(&ctx, &storage, start + 1..start + 8, DType::F32).is_err());
+    assert!(CPUBackend::import_storage(&ctx, &storage, start..start + 64, DType::F32).is_err());
+    Ok(())
+}
@@ -0,0 +1,190 @@
+//! Host implementations of the fused loss kernels.
+
+use super::check_operand;
+use crate::error::{Result, FerroFlowError};
+
+/// Losses over class scores selected by integer targets,
+/// see [`ComputeBackend::class_loss`](super::ComputeBackend::class_loss)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClassLossOp {
+    /// Softmax cross-entropy over logits. With label smoothing `ε` the target distribution
+    /// is `(1 - ε) * one_hot + ε / classes`.
+    CrossEntropy { label_smoothing: f32 },
+    /// Negative log-likelihood of log-probabilities, `-input[target]`
+    Nll,
+}
+
+/// Geometry of a class loss over a row-major `[rows, classes]` input and `rows` i64 targets
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClassLossParams {
+    pub op: ClassLossOp,
+    pub rows: usize,
+    pub classes: usize,
+    /// Rows whose target equals this value contribute neither loss nor gradient
+    pub ignore_index: Option<i64>,
+}
+
+impl ClassLossParams {
+    /// Returns the class selected by `target`, or `None` for an ignored row
+    fn class(&self, target: i64) -> Result<Option<usize>> {
+        if Some(target) == self.ignore_index {
+            return Ok(None);
+        }
+        if target < 0 || target as usize >= self.classes {
+            return Err(FerroFlowError::InvalidOperation(format!(
+                "Target {} is out of range for {} classes", target, self.classes
+            )));
+        }
+        Ok(Some(target as usize))
+    }
+}
+
+/// Writes the loss of every row into the zeroed `losses`; ignored rows keep 0.
+/// Returns the number of rows that were not ignored.
+pub(crate) fn class_loss(params: &ClassLossParams, input: &[f32], targets: &[i64], losses: &mut [f32]) -> Result<usize> {
+    check_operand("Loss input", input, params.rows * params.classes)?;
+    check_operand("Loss targets", targets, params.rows)?;
+    let mut counted = 0;
+    for ((loss, row), &target) in losses.iter_mut().zip(input.chunks_exact(params.classes)).zip(targets) {
+        let Some(class) = params.class(target)? else {
+            continue;
+        };
+        counted += 1;
+        *loss = match params.op {
+            ClassLossOp::CrossEntropy { label_smoothing } => {
+                let log_sum_exp = log_sum_exp(row);
+                let nll = log_sum_exp - row[class];
+                if label_smoothing == 0.0 {
+                    nll
+                } else {
+                    // The uniform part is the mean over classes of -log p = lse - x
+                    let uniform = log_sum_exp - row.iter().map(|&x| x as f64).sum::<f64>() as f32 / params.classes as f32;
+                    (1.0 - label_smoothing) * nll + label_smoothing * uniform
+                }
+            }
+            ClassLossOp::Nll => -row[class],
+        };
+    }
+    Ok(counted)
+}
+
+/// Writes the gradient of `class_loss` with respect to its input into the zeroed
+/// `grad_input`, given the gradient of each row's loss. For cross-entropy this is
+/// `(softmax(x) - target_distribution) * grad`.
+pub(crate) fn class_loss_backward(
+    params: &ClassLossParams,
+    input: &[f32],
+    targets: &[i64],
+    grad: &[f32],
+    grad_input: &mut [f32]
+) -> Result<()> {
+    check_operand("Loss input", input, params.rows * params.classes)?;
+    check_operand("Loss targets", targets, params.rows)?;
+    check_operand("Loss gradient", grad, params.rows)?;
+    let rows = grad_input.chunks_exact_mut(params.classes).zip(input.chunks_exact(params.classes));
+    for (((out, row), &target), &g) in rows.zip(targets).zip(grad) {
+        let Some(class) = params.class(target)? else {
+            continue;
+        };
+        match params.op {
+            ClassLossOp::CrossEntropy { label_smoothing } => {
+                let log_sum_exp = log_sum_exp(row);
+                let uniform = label_smoothing / params.classes as f32;
+                for (o, &x) in out.iter_mut().zip(row) {
+                    *o = ((x - log_sum_exp).exp() - uniform) * g;
+                }
+                out[class] -= (1.0 - label_smoothing) * g;
+            }
+            ClassLossOp::Nll => out[class] = -g,
+        }
+    }
+    Ok(())
+}
+
+/// Writes `op` applied to each input and target pair into `out`
+pub(crate) fn pointwise_loss(op: PointwiseLossOp, input: &[f32], target: &[f32], out: &mut [f32]) -> Result<()> {
+    check_operand("Loss input", input, out.len())?;
+    check_operand("Loss target", target, out.len())?;
+    for ((out, &x), &y) in out.iter_mut().zip(input).zip(target) {
+        *out = op.apply(x, y);
+    }
+    Ok(())
+}
+
+/// Writes the gradient of `pointwise_loss` with respect to its input into `out`, given the
+/// gradient of each element's loss
+pub(crate) fn pointwise_loss_backward(op: PointwiseLossOp, input: &[f32], target: &[f32], grad: &[f32], out: &mut [f32]) -> Result<()> {
+    check_operand("Loss input", input, out.len())?;
+    check_operand("Loss target", target, out.len())?;
+    check_operand("Loss gradient", grad, out.len())?;
+    for (((out, &x), &y), &g) in out.iter_mut().zip(input).zip(target).zip(grad) {
+        *out = g * op.derivative(x, y);
+    }
+    Ok(())
+}
+
+/// `log(sum(exp(x)))` shifted by the maximum so no exponent overflows
+fn log_sum_exp(row: &[f32]) -> f32 {
+    let max = row.iter().fold(f32::NEG_INFINITY, |m, &x| m.max(x));
+    if max == f32::NEG_INFINITY {
+        return max;
+    }
+    max + row.iter().map(|&x| (x - max).exp()).sum::<f32>().ln()
+}
+
+/// Element-wise losses between an input and a target of the same shape,
+/// see [`ComputeBackend::pointwise_loss`](super::ComputeBackend::pointwise_loss)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PointwiseLossOp {
+    /// `(x - y)^2`
+    Mse,
+    /// `|x - y|`
+    L1,
+    /// `0.5 * d^2 / beta` for `|d| < beta`, otherwise `|d| - 0.5 * beta`, with `d = x - y`
+    SmoothL1 { beta: f32 },
+    /// `0.5 * d^2` for `|d| <= delta`, otherwise `delta * (|d| - 0.5 * delta)`
+    Huber { delta: f32 },
+    /// Binary cross-entropy of `sigmoid(x)` against probabilities `y`, evaluated on the logits
+    BceWithLogits,
+}
+
+impl PointwiseLossOp {
+    #[inline]
+    pub fn apply(self, x: f32, y: f32) -> f32 {
+        let d = x - y;
+        match self {
+            PointwiseLossOp::Mse => d * d,
+            PointwiseLossOp::L1 => d.abs(),
+            PointwiseLossOp::SmoothL1 { beta } => {
+                if d.abs() < beta { 0.5 * d * d / beta } else { d.abs() - 0.5 * beta }
+            }
+            PointwiseLossOp::Huber { delta } => {
+                if d.abs() <= delta { 0.5 * d * d } else { delta * (d.abs() - 0.5 * delta) }
+            }
+            // max(x, 0) - x * y + log(1 + e^-|x|) never exponentiates a positive number
+            PointwiseLossOp::BceWithLogits => x.max(0.0) - x * y + (-x.abs()).exp().ln_1p(),
+        }
+    }
+
+    /// Returns d apply(x, y) / dx. The kink of L1 at `x == y` gets a derivative of 0.
+    #[inline]
+    pub fn derivative(self, x: f32, y: f32) -> f32 {
+        let d = x - y;
+        match self {
+            PointwiseLossOp::Mse => 2.0 * d,
+            PointwiseLossOp::L1 => sign(d),
+            PointwiseLossOp::SmoothL1 { beta } => if d.abs() < beta { d / beta } else { sign(d) },
+            PointwiseLossOp::Huber { delta } => d.clamp(-delta, delta),
+            PointwiseLossOp::BceWithLogits => {
+                let sigmoid = if x >= 0.0 { 1.0 / (1.0 + (-x).exp()) } else { x.exp() / (1.0 + x.exp()) };
+                sigmoid - y
+            }
+        }
+    }
+}
+
+/// Sign of `d`, with 0 for both zeros
+#[inline]
+fn sign(d: f32) -> f32 {
+    if d > 0.0 { 1.0 } else if d < 0.0 { -1.0 } else { 0.0 }
+}
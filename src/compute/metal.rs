@@ -1,4 +1,6 @@
-use super::ComputeBackend;
+use super::allocator::BufferCache;
+use super::{ComputeBackend, MemoryStats};
+use crate::dtype::DType;
 use crate::error::{Result, FerroFlowError};
 use metal::{self, Device, CommandQueue, Library, ComputePipelineState, Buffer};
 use std::sync::Arc;
@@ -19,6 +21,8 @@ pub struct MetalContext {
     pub(crate) matmul_batched_tiled_pipeline: ComputePipelineState,
     pub(crate) matmul_transposed_pipeline: ComputePipelineState,
     pub(crate) matmul_transposed_tiled_pipeline: ComputePipelineState,
+    pub(crate) matmul_transposed_batched_pipeline: ComputePipelineState,
+    buffers: BufferCache<Buffer>,
 }
 
 pub struct MetalBackend;
@@ -44,6 +48,37 @@ impl MetalContext {
         let transposed_tiled = Self::create_pipeline(device, library, "matmul_transposed_tiled")?;
         Ok((basic, tiled, batched, batched_tiled, transposed, transposed_tiled))
     }
+
+    /// Returns the statistics of the cache recycling released buffers
+    pub fn memory_stats(&self) -> MemoryStats {
+        self.buffers.stats()
+    }
+
+    /// Frees the buffers kept for reuse
+    pub fn empty_cache(&self) {
+        self.buffers.clear()
+    }
+
+    /// Returns a shared buffer of `byte_len` bytes initialized from `data`, or zeroed, reusing a
+    /// released buffer of the same length if one is cached. Buckets hold exact lengths because
+    /// `buffer_len` is derived from the buffer's length.
+    fn new_buffer(&self, byte_len: usize, data: Option<&[u8]>) -> Buffer {
+        let buffer = self.buffers.allocate(
+            byte_len,
+            || self.device.new_buffer(byte_len as u64, metal::MTLResourceOptions::StorageModeShared),
+            |buffer| buffer.contents() as usize,
+        );
+        let contents = buffer.contents() as *mut u8;
+        if byte_len > 0 {
+            unsafe {
+                match data {
+                    Some(data) => std::ptr::copy_nonoverlapping(data.as_ptr(), contents, byte_len),
+                    None => std::ptr::write_bytes(contents, 0, byte_len),
+                }
+            }
+        }
+        buffer
+    }
 }
 
 impl ComputeBackend for MetalBackend {
@@ -63,6 +98,7 @@ impl ComputeBackend for MetalBackend {
         let multiply_pipeline = MetalContext::create_pipeline(&device, &library, "element_wise_multiply")?;
         let scalar_multiply_pipeline = MetalContext::create_pipeline(&device, &library, "scalar_multiply")?;
         let (matmul_pipeline, matmul_tiled_pipeline, matmul_batched_pipeline, matmul_batched_tiled_pipeline, matmul_transposed_pipeline, matmul_transposed_tiled_pipeline) = MetalContext::create_matmul_pipelines(&device, &library)?;
+        let matmul_transposed_batched_pipeline = MetalContext::create_pipeline(&device, &library, "matmul_transposed_batched")?;
         
         Ok(Arc::new(MetalContext {
             device,
@@ -77,31 +113,41 @@ impl ComputeBackend for MetalBackend {
             matmul_batched_tiled_pipeline,
             matmul_transposed_pipeline,
             matmul_transposed_tiled_pipeline,
+            matmul_transposed_batched_pipeline,
+            buffers: BufferCache::new(),
         }))
     }
 
     fn allocate_buffer(ctx: &Self::Context, size: usize, data: Option<&[f32]>) -> Result<Self::Buffer> {
-        let buffer_size = (size * std::mem::size_of::<f32>()) as u64;
+        let buffer_size = size * std::mem::size_of::<f32>();
         
         match data {
             Some(data) => {
                 if data.len() != size {
                     return Err(FerroFlowError::BufferError("Data size mismatch".into()));
                 }
-                Ok(ctx.device.new_buffer_with_data(
-                    data.as_ptr() as *const _,
-                    buffer_size,
-                    metal::MTLResourceOptions::StorageModeShared,
-                ))
+                let bytes = unsafe { std::slice::from_raw_parts(data.as_ptr() as *const u8, buffer_size) };
+                Ok(ctx.new_buffer(buffer_size, Some(bytes)))
             },
-            None => Ok(ctx.device.new_buffer(
-                buffer_size,
-                metal::MTLResourceOptions::StorageModeShared,
-            ))
+            None => Ok(ctx.new_buffer(buffer_size, None))
         }
     }
 
-    fn read_buffer(ctx: &Self::Context, buffer: &Self::Buffer) -> Result<Vec<f32>> {
+    fn allocate_typed_buffer(ctx: &Self::Context, dtype: DType, size: usize, data: Option<&[u8]>) -> Result<Self::Buffer> {
+        let byte_len = size * dtype.size_in_bytes();
+        if data.is_some_and(|data| data.len() != byte_len) {
+            return Err(FerroFlowError::BufferError("Data size mismatch".into()));
+        }
+        Ok(ctx.new_buffer(byte_len, data))
+    }
+
+    fn read_buffer_bytes(_ctx: &Self::Context, buffer: &Self::Buffer, dtype: DType) -> Result<Vec<u8>> {
+        let size = Self::buffer_len(buffer, dtype) * dtype.size_in_bytes();
+        let contents = buffer.contents() as *const u8;
+        Ok(unsafe { std::slice::from_raw_parts(contents, size) }.to_vec())
+    }
+
+    fn read_buffer(_ctx: &Self::Context, buffer: &Self::Buffer) -> Result<Vec<f32>> {
         let contents = buffer.contents() as *const f32;
         let size = buffer.length() as usize / std::mem::size_of::<f32>();
         let mut result = Vec::with_capacity(size);
@@ -114,6 +160,23 @@ impl ComputeBackend for MetalBackend {
         Ok(result)
     }
 
+    fn buffer_len(buffer: &Self::Buffer, dtype: DType) -> usize {
+        buffer.length() as usize / dtype.size_in_bytes()
+    }
+
+    fn release_buffer(ctx: &Self::Context, buffer: &mut Self::Buffer) {
+        // The cache keeps its own reference, the caller drops this one
+        ctx.buffers.release(buffer.clone(), buffer.contents() as usize);
+    }
+
+    fn memory_stats(ctx: &Self::Context) -> MemoryStats {
+        ctx.memory_stats()
+    }
+
+    fn empty_cache(ctx: &Self::Context) {
+        ctx.empty_cache()
+    }
+
     fn element_wise_add(
         ctx: &Self::Context,
         a: &Self::Buffer,
@@ -290,6 +353,7 @@ impl ComputeBackend for MetalBackend {
         Ok(result_buffer)
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn matmul_transposed(
         ctx: &Self::Context,
         a: &Self::Buffer,
@@ -304,16 +368,14 @@ impl ComputeBackend for MetalBackend {
         let compute_encoder = command_buffer.new_compute_command_encoder();
 
         // Choose between tiled and non-tiled based on matrix size
-        let pipeline = if m >= TILE_SIZE && n >= TILE_SIZE && k >= TILE_SIZE {
+        let use_tiled = m >= TILE_SIZE as usize && n >= TILE_SIZE as usize && k >= TILE_SIZE as usize;
+        let pipeline = if use_tiled {
             &ctx.matmul_transposed_tiled_pipeline
         } else {
             &ctx.matmul_transposed_pipeline
         };
 
-        let result_buffer = ctx.device.new_buffer(
-            (m * n * std::mem::size_of::<f32>()) as u64,
-            metal::MTLResourceOptions::StorageModeShared
-        );
+        let result_buffer = Self::allocate_buffer(ctx, m * n, None)?;
 
         compute_encoder.set_compute_pipeline_state(pipeline);
         compute_encoder.set_buffer(0, Some(a), 0);
@@ -342,7 +404,52 @@ impl ComputeBackend for MetalBackend {
         Ok(result_buffer)
     }
 
-    fn synchronize(ctx: &Self::Context) -> Result<()> {
+    #[allow(clippy::too_many_arguments)]
+    fn matmul_transposed_batched(
+        ctx: &Self::Context,
+        a: &Self::Buffer,
+        b: &Self::Buffer,
+        batch_size: usize,
+        m: usize,
+        n: usize,
+        k: usize,
+        transpose_a: bool,
+        transpose_b: bool
+    ) -> Result<Self::Buffer> {
+        let result_buffer = Self::allocate_buffer(ctx, batch_size * m * n, None)?;
+
+        let command_buffer = ctx.command_queue.new_command_buffer();
+        let compute_encoder = command_buffer.new_compute_command_encoder();
+
+        compute_encoder.set_compute_pipeline_state(&ctx.matmul_transposed_batched_pipeline);
+        compute_encoder.set_buffer(0, Some(a), 0);
+        compute_encoder.set_buffer(1, Some(b), 0);
+        compute_encoder.set_buffer(2, Some(&result_buffer), 0);
+
+        compute_encoder.set_bytes(3, std::mem::size_of::<u32>() as u64, &(m as u32) as *const u32 as *const _);
+        compute_encoder.set_bytes(4, std::mem::size_of::<u32>() as u64, &(n as u32) as *const u32 as *const _);
+        compute_encoder.set_bytes(5, std::mem::size_of::<u32>() as u64, &(k as u32) as *const u32 as *const _);
+        compute_encoder.set_bytes(6, std::mem::size_of::<u32>() as u64, &(batch_size as u32) as *const u32 as *const _);
+        compute_encoder.set_bytes(7, std::mem::size_of::<bool>() as u64, &transpose_a as *const bool as *const _);
+        compute_encoder.set_bytes(8, std::mem::size_of::<bool>() as u64, &transpose_b as *const bool as *const _);
+
+        let grid_size = metal::MTLSize::new(n as u64, m as u64, batch_size as u64);
+        let threadgroup_size = metal::MTLSize::new(
+            TILE_SIZE as u64,
+            TILE_SIZE as u64,
+            1
+        );
+
+        compute_encoder.dispatch_threads(grid_size, threadgroup_size);
+        compute_encoder.end_encoding();
+
+        command_buffer.commit();
+        command_buffer.wait_until_completed();
+
+        Ok(result_buffer)
+    }
+
+    fn synchronize(_ctx: &Self::Context) -> Result<()> {
         Ok(())
     }
 } 
\ No newline at end of file
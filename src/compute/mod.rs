@@ -1,7 +1,111 @@
+use std::ops::Range;
 use std::sync::Arc;
-use crate::error::Result;
+use crate::dtype::{self, DType};
+use crate::error::{Result, FerroFlowError};
+use crate::io::Storage;
+
+/// Element-wise binary operations supported by [`ComputeBackend::binary_op`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+impl BinaryOp {
+    /// Applies the operation to a single pair of values
+    #[inline(always)]
+    pub fn apply(self, a: f32, b: f32) -> f32 {
+        match self {
+            BinaryOp::Add => a + b,
+            BinaryOp::Subtract => a - b,
+            BinaryOp::Multiply => a * b,
+            BinaryOp::Divide => a / b,
+        }
+    }
+}
+
+/// Reductions along one dimension supported by [`ComputeBackend::reduce`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReduceOp {
+    Sum,
+    Mean,
+    Max,
+    Min,
+    Prod,
+    /// Index of the first maximum, as i64
+    ArgMax,
+    /// Index of the first minimum, as i64
+    ArgMin,
+    /// Variance with `size - correction` in the denominator (1 gives the unbiased estimate)
+    Var { correction: usize },
+    /// Square root of `Var`
+    Std { correction: usize },
+}
+
+impl ReduceOp {
+    /// Returns the element type of the reduction's output
+    pub fn output_dtype(self) -> DType {
+        match self {
+            ReduceOp::ArgMax | ReduceOp::ArgMin => DType::I64,
+            _ => DType::F32,
+        }
+    }
+}
+
+/// A strided view into a backend buffer. Strides and offset are in elements;
+/// a stride of 0 repeats the same element, which is how broadcasting is expressed.
+#[derive(Debug)]
+pub struct BufferView<'a, T> {
+    pub buffer: &'a T,
+    pub strides: &'a [usize],
+    pub offset: usize,
+}
+
+impl<T> Clone for BufferView<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for BufferView<'_, T> {}
+
+impl<T> BufferView<'_, T> {
+    /// Returns true if the view walks `shape` densely in row-major order from offset 0
+    pub fn is_dense(&self, shape: &[usize]) -> bool {
+        self.offset == 0 && strided::is_row_major(shape, self.strides)
+    }
+
+    /// Checks that the view has a stride per dimension of `shape` and only reads elements of a
+    /// buffer holding `len` of them, so kernels can index it without bounds failures
+    pub fn check_bounds(&self, shape: &[usize], len: usize) -> Result<()> {
+        if self.strides.len() != shape.len() {
+            return Err(FerroFlowError::ShapeMismatch(format!(
+                "View has {} strides for shape {:?}", self.strides.len(), shape
+            )));
+        }
+        if shape.contains(&0) {
+            return Ok(());
+        }
+        match strided::last_offset(shape, self.strides, self.offset) {
+            Some(last) if last < len => Ok(()),
+            _ => Err(FerroFlowError::ShapeMismatch(format!(
+                "View of shape {:?} with strides {:?} and offset {} reads past a buffer of {} elements",
+                shape, self.strides, self.offset, len
+            ))),
+        }
+    }
+}
 
 /// Trait representing the capabilities required for a compute backend.
+///
+/// Buffers may hold any [`DType`], but the compute kernels operate on f32 buffers only;
+/// other element types are stored, copied and converted with `cast`.
+///
+/// Kernels with a default implementation run on the host: they read their operands with
+/// `read_buffer`, compute with the CPU kernels and allocate a buffer for the result. Backends
+/// override them with native kernels.
 pub trait ComputeBackend: Send + Sync + 'static {
     /// The buffer type used by this backend
     type Buffer: Send + Sync;
@@ -11,12 +115,85 @@ pub trait ComputeBackend: Send + Sync + 'static {
     /// Creates a new instance of the compute backend
     fn new() -> Result<Arc<Self::Context>>;
 
-    /// Allocates a buffer of the given size (in elements)
+    /// Allocates an f32 buffer of the given size (in elements)
     fn allocate_buffer(ctx: &Self::Context, size: usize, data: Option<&[f32]>) -> Result<Self::Buffer>;
 
-    /// Reads data from the buffer into a Vec<f32>
+    /// Allocates a buffer of `size` elements of `dtype`, optionally initialized from their
+    /// native-endian bytes. Uninitialized buffers are zeroed.
+    fn allocate_typed_buffer(ctx: &Self::Context, dtype: DType, size: usize, data: Option<&[u8]>) -> Result<Self::Buffer>;
+
+    /// Creates a buffer holding the native-endian `dtype` elements in `range` of a loaded file.
+    /// The default implementation copies them into a new buffer; backends that compute on host
+    /// memory may keep `storage` alive and read them in place.
+    fn import_storage(ctx: &Self::Context, storage: &Arc<Storage>, range: Range<usize>, dtype: DType) -> Result<Self::Buffer> {
+        let bytes = storage_bytes(storage, range, dtype)?;
+        Self::allocate_typed_buffer(ctx, dtype, bytes.len() / dtype.size_in_bytes(), Some(bytes))
+    }
+
+    /// Reads data from an f32 buffer into a Vec<f32>
     fn read_buffer(ctx: &Self::Context, buffer: &Self::Buffer) -> Result<Vec<f32>>;
 
+    /// Reads the native-endian bytes of a buffer holding elements of `dtype`
+    fn read_buffer_bytes(ctx: &Self::Context, buffer: &Self::Buffer, dtype: DType) -> Result<Vec<u8>>;
+
+    /// Returns the number of `dtype` elements the buffer holds
+    fn buffer_len(buffer: &Self::Buffer, dtype: DType) -> usize;
+
+    /// Hands back a buffer that is no longer used, e.g. when the last tensor referring to it is
+    /// dropped, so the backend can cache its memory for reuse. The backend may take the storage
+    /// out of `buffer`, which is dropped right after. The default implementation does nothing.
+    fn release_buffer(_ctx: &Self::Context, _buffer: &mut Self::Buffer) {}
+
+    /// Returns the allocation statistics of the context's buffer cache.
+    /// The default implementation reports nothing, for backends without a cache.
+    fn memory_stats(_ctx: &Self::Context) -> MemoryStats {
+        MemoryStats::default()
+    }
+
+    /// Frees the buffers the context keeps cached for reuse
+    fn empty_cache(_ctx: &Self::Context) {}
+
+    /// Copies a strided view of `input` into a new contiguous buffer.
+    /// `shape` and `strides` are given in elements, starting at element `offset`.
+    /// The default implementation gathers on the host via `read_buffer_bytes`.
+    fn copy_strided(
+        ctx: &Self::Context,
+        input: &Self::Buffer,
+        dtype: DType,
+        shape: &[usize],
+        strides: &[usize],
+        offset: usize
+    ) -> Result<Self::Buffer> {
+        let data = Self::read_buffer_bytes(ctx, input, dtype)?;
+        let gathered = strided::gather_bytes(&data, dtype.size_in_bytes(), shape, strides, offset);
+        Self::allocate_typed_buffer(ctx, dtype, shape.iter().product(), Some(&gathered))
+    }
+
+    /// Like `copy_strided`, but writes into `out`, which must hold `shape.iter().product()`
+    /// elements of `dtype`. The default implementation copies into a new buffer that replaces `out`.
+    fn copy_strided_into(
+        ctx: &Self::Context,
+        input: &Self::Buffer,
+        dtype: DType,
+        shape: &[usize],
+        strides: &[usize],
+        offset: usize,
+        out: &mut Self::Buffer
+    ) -> Result<()> {
+        check_output::<Self>(out, dtype, shape.iter().product())?;
+        let copied = Self::copy_strided(ctx, input, dtype, shape, strides, offset)?;
+        replace_buffer::<Self>(ctx, out, copied);
+        Ok(())
+    }
+
+    /// Converts the `size` elements of a dense buffer from one element type to another.
+    /// The default implementation converts on the host.
+    fn cast(ctx: &Self::Context, input: &Self::Buffer, from: DType, to: DType, size: usize) -> Result<Self::Buffer> {
+        let data = Self::read_buffer_bytes(ctx, input, from)?;
+        let converted = dtype::convert_bytes(&data, from, to);
+        Self::allocate_typed_buffer(ctx, to, size, Some(&converted))
+    }
+
     /// Performs element-wise addition
     fn element_wise_add(
         ctx: &Self::Context,
@@ -33,6 +210,345 @@ pub trait ComputeBackend: Send + Sync + 'static {
         size: usize
     ) -> Result<Self::Buffer>;
 
+    /// Applies `op` element-wise over two strided views broadcast to `shape`,
+    /// producing a new contiguous buffer.
+    ///
+    /// The default implementation dispatches to `element_wise_add`/`element_wise_multiply`
+    /// when both operands are dense and otherwise evaluates on the host via `read_buffer`.
+    fn binary_op(
+        ctx: &Self::Context,
+        op: BinaryOp,
+        a: BufferView<'_, Self::Buffer>,
+        b: BufferView<'_, Self::Buffer>,
+        shape: &[usize]
+    ) -> Result<Self::Buffer> {
+        let size = shape.iter().product();
+        let dense = a.is_dense(shape) && b.is_dense(shape)
+            && Self::buffer_len(a.buffer, DType::F32) == size && Self::buffer_len(b.buffer, DType::F32) == size;
+
+        match op {
+            BinaryOp::Add if dense => Self::element_wise_add(ctx, a.buffer, b.buffer, size),
+            BinaryOp::Multiply if dense => Self::element_wise_multiply(ctx, a.buffer, b.buffer, size),
+            _ => {
+                let a_data = Self::read_buffer(ctx, a.buffer)?;
+                let b_data = Self::read_buffer(ctx, b.buffer)?;
+                a.check_bounds(shape, a_data.len())?;
+                b.check_bounds(shape, b_data.len())?;
+                let mut out = Vec::with_capacity(size);
+                strided::for_each_offset_pair(shape, (a.strides, a.offset), (b.strides, b.offset), |i, j| {
+                    out.push(op.apply(a_data[i], b_data[j]));
+                });
+                Self::allocate_buffer(ctx, size, Some(&out))
+            }
+        }
+    }
+
+    /// Like `binary_op`, but writes the result into `out`, a dense f32 buffer of `shape`'s
+    /// element count. The default implementation calls `binary_op` and replaces `out`.
+    fn binary_op_into(
+        ctx: &Self::Context,
+        op: BinaryOp,
+        a: BufferView<'_, Self::Buffer>,
+        b: BufferView<'_, Self::Buffer>,
+        shape: &[usize],
+        out: &mut Self::Buffer
+    ) -> Result<()> {
+        check_output::<Self>(out, DType::F32, shape.iter().product())?;
+        let result = Self::binary_op(ctx, op, a, b, shape)?;
+        replace_buffer::<Self>(ctx, out, result);
+        Ok(())
+    }
+
+    /// Updates the dense f32 buffer `target`, holding `shape`'s elements, to `target op b`
+    /// in place, with `b` broadcast to `shape` through its strides.
+    ///
+    /// The default implementation replaces `target` with a new buffer.
+    fn binary_op_assign(
+        ctx: &Self::Context,
+        op: BinaryOp,
+        target: &mut Self::Buffer,
+        b: BufferView<'_, Self::Buffer>,
+        shape: &[usize]
+    ) -> Result<()> {
+        let size = shape.iter().product();
+        check_output::<Self>(target, DType::F32, size)?;
+        let mut data = Self::read_buffer(ctx, target)?;
+        let b_data = Self::read_buffer(ctx, b.buffer)?;
+        b.check_bounds(shape, b_data.len())?;
+        let mut index = 0;
+        strided::for_each_offset(shape, b.strides, b.offset, |j| {
+            data[index] = op.apply(data[index], b_data[j]);
+            index += 1;
+        });
+        let result = Self::allocate_buffer(ctx, size, Some(&data))?;
+        replace_buffer::<Self>(ctx, target, result);
+        Ok(())
+    }
+
+    /// Reduces a dense f32 buffer, viewed as a row-major `(outer, size, inner)` array, along its
+    /// middle dimension into `outer * inner` elements of `op.output_dtype()`.
+    fn reduce(
+        ctx: &Self::Context,
+        op: ReduceOp,
+        input: &Self::Buffer,
+        outer: usize,
+        size: usize,
+        inner: usize
+    ) -> Result<Self::Buffer> {
+        let data = Self::read_buffer(ctx, input)?;
+        if op.output_dtype() == DType::I64 {
+            let indices = reduce::arg_reduce(op, &data, outer, size, inner);
+            return Self::allocate_typed_buffer(ctx, DType::I64, indices.len(), Some(&dtype::to_bytes(&indices)));
+        }
+        let mut values = vec![0.0; outer * inner];
+        reduce::reduce(op, &data, outer, size, inner, &mut values);
+        Self::allocate_buffer(ctx, values.len(), Some(&values))
+    }
+
+    /// Applies a fused softmax-family `op` to a dense f32 buffer, viewed as a row-major
+    /// `(outer, size, inner)` array, along its middle dimension. `LogSumExp` produces
+    /// `outer * inner` elements; the other ops keep the input layout.
+    fn softmax(
+        ctx: &Self::Context,
+        op: SoftmaxOp,
+        input: &Self::Buffer,
+        outer: usize,
+        size: usize,
+        inner: usize
+    ) -> Result<Self::Buffer> {
+        let data = Self::read_buffer(ctx, input)?;
+        let mut out = vec![0.0; op.output_len(data.len(), outer, inner)];
+        softmax::softmax(op, &data, outer, size, inner, &mut out);
+        Self::allocate_buffer(ctx, out.len(), Some(&out))
+    }
+
+    /// 2D convolution of a dense NCHW `input` with a `[C_out, C_in / groups, kh, kw]` weight,
+    /// producing `[N, C_out, H_out, W_out]`.
+    fn conv2d(ctx: &Self::Context, input: &Self::Buffer, weight: &Self::Buffer, params: &Conv2dParams) -> Result<Self::Buffer> {
+        let mut output = vec![0.0; params.output_len()];
+        conv::conv2d(params, &Self::read_buffer(ctx, input)?, &Self::read_buffer(ctx, weight)?, &mut output)?;
+        Self::allocate_buffer(ctx, output.len(), Some(&output))
+    }
+
+    /// Gradient of `conv2d` with respect to its input, `[N, C_in, H, W]`.
+    /// With the roles of the channels swapped this is also the transposed convolution.
+    fn conv2d_backward_input(
+        ctx: &Self::Context,
+        grad_output: &Self::Buffer,
+        weight: &Self::Buffer,
+        params: &Conv2dParams
+    ) -> Result<Self::Buffer> {
+        let mut grad = vec![0.0; params.input_len()];
+        conv::conv2d_backward_input(params, &Self::read_buffer(ctx, grad_output)?, &Self::read_buffer(ctx, weight)?, &mut grad)?;
+        Self::allocate_buffer(ctx, grad.len(), Some(&grad))
+    }
+
+    /// Gradient of `conv2d` with respect to its weight, `[C_out, C_in / groups, kh, kw]`
+    fn conv2d_backward_weight(
+        ctx: &Self::Context,
+        input: &Self::Buffer,
+        grad_output: &Self::Buffer,
+        params: &Conv2dParams
+    ) -> Result<Self::Buffer> {
+        let mut grad = vec![0.0; params.weight_len()];
+        conv::conv2d_backward_weight(params, &Self::read_buffer(ctx, input)?, &Self::read_buffer(ctx, grad_output)?, &mut grad)?;
+        Self::allocate_buffer(ctx, grad.len(), Some(&grad))
+    }
+
+    /// 2D max pooling of a dense NCHW input. Returns the f32 maxima and an i64 buffer holding,
+    /// for each, the flat index of the selected element within its `H x W` input plane.
+    fn max_pool2d(ctx: &Self::Context, input: &Self::Buffer, params: &Pool2dParams) -> Result<(Self::Buffer, Self::Buffer)> {
+        let mut values = vec![0.0; params.output_len()];
+        let indices = pool::max_pool2d(params, &Self::read_buffer(ctx, input)?, &mut values)?;
+        Ok((
+            Self::allocate_buffer(ctx, values.len(), Some(&values))?,
+            Self::allocate_typed_buffer(ctx, DType::I64, indices.len(), Some(&dtype::to_bytes(&indices)))?,
+        ))
+    }
+
+    /// Gradient of `max_pool2d` with respect to its input, given the indices it returned
+    fn max_pool2d_backward(
+        ctx: &Self::Context,
+        grad_output: &Self::Buffer,
+        indices: &Self::Buffer,
+        params: &Pool2dParams
+    ) -> Result<Self::Buffer> {
+        let indices = dtype::from_bytes::<i64>(&Self::read_buffer_bytes(ctx, indices, DType::I64)?);
+        let mut grad = vec![0.0; params.input_len()];
+        pool::max_pool2d_backward(params, &Self::read_buffer(ctx, grad_output)?, &indices, &mut grad)?;
+        Self::allocate_buffer(ctx, grad.len(), Some(&grad))
+    }
+
+    /// 2D average pooling of a dense NCHW input. With `count_include_pad` the zero padding
+    /// counts towards each window's divisor.
+    fn avg_pool2d(ctx: &Self::Context, input: &Self::Buffer, params: &Pool2dParams, count_include_pad: bool) -> Result<Self::Buffer> {
+        let mut output = vec![0.0; params.output_len()];
+        pool::avg_pool2d(params, &Self::read_buffer(ctx, input)?, count_include_pad, &mut output)?;
+        Self::allocate_buffer(ctx, output.len(), Some(&output))
+    }
+
+    /// Gradient of `avg_pool2d` with respect to its input
+    fn avg_pool2d_backward(
+        ctx: &Self::Context,
+        grad_output: &Self::Buffer,
+        params: &Pool2dParams,
+        count_include_pad: bool
+    ) -> Result<Self::Buffer> {
+        let mut grad = vec![0.0; params.input_len()];
+        pool::avg_pool2d_backward(params, &Self::read_buffer(ctx, grad_output)?, count_include_pad, &mut grad)?;
+        Self::allocate_buffer(ctx, grad.len(), Some(&grad))
+    }
+
+    /// Normalizes each of the `rows` contiguous rows of `size` elements in a dense f32 buffer.
+    /// Returns the normalized rows and a `rows`-element buffer of reciprocal standard
+    /// deviations (reciprocal RMS values for `NormOp::RmsNorm`).
+    fn normalize(
+        ctx: &Self::Context,
+        op: NormOp,
+        input: &Self::Buffer,
+        rows: usize,
+        size: usize,
+        eps: f32
+    ) -> Result<(Self::Buffer, Self::Buffer)> {
+        let (mut output, mut rstd) = (vec![0.0; rows * size], vec![0.0; rows]);
+        norm::normalize(op, &Self::read_buffer(ctx, input)?, size, eps, &mut output, &mut rstd)?;
+        Ok((
+            Self::allocate_buffer(ctx, output.len(), Some(&output))?,
+            Self::allocate_buffer(ctx, rstd.len(), Some(&rstd))?,
+        ))
+    }
+
+    /// Gradient of `normalize` with respect to its input, given its two outputs
+    fn normalize_backward(
+        ctx: &Self::Context,
+        op: NormOp,
+        normalized: &Self::Buffer,
+        rstd: &Self::Buffer,
+        grad: &Self::Buffer,
+        size: usize
+    ) -> Result<Self::Buffer> {
+        let normalized = Self::read_buffer(ctx, normalized)?;
+        let mut grad_input = vec![0.0; normalized.len()];
+        norm::normalize_backward(
+            op,
+            &normalized,
+            &Self::read_buffer(ctx, rstd)?,
+            &Self::read_buffer(ctx, grad)?,
+            size,
+            &mut grad_input,
+        )?;
+        Self::allocate_buffer(ctx, grad_input.len(), Some(&grad_input))
+    }
+
+    /// Computes the loss of each row of a dense `[rows, classes]` f32 input against a buffer of
+    /// `rows` i64 class targets, producing `rows` values and the number of rows not ignored,
+    /// which a mean reduction divides by. Rows whose target is the ignore index produce 0; any
+    /// other target outside `0..classes` is an error.
+    fn class_loss(
+        ctx: &Self::Context,
+        input: &Self::Buffer,
+        targets: &Self::Buffer,
+        params: &ClassLossParams
+    ) -> Result<(Self::Buffer, usize)> {
+        let targets = dtype::from_bytes::<i64>(&Self::read_buffer_bytes(ctx, targets, DType::I64)?);
+        let mut losses = vec![0.0; params.rows];
+        let counted = loss::class_loss(params, &Self::read_buffer(ctx, input)?, &targets, &mut losses)?;
+        Ok((Self::allocate_buffer(ctx, losses.len(), Some(&losses))?, counted))
+    }
+
+    /// Gradient of `class_loss` with respect to its input, given the gradient of each row's loss
+    fn class_loss_backward(
+        ctx: &Self::Context,
+        input: &Self::Buffer,
+        targets: &Self::Buffer,
+        grad: &Self::Buffer,
+        params: &ClassLossParams
+    ) -> Result<Self::Buffer> {
+        let targets = dtype::from_bytes::<i64>(&Self::read_buffer_bytes(ctx, targets, DType::I64)?);
+        let mut grad_input = vec![0.0; params.rows * params.classes];
+        loss::class_loss_backward(params, &Self::read_buffer(ctx, input)?, &targets, &Self::read_buffer(ctx, grad)?, &mut grad_input)?;
+        Self::allocate_buffer(ctx, grad_input.len(), Some(&grad_input))
+    }
+
+    /// Applies the element-wise loss `op` to `size` pairs of inputs and targets.
+    fn pointwise_loss(
+        ctx: &Self::Context,
+        op: PointwiseLossOp,
+        input: &Self::Buffer,
+        target: &Self::Buffer,
+        size: usize
+    ) -> Result<Self::Buffer> {
+        let mut out = vec![0.0; size];
+        loss::pointwise_loss(op, &Self::read_buffer(ctx, input)?, &Self::read_buffer(ctx, target)?, &mut out)?;
+        Self::allocate_buffer(ctx, size, Some(&out))
+    }
+
+    /// Computes `grad * d op(input, target) / d input` element-wise, the backward pass of
+    /// `pointwise_loss` with respect to its input.
+    fn pointwise_loss_backward(
+        ctx: &Self::Context,
+        op: PointwiseLossOp,
+        input: &Self::Buffer,
+        target: &Self::Buffer,
+        grad: &Self::Buffer,
+        size: usize
+    ) -> Result<Self::Buffer> {
+        let (input, target) = (Self::read_buffer(ctx, input)?, Self::read_buffer(ctx, target)?);
+        let mut out = vec![0.0; size];
+        loss::pointwise_loss_backward(op, &input, &target, &Self::read_buffer(ctx, grad)?, &mut out)?;
+        Self::allocate_buffer(ctx, size, Some(&out))
+    }
+
+    /// Applies one optimizer update in place to a dense f32 parameter of `size` elements, given
+    /// its gradient and the `update.state_len()` state buffers the optimizer keeps for it.
+    /// The parameter and state buffers must not be shared with any other operand.
+    ///
+    /// The default implementation replaces the parameter and state buffers with newly
+    /// allocated ones.
+    fn optimizer_step(
+        ctx: &Self::Context,
+        update: &OptimizerUpdate,
+        param: &mut Self::Buffer,
+        grad: &Self::Buffer,
+        state: &mut [&mut Self::Buffer],
+        size: usize
+    ) -> Result<()> {
+        let mut values = Self::read_buffer(ctx, param)?;
+        let grad = Self::read_buffer(ctx, grad)?;
+        let mut host_state = state.iter().map(|buffer| Self::read_buffer(ctx, buffer)).collect::<Result<Vec<_>>>()?;
+        let mut slices: Vec<&mut [f32]> = host_state.iter_mut().map(Vec::as_mut_slice).collect();
+        optim::step(update, &mut values, &grad, &mut slices);
+
+        let updated = Self::allocate_buffer(ctx, size, Some(&values))?;
+        replace_buffer::<Self>(ctx, param, updated);
+        for (buffer, values) in state.iter_mut().zip(&host_state) {
+            let updated = Self::allocate_buffer(ctx, size, Some(values))?;
+            replace_buffer::<Self>(ctx, buffer, updated);
+        }
+        Ok(())
+    }
+
+    /// Applies `op` to each of the `size` elements of a dense f32 buffer.
+    fn unary_op(ctx: &Self::Context, op: UnaryOp, input: &Self::Buffer, size: usize) -> Result<Self::Buffer> {
+        let data = Self::read_buffer(ctx, input)?;
+        let out: Vec<f32> = data.iter().map(|&x| op.apply(x)).collect();
+        Self::allocate_buffer(ctx, size, Some(&out))
+    }
+
+    /// Computes `grad * op'(input)` element-wise, the backward pass of `unary_op`.
+    fn unary_op_backward(
+        ctx: &Self::Context,
+        op: UnaryOp,
+        input: &Self::Buffer,
+        grad: &Self::Buffer,
+        size: usize
+    ) -> Result<Self::Buffer> {
+        let data = Self::read_buffer(ctx, input)?;
+        let grad = Self::read_buffer(ctx, grad)?;
+        let out: Vec<f32> = data.iter().zip(&grad).map(|(&x, &g)| g * op.derivative(x)).collect();
+        Self::allocate_buffer(ctx, size, Some(&out))
+    }
+
     /// Performs scalar multiplication
     fn scalar_multiply(
         ctx: &Self::Context,
@@ -41,6 +557,30 @@ pub trait ComputeBackend: Send + Sync + 'static {
         size: usize
     ) -> Result<Self::Buffer>;
 
+    /// Like `scalar_multiply`, but writes into `out`, a dense f32 buffer of `size` elements.
+    /// The default implementation calls `scalar_multiply` and replaces `out`.
+    fn scalar_multiply_into(
+        ctx: &Self::Context,
+        input: &Self::Buffer,
+        scalar: f32,
+        size: usize,
+        out: &mut Self::Buffer
+    ) -> Result<()> {
+        check_output::<Self>(out, DType::F32, size)?;
+        let result = Self::scalar_multiply(ctx, input, scalar, size)?;
+        replace_buffer::<Self>(ctx, out, result);
+        Ok(())
+    }
+
+    /// Multiplies the `size` elements of a dense f32 buffer by `scalar` in place.
+    /// The default implementation calls `scalar_multiply` and replaces `target`.
+    fn scalar_multiply_assign(ctx: &Self::Context, target: &mut Self::Buffer, scalar: f32, size: usize) -> Result<()> {
+        check_output::<Self>(target, DType::F32, size)?;
+        let result = Self::scalar_multiply(ctx, target, scalar, size)?;
+        replace_buffer::<Self>(ctx, target, result);
+        Ok(())
+    }
+
     /// Synchronizes the backend (if needed)
     fn synchronize(ctx: &Self::Context) -> Result<()>;
 
@@ -55,6 +595,7 @@ pub trait ComputeBackend: Send + Sync + 'static {
     /// * `k` - Inner dimension
     /// * `transpose_a` - Whether to transpose matrix A
     /// * `transpose_b` - Whether to transpose matrix B
+    #[allow(clippy::too_many_arguments)]
     fn matmul_transposed(
         ctx: &Self::Context,
         a: &Self::Buffer,
@@ -67,6 +608,7 @@ pub trait ComputeBackend: Send + Sync + 'static {
     ) -> Result<Self::Buffer>;
 
     /// Performs batched matrix multiplication with optional transposition
+    #[allow(clippy::too_many_arguments)]
     fn matmul_transposed_batched(
         ctx: &Self::Context,
         a: &Self::Buffer,
@@ -89,10 +631,89 @@ pub trait ComputeBackend: Send + Sync + 'static {
         batch_size: usize, m: usize, n: usize, k: usize) -> Result<Self::Buffer> {
         Self::matmul_transposed_batched(ctx, a, b, batch_size, m, n, k, false, false)
     }
+
+    /// Like `matmul_transposed_batched`, but writes the `batch_size` (M x N) results into `out`,
+    /// a dense f32 buffer of `batch_size * m * n` elements.
+    /// The default implementation calls `matmul_transposed_batched` and replaces `out`.
+    #[allow(clippy::too_many_arguments)]
+    fn matmul_into(
+        ctx: &Self::Context,
+        a: &Self::Buffer,
+        b: &Self::Buffer,
+        out: &mut Self::Buffer,
+        batch_size: usize,
+        m: usize,
+        n: usize,
+        k: usize,
+        transpose_a: bool,
+        transpose_b: bool
+    ) -> Result<()> {
+        check_output::<Self>(out, DType::F32, batch_size * m * n)?;
+        let result = Self::matmul_transposed_batched(ctx, a, b, batch_size, m, n, k, transpose_a, transpose_b)?;
+        replace_buffer::<Self>(ctx, out, result);
+        Ok(())
+    }
+}
+
+/// Checks that an output buffer handed to a `*_into` method holds `size` elements of `dtype`
+fn check_output<B: ComputeBackend + ?Sized>(out: &B::Buffer, dtype: DType, size: usize) -> Result<()> {
+    let len = B::buffer_len(out, dtype);
+    if len != size {
+        return Err(FerroFlowError::ShapeMismatch(format!(
+            "Output buffer holds {} {} elements, expected {}", len, dtype, size
+        )));
+    }
+    Ok(())
+}
+
+/// Replaces a buffer with a newly computed one, handing the old one back to the backend
+fn replace_buffer<B: ComputeBackend + ?Sized>(ctx: &B::Context, buffer: &mut B::Buffer, replacement: B::Buffer) {
+    B::release_buffer(ctx, buffer);
+    *buffer = replacement;
+}
+
+/// Checks that a kernel operand holds the `expected` number of elements
+fn check_operand<T>(name: &str, buffer: &[T], expected: usize) -> Result<()> {
+    if buffer.len() != expected {
+        return Err(FerroFlowError::ShapeMismatch(format!(
+            "{} has {} elements, expected {}", name, buffer.len(), expected
+        )));
+    }
+    Ok(())
+}
+
+/// Borrows the bytes in `range` of a loaded file, checking that they hold whole `dtype` elements
+fn storage_bytes(storage: &Storage, range: Range<usize>, dtype: DType) -> Result<&[u8]> {
+    match storage.get(range.clone()) {
+        Some(bytes) if bytes.len().is_multiple_of(dtype.size_in_bytes()) => Ok(bytes),
+        _ => Err(FerroFlowError::BufferError(format!(
+            "Bytes {:?} of a {} byte file don't hold whole {} elements", range, storage.len(), dtype
+        ))),
+    }
 }
 
+mod allocator;
+mod conv;
 mod cpu;
+mod loss;
+mod norm;
+mod optim;
+mod pool;
+pub(crate) mod reduce;
+mod softmax;
+pub(crate) mod strided;
+mod unary;
+#[cfg(all(feature = "metal", target_os = "macos"))]
 mod metal;
 
-pub use cpu::CPUBackend;
+pub use allocator::MemoryStats;
+pub use conv::Conv2dParams;
+pub use cpu::{CPUBackend, CPUBuffer, CPUContext, MappedBuffer, SimdLevel};
+pub use loss::{ClassLossOp, ClassLossParams, PointwiseLossOp};
+pub use norm::NormOp;
+pub use optim::OptimizerUpdate;
+pub use pool::{Pool2dParams, PoolWindow};
+pub use softmax::SoftmaxOp;
+pub use unary::UnaryOp;
+#[cfg(all(feature = "metal", target_os = "macos"))]
 pub use metal::MetalBackend; 
\ No newline at end of file
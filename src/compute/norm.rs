@@ -0,0 +1,76 @@
+//! Host implementation of the fused kernels behind [`ComputeBackend::normalize`](super::ComputeBackend::normalize).
+
+use super::check_operand;
+use crate::error::Result;
+
+/// Row normalizations supported by [`ComputeBackend::normalize`](super::ComputeBackend::normalize)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NormOp {
+    /// `(x - mean) / sqrt(var + eps)` with the biased variance
+    LayerNorm,
+    /// `x / sqrt(mean(x^2) + eps)`
+    RmsNorm,
+}
+
+/// Normalizes each contiguous row of `size` elements in `data`, which holds one row per
+/// element of `rstd`. Writes the normalized rows into `output` and the reciprocal standard
+/// deviation (or RMS) of each row, which is all the backward pass needs, into `rstd`.
+pub(crate) fn normalize(op: NormOp, data: &[f32], size: usize, eps: f32, output: &mut [f32], rstd: &mut [f32]) -> Result<()> {
+    check_operand("Normalization input", data, rstd.len() * size)?;
+    for (row, (input, out)) in data.chunks_exact(size).zip(output.chunks_exact_mut(size)).enumerate() {
+        let (center, mean_square) = match op {
+            NormOp::LayerNorm => welford(input),
+            NormOp::RmsNorm => (0.0, input.iter().map(|&x| x as f64 * x as f64).sum::<f64>() / size as f64),
+        };
+        let inv = (1.0 / (mean_square + eps as f64).sqrt()) as f32;
+        let center = center as f32;
+        for (o, &x) in out.iter_mut().zip(input) {
+            *o = (x - center) * inv;
+        }
+        rstd[row] = inv;
+    }
+    Ok(())
+}
+
+/// Gradient of `normalize` with respect to its input, given the normalized rows `x̂`, their
+/// `rstd` and the incoming gradient `g`:
+/// `rstd * (g - mean(g) - x̂ * mean(g * x̂))`, without the `mean(g)` term for RMSNorm.
+pub(crate) fn normalize_backward(
+    op: NormOp,
+    normalized: &[f32],
+    rstd: &[f32],
+    grad: &[f32],
+    size: usize,
+    grad_input: &mut [f32]
+) -> Result<()> {
+    check_operand("Normalization gradient", grad, normalized.len())?;
+    check_operand("Normalization input", normalized, rstd.len() * size)?;
+    let rows = normalized.chunks_exact(size).zip(grad.chunks_exact(size)).zip(grad_input.chunks_exact_mut(size));
+    for (((x_hat, g), out), &inv) in rows.zip(rstd) {
+        let mut grad_mean = 0.0f64;
+        let mut projection = 0.0f64;
+        for (&x, &g) in x_hat.iter().zip(g) {
+            grad_mean += g as f64;
+            projection += g as f64 * x as f64;
+        }
+        let grad_mean = if op == NormOp::LayerNorm { (grad_mean / size as f64) as f32 } else { 0.0 };
+        let projection = (projection / size as f64) as f32;
+        for ((o, &x), &g) in out.iter_mut().zip(x_hat).zip(g) {
+            *o = inv * (g - grad_mean - x * projection);
+        }
+    }
+    Ok(())
+}
+
+/// Returns the mean and biased variance of `values` in a single pass using Welford's update,
+/// which does not suffer the cancellation of `E[x^2] - E[x]^2`
+fn welford(values: &[f32]) -> (f64, f64) {
+    let (mut mean, mut m2) = (0.0f64, 0.0f64);
+    for (count, &x) in values.iter().enumerate() {
+        let x = x as f64;
+        let delta = x - mean;
+        mean += delta / (count + 1) as f64;
+        m2 += delta * (x - mean);
+    }
+    (mean, m2 / values.len() as f64)
+}
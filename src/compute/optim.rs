@@ -0,0 +1,93 @@
+//! Host implementation of the fused kernel behind [`ComputeBackend::optimizer_step`](super::ComputeBackend::optimizer_step).
+
+/// A single optimizer update of one parameter, with the hyperparameters of its group.
+/// Each variant keeps [`state_len`](Self::state_len) per-parameter state buffers of the
+/// parameter's size, in the order listed on the variant.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OptimizerUpdate {
+    /// Stochastic gradient descent; state `[momentum_buffer]` when `momentum` is non-zero.
+    /// `step` counts from 1, and the first step seeds the momentum buffer with the gradient.
+    Sgd { lr: f32, momentum: f32, dampening: f32, weight_decay: f32, nesterov: bool, step: u64 },
+    /// Adam with bias correction; state `[exp_avg, exp_avg_sq]`. With `decoupled` the weight
+    /// decay shrinks the parameter directly (AdamW) instead of being added to the gradient.
+    Adam { lr: f32, beta1: f32, beta2: f32, eps: f32, weight_decay: f32, decoupled: bool, step: u64 },
+    /// RMSProp; state `[square_avg]`, followed by `grad_avg` when `centered` and by
+    /// `momentum_buffer` when `momentum` is non-zero
+    RmsProp { lr: f32, alpha: f32, eps: f32, weight_decay: f32, momentum: f32, centered: bool },
+}
+
+impl OptimizerUpdate {
+    /// Returns the number of state buffers the update reads and writes
+    pub fn state_len(&self) -> usize {
+        match *self {
+            OptimizerUpdate::Sgd { momentum, .. } => usize::from(momentum != 0.0),
+            OptimizerUpdate::Adam { .. } => 2,
+            OptimizerUpdate::RmsProp { momentum, centered, .. } => 1 + usize::from(centered) + usize::from(momentum != 0.0),
+        }
+    }
+}
+
+/// Updates `param` and its `state` in place from `grad`. All slices have the same length,
+/// and `state` holds `update.state_len()` of them.
+pub(crate) fn step(update: &OptimizerUpdate, param: &mut [f32], grad: &[f32], state: &mut [&mut [f32]]) {
+    match *update {
+        OptimizerUpdate::Sgd { lr, momentum, dampening, weight_decay, nesterov, step } => {
+            for (i, (p, &g)) in param.iter_mut().zip(grad).enumerate() {
+                let mut g = g + weight_decay * *p;
+                if momentum != 0.0 {
+                    let buf = &mut state[0][i];
+                    *buf = if step <= 1 { g } else { momentum * *buf + (1.0 - dampening) * g };
+                    g = if nesterov { g + momentum * *buf } else { *buf };
+                }
+                *p -= lr * g;
+            }
+        }
+        OptimizerUpdate::Adam { lr, beta1, beta2, eps, weight_decay, decoupled, step } => {
+            let bias_correction1 = 1.0 - beta1.powi(step as i32);
+            let bias_correction2 = 1.0 - beta2.powi(step as i32);
+            let (exp_avg, exp_avg_sq) = match state {
+                [exp_avg, exp_avg_sq] => (exp_avg, exp_avg_sq),
+                _ => unreachable!("Adam keeps two state buffers"),
+            };
+            for (i, (p, &g)) in param.iter_mut().zip(grad).enumerate() {
+                let g = if decoupled {
+                    *p *= 1.0 - lr * weight_decay;
+                    g
+                } else {
+                    g + weight_decay * *p
+                };
+                let m = beta1 * exp_avg[i] + (1.0 - beta1) * g;
+                let v = beta2 * exp_avg_sq[i] + (1.0 - beta2) * g * g;
+                exp_avg[i] = m;
+                exp_avg_sq[i] = v;
+                *p -= lr * (m / bias_correction1) / ((v / bias_correction2).sqrt() + eps);
+            }
+        }
+        OptimizerUpdate::RmsProp { lr, alpha, eps, weight_decay, momentum, centered } => {
+            let (square_avg, rest) = state.split_first_mut().expect("RMSProp keeps a square average");
+            let (mut grad_avg, mut momentum_buffer) = match (centered, rest) {
+                (true, [grad_avg, rest @ ..]) => (Some(grad_avg), rest.first_mut()),
+                (_, rest) => (None, rest.first_mut()),
+            };
+            for (i, (p, &g)) in param.iter_mut().zip(grad).enumerate() {
+                let g = g + weight_decay * *p;
+                square_avg[i] = alpha * square_avg[i] + (1.0 - alpha) * g * g;
+                let variance = match grad_avg.as_mut() {
+                    Some(grad_avg) => {
+                        grad_avg[i] = alpha * grad_avg[i] + (1.0 - alpha) * g;
+                        square_avg[i] - grad_avg[i] * grad_avg[i]
+                    }
+                    None => square_avg[i],
+                };
+                let update = g / (variance.sqrt() + eps);
+                match momentum_buffer.as_mut() {
+                    Some(buf) => {
+                        buf[i] = momentum * buf[i] + update;
+                        *p -= lr * buf[i];
+                    }
+                    None => *p -= lr * update,
+                }
+            }
+        }
+    }
+}
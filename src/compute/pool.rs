@@ -0,0 +1,190 @@
+//! Host implementation of 2D max and average pooling over NCHW inputs.
+
+use super::check_operand;
+use crate::error::{Result, FerroFlowError};
+
+/// How pooling windows are laid out over each `H x W` plane
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PoolWindow {
+    /// `kernel_size` windows every `stride` pixels over the input padded by `padding` on each side
+    Fixed {
+        kernel_size: (usize, usize),
+        stride: (usize, usize),
+        padding: (usize, usize),
+    },
+    /// Exactly `output_size` windows per axis; along an axis of length `L` with `O` outputs,
+    /// window `i` covers `[floor(i * L / O), ceil((i + 1) * L / O))`
+    Adaptive { output_size: (usize, usize) },
+}
+
+/// Geometry of a 2D pooling over `[N, C, H, W]` inputs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pool2dParams {
+    pub batch_size: usize,
+    pub channels: usize,
+    pub input_size: (usize, usize),
+    pub window: PoolWindow,
+}
+
+/// The input range `[start, end)` covered by a window along one axis, and the window's
+/// length including padding
+#[derive(Debug, Clone, Copy)]
+struct Span {
+    start: usize,
+    end: usize,
+    padded_len: usize,
+}
+
+impl Pool2dParams {
+    /// Returns the spatial size of the output, `(H_out, W_out)`.
+    /// Inputs smaller than a fixed window produce an empty output.
+    pub fn output_size(&self) -> (usize, usize) {
+        match self.window {
+            PoolWindow::Fixed { kernel_size, stride, padding } => {
+                let out = |input: usize, kernel: usize, stride: usize, padding: usize| {
+                    (input + 2 * padding).checked_sub(kernel).map_or(0, |room| room / stride + 1)
+                };
+                (
+                    out(self.input_size.0, kernel_size.0, stride.0, padding.0),
+                    out(self.input_size.1, kernel_size.1, stride.1, padding.1),
+                )
+            }
+            PoolWindow::Adaptive { output_size } => output_size,
+        }
+    }
+
+    pub fn input_len(&self) -> usize {
+        self.batch_size * self.channels * self.input_size.0 * self.input_size.1
+    }
+
+    pub fn output_len(&self) -> usize {
+        let (h, w) = self.output_size();
+        self.batch_size * self.channels * h * w
+    }
+
+    /// Returns the spans of every output row and column
+    fn spans(&self) -> (Vec<Span>, Vec<Span>) {
+        let (out_h, out_w) = self.output_size();
+        let axis = |len: usize, outputs: usize, fixed: Option<(usize, usize, usize)>| -> Vec<Span> {
+            (0..outputs)
+                .map(|i| match fixed {
+                    Some((kernel, stride, padding)) => {
+                        let start = (i * stride) as isize - padding as isize;
+                        let end = (start + kernel as isize).min(len as isize);
+                        Span { start: start.max(0) as usize, end: end.max(0) as usize, padded_len: kernel }
+                    }
+                    None => {
+                        let (start, end) = (i * len / outputs, ((i + 1) * len).div_ceil(outputs));
+                        Span { start, end, padded_len: end - start }
+                    }
+                })
+                .collect()
+        };
+
+        let (rows, cols) = match self.window {
+            PoolWindow::Fixed { kernel_size, stride, padding } => (
+                Some((kernel_size.0, stride.0, padding.0)),
+                Some((kernel_size.1, stride.1, padding.1)),
+            ),
+            PoolWindow::Adaptive { .. } => (None, None),
+        };
+        (axis(self.input_size.0, out_h, rows), axis(self.input_size.1, out_w, cols))
+    }
+
+    /// Calls `f(plane, output_index, rows, cols)` for every window, where `output_index`
+    /// is the flat index into the output
+    fn for_each_window(&self, mut f: impl FnMut(usize, usize, Span, Span)) {
+        let (rows, cols) = self.spans();
+        let mut output_index = 0;
+        for plane in 0..self.batch_size * self.channels {
+            for &row in &rows {
+                for &col in &cols {
+                    f(plane, output_index, row, col);
+                    output_index += 1;
+                }
+            }
+        }
+    }
+
+    /// Returns the number of elements an average divides by
+    fn divisor(rows: Span, cols: Span, count_include_pad: bool) -> f32 {
+        if count_include_pad {
+            (rows.padded_len * cols.padded_len) as f32
+        } else {
+            ((rows.end - rows.start) * (cols.end - cols.start)) as f32
+        }
+    }
+}
+
+/// Max pooling, writing the maxima into `values` and returning, for each, the flat index of the
+/// winning element within its `H x W` input plane. The first maximum wins; NaN propagates.
+pub(crate) fn max_pool2d(params: &Pool2dParams, input: &[f32], values: &mut [f32]) -> Result<Vec<i64>> {
+    check_operand("Pooling input", input, params.input_len())?;
+    let (h, w) = params.input_size;
+    let mut indices = vec![0; params.output_len()];
+
+    params.for_each_window(|plane, out, rows, cols| {
+        let image = &input[plane * h * w..(plane + 1) * h * w];
+        let (mut best, mut best_index) = (f32::NEG_INFINITY, rows.start * w + cols.start);
+        'window: for y in rows.start..rows.end {
+            for x in cols.start..cols.end {
+                let value = image[y * w + x];
+                if value > best || value.is_nan() {
+                    (best, best_index) = (value, y * w + x);
+                    if value.is_nan() {
+                        break 'window;
+                    }
+                }
+            }
+        }
+        values[out] = best;
+        indices[out] = best_index as i64;
+    });
+    Ok(indices)
+}
+
+/// Routes each output gradient to the input element recorded in `indices`, adding it to the
+/// zeroed `grad_input`. Fails if an index lies outside its input plane.
+pub(crate) fn max_pool2d_backward(params: &Pool2dParams, grad_output: &[f32], indices: &[i64], grad_input: &mut [f32]) -> Result<()> {
+    check_operand("Pooling output gradient", grad_output, params.output_len())?;
+    check_operand("Pooling indices", indices, params.output_len())?;
+    let plane_len = params.input_size.0 * params.input_size.1;
+    if indices.iter().any(|&index| index < 0 || index as usize >= plane_len) {
+        return Err(FerroFlowError::InvalidOperation("Pooling index out of range".into()));
+    }
+    params.for_each_window(|plane, out, _, _| {
+        grad_input[plane * plane_len + indices[out] as usize] += grad_output[out];
+    });
+    Ok(())
+}
+
+/// Average pooling into `output`. Padded positions count towards the divisor when
+/// `count_include_pad` is set.
+pub(crate) fn avg_pool2d(params: &Pool2dParams, input: &[f32], count_include_pad: bool, output: &mut [f32]) -> Result<()> {
+    check_operand("Pooling input", input, params.input_len())?;
+    let (h, w) = params.input_size;
+    params.for_each_window(|plane, out, rows, cols| {
+        let image = &input[plane * h * w..(plane + 1) * h * w];
+        let sum: f32 = (rows.start..rows.end)
+            .map(|y| image[y * w + cols.start..y * w + cols.end].iter().sum::<f32>())
+            .sum();
+        output[out] = sum / Pool2dParams::divisor(rows, cols, count_include_pad);
+    });
+    Ok(())
+}
+
+/// Spreads each output gradient evenly over its window, adding it to the zeroed `grad_input`
+pub(crate) fn avg_pool2d_backward(params: &Pool2dParams, grad_output: &[f32], count_include_pad: bool, grad_input: &mut [f32]) -> Result<()> {
+    check_operand("Pooling output gradient", grad_output, params.output_len())?;
+    let (h, w) = params.input_size;
+    params.for_each_window(|plane, out, rows, cols| {
+        let share = grad_output[out] / Pool2dParams::divisor(rows, cols, count_include_pad);
+        let image = &mut grad_input[plane * h * w..(plane + 1) * h * w];
+        for y in rows.start..rows.end {
+            for value in &mut image[y * w + cols.start..y * w + cols.end] {
+                *value += share;
+            }
+        }
+    });
+    Ok(())
+}
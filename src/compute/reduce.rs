@@ -0,0 +1,111 @@
+//! Host implementations of the reductions behind [`ComputeBackend::reduce`](super::ComputeBackend::reduce).
+
+use super::ReduceOp;
+
+/// Below this length a lane is summed directly with several independent accumulators
+const PAIRWISE_BLOCK: usize = 128;
+
+/// Reduces `data`, viewed as a row-major `(outer, size, inner)` array, along its middle
+/// dimension into `out`, ordered as the remaining `(outer, inner)` array. Arg reductions
+/// produce indices and go through [`arg_reduce`] instead.
+pub(crate) fn reduce(op: ReduceOp, data: &[f32], outer: usize, size: usize, inner: usize, out: &mut [f32]) {
+    let mut scratch = Vec::new();
+    for_each_lane(data, outer, size, inner, |index, lane| out[index] = reduce_lane(op, lane, &mut scratch));
+}
+
+/// Returns the index of the maximum (`ArgMax`) or minimum (`ArgMin`) of every lane, laid out
+/// like the results of [`reduce`]
+pub(crate) fn arg_reduce(op: ReduceOp, data: &[f32], outer: usize, size: usize, inner: usize) -> Vec<i64> {
+    let mut indices = vec![0; outer * inner];
+    for_each_lane(data, outer, size, inner, |index, lane| {
+        indices[index] = match op {
+            ReduceOp::ArgMax => arg_extreme(lane, |v, best| v > best),
+            ReduceOp::ArgMin => arg_extreme(lane, |v, best| v < best),
+            _ => unreachable!("{:?} produces values", op),
+        }
+    });
+    indices
+}
+
+/// Calls `f` with the index of each lane in the `(outer, inner)` result and its `size` values
+fn for_each_lane(data: &[f32], outer: usize, size: usize, inner: usize, mut f: impl FnMut(usize, &[f32])) {
+    let mut lane = Vec::with_capacity(if inner == 1 { 0 } else { size });
+    for o in 0..outer {
+        for i in 0..inner {
+            let base = o * size * inner + i;
+            // Lanes of the innermost dimension are already contiguous
+            let lane: &[f32] = if inner == 1 {
+                &data[base..base + size]
+            } else {
+                lane.clear();
+                lane.extend((0..size).map(|r| data[base + r * inner]));
+                &lane
+            };
+            f(o * inner + i, lane);
+        }
+    }
+}
+
+fn reduce_lane(op: ReduceOp, lane: &[f32], scratch: &mut Vec<f32>) -> f32 {
+    let len = lane.len() as f32;
+    match op {
+        ReduceOp::Sum => pairwise_sum(lane),
+        ReduceOp::Mean => pairwise_sum(lane) / len,
+        // NaN propagates: once the running value is NaN no comparison replaces it
+        ReduceOp::Max => lane.iter().fold(f32::NEG_INFINITY, |m, &v| if v.is_nan() || v > m { v } else { m }),
+        ReduceOp::Min => lane.iter().fold(f32::INFINITY, |m, &v| if v.is_nan() || v < m { v } else { m }),
+        ReduceOp::Prod => lane.iter().fold(1.0f64, |p, &v| p * v as f64) as f32,
+        ReduceOp::Var { correction } => variance(lane, correction, scratch),
+        ReduceOp::Std { correction } => variance(lane, correction, scratch).sqrt(),
+        ReduceOp::ArgMax | ReduceOp::ArgMin => unreachable!("arg reductions produce indices"),
+    }
+}
+
+/// Two-pass variance: the squared deviations from the mean are summed pairwise,
+/// which avoids the cancellation of the textbook `E[x^2] - E[x]^2` formula
+fn variance(lane: &[f32], correction: usize, scratch: &mut Vec<f32>) -> f32 {
+    let mean = pairwise_sum(lane) / lane.len() as f32;
+    scratch.clear();
+    scratch.extend(lane.iter().map(|&v| (v - mean) * (v - mean)));
+    pairwise_sum(scratch) / (lane.len() as f32 - correction as f32).max(0.0)
+}
+
+/// Returns the index of the first element preferred by `better`; a NaN always wins
+fn arg_extreme(lane: &[f32], better: impl Fn(f32, f32) -> bool) -> i64 {
+    let mut best = 0;
+    for (index, &value) in lane.iter().enumerate() {
+        if value.is_nan() {
+            return index as i64;
+        }
+        if better(value, lane[best]) {
+            best = index;
+        }
+    }
+    best as i64
+}
+
+/// Sums with pairwise (cascade) summation, whose rounding error grows with
+/// O(log n) instead of the O(n) of a running total
+pub(crate) fn pairwise_sum(values: &[f32]) -> f32 {
+    if values.len() <= PAIRWISE_BLOCK {
+        let mut accumulators = [0.0f32; 8];
+        let chunks = values.chunks_exact(8);
+        let remainder = chunks.remainder();
+        for chunk in chunks {
+            for (acc, &v) in accumulators.iter_mut().zip(chunk) {
+                *acc += v;
+            }
+        }
+        let mut total = ((accumulators[0] + accumulators[1]) + (accumulators[2] + accumulators[3]))
+            + ((accumulators[4] + accumulators[5]) + (accumulators[6] + accumulators[7]));
+        for &v in remainder {
+            total += v;
+        }
+        return total;
+    }
+
+    // Split on a block boundary so the leaves stay full
+    let half = (values.len() / 2).div_ceil(PAIRWISE_BLOCK) * PAIRWISE_BLOCK;
+    let (left, right) = values.split_at(half.min(values.len()));
+    pairwise_sum(left) + pairwise_sum(right)
+}
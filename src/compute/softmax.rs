@@ -0,0 +1,65 @@
+//! Host implementation of the fused kernels behind [`ComputeBackend::softmax`](super::ComputeBackend::softmax).
+
+/// Normalizations along one dimension supported by [`ComputeBackend::softmax`](super::ComputeBackend::softmax)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SoftmaxOp {
+    Softmax,
+    LogSoftmax,
+    /// `log(sum(exp(x)))`, which removes the dimension
+    LogSumExp,
+}
+
+impl SoftmaxOp {
+    /// Returns the number of values `op` produces from `len` input values of which `outer * inner`
+    /// lanes are normalized
+    pub(crate) fn output_len(self, len: usize, outer: usize, inner: usize) -> usize {
+        if self == SoftmaxOp::LogSumExp { outer * inner } else { len }
+    }
+}
+
+/// Applies `op` to `data`, viewed as a row-major `(outer, size, inner)` array, along its middle
+/// dimension, writing into `out`. `LogSumExp` produces `outer * inner` values, the others keep
+/// the input layout.
+pub(crate) fn softmax(op: SoftmaxOp, data: &[f32], outer: usize, size: usize, inner: usize, out: &mut [f32]) {
+    for o in 0..outer {
+        for i in 0..inner {
+            let base = o * size * inner + i;
+            let index = |r: usize| base + r * inner;
+            let (max, sum) = online_max_sum((0..size).map(|r| data[index(r)]));
+
+            match op {
+                SoftmaxOp::Softmax => {
+                    let scale = 1.0 / sum;
+                    for r in 0..size {
+                        out[index(r)] = (data[index(r)] - max).exp() * scale;
+                    }
+                }
+                SoftmaxOp::LogSoftmax => {
+                    // Subtracting the max first keeps the result exact for large logits
+                    let log_sum = sum.ln();
+                    for r in 0..size {
+                        out[index(r)] = (data[index(r)] - max) - log_sum;
+                    }
+                }
+                SoftmaxOp::LogSumExp => out[o * inner + i] = max + sum.ln(),
+            }
+        }
+    }
+}
+
+/// Computes the maximum `m` and `sum(exp(x - m))` in a single pass, rescaling the running
+/// sum whenever a new maximum appears so no exponent is ever positive. Elements equal to -inf
+/// are skipped, so a fully masked lane yields a sum of 0 instead of NaN.
+fn online_max_sum(values: impl Iterator<Item = f32>) -> (f32, f32) {
+    let mut max = f32::NEG_INFINITY;
+    let mut sum = 0.0f32;
+    for x in values {
+        if x > max {
+            sum = sum * (max - x).exp() + 1.0;
+            max = x;
+        } else if x != f32::NEG_INFINITY {
+            sum += (x - max).exp();
+        }
+    }
+    (max, sum)
+}
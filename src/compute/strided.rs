@@ -0,0 +1,127 @@
+//! Helpers for walking strided views of a flat buffer.
+
+/// Calls `f` with the buffer offset of every element of a strided view, in row-major order.
+pub(crate) fn for_each_offset(shape: &[usize], strides: &[usize], offset: usize, mut f: impl FnMut(usize)) {
+    if shape.contains(&0) {
+        return;
+    }
+    let Some((&inner, outer_shape)) = shape.split_last() else {
+        f(offset);
+        return;
+    };
+    let inner_stride = strides[shape.len() - 1];
+
+    let mut index = vec![0; outer_shape.len()];
+    let mut base = offset;
+    loop {
+        for i in 0..inner {
+            f(base + i * inner_stride);
+        }
+
+        // Advance the outer dimensions like an odometer
+        let mut dim = outer_shape.len();
+        loop {
+            if dim == 0 {
+                return;
+            }
+            dim -= 1;
+            index[dim] += 1;
+            base += strides[dim];
+            if index[dim] < outer_shape[dim] {
+                break;
+            }
+            base -= strides[dim] * outer_shape[dim];
+            index[dim] = 0;
+        }
+    }
+}
+
+/// Walks two strided views of the same logical shape in lockstep, calling `f` with the
+/// buffer offsets of each pair of elements in row-major order. Broadcast dimensions have stride 0.
+pub(crate) fn for_each_offset_pair(
+    shape: &[usize],
+    (a_strides, a_offset): (&[usize], usize),
+    (b_strides, b_offset): (&[usize], usize),
+    mut f: impl FnMut(usize, usize),
+) {
+    if shape.contains(&0) {
+        return;
+    }
+    let Some((&inner, outer_shape)) = shape.split_last() else {
+        f(a_offset, b_offset);
+        return;
+    };
+    let (a_inner, b_inner) = (a_strides[shape.len() - 1], b_strides[shape.len() - 1]);
+
+    let mut index = vec![0; outer_shape.len()];
+    let (mut a_base, mut b_base) = (a_offset, b_offset);
+    loop {
+        for i in 0..inner {
+            f(a_base + i * a_inner, b_base + i * b_inner);
+        }
+
+        let mut dim = outer_shape.len();
+        loop {
+            if dim == 0 {
+                return;
+            }
+            dim -= 1;
+            index[dim] += 1;
+            a_base += a_strides[dim];
+            b_base += b_strides[dim];
+            if index[dim] < outer_shape[dim] {
+                break;
+            }
+            a_base -= a_strides[dim] * outer_shape[dim];
+            b_base -= b_strides[dim] * outer_shape[dim];
+            index[dim] = 0;
+        }
+    }
+}
+
+/// Returns true if `strides` describe a dense row-major layout of `shape`.
+/// Dimensions of size 1 are ignored since their stride is never used.
+pub(crate) fn is_row_major(shape: &[usize], strides: &[usize]) -> bool {
+    let mut expected = 1;
+    for (&dim, &stride) in shape.iter().zip(strides).rev() {
+        if dim != 1 && stride != expected {
+            return false;
+        }
+        expected *= dim;
+    }
+    true
+}
+
+/// Returns the largest buffer offset a strided view of `shape` reads, `None` for an empty view
+/// or one whose offsets overflow `usize`, which no buffer can hold.
+pub(crate) fn last_offset(shape: &[usize], strides: &[usize], offset: usize) -> Option<usize> {
+    if shape.contains(&0) {
+        return None;
+    }
+    shape.iter().zip(strides).try_fold(offset, |last, (&dim, &stride)| last.checked_add((dim - 1).checked_mul(stride)?))
+}
+
+/// Copies a strided view of `src` into a new contiguous row-major vector.
+pub(crate) fn gather<T: Copy>(src: &[T], shape: &[usize], strides: &[usize], offset: usize) -> Vec<T> {
+    let mut out = Vec::with_capacity(shape.iter().product());
+    for_each_offset(shape, strides, offset, |i| out.push(src[i]));
+    out
+}
+
+/// Like [`gather`], writing into `out`, which holds the view's element count.
+pub(crate) fn gather_into<T: Copy>(src: &[T], shape: &[usize], strides: &[usize], offset: usize, out: &mut [T]) {
+    let mut index = 0;
+    for_each_offset(shape, strides, offset, |i| {
+        out[index] = src[i];
+        index += 1;
+    });
+}
+
+/// Like [`gather`] for a raw byte buffer whose elements are `element_size` bytes wide.
+pub(crate) fn gather_bytes(src: &[u8], element_size: usize, shape: &[usize], strides: &[usize], offset: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(shape.iter().product::<usize>() * element_size);
+    for_each_offset(shape, strides, offset, |i| {
+        out.extend_from_slice(&src[i * element_size..(i + 1) * element_size]);
+    });
+    out
+}
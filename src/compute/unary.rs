@@ -0,0 +1,116 @@
+//! Element-wise unary math and activation functions.
+
+/// Element-wise unary operations supported by [`ComputeBackend::unary_op`](super::ComputeBackend::unary_op).
+/// Adding a variant only requires extending `apply` and `derivative`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOp {
+    Exp,
+    Log,
+    Sqrt,
+    /// `1 / sqrt(x)`
+    Rsqrt,
+    Abs,
+    Sin,
+    Cos,
+    Tanh,
+    Sigmoid,
+    Relu,
+    /// `x * Φ(x)` with the exact normal CDF
+    Gelu,
+    /// The tanh approximation of GELU used by GPT-2 and BERT
+    GeluTanh,
+    /// `x * sigmoid(x)`, also known as swish
+    Silu,
+    /// `ln(1 + e^x)`
+    Softplus,
+    /// `x^exponent`
+    Pow(f32),
+    Clamp { min: f32, max: f32 },
+}
+
+const SQRT_2_OVER_PI: f32 = 0.797_884_6;
+const GELU_TANH_COEFF: f32 = 0.044_715;
+
+impl UnaryOp {
+    /// Applies the operation to a single value
+    #[inline]
+    pub fn apply(self, x: f32) -> f32 {
+        match self {
+            UnaryOp::Exp => x.exp(),
+            UnaryOp::Log => x.ln(),
+            UnaryOp::Sqrt => x.sqrt(),
+            UnaryOp::Rsqrt => 1.0 / x.sqrt(),
+            UnaryOp::Abs => x.abs(),
+            UnaryOp::Sin => x.sin(),
+            UnaryOp::Cos => x.cos(),
+            UnaryOp::Tanh => x.tanh(),
+            UnaryOp::Sigmoid => sigmoid(x),
+            UnaryOp::Relu => x.max(0.0),
+            UnaryOp::Gelu => x * normal_cdf(x),
+            UnaryOp::GeluTanh => 0.5 * x * (1.0 + (SQRT_2_OVER_PI * (x + GELU_TANH_COEFF * x * x * x)).tanh()),
+            UnaryOp::Silu => x * sigmoid(x),
+            // Rewritten so large |x| neither overflows nor loses the linear part
+            UnaryOp::Softplus => x.max(0.0) + (-x.abs()).exp().ln_1p(),
+            UnaryOp::Pow(exponent) => x.powf(exponent),
+            UnaryOp::Clamp { min, max } => x.clamp(min, max),
+        }
+    }
+
+    /// Returns d apply(x) / dx. At kinks (abs and relu at 0) the derivative is 0;
+    /// clamp passes the gradient through on its boundaries.
+    #[inline]
+    pub fn derivative(self, x: f32) -> f32 {
+        match self {
+            UnaryOp::Exp => x.exp(),
+            UnaryOp::Log => 1.0 / x,
+            UnaryOp::Sqrt => 0.5 / x.sqrt(),
+            UnaryOp::Rsqrt => -0.5 / (x * x.sqrt()),
+            UnaryOp::Abs => if x > 0.0 { 1.0 } else if x < 0.0 { -1.0 } else { 0.0 },
+            UnaryOp::Sin => x.cos(),
+            UnaryOp::Cos => -x.sin(),
+            UnaryOp::Tanh => 1.0 - x.tanh() * x.tanh(),
+            UnaryOp::Sigmoid => {
+                let s = sigmoid(x);
+                s * (1.0 - s)
+            }
+            UnaryOp::Relu => if x > 0.0 { 1.0 } else { 0.0 },
+            UnaryOp::Gelu => normal_cdf(x) + x * (-0.5 * x * x).exp() * (0.5 * SQRT_2_OVER_PI),
+            UnaryOp::GeluTanh => {
+                let t = (SQRT_2_OVER_PI * (x + GELU_TANH_COEFF * x * x * x)).tanh();
+                0.5 * (1.0 + t) + 0.5 * x * (1.0 - t * t) * SQRT_2_OVER_PI * (1.0 + 3.0 * GELU_TANH_COEFF * x * x)
+            }
+            UnaryOp::Silu => {
+                let s = sigmoid(x);
+                s * (1.0 + x * (1.0 - s))
+            }
+            UnaryOp::Softplus => sigmoid(x),
+            UnaryOp::Pow(exponent) => exponent * x.powf(exponent - 1.0),
+            UnaryOp::Clamp { min, max } => if x >= min && x <= max { 1.0 } else { 0.0 },
+        }
+    }
+}
+
+/// Logistic function evaluated so that `exp` never overflows
+#[inline]
+fn sigmoid(x: f32) -> f32 {
+    if x >= 0.0 {
+        1.0 / (1.0 + (-x).exp())
+    } else {
+        let e = x.exp();
+        e / (1.0 + e)
+    }
+}
+
+/// Standard normal CDF, `Φ(x) = (1 + erf(x / √2)) / 2`
+#[inline]
+fn normal_cdf(x: f32) -> f32 {
+    (0.5 * (1.0 + erf(x as f64 / std::f64::consts::SQRT_2))) as f32
+}
+
+/// Error function (Abramowitz & Stegun 7.1.26), accurate to 1.5e-7, which is below f32 precision
+fn erf(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.327_591_1 * x.abs());
+    let poly = t * (0.254_829_592 + t * (-0.284_496_736 + t * (1.421_413_741 + t * (-1.453_152_027 + t * 1.061_405_429))));
+    let magnitude = 1.0 - poly * (-x * x).exp();
+    if x < 0.0 { -magnitude } else { magnitude }
+}
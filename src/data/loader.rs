@@ -0,0 +1,188 @@
+use super::Dataset;
+use crate::error::{Result, FerroFlowError};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+/// Batching, shuffling and prefetching settings of a [`DataLoader`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DataLoaderOptions {
+    pub batch_size: usize,
+    /// Visits the examples in a new random order every epoch
+    pub shuffle: bool,
+    /// Seeds the shuffling so the sequence of epochs is reproducible; drawn from the OS when None
+    pub seed: Option<u64>,
+    /// Skips the last batch of an epoch when it holds fewer than `batch_size` examples
+    pub drop_last: bool,
+    /// Threads loading batches in the background, or 0 to load them on the iterating thread
+    pub num_workers: usize,
+    /// Batches each worker loads ahead of the consumer
+    pub prefetch_factor: usize,
+}
+
+impl DataLoaderOptions {
+    /// Batches of `batch_size` examples in dataset order, loaded on the iterating thread
+    pub fn new(batch_size: usize) -> Self {
+        Self { batch_size, shuffle: false, seed: None, drop_last: false, num_workers: 0, prefetch_factor: 2 }
+    }
+}
+
+type CollateFn<I, O> = dyn Fn(Vec<I>) -> Result<O> + Send + Sync;
+
+/// Iterates over a [`Dataset`] in batches, merging the examples of each batch with a collate
+/// function. Every call to [`iter`](Self::iter) is one epoch.
+pub struct DataLoader<D: Dataset, O> {
+    dataset: Arc<D>,
+    collate: Arc<CollateFn<D::Item, O>>,
+    options: DataLoaderOptions,
+    rng: StdRng,
+}
+
+impl<D: Dataset + 'static, O: Send + 'static> DataLoader<D, O> {
+    /// Creates a loader that turns the examples of each batch into a batch with `collate`,
+    /// e.g. [`stack_pairs`](super::stack_pairs)
+    pub fn new(
+        dataset: Arc<D>,
+        options: DataLoaderOptions,
+        collate: impl Fn(Vec<D::Item>) -> Result<O> + Send + Sync + 'static,
+    ) -> Result<Self> {
+        if options.batch_size == 0 {
+            return Err(FerroFlowError::InvalidOperation("DataLoader batch size must be positive".into()));
+        }
+        if options.num_workers > 0 && options.prefetch_factor == 0 {
+            return Err(FerroFlowError::InvalidOperation(
+                "DataLoader workers need a prefetch factor of at least 1".into()
+            ));
+        }
+        let rng = match options.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        Ok(Self { dataset, collate: Arc::new(collate), options, rng })
+    }
+
+    pub fn dataset(&self) -> &Arc<D> {
+        &self.dataset
+    }
+
+    pub fn options(&self) -> &DataLoaderOptions {
+        &self.options
+    }
+
+    /// Returns the number of batches in an epoch
+    pub fn len(&self) -> usize {
+        let examples = self.dataset.len();
+        if self.options.drop_last {
+            examples / self.options.batch_size
+        } else {
+            examples.div_ceil(self.options.batch_size)
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Starts an epoch, reshuffling the examples if enabled. Workers are started here and
+    /// stopped when the returned iterator is dropped.
+    pub fn iter(&mut self) -> Batches<O> {
+        let mut order: Vec<usize> = (0..self.dataset.len()).collect();
+        if self.options.shuffle {
+            order.shuffle(&mut self.rng);
+        }
+        let batches: Arc<[Vec<usize>]> = order.chunks(self.options.batch_size).take(self.len()).map(<[usize]>::to_vec).collect();
+        let (dataset, collate) = (Arc::clone(&self.dataset), Arc::clone(&self.collate));
+        let load = Arc::new(move |indices: &[usize]| -> Result<O> {
+            collate(indices.iter().map(|&i| dataset.get(i)).collect::<Result<_>>()?)
+        });
+
+        let len = batches.len();
+        let workers = self.options.num_workers.min(len);
+        let source = if workers == 0 {
+            Source::Inline(Box::new(move |i| load(&batches[i])))
+        } else {
+            // Worker `w` loads batches `w`, `w + workers`, ... so reading the workers' channels
+            // in turn yields the batches in order
+            let (mut receivers, mut handles) = (Vec::with_capacity(workers), Vec::with_capacity(workers));
+            for worker in 0..workers {
+                let (sender, receiver) = mpsc::sync_channel(self.options.prefetch_factor);
+                let (batches, load) = (Arc::clone(&batches), Arc::clone(&load));
+                handles.push(std::thread::spawn(move || {
+                    for indices in batches.iter().skip(worker).step_by(workers) {
+                        if sender.send(load(indices)).is_err() {
+                            break;
+                        }
+                    }
+                }));
+                receivers.push(receiver);
+            }
+            Source::Workers { receivers, handles }
+        };
+        Batches { source, next: 0, len }
+    }
+}
+
+impl<D: Dataset + 'static, O: Send + 'static> IntoIterator for &mut DataLoader<D, O> {
+    type Item = Result<O>;
+    type IntoIter = Batches<O>;
+
+    fn into_iter(self) -> Batches<O> {
+        self.iter()
+    }
+}
+
+enum Source<O> {
+    /// Loads batch `i` on the iterating thread
+    Inline(Box<dyn FnMut(usize) -> Result<O> + Send>),
+    Workers { receivers: Vec<Receiver<Result<O>>>, handles: Vec<JoinHandle<()>> },
+}
+
+/// The batches of one epoch of a [`DataLoader`], in order. A batch whose examples or collate
+/// function fail is yielded as an error and the epoch continues.
+pub struct Batches<O> {
+    source: Source<O>,
+    next: usize,
+    len: usize,
+}
+
+impl<O> Iterator for Batches<O> {
+    type Item = Result<O>;
+
+    fn next(&mut self) -> Option<Result<O>> {
+        if self.next >= self.len {
+            return None;
+        }
+        let batch = match &mut self.source {
+            Source::Inline(load) => load(self.next),
+            Source::Workers { receivers, .. } => receivers[self.next % receivers.len()].recv().unwrap_or_else(|_| {
+                // The worker hung up before sending every batch, which only a panic causes
+                self.len = self.next;
+                Err(FerroFlowError::InvalidOperation("A DataLoader worker thread panicked".into()))
+            }),
+        };
+        self.next += 1;
+        Some(batch)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.len.saturating_sub(self.next);
+        (remaining, Some(remaining))
+    }
+}
+
+impl<O> ExactSizeIterator for Batches<O> {}
+
+impl<O> Drop for Batches<O> {
+    fn drop(&mut self) {
+        if let Source::Workers { receivers, handles } = &mut self.source {
+            // Closing the channels makes workers stop after the batch they are loading
+            receivers.clear();
+            for handle in handles.drain(..) {
+                let _ = handle.join();
+            }
+        }
+    }
+}
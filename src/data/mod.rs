@@ -0,0 +1,120 @@
+//! Feeding training data to models in batches.
+//!
+//! A [`Dataset`] gives random access to individual examples. A [`DataLoader`] groups their
+//! indices into batches, optionally shuffled with a seeded generator, and merges each batch's
+//! examples with a collate function such as [`stack`] or [`stack_pairs`]. With
+//! [`DataLoaderOptions::num_workers`] set, worker threads load batches ahead of the consumer.
+
+use crate::compute::ComputeBackend;
+use crate::error::{Result, FerroFlowError};
+use crate::tensor::{Shape, Tensor};
+use std::sync::Arc;
+
+mod loader;
+#[cfg(test)]
+mod tests;
+
+pub use loader::{Batches, DataLoader, DataLoaderOptions};
+
+/// A collection of examples that can be read in any order, from any thread.
+pub trait Dataset: Send + Sync {
+    /// A single example, such as an `(input, target)` pair
+    type Item: Send + 'static;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Reads example `index`, which is less than `len()`
+    fn get(&self, index: usize) -> Result<Self::Item>;
+}
+
+impl<T: Clone + Send + Sync + 'static> Dataset for Vec<T> {
+    type Item = T;
+
+    fn len(&self) -> usize {
+        self.as_slice().len()
+    }
+
+    fn get(&self, index: usize) -> Result<T> {
+        self.as_slice().get(index).cloned().ok_or_else(|| out_of_range(index, self.as_slice().len()))
+    }
+}
+
+/// Examples stored as rows of an input and a target tensor, whose first dimension indexes them.
+/// Examples are views of the rows without their leading dimension.
+pub struct TensorDataset<B: ComputeBackend> {
+    inputs: Tensor<B>,
+    targets: Tensor<B>,
+}
+
+impl<B: ComputeBackend> TensorDataset<B> {
+    pub fn new(inputs: Tensor<B>, targets: Tensor<B>) -> Result<Self> {
+        match (inputs.shape().dims().first(), targets.shape().dims().first()) {
+            (Some(n), Some(m)) if n == m => Ok(Self { inputs, targets }),
+            _ => Err(FerroFlowError::ShapeMismatch(format!(
+                "Inputs of shape {:?} and targets of shape {:?} must hold the same number of examples",
+                inputs.shape().dims(), targets.shape().dims()
+            ))),
+        }
+    }
+}
+
+impl<B: ComputeBackend> Dataset for TensorDataset<B> {
+    type Item = (Tensor<B>, Tensor<B>);
+
+    fn len(&self) -> usize {
+        self.inputs.shape().dims()[0]
+    }
+
+    fn get(&self, index: usize) -> Result<Self::Item> {
+        if index >= self.len() {
+            return Err(out_of_range(index, self.len()));
+        }
+        let row = |tensor: &Tensor<B>| tensor.narrow(0, index, 1)?.reshape(Shape::new(tensor.shape().dims()[1..].to_vec()));
+        Ok((row(&self.inputs)?, row(&self.targets)?))
+    }
+}
+
+/// Collates equally shaped examples into a tensor of the same dtype with a leading batch
+/// dimension: scalars into `[batch]`, vectors of length `n` into `[batch, n]` and matrices into
+/// `[batch, rows, cols]`. Class indices and feature vectors thus collate into the targets and
+/// inputs of losses such as [`cross_entropy`](crate::loss::cross_entropy). Examples of higher
+/// rank need a custom collate function.
+pub fn stack<B: ComputeBackend>(tensors: &[Tensor<B>]) -> Result<Tensor<B>> {
+    let first = tensors.first().ok_or_else(|| {
+        FerroFlowError::InvalidOperation("Cannot stack an empty batch".into())
+    })?;
+    let shape = match *first.shape().dims() {
+        [] => Shape::new(vec![tensors.len()]),
+        [len] => Shape::new(vec![tensors.len(), len]),
+        [rows, cols] => Shape::new_batched(tensors.len(), rows, cols),
+        ref dims => return Err(FerroFlowError::ShapeMismatch(format!(
+            "stack collates scalars, vectors and matrices, got an example of shape {:?}", dims
+        ))),
+    };
+    let mut bytes = Vec::with_capacity(shape.size() * first.dtype().size_in_bytes());
+    for tensor in tensors {
+        if tensor.shape() != first.shape() || tensor.dtype() != first.dtype() {
+            return Err(FerroFlowError::ShapeMismatch(format!(
+                "Cannot stack a {} example of shape {:?} with {} examples of shape {:?}",
+                tensor.dtype(), tensor.shape().dims(), first.dtype(), first.shape().dims()
+            )));
+        }
+        bytes.extend_from_slice(&tensor.to_bytes()?);
+    }
+    Tensor::from_bytes(Arc::clone(first.context()), shape, first.dtype(), &bytes)
+}
+
+/// Collates `(input, target)` pairs, such as the examples of a [`TensorDataset`], by
+/// [`stack`]ing the inputs and the targets
+pub fn stack_pairs<B: ComputeBackend>(pairs: Vec<(Tensor<B>, Tensor<B>)>) -> Result<(Tensor<B>, Tensor<B>)> {
+    let (inputs, targets): (Vec<_>, Vec<_>) = pairs.into_iter().unzip();
+    Ok((stack(&inputs)?, stack(&targets)?))
+}
+
+fn out_of_range(index: usize, len: usize) -> FerroFlowError {
+    FerroFlowError::InvalidOperation(format!("Index {} is out of range for a dataset of {} examples", index, len))
+}
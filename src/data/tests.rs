@@ -0,0 +1,159 @@
+use super::*;
+use crate::compute::CPUBackend;
+use crate::dtype::DType;
+use crate::loss::{cross_entropy, CrossEntropyOptions};
+use crate::nn::{Linear, Module};
+
+/// Collates examples into a plain list
+fn collect(items: Vec<usize>) -> Result<Vec<usize>> {
+    Ok(items)
+}
+
+fn epoch(loader: &mut DataLoader<Vec<usize>, Vec<usize>>) -> Result<Vec<Vec<usize>>> {
+    loader.iter().collect()
+}
+
+/// Fails to read example 5
+struct Faulty;
+
+impl Dataset for Faulty {
+    type Item = usize;
+
+    fn len(&self) -> usize {
+        8
+    }
+
+    fn get(&self, index: usize) -> Result<usize> {
+        if index == 5 {
+            return Err(FerroFlowError::InvalidOperation("unreadable example".into()));
+        }
+        Ok(index)
+    }
+}
+
+#[test]
+fn test_data_loader_batches() -> Result<()> {
+    let dataset = Arc::new((0..10).collect::<Vec<usize>>());
+    let mut loader = DataLoader::new(Arc::clone(&dataset), DataLoaderOptions::new(4), collect)?;
+    assert_eq!(loader.len(), 3);
+    assert_eq!(loader.iter().len(), 3);
+    assert_eq!(epoch(&mut loader)?, vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7], vec![8, 9]]);
+
+    let options = DataLoaderOptions { drop_last: true, ..DataLoaderOptions::new(4) };
+    let mut loader = DataLoader::new(Arc::clone(&dataset), options, collect)?;
+    assert_eq!(loader.len(), 2);
+    assert_eq!(epoch(&mut loader)?, vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7]]);
+
+    let options = DataLoaderOptions { drop_last: true, ..DataLoaderOptions::new(11) };
+    let mut loader = DataLoader::new(dataset, options, collect)?;
+    assert!(loader.is_empty());
+    assert_eq!(loader.iter().next().map(|batch| batch.is_ok()), None);
+
+    assert!(DataLoader::new(Arc::new(vec![1usize]), DataLoaderOptions::new(0), collect).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_data_loader_shuffling_is_seeded() -> Result<()> {
+    let dataset = Arc::new((0..32).collect::<Vec<usize>>());
+    let options = DataLoaderOptions { shuffle: true, seed: Some(7), ..DataLoaderOptions::new(5) };
+    let mut loader = DataLoader::new(Arc::clone(&dataset), options, collect)?;
+    let mut same_seed = DataLoader::new(Arc::clone(&dataset), options, collect)?;
+    let other_options = DataLoaderOptions { seed: Some(8), ..options };
+    let mut other_seed = DataLoader::new(Arc::clone(&dataset), other_options, collect)?;
+
+    let first = epoch(&mut loader)?;
+    let second = epoch(&mut loader)?;
+    assert_eq!(first, epoch(&mut same_seed)?);
+    assert_eq!(second, epoch(&mut same_seed)?);
+    assert_ne!(first, second, "each epoch is reshuffled");
+    assert_ne!(first, epoch(&mut other_seed)?);
+
+    for batches in [first, second] {
+        assert_eq!(batches.iter().map(Vec::len).collect::<Vec<_>>(), [5, 5, 5, 5, 5, 5, 2]);
+        let mut seen: Vec<usize> = batches.concat();
+        seen.sort_unstable();
+        assert_eq!(&seen, dataset.as_ref());
+    }
+    Ok(())
+}
+
+#[test]
+fn test_data_loader_workers() -> Result<()> {
+    let dataset = Arc::new((0..50).collect::<Vec<usize>>());
+    let options = DataLoaderOptions { shuffle: true, seed: Some(3), ..DataLoaderOptions::new(4) };
+    let mut inline = DataLoader::new(Arc::clone(&dataset), options, collect)?;
+    let threaded_options = DataLoaderOptions { num_workers: 3, prefetch_factor: 1, ..options };
+    let mut threaded = DataLoader::new(Arc::clone(&dataset), threaded_options, collect)?;
+    for _ in 0..2 {
+        assert_eq!(epoch(&mut threaded)?, epoch(&mut inline)?);
+    }
+
+    // Dropping an epoch early stops the workers
+    let mut batches = threaded.iter();
+    assert!(batches.next().is_some());
+    drop(batches);
+
+    // A failing batch is reported and the epoch goes on
+    let options = DataLoaderOptions { num_workers: 2, ..DataLoaderOptions::new(2) };
+    let mut loader = DataLoader::new(Arc::new(Faulty), options, collect)?;
+    let results: Vec<_> = loader.iter().collect();
+    assert_eq!(results.len(), 4);
+    assert!(matches!(results[2], Err(FerroFlowError::InvalidOperation(_))));
+    assert_eq!(results[3].as_ref().ok(), Some(&vec![6, 7]));
+    Ok(())
+}
+
+#[test]
+fn test_tensor_dataset_collates_batches() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let values: Vec<f32> = (0..30).map(|v| v as f32).collect();
+    let inputs = Tensor::<CPUBackend>::new(Arc::clone(&ctx), Shape::new(vec![5, 2, 3]), &values)?;
+    let labels = Tensor::<CPUBackend>::from_slice(Arc::clone(&ctx), Shape::new(vec![5]), &[0i64, 1, 2, 3, 4])?;
+    let dataset = Arc::new(TensorDataset::new(inputs, labels)?);
+    assert_eq!(dataset.len(), 5);
+    assert!(dataset.get(5).is_err());
+
+    let options = DataLoaderOptions { num_workers: 2, ..DataLoaderOptions::new(2) };
+    let mut loader = DataLoader::new(dataset, options, stack_pairs)?;
+    let batches: Vec<_> = loader.iter().collect::<Result<_>>()?;
+    assert_eq!(batches.len(), 3);
+    let (x, y) = &batches[1];
+    assert_eq!(x.shape(), &Shape::new_batched(2, 2, 3));
+    assert_eq!(x.data()?, (12..24).map(|v| v as f32).collect::<Vec<_>>());
+    assert_eq!(y.shape(), &Shape::new(vec![2]));
+    assert_eq!(y.dtype(), DType::I64);
+    assert_eq!(y.to_vec::<i64>()?, [2, 3]);
+    assert_eq!(batches[2].0.shape(), &Shape::new_batched(1, 2, 3));
+
+    // Only scalars, vectors and matrices of one shape can be stacked
+    let vector = Tensor::<CPUBackend>::new(Arc::clone(&ctx), Shape::new(vec![3]), &[1.0, 2.0, 3.0])?;
+    assert_eq!(stack(&[vector.clone(), vector.clone()])?.shape(), &Shape::new(vec![2, 3]));
+    let matrix = Tensor::<CPUBackend>::zeros(Arc::clone(&ctx), Shape::new(vec![1, 3]))?;
+    assert!(matches!(stack(&[vector, matrix]), Err(FerroFlowError::ShapeMismatch(_))));
+    let cube = Tensor::<CPUBackend>::zeros(Arc::clone(&ctx), Shape::new(vec![1, 1, 1]))?;
+    assert!(matches!(stack(&[cube]), Err(FerroFlowError::ShapeMismatch(_))));
+    assert!(stack::<CPUBackend>(&[]).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_collated_batches_feed_linear_and_cross_entropy() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let values: Vec<f32> = (0..24).map(|v| v as f32 / 24.0).collect();
+    let features = Tensor::<CPUBackend>::new(Arc::clone(&ctx), Shape::new(vec![6, 4]), &values)?;
+    let labels = Tensor::<CPUBackend>::from_slice(Arc::clone(&ctx), Shape::new(vec![6]), &[0i64, 1, 2, 2, 1, 0])?;
+    let mut loader = DataLoader::new(Arc::new(TensorDataset::new(features, labels)?), DataLoaderOptions::new(3), stack_pairs)?;
+
+    let layer = Linear::<CPUBackend>::new(Arc::clone(&ctx), 4, 3, true)?;
+    for batch in &mut loader {
+        let (x, y) = batch?;
+        assert_eq!(x.shape(), &Shape::new(vec![3, 4]));
+        assert_eq!(y.shape(), &Shape::new(vec![3]));
+        let loss = cross_entropy(&layer.forward(&x)?, &y, CrossEntropyOptions::default())?;
+        assert!(loss.data()?[0].is_finite());
+        loss.backward()?;
+    }
+    assert_eq!(layer.weight().grad().map(|grad| grad.shape().clone()), Some(Shape::new(vec![3, 4])));
+    Ok(())
+}
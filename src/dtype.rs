@@ -0,0 +1,186 @@
+//! Element types a tensor can hold.
+//!
+//! Backend kernels currently compute in `f32`; the other types are supported for storage,
+//! I/O and conversion with `Tensor::to_dtype`.
+
+use half::{bf16, f16};
+use std::fmt;
+
+/// The element type of a tensor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DType {
+    F32,
+    F64,
+    F16,
+    BF16,
+    I32,
+    I64,
+    U8,
+    Bool,
+}
+
+impl DType {
+    /// Every supported element type
+    pub const ALL: [DType; 8] = [
+        DType::F32, DType::F64, DType::F16, DType::BF16,
+        DType::I32, DType::I64, DType::U8, DType::Bool,
+    ];
+
+    /// Returns the number of bytes one element occupies in a buffer
+    pub fn size_in_bytes(self) -> usize {
+        match self {
+            DType::F64 | DType::I64 => 8,
+            DType::F32 | DType::I32 => 4,
+            DType::F16 | DType::BF16 => 2,
+            DType::U8 | DType::Bool => 1,
+        }
+    }
+
+    /// Returns true for floating point types
+    pub fn is_float(self) -> bool {
+        matches!(self, DType::F32 | DType::F64 | DType::F16 | DType::BF16)
+    }
+
+    /// Returns the short lowercase name of the type, e.g. `"bf16"`
+    pub fn name(self) -> &'static str {
+        match self {
+            DType::F32 => "f32",
+            DType::F64 => "f64",
+            DType::F16 => "f16",
+            DType::BF16 => "bf16",
+            DType::I32 => "i32",
+            DType::I64 => "i64",
+            DType::U8 => "u8",
+            DType::Bool => "bool",
+        }
+    }
+}
+
+impl fmt::Display for DType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A Rust type that can be stored in a tensor.
+///
+/// Conversions between element types go through `f64`, which represents every value of the
+/// supported types exactly except 64-bit integers beyond 2^53.
+pub trait Element: Copy + Default + PartialEq + Send + Sync + fmt::Debug + 'static {
+    /// The tensor element type corresponding to `Self`
+    const DTYPE: DType;
+
+    fn to_f64(self) -> f64;
+
+    /// Converts from `f64`, truncating towards zero and saturating for integer types
+    fn from_f64(value: f64) -> Self;
+
+    /// Appends the native-endian representation of `self` to `out`
+    fn extend_ne_bytes(self, out: &mut Vec<u8>);
+
+    /// Reads a value from exactly `DTYPE.size_in_bytes()` native-endian bytes
+    fn from_ne_slice(bytes: &[u8]) -> Self;
+}
+
+macro_rules! impl_element {
+    ($ty:ty, $dtype:expr, |$v:ident| $to_f64:expr, |$f:ident| $from_f64:expr) => {
+        impl Element for $ty {
+            const DTYPE: DType = $dtype;
+
+            #[inline]
+            fn to_f64(self) -> f64 {
+                let $v = self;
+                $to_f64
+            }
+
+            #[inline]
+            fn from_f64($f: f64) -> Self {
+                $from_f64
+            }
+
+            fn extend_ne_bytes(self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_ne_bytes());
+            }
+
+            fn from_ne_slice(bytes: &[u8]) -> Self {
+                <$ty>::from_ne_bytes(bytes.try_into().expect("element byte width"))
+            }
+        }
+    };
+}
+
+impl_element!(f32, DType::F32, |v| v as f64, |f| f as f32);
+impl_element!(f64, DType::F64, |v| v, |f| f);
+impl_element!(f16, DType::F16, |v| v.to_f64(), |f| f16::from_f64(f));
+impl_element!(bf16, DType::BF16, |v| v.to_f64(), |f| bf16::from_f64(f));
+impl_element!(i32, DType::I32, |v| v as f64, |f| f as i32);
+impl_element!(i64, DType::I64, |v| v as f64, |f| f as i64);
+impl_element!(u8, DType::U8, |v| v as f64, |f| f as u8);
+
+impl Element for bool {
+    const DTYPE: DType = DType::Bool;
+
+    #[inline]
+    fn to_f64(self) -> f64 {
+        if self { 1.0 } else { 0.0 }
+    }
+
+    #[inline]
+    fn from_f64(value: f64) -> Self {
+        value != 0.0
+    }
+
+    fn extend_ne_bytes(self, out: &mut Vec<u8>) {
+        out.push(self as u8);
+    }
+
+    fn from_ne_slice(bytes: &[u8]) -> Self {
+        bytes[0] != 0
+    }
+}
+
+/// Converts a slice of one element type into another
+pub fn convert<S: Element, T: Element>(values: &[S]) -> Vec<T> {
+    values.iter().map(|&v| T::from_f64(v.to_f64())).collect()
+}
+
+/// Serializes elements into their native-endian byte representation
+pub fn to_bytes<T: Element>(values: &[T]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(values.len() * T::DTYPE.size_in_bytes());
+    for &value in values {
+        value.extend_ne_bytes(&mut out);
+    }
+    out
+}
+
+/// Deserializes native-endian bytes produced by [`to_bytes`].
+/// Trailing bytes that do not form a whole element are ignored.
+pub fn from_bytes<T: Element>(bytes: &[u8]) -> Vec<T> {
+    bytes.chunks_exact(T::DTYPE.size_in_bytes()).map(T::from_ne_slice).collect()
+}
+
+/// Calls `$body` with the type alias `$t` bound to the Rust type of `$dtype`
+macro_rules! with_dtype {
+    ($dtype:expr, $t:ident => $body:expr) => {
+        match $dtype {
+            $crate::dtype::DType::F32 => { type $t = f32; $body }
+            $crate::dtype::DType::F64 => { type $t = f64; $body }
+            $crate::dtype::DType::F16 => { type $t = half::f16; $body }
+            $crate::dtype::DType::BF16 => { type $t = half::bf16; $body }
+            $crate::dtype::DType::I32 => { type $t = i32; $body }
+            $crate::dtype::DType::I64 => { type $t = i64; $body }
+            $crate::dtype::DType::U8 => { type $t = u8; $body }
+            $crate::dtype::DType::Bool => { type $t = bool; $body }
+        }
+    };
+}
+
+pub(crate) use with_dtype;
+
+/// Converts a native-endian byte buffer of `from` elements into one of `to` elements
+pub(crate) fn convert_bytes(bytes: &[u8], from: DType, to: DType) -> Vec<u8> {
+    if from == to {
+        return bytes.to_vec();
+    }
+    with_dtype!(from, S => with_dtype!(to, T => to_bytes(&convert::<S, T>(&from_bytes::<S>(bytes)))))
+}
@@ -19,6 +19,21 @@ pub enum FerroFlowError {
     
     #[error("Buffer error: {0}")]
     BufferError(String),
+
+    #[error("DType mismatch: {0}")]
+    DTypeMismatch(String),
+
+    #[error("Unsupported dtype: {0}")]
+    UnsupportedDType(String),
+
+    #[error("Invalid file format: {0}")]
+    FormatError(String),
+
+    #[error("Unsupported operator: {0}")]
+    UnsupportedOperator(String),
+
+    #[error("I/O error: {0}")]
+    IoError(#[from] std::io::Error),
 }
 
 pub type Result<T> = std::result::Result<T, FerroFlowError>; 
\ No newline at end of file
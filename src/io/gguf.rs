@@ -0,0 +1,356 @@
+//! The GGUF format of llama.cpp: a header with typed metadata key/value pairs and tensor infos,
+//! then the tensor data, each tensor aligned to `general.alignment` bytes. Tensors are stored
+//! as dense arrays or in GGML block-quantized formats.
+
+use super::{convert_endianness, ByteReader, Storage};
+use crate::compute::ComputeBackend;
+use crate::dtype::DType;
+use crate::error::{Result, FerroFlowError};
+use crate::tensor::{QuantType, QuantizedTensor, Shape, Tensor};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+const MAGIC: &[u8; 4] = b"GGUF";
+const FORMAT: &str = "gguf";
+
+/// Alignment of tensor data when the file has no `general.alignment` entry
+const DEFAULT_ALIGNMENT: usize = 32;
+
+/// Deepest nesting of metadata arrays accepted
+const MAX_ARRAY_DEPTH: usize = 8;
+
+/// A parsed GGUF file. Tensor data is copied once when loaded, or dequantized straight from the
+/// file's bytes.
+pub struct GgufFile {
+    bytes: Storage,
+    version: u32,
+    metadata: HashMap<String, MetadataValue>,
+    tensors: Vec<TensorInfo>,
+    index: HashMap<String, usize>,
+    data_start: usize,
+}
+
+/// A typed metadata value
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetadataValue {
+    U8(u8),
+    I8(i8),
+    U16(u16),
+    I16(i16),
+    U32(u32),
+    I32(i32),
+    U64(u64),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+    Bool(bool),
+    String(String),
+    Array(Vec<MetadataValue>),
+}
+
+impl MetadataValue {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            MetadataValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns integer values that are not negative
+    pub fn as_u64(&self) -> Option<u64> {
+        match *self {
+            MetadataValue::U8(v) => Some(v.into()),
+            MetadataValue::U16(v) => Some(v.into()),
+            MetadataValue::U32(v) => Some(v.into()),
+            MetadataValue::U64(v) => Some(v),
+            MetadataValue::I8(v) => u64::try_from(v).ok(),
+            MetadataValue::I16(v) => u64::try_from(v).ok(),
+            MetadataValue::I32(v) => u64::try_from(v).ok(),
+            MetadataValue::I64(v) => u64::try_from(v).ok(),
+            _ => None,
+        }
+    }
+
+    /// Returns numeric values as `f64`
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            MetadataValue::F32(v) => Some(v.into()),
+            MetadataValue::F64(v) => Some(v),
+            MetadataValue::I8(v) => Some(v.into()),
+            MetadataValue::I16(v) => Some(v.into()),
+            MetadataValue::I32(v) => Some(v.into()),
+            MetadataValue::I64(v) => Some(v as f64),
+            _ => self.as_u64().map(|v| v as f64),
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[MetadataValue]> {
+        match self {
+            MetadataValue::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+/// The element type of a GGUF tensor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GgmlType {
+    /// One value per element, loaded as a tensor of that dtype
+    Dense(DType),
+    /// Blocks in a quantization format
+    Quantized(QuantType),
+    /// Another GGML type id, whose tensors can be listed but not loaded
+    Unsupported(u32),
+}
+
+impl GgmlType {
+    fn from_id(id: u32) -> Self {
+        match id {
+            0 => GgmlType::Dense(DType::F32),
+            1 => GgmlType::Dense(DType::F16),
+            2 => GgmlType::Quantized(QuantType::Q4_0),
+            8 => GgmlType::Quantized(QuantType::Q8_0),
+            12 => GgmlType::Quantized(QuantType::Q4_K),
+            14 => GgmlType::Quantized(QuantType::Q6_K),
+            26 => GgmlType::Dense(DType::I32),
+            27 => GgmlType::Dense(DType::I64),
+            28 => GgmlType::Dense(DType::F64),
+            30 => GgmlType::Dense(DType::BF16),
+            id => GgmlType::Unsupported(id),
+        }
+    }
+}
+
+/// The name, type, shape and data location of a tensor in a [`GgufFile`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TensorInfo {
+    pub name: String,
+    pub ggml_type: GgmlType,
+    /// Row-major dimensions, the reverse of GGML's innermost-first order
+    pub shape: Vec<usize>,
+    /// Offset of the data from the start of the data section
+    pub offset: usize,
+}
+
+impl TensorInfo {
+    /// Returns the size of the tensor's data, or None for unsupported types
+    fn byte_len(&self) -> Result<Option<usize>> {
+        let size = self.shape.iter().try_fold(1usize, |size, &dim| size.checked_mul(dim));
+        let size = size.ok_or_else(|| format_error(format!("tensor {:?} is too large", self.name)))?;
+        match self.ggml_type {
+            GgmlType::Dense(dtype) => size.checked_mul(dtype.size_in_bytes())
+                .map(Some)
+                .ok_or_else(|| format_error(format!("tensor {:?} is too large", self.name))),
+            GgmlType::Quantized(quant_type) => QuantizedTensor::byte_len(quant_type, &Shape::new(self.shape.clone()))
+                .map(Some)
+                .map_err(|_| format_error(format!(
+                    "rows of {:?} tensor {:?} of shape {:?} don't hold whole blocks", quant_type, self.name, self.shape
+                ))),
+            GgmlType::Unsupported(_) => Ok(None),
+        }
+    }
+}
+
+impl GgufFile {
+    /// Reads and parses the file at `path`
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::parse(Storage::read(path)?)
+    }
+
+    /// Memory-maps and parses the file at `path`, so only the data that is read is loaded
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated, by this or any other process, while the
+    /// returned value is alive.
+    pub unsafe fn open_mmap(path: impl AsRef<Path>) -> Result<Self> {
+        // Safety: upheld by the caller
+        Self::parse(unsafe { Storage::map(path)? })
+    }
+
+    /// Parses a file held in memory
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self> {
+        Self::parse(Storage::Owned(bytes))
+    }
+
+    fn parse(bytes: Storage) -> Result<Self> {
+        let mut reader = ByteReader::new(&bytes, 0, FORMAT);
+        if &reader.array::<4>()? != MAGIC {
+            return Err(format_error("missing GGUF magic"));
+        }
+        let version = reader.u32()?;
+        if !(2..=3).contains(&version) {
+            // Big-endian files store the version byte-swapped
+            let hint = if version.swap_bytes() <= 3 { ", big-endian files are not supported" } else { "" };
+            return Err(format_error(format!("unsupported version {}{}", version, hint)));
+        }
+        let tensor_count = reader.u64()?;
+        let metadata_count = reader.u64()?;
+
+        let mut metadata = HashMap::new();
+        for _ in 0..metadata_count {
+            let key = read_string(&mut reader)?;
+            let value_type = reader.u32()?;
+            let value = read_value(&mut reader, value_type, 0)?;
+            if metadata.insert(key.clone(), value).is_some() {
+                return Err(format_error(format!("metadata key {:?} appears more than once", key)));
+            }
+        }
+        let alignment = match metadata.get("general.alignment") {
+            None => DEFAULT_ALIGNMENT,
+            Some(MetadataValue::U32(alignment)) if alignment.is_power_of_two() => *alignment as usize,
+            Some(value) => return Err(format_error(format!("general.alignment {:?} is not a power of two", value))),
+        };
+
+        // Each tensor info takes at least 24 bytes, so a corrupt count can't over-allocate
+        let mut tensors = Vec::with_capacity(usize::try_from(tensor_count).unwrap_or(usize::MAX).min(bytes.len() / 24));
+        for _ in 0..tensor_count {
+            let name = read_string(&mut reader)?;
+            let rank = reader.u32()?;
+            let mut shape = (0..rank).map(|_| to_usize(reader.u64()?)).collect::<Result<Vec<_>>>()?;
+            shape.reverse();
+            let ggml_type = GgmlType::from_id(reader.u32()?);
+            let offset = to_usize(reader.u64()?)?;
+            tensors.push(TensorInfo { name, ggml_type, shape, offset });
+        }
+
+        let data_start = reader.position().next_multiple_of(alignment);
+        if !tensors.is_empty() && data_start > bytes.len() {
+            return Err(format_error(format!(
+                "file ends at byte {} before the data section at byte {}", bytes.len(), data_start
+            )));
+        }
+        let data_len = bytes.len().saturating_sub(data_start);
+        let mut index = HashMap::new();
+        for (i, info) in tensors.iter().enumerate() {
+            if index.insert(info.name.clone(), i).is_some() {
+                return Err(format_error(format!("tensor name {:?} appears more than once", info.name)));
+            }
+            if !info.offset.is_multiple_of(alignment) {
+                return Err(format_error(format!(
+                    "data of tensor {:?} at offset {} is not aligned to {} bytes", info.name, info.offset, alignment
+                )));
+            }
+            let end = info.byte_len()?.and_then(|len| info.offset.checked_add(len)).unwrap_or(info.offset);
+            if end > data_len {
+                return Err(format_error(format!(
+                    "data of tensor {:?} ends at byte {} of the data section, which holds {}", info.name, end, data_len
+                )));
+            }
+        }
+        Ok(Self { bytes, version, metadata, tensors, index, data_start })
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn metadata(&self) -> &HashMap<String, MetadataValue> {
+        &self.metadata
+    }
+
+    /// Returns the tensor infos in file order
+    pub fn tensor_infos(&self) -> &[TensorInfo] {
+        &self.tensors
+    }
+
+    pub fn info(&self, name: &str) -> Result<&TensorInfo> {
+        self.index.get(name).map(|&i| &self.tensors[i]).ok_or_else(|| {
+            FerroFlowError::InvalidOperation(format!("No tensor named {:?} in the GGUF file", name))
+        })
+    }
+
+    /// Borrows the raw little-endian data or quantized blocks of the tensor called `name`
+    pub fn raw_data(&self, name: &str) -> Result<&[u8]> {
+        let info = self.info(name)?;
+        let len = info.byte_len()?.ok_or_else(|| unsupported(info))?;
+        let start = self.data_start + info.offset;
+        Ok(&self.bytes[start..start + len])
+    }
+
+    /// Loads the tensor called `name` into a buffer of backend `B`. Dense tensors keep their
+    /// dtype and quantized ones are dequantized to f32.
+    pub fn load<B: ComputeBackend>(&self, ctx: Arc<B::Context>, name: &str) -> Result<Tensor<B>> {
+        let info = self.info(name)?;
+        match info.ggml_type {
+            GgmlType::Dense(dtype) => {
+                let data = convert_endianness(self.raw_data(name)?, dtype.size_in_bytes(), true);
+                Tensor::from_bytes(ctx, Shape::new(info.shape.clone()), dtype, &data)
+            }
+            GgmlType::Quantized(_) => self.load_quantized(name)?.dequantize(ctx),
+            GgmlType::Unsupported(_) => Err(unsupported(info)),
+        }
+    }
+
+    /// Loads the quantized tensor called `name` without dequantizing it
+    pub fn load_quantized(&self, name: &str) -> Result<QuantizedTensor> {
+        let info = self.info(name)?;
+        let GgmlType::Quantized(quant_type) = info.ggml_type else {
+            return Err(FerroFlowError::InvalidOperation(format!(
+                "Tensor {:?} is stored as {:?}, not quantized", name, info.ggml_type
+            )));
+        };
+        QuantizedTensor::new(quant_type, Shape::new(info.shape.clone()), self.raw_data(name)?)
+    }
+
+    /// Loads every tensor, keyed by name
+    pub fn load_all<B: ComputeBackend>(&self, ctx: Arc<B::Context>) -> Result<HashMap<String, Tensor<B>>> {
+        self.tensors
+            .iter()
+            .map(|info| Ok((info.name.clone(), self.load(Arc::clone(&ctx), &info.name)?)))
+            .collect()
+    }
+}
+
+fn read_string(reader: &mut ByteReader<'_>) -> Result<String> {
+    let len = to_usize(reader.u64()?)?;
+    String::from_utf8(reader.take(len)?.to_vec()).map_err(|_| format_error("string is not UTF-8"))
+}
+
+fn read_value(reader: &mut ByteReader<'_>, value_type: u32, depth: usize) -> Result<MetadataValue> {
+    Ok(match value_type {
+        0 => MetadataValue::U8(reader.u8()?),
+        1 => MetadataValue::I8(reader.u8()? as i8),
+        2 => MetadataValue::U16(reader.u16()?),
+        3 => MetadataValue::I16(reader.u16()? as i16),
+        4 => MetadataValue::U32(reader.u32()?),
+        5 => MetadataValue::I32(reader.u32()? as i32),
+        6 => MetadataValue::F32(f32::from_bits(reader.u32()?)),
+        7 => match reader.u8()? {
+            0 => MetadataValue::Bool(false),
+            1 => MetadataValue::Bool(true),
+            value => return Err(format_error(format!("bool metadata value {} is not 0 or 1", value))),
+        },
+        8 => MetadataValue::String(read_string(reader)?),
+        9 => {
+            if depth >= MAX_ARRAY_DEPTH {
+                return Err(format_error("metadata arrays are nested too deeply"));
+            }
+            let element_type = reader.u32()?;
+            let len = to_usize(reader.u64()?)?;
+            // Every element takes at least a byte, so a corrupt length fails before allocating
+            let mut values = Vec::with_capacity(len.min(reader.remaining()));
+            for _ in 0..len {
+                values.push(read_value(reader, element_type, depth + 1)?);
+            }
+            MetadataValue::Array(values)
+        }
+        10 => MetadataValue::U64(reader.u64()?),
+        11 => MetadataValue::I64(reader.u64()? as i64),
+        12 => MetadataValue::F64(f64::from_bits(reader.u64()?)),
+        _ => return Err(format_error(format!("unknown metadata value type {}", value_type))),
+    })
+}
+
+fn unsupported(info: &TensorInfo) -> FerroFlowError {
+    FerroFlowError::UnsupportedDType(format!("GGML type {:?} of tensor {:?}", info.ggml_type, info.name))
+}
+
+fn to_usize(value: u64) -> Result<usize> {
+    usize::try_from(value).map_err(|_| format_error(format!("size {} doesn't fit in memory", value)))
+}
+
+fn format_error(message: impl Into<String>) -> FerroFlowError {
+    FerroFlowError::FormatError(format!("{}: {}", FORMAT, message.into()))
+}
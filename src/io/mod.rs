@@ -0,0 +1,124 @@
+//! Reading and writing tensors in file formats shared with other frameworks.
+//!
+//! - [`safetensors`]: named tensors in the Hugging Face format, read into memory or memory-mapped,
+//!   and read in place by CPU tensors. Combined with [`Module::state_dict`](crate::nn::Module::state_dict)
+//!   it saves and restores model weights exchanged with PyTorch.
+//! - [`gguf`]: llama.cpp model files with typed metadata and block-quantized tensors, loaded
+//!   dequantized or as [`QuantizedTensor`](crate::tensor::QuantizedTensor)s.
+//! - [`npy`]: NumPy's `.npy` arrays and `.npz` archives, for comparing tensors against Python.
+
+use crate::error::{Result, FerroFlowError};
+use memmap2::Mmap;
+use std::borrow::Cow;
+use std::fs::File;
+use std::ops::Deref;
+use std::path::Path;
+
+pub mod gguf;
+pub mod npy;
+pub mod safetensors;
+mod zip;
+
+/// Converts element bytes between native order and little (`little_endian`) or big endian order,
+/// which is the same swap in both directions. Bytes already in the right order are borrowed.
+pub(crate) fn convert_endianness(bytes: &[u8], element_size: usize, little_endian: bool) -> Cow<'_, [u8]> {
+    if element_size == 1 || little_endian == cfg!(target_endian = "little") {
+        return Cow::Borrowed(bytes);
+    }
+    let mut swapped = bytes.to_vec();
+    for element in swapped.chunks_exact_mut(element_size) {
+        element.reverse();
+    }
+    Cow::Owned(swapped)
+}
+
+/// The bytes of a file, memory-mapped or held in memory. Backends receive it in
+/// [`ComputeBackend::import_storage`](crate::compute::ComputeBackend::import_storage).
+pub enum Storage {
+    Mapped(Mmap),
+    Owned(Vec<u8>),
+}
+
+impl Storage {
+    /// Reads the file at `path` into memory
+    pub(crate) fn read(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Storage::Owned(std::fs::read(path)?))
+    }
+
+    /// Memory-maps the file at `path`
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated, by this or any other process, while the map
+    /// or any buffer borrowing it is alive. Reads would otherwise see changing data or fault.
+    pub(crate) unsafe fn map(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::open(path)?;
+        // Safety: upheld by the caller
+        Ok(Storage::Mapped(unsafe { Mmap::map(&file)? }))
+    }
+}
+
+impl Deref for Storage {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Storage::Mapped(map) => map,
+            Storage::Owned(bytes) => bytes,
+        }
+    }
+}
+
+/// Reads little-endian values from a byte slice. Reading past the end fails with a
+/// `FormatError` prefixed by the format's name.
+pub(crate) struct ByteReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    format: &'static str,
+}
+
+impl<'a> ByteReader<'a> {
+    pub(crate) fn new(bytes: &'a [u8], position: usize, format: &'static str) -> Self {
+        Self { bytes, position, format }
+    }
+
+    pub(crate) fn position(&self) -> usize {
+        self.position
+    }
+
+    pub(crate) fn remaining(&self) -> usize {
+        self.bytes.len() - self.position
+    }
+
+    pub(crate) fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self.position.checked_add(len).filter(|&end| end <= self.bytes.len()).ok_or_else(|| {
+            FerroFlowError::FormatError(format!("{}: file is truncated at byte {}", self.format, self.position))
+        })?;
+        let slice = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(slice)
+    }
+
+    pub(crate) fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(crate) fn u16(&mut self) -> Result<u16> {
+        self.array().map(u16::from_le_bytes)
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    pub(crate) fn u64(&mut self) -> Result<u64> {
+        self.array().map(u64::from_le_bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests;
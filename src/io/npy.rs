@@ -0,0 +1,311 @@
+//! NumPy's `.npy` format: a magic string, a version, a Python dict literal describing the dtype,
+//! memory order and shape, then the raw array data. `.npz` archives are ZIP files holding one
+//! `.npy` entry per array, stored by `numpy.savez` and deflated by `numpy.savez_compressed`.
+
+use super::convert_endianness;
+use super::zip;
+use crate::compute::ComputeBackend;
+use crate::dtype::DType;
+use crate::error::{Result, FerroFlowError};
+use crate::tensor::{Shape, Tensor};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Arc;
+
+pub use super::zip::Compression;
+
+const MAGIC: &[u8] = b"\x93NUMPY";
+
+/// Headers are padded so the data starts at a multiple of this, as NumPy does
+const ALIGNMENT: usize = 64;
+
+impl<B: ComputeBackend> Tensor<B> {
+    /// Loads the `.npy` file at `path`
+    pub fn read_npy(ctx: Arc<B::Context>, path: impl AsRef<Path>) -> Result<Self> {
+        deserialize(ctx, &std::fs::read(path)?)
+    }
+
+    /// Writes the tensor to a `.npy` file at `path`, readable with `numpy.load`
+    pub fn write_npy(&self, path: impl AsRef<Path>) -> Result<()> {
+        std::fs::write(path, serialize(self)?)?;
+        Ok(())
+    }
+}
+
+/// Decodes an `.npy` file held in memory. Big-endian data is converted to native order and
+/// Fortran-ordered arrays are copied into row-major order.
+pub fn deserialize<B: ComputeBackend>(ctx: Arc<B::Context>, bytes: &[u8]) -> Result<Tensor<B>> {
+    if bytes.get(..MAGIC.len()) != Some(MAGIC) {
+        return Err(format_error("missing magic string"));
+    }
+    let (header_start, header_len) = match bytes.get(MAGIC.len()..MAGIC.len() + 2) {
+        Some([1, _]) => (10usize, bytes.get(8..10).map(|len| u16::from_le_bytes([len[0], len[1]]) as usize)),
+        Some([2 | 3, _]) => (12, bytes.get(8..12).map(|len| u32::from_le_bytes(len.try_into().unwrap()) as usize)),
+        Some([major, minor]) => return Err(format_error(format!("unsupported format version {}.{}", major, minor))),
+        _ => (0, None),
+    };
+    let header_end = header_len
+        .and_then(|len| header_start.checked_add(len))
+        .filter(|&end| end <= bytes.len())
+        .ok_or_else(|| format_error("file is too short to hold the header"))?;
+    let header = std::str::from_utf8(&bytes[header_start..header_end])
+        .map_err(|_| format_error("header is not UTF-8"))?;
+    let Header { dtype, little_endian, fortran_order, shape } = Header::parse(header)?;
+
+    let data = &bytes[header_end..];
+    let byte_len = shape.iter().try_fold(dtype.size_in_bytes(), |len, &dim| len.checked_mul(dim));
+    if byte_len != Some(data.len()) {
+        return Err(format_error(format!(
+            "{} bytes of data don't hold a {} array of shape {:?}", data.len(), dtype, shape
+        )));
+    }
+    let data = convert_endianness(data, dtype.size_in_bytes(), little_endian);
+    if !fortran_order || shape.len() < 2 {
+        return Tensor::from_bytes(ctx, Shape::new(shape), dtype, &data);
+    }
+    // Column-major data is the row-major layout of the reversed shape
+    let reversed: Vec<usize> = shape.iter().rev().copied().collect();
+    let axes: Vec<usize> = (0..shape.len()).rev().collect();
+    Tensor::from_bytes(ctx, Shape::new(reversed), dtype, &data)?.permute(&axes)?.contiguous()
+}
+
+/// Encodes a tensor as a little-endian, row-major `.npy` file
+pub fn serialize<B: ComputeBackend>(tensor: &Tensor<B>) -> Result<Vec<u8>> {
+    let dtype = tensor.dtype();
+    let descr = descr(dtype)?;
+    let shape = match tensor.shape().dims() {
+        [dim] => format!("({},)", dim),
+        dims => format!("({})", dims.iter().map(usize::to_string).collect::<Vec<_>>().join(", ")),
+    };
+    let mut header = format!("{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}", descr, shape).into_bytes();
+
+    // The header ends with a newline after its padding. Version 1.0 has a 2-byte header length,
+    // larger headers need version 2.0.
+    let padded_len = |prefix_len: usize| (prefix_len + header.len() + 1).next_multiple_of(ALIGNMENT) - prefix_len;
+    let (version, prefix_len) = if padded_len(10) <= u16::MAX as usize { (1u8, 10) } else { (2, 12) };
+    header.resize(padded_len(prefix_len) - 1, b' ');
+    header.push(b'\n');
+
+    let data = tensor.to_bytes()?;
+    let mut bytes = Vec::with_capacity(prefix_len + header.len() + data.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&[version, 0]);
+    if version == 1 {
+        bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    } else {
+        bytes.extend_from_slice(&(header.len() as u32).to_le_bytes());
+    }
+    bytes.extend_from_slice(&header);
+    bytes.extend_from_slice(&convert_endianness(&data, dtype.size_in_bytes(), true));
+    Ok(bytes)
+}
+
+/// Loads every array of the `.npz` archive at `path`, keyed by name without the `.npy` suffix
+pub fn load_npz<B: ComputeBackend>(ctx: Arc<B::Context>, path: impl AsRef<Path>) -> Result<HashMap<String, Tensor<B>>> {
+    zip::read_entries(&std::fs::read(path)?)?
+        .into_iter()
+        .map(|(name, contents)| {
+            let tensor = deserialize(Arc::clone(&ctx), &contents)?;
+            let name = name.strip_suffix(".npy").map(str::to_string).unwrap_or(name);
+            Ok((name, tensor))
+        })
+        .collect()
+}
+
+/// Writes named tensors to an `.npz` archive at `path`, readable with `numpy.load`
+pub fn save_npz<B: ComputeBackend>(
+    tensors: &[(String, Tensor<B>)],
+    compression: Compression,
+    path: impl AsRef<Path>
+) -> Result<()> {
+    let mut names = HashSet::new();
+    let entries = tensors
+        .iter()
+        .map(|(name, tensor)| {
+            if !names.insert(name) {
+                return Err(FerroFlowError::InvalidOperation(format!("Array name {:?} is used more than once", name)));
+            }
+            Ok((format!("{}.npy", name), serialize(tensor)?))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    std::fs::write(path, zip::write_archive(&entries, compression)?)?;
+    Ok(())
+}
+
+/// Returns the NumPy type string of `dtype` in little-endian order
+fn descr(dtype: DType) -> Result<&'static str> {
+    Ok(match dtype {
+        DType::F32 => "<f4",
+        DType::F64 => "<f8",
+        DType::F16 => "<f2",
+        DType::I32 => "<i4",
+        DType::I64 => "<i8",
+        DType::U8 => "|u1",
+        DType::Bool => "|b1",
+        DType::BF16 => return Err(FerroFlowError::UnsupportedDType(
+            "bf16 has no NumPy equivalent, convert with to_dtype before saving".into()
+        )),
+    })
+}
+
+/// The fields of an `.npy` header
+struct Header {
+    dtype: DType,
+    little_endian: bool,
+    fortran_order: bool,
+    shape: Vec<usize>,
+}
+
+impl Header {
+    /// Parses a header such as `{'descr': '<f4', 'fortran_order': False, 'shape': (2, 3), }`
+    fn parse(text: &str) -> Result<Self> {
+        let mut parser = Parser { text: text.as_bytes(), position: 0, depth: 0 };
+        let Literal::Dict(entries) = parser.literal()? else {
+            return Err(format_error("header is not a dict"));
+        };
+        if parser.peek().is_some() {
+            return Err(format_error("unexpected text after the header dict"));
+        }
+
+        let field = |key: &str| {
+            entries.iter().find(|(k, _)| k == key).map(|(_, value)| value)
+                .ok_or_else(|| format_error(format!("header has no {:?}", key)))
+        };
+        let Literal::Str(descr) = field("descr")? else {
+            return Err(FerroFlowError::UnsupportedDType("structured NumPy dtypes".into()));
+        };
+        let (order, code) = match descr.as_bytes().first() {
+            Some(b'<' | b'>' | b'|' | b'=') => descr.split_at(1),
+            _ => ("", descr.as_str()),
+        };
+        let dtype = match code {
+            "f4" => DType::F32,
+            "f8" => DType::F64,
+            "f2" => DType::F16,
+            "i4" => DType::I32,
+            "i8" => DType::I64,
+            "u1" => DType::U8,
+            "b1" => DType::Bool,
+            _ => return Err(FerroFlowError::UnsupportedDType(format!("NumPy dtype {:?}", descr))),
+        };
+        let Literal::Bool(fortran_order) = *field("fortran_order")? else {
+            return Err(format_error("fortran_order is not a bool"));
+        };
+        let shape = match field("shape")? {
+            Literal::Tuple(dims) => dims.iter().map(|dim| match dim {
+                Literal::Int(dim) => Some(*dim),
+                _ => None,
+            }).collect(),
+            _ => None,
+        };
+        let shape = shape.ok_or_else(|| format_error("shape is not a tuple of non-negative integers"))?;
+        let little_endian = match order {
+            "<" => true,
+            ">" => false,
+            _ => cfg!(target_endian = "little"),
+        };
+        Ok(Self { dtype, little_endian, fortran_order, shape })
+    }
+}
+
+/// The Python literals found in `.npy` headers
+#[derive(Debug)]
+enum Literal {
+    Str(String),
+    Int(usize),
+    Bool(bool),
+    Tuple(Vec<Literal>),
+    Dict(Vec<(String, Literal)>),
+}
+
+/// Deepest nesting of tuples and dicts accepted in a header
+const MAX_DEPTH: usize = 8;
+
+/// A recursive descent parser for [`Literal`]s
+struct Parser<'a> {
+    text: &'a [u8],
+    position: usize,
+    depth: usize,
+}
+
+impl Parser<'_> {
+    /// Skips whitespace and returns the next character without consuming it
+    fn peek(&mut self) -> Option<u8> {
+        while self.text.get(self.position).is_some_and(u8::is_ascii_whitespace) {
+            self.position += 1;
+        }
+        self.text.get(self.position).copied()
+    }
+
+    fn expect(&mut self, expected: u8) -> Result<()> {
+        if self.peek() != Some(expected) {
+            return Err(format_error(format!("expected {:?} at byte {} of the header", expected as char, self.position)));
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    fn literal(&mut self) -> Result<Literal> {
+        match self.peek() {
+            Some(b'{') => {
+                let entries = self.sequence(b'{', b'}', |parser| {
+                    let Literal::Str(key) = parser.literal()? else {
+                        return Err(format_error("header dict keys must be strings"));
+                    };
+                    parser.expect(b':')?;
+                    Ok((key, parser.literal()?))
+                })?;
+                Ok(Literal::Dict(entries))
+            }
+            Some(open @ (b'(' | b'[')) => {
+                let close = if open == b'(' { b')' } else { b']' };
+                Ok(Literal::Tuple(self.sequence(open, close, Self::literal)?))
+            }
+            Some(quote @ (b'\'' | b'"')) => {
+                let start = self.position + 1;
+                let len = self.text[start..].iter().position(|&c| c == quote)
+                    .ok_or_else(|| format_error("unterminated string in the header"))?;
+                self.position = start + len + 1;
+                Ok(Literal::Str(String::from_utf8_lossy(&self.text[start..start + len]).into_owned()))
+            }
+            _ => {
+                let start = self.position;
+                let len = self.text[start..].iter().take_while(|c| c.is_ascii_alphanumeric()).count();
+                self.position += len;
+                let word = std::str::from_utf8(&self.text[start..start + len]).unwrap_or_default();
+                match word {
+                    "True" => Ok(Literal::Bool(true)),
+                    "False" => Ok(Literal::Bool(false)),
+                    // Python 2 wrote long integers with an `L` suffix
+                    _ => word.strip_suffix('L').unwrap_or(word).parse().map(Literal::Int).map_err(|_| {
+                        format_error(format!("unexpected value {:?} at byte {} of the header", word, start))
+                    }),
+                }
+            }
+        }
+    }
+
+    /// Parses comma-separated items between `open` and `close`, allowing a trailing comma
+    fn sequence<T>(&mut self, open: u8, close: u8, mut item: impl FnMut(&mut Self) -> Result<T>) -> Result<Vec<T>> {
+        self.expect(open)?;
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(format_error("header is nested too deeply"));
+        }
+        let mut items = Vec::new();
+        while self.peek() != Some(close) {
+            items.push(item(self)?);
+            if self.peek() != Some(b',') {
+                break;
+            }
+            self.position += 1;
+        }
+        self.expect(close)?;
+        self.depth -= 1;
+        Ok(items)
+    }
+}
+
+fn format_error(message: impl Into<String>) -> FerroFlowError {
+    FerroFlowError::FormatError(format!("npy: {}", message.into()))
+}
@@ -0,0 +1,290 @@
+//! The safetensors format: an 8-byte little-endian header length, a JSON header mapping each
+//! tensor name to its dtype, shape and byte range, then the little-endian tensor data. The
+//! optional `__metadata__` header entry holds free-form string pairs.
+
+use super::{convert_endianness, Storage};
+use crate::compute::ComputeBackend;
+use crate::dtype::DType;
+use crate::error::{Result, FerroFlowError};
+use crate::tensor::{Shape, Tensor};
+use serde_json::{Map, Value};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+
+const METADATA_KEY: &str = "__metadata__";
+
+/// Largest header accepted, so a corrupt length prefix can't trigger a huge allocation
+const MAX_HEADER_LEN: usize = 100 << 20;
+
+/// A parsed safetensors file. Views borrow the file's data, and tensors loaded into
+/// [`CPUBackend`](crate::CPUBackend) buffers share it until they are written.
+pub struct SafeTensors {
+    bytes: Arc<Storage>,
+    data_start: usize,
+    tensors: HashMap<String, TensorInfo>,
+    metadata: HashMap<String, String>,
+}
+
+/// A tensor's dtype, shape and little-endian data, borrowed from a [`SafeTensors`] file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TensorView<'a> {
+    pub dtype: DType,
+    pub shape: &'a [usize],
+    pub data: &'a [u8],
+}
+
+#[derive(Debug, Clone)]
+struct TensorInfo {
+    dtype: DType,
+    shape: Vec<usize>,
+    start: usize,
+    end: usize,
+}
+
+impl SafeTensors {
+    /// Reads and validates the file at `path`
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::parse(Storage::read(path)?)
+    }
+
+    /// Memory-maps and validates the file at `path`, so only the data that is read is loaded
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated, by this or any other process, while the
+    /// returned value or any CPU tensor loaded from it is alive.
+    pub unsafe fn open_mmap(path: impl AsRef<Path>) -> Result<Self> {
+        // Safety: upheld by the caller
+        Self::parse(unsafe { Storage::map(path)? })
+    }
+
+    /// Validates a file held in memory
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self> {
+        Self::parse(Storage::Owned(bytes))
+    }
+
+    fn parse(bytes: Storage) -> Result<Self> {
+        let len_prefix: [u8; 8] = bytes.get(..8)
+            .and_then(|prefix| prefix.try_into().ok())
+            .ok_or_else(|| format_error("file is too short to hold the header length"))?;
+        let header_len = usize::try_from(u64::from_le_bytes(len_prefix)).unwrap_or(usize::MAX);
+        if header_len > MAX_HEADER_LEN || header_len > bytes.len() - 8 {
+            return Err(format_error(format!(
+                "header length {} exceeds the file size of {} bytes", header_len, bytes.len()
+            )));
+        }
+        let data_start = 8 + header_len;
+        let header = std::str::from_utf8(&bytes[8..data_start])
+            .map_err(|e| format_error(format!("header is not UTF-8: {}", e)))?;
+        let header: Map<String, Value> = serde_json::from_str(header)
+            .map_err(|e| format_error(format!("header is not a JSON object: {}", e)))?;
+
+        let mut tensors = HashMap::new();
+        let mut metadata = HashMap::new();
+        for (name, entry) in header {
+            if name == METADATA_KEY {
+                metadata = parse_metadata(entry)?;
+            } else {
+                let info = parse_tensor_info(&name, &entry)?;
+                tensors.insert(name, info);
+            }
+        }
+        check_data_layout(&tensors, bytes.len() - data_start)?;
+        Ok(Self { bytes: Arc::new(bytes), data_start, tensors, metadata })
+    }
+
+    /// Returns the tensor names in sorted order
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.tensors.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+
+    pub fn len(&self) -> usize {
+        self.tensors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tensors.is_empty()
+    }
+
+    /// Returns the string pairs stored under `__metadata__`
+    pub fn metadata(&self) -> &HashMap<String, String> {
+        &self.metadata
+    }
+
+    /// Borrows the tensor called `name` without copying its data
+    pub fn view(&self, name: &str) -> Result<TensorView<'_>> {
+        let (info, range) = self.locate(name)?;
+        Ok(TensorView { dtype: info.dtype, shape: &info.shape, data: &self.bytes[range] })
+    }
+
+    /// Loads the tensor called `name` into backend `B`. CPU tensors read the file's data in place
+    /// when it is aligned and in native byte order, other tensors get a copy of it.
+    pub fn load<B: ComputeBackend>(&self, ctx: Arc<B::Context>, name: &str) -> Result<Tensor<B>> {
+        let (info, range) = self.locate(name)?;
+        let shape = Shape::new(info.shape.clone());
+        match convert_endianness(&self.bytes[range.clone()], info.dtype.size_in_bytes(), true) {
+            Cow::Borrowed(_) => Tensor::from_storage(ctx, shape, info.dtype, &self.bytes, range),
+            Cow::Owned(data) => Tensor::from_bytes(ctx, shape, info.dtype, &data),
+        }
+    }
+
+    /// Loads every tensor, keyed by name
+    pub fn load_all<B: ComputeBackend>(&self, ctx: Arc<B::Context>) -> Result<HashMap<String, Tensor<B>>> {
+        self.tensors
+            .keys()
+            .map(|name| Ok((name.clone(), self.load(Arc::clone(&ctx), name)?)))
+            .collect()
+    }
+
+    /// Returns the entry of the tensor called `name` and the byte range of its data
+    fn locate(&self, name: &str) -> Result<(&TensorInfo, Range<usize>)> {
+        let info = self.tensors.get(name).ok_or_else(|| {
+            FerroFlowError::InvalidOperation(format!("No tensor named {:?} in the safetensors file", name))
+        })?;
+        Ok((info, self.data_start + info.start..self.data_start + info.end))
+    }
+}
+
+/// Loads every tensor of the safetensors file at `path`, keyed by name
+pub fn load_file<B: ComputeBackend>(ctx: Arc<B::Context>, path: impl AsRef<Path>) -> Result<HashMap<String, Tensor<B>>> {
+    SafeTensors::open(path)?.load_all(ctx)
+}
+
+/// Writes named tensors, such as a module's [`state_dict`](crate::nn::Module::state_dict), and
+/// optional metadata to a safetensors file at `path`
+pub fn save_file<B: ComputeBackend>(
+    tensors: &[(String, Tensor<B>)],
+    metadata: Option<&HashMap<String, String>>,
+    path: impl AsRef<Path>
+) -> Result<()> {
+    std::fs::write(path, serialize(tensors, metadata)?)?;
+    Ok(())
+}
+
+/// Encodes named tensors and optional metadata in the safetensors format. The data follows the
+/// order of `tensors` and starts 8-byte aligned.
+pub fn serialize<B: ComputeBackend>(
+    tensors: &[(String, Tensor<B>)],
+    metadata: Option<&HashMap<String, String>>
+) -> Result<Vec<u8>> {
+    let mut header = Map::new();
+    if let Some(metadata) = metadata {
+        let entries = metadata.iter().map(|(key, value)| (key.clone(), Value::from(value.as_str())));
+        header.insert(METADATA_KEY.into(), Value::Object(entries.collect()));
+    }
+
+    let mut names = HashSet::new();
+    let mut data = Vec::new();
+    for (name, tensor) in tensors {
+        if name == METADATA_KEY || !names.insert(name) {
+            return Err(FerroFlowError::InvalidOperation(format!(
+                "Tensor name {:?} is reserved or used more than once", name
+            )));
+        }
+        let dtype = tensor.dtype();
+        let start = data.len();
+        data.extend_from_slice(&convert_endianness(&tensor.to_bytes()?, dtype.size_in_bytes(), true));
+        let mut entry = Map::new();
+        entry.insert("dtype".into(), dtype_name(dtype).into());
+        entry.insert("shape".into(), tensor.shape().dims().into());
+        entry.insert("data_offsets".into(), vec![start, data.len()].into());
+        header.insert(name.clone(), Value::Object(entry));
+    }
+
+    let mut header = Value::Object(header).to_string().into_bytes();
+    header.resize(header.len().next_multiple_of(8), b' ');
+    let mut bytes = Vec::with_capacity(8 + header.len() + data.len());
+    bytes.extend_from_slice(&(header.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(&header);
+    bytes.extend_from_slice(&data);
+    Ok(bytes)
+}
+
+fn dtype_name(dtype: DType) -> &'static str {
+    match dtype {
+        DType::F32 => "F32",
+        DType::F64 => "F64",
+        DType::F16 => "F16",
+        DType::BF16 => "BF16",
+        DType::I32 => "I32",
+        DType::I64 => "I64",
+        DType::U8 => "U8",
+        DType::Bool => "BOOL",
+    }
+}
+
+fn parse_dtype(name: &str) -> Option<DType> {
+    DType::ALL.into_iter().find(|&dtype| dtype_name(dtype) == name)
+}
+
+fn parse_metadata(entry: Value) -> Result<HashMap<String, String>> {
+    let Value::Object(entries) = entry else {
+        return Err(format_error("__metadata__ is not an object"));
+    };
+    entries
+        .into_iter()
+        .map(|(key, value)| match value {
+            Value::String(value) => Ok((key, value)),
+            _ => Err(format_error(format!("metadata value of {:?} is not a string", key))),
+        })
+        .collect()
+}
+
+fn parse_tensor_info(name: &str, entry: &Value) -> Result<TensorInfo> {
+    let field = |key: &str| entry.get(key).ok_or_else(|| format_error(format!("tensor {:?} has no {}", name, key)));
+    let integers = |key: &str| -> Result<Vec<usize>> {
+        field(key)?
+            .as_array()
+            .and_then(|values| values.iter().map(|v| v.as_u64().and_then(|v| usize::try_from(v).ok())).collect())
+            .ok_or_else(|| format_error(format!("{} of tensor {:?} is not a list of non-negative integers", key, name)))
+    };
+
+    let dtype_name = field("dtype")?
+        .as_str()
+        .ok_or_else(|| format_error(format!("dtype of tensor {:?} is not a string", name)))?;
+    let dtype = parse_dtype(dtype_name).ok_or_else(|| {
+        FerroFlowError::UnsupportedDType(format!("safetensors dtype {} of tensor {:?}", dtype_name, name))
+    })?;
+    let shape = integers("shape")?;
+    let [start, end] = integers("data_offsets")?[..] else {
+        return Err(format_error(format!("data_offsets of tensor {:?} must hold a start and an end", name)));
+    };
+
+    let byte_len = shape.iter().try_fold(dtype.size_in_bytes(), |len, &dim| len.checked_mul(dim));
+    if start > end || byte_len != Some(end - start) {
+        return Err(format_error(format!(
+            "tensor {:?} of shape {:?} and dtype {} doesn't fit its data range {}..{}", name, shape, dtype, start, end
+        )));
+    }
+    Ok(TensorInfo { dtype, shape, start, end })
+}
+
+/// Checks that the tensors' byte ranges cover the data section exactly, without gaps or overlaps
+fn check_data_layout(tensors: &HashMap<String, TensorInfo>, data_len: usize) -> Result<()> {
+    let mut ranges: Vec<(usize, usize, &str)> = tensors.iter().map(|(name, info)| (info.start, info.end, name.as_str())).collect();
+    ranges.sort_unstable();
+    let mut position = 0;
+    for (start, end, name) in ranges {
+        if start != position {
+            return Err(format_error(format!(
+                "data of tensor {:?} starts at byte {}, expected {}", name, start, position
+            )));
+        }
+        position = end;
+    }
+    if position != data_len {
+        return Err(format_error(format!(
+            "tensors cover {} bytes of data, the file holds {}", position, data_len
+        )));
+    }
+    Ok(())
+}
+
+fn format_error(message: impl Into<String>) -> FerroFlowError {
+    FerroFlowError::FormatError(format!("safetensors: {}", message.into()))
+}
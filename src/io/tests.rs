@@ -0,0 +1,523 @@
+use super::gguf::{GgmlType, GgufFile, MetadataValue};
+use super::npy::{self, Compression};
+use super::safetensors::{self, SafeTensors};
+use crate::compute::{CPUBackend, ComputeBackend, UnaryOp};
+use crate::dtype::DType;
+use crate::error::{Result, FerroFlowError};
+use crate::nn::{Activation, BatchNorm2d, Linear, Module, Sequential};
+use crate::tensor::{QuantType, Shape, Tensor};
+use half::f16;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+type T = Tensor<CPUBackend>;
+
+/// Returns a path in the temp directory that is unique to this process and test
+fn temp_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("ferroflow-{}-{}", std::process::id(), name))
+}
+
+/// Builds a safetensors file from a raw header and data section
+fn raw_file(header: &str, data: &[u8]) -> Vec<u8> {
+    let mut bytes = (header.len() as u64).to_le_bytes().to_vec();
+    bytes.extend_from_slice(header.as_bytes());
+    bytes.extend_from_slice(data);
+    bytes
+}
+
+/// Builds an .npy file from a raw header, padded like NumPy's, and data
+fn raw_npy(version: u8, header: &str, data: &[u8]) -> Vec<u8> {
+    let prefix_len = if version == 1 { 10 } else { 12 };
+    let mut header = header.as_bytes().to_vec();
+    header.resize((prefix_len + header.len() + 1).next_multiple_of(64) - prefix_len - 1, b' ');
+    header.push(b'\n');
+    let mut bytes = vec![0x93, b'N', b'U', b'M', b'P', b'Y', version, 0];
+    if version == 1 {
+        bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    } else {
+        bytes.extend_from_slice(&(header.len() as u32).to_le_bytes());
+    }
+    bytes.extend_from_slice(&header);
+    bytes.extend_from_slice(data);
+    bytes
+}
+
+#[test]
+fn test_safetensors_round_trip() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let matrix = T::new(Arc::clone(&ctx), Shape::new(vec![2, 3]), &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0])?;
+    let tensors = vec![
+        ("matrix".to_string(), matrix.clone()),
+        ("transposed".to_string(), matrix.transpose(0, 1)?),
+        ("half".to_string(), T::from_slice(Arc::clone(&ctx), Shape::new(vec![2]), &[f16::from_f32(1.5), f16::from_f32(-2.0)])?),
+        ("indices".to_string(), T::from_slice(Arc::clone(&ctx), Shape::new(vec![3]), &[7i64, -1, 1 << 40])?),
+        ("mask".to_string(), T::from_slice(Arc::clone(&ctx), Shape::new(vec![2, 1]), &[true, false])?),
+        ("empty".to_string(), T::zeros(Arc::clone(&ctx), Shape::new(vec![0, 4]))?),
+    ];
+    let metadata = HashMap::from([("format".to_string(), "pt".to_string())]);
+
+    let bytes = safetensors::serialize(&tensors, Some(&metadata))?;
+    let header_len = u64::from_le_bytes(bytes[..8].try_into().unwrap()) as usize;
+    assert_eq!(header_len % 8, 0);
+
+    let file = SafeTensors::from_bytes(bytes)?;
+    assert_eq!(file.names(), vec!["empty", "half", "indices", "mask", "matrix", "transposed"]);
+    assert_eq!(file.metadata(), &metadata);
+    let view = file.view("transposed")?;
+    assert_eq!((view.dtype, view.shape), (DType::F32, &[3, 2][..]));
+    assert_eq!(&view.data[..8], &[0, 0, 128, 63, 0, 0, 128, 64]);
+
+    let loaded = file.load_all::<CPUBackend>(Arc::clone(&ctx))?;
+    for (name, tensor) in &tensors {
+        let restored = &loaded[name];
+        assert_eq!((restored.dtype(), restored.shape()), (tensor.dtype(), tensor.shape()), "{}", name);
+        assert_eq!(restored.to_bytes()?, tensor.to_bytes()?, "{}", name);
+    }
+    assert!(file.view("missing").is_err());
+
+    // Files on disk are read into memory or memory-mapped
+    let path = temp_path("round-trip.safetensors");
+    safetensors::save_file(&tensors, None, &path)?;
+    let opened = SafeTensors::open(&path)?;
+    assert!(opened.metadata().is_empty());
+    assert_eq!(opened.load::<CPUBackend>(Arc::clone(&ctx), "transposed")?.data()?, vec![1.0, 4.0, 2.0, 5.0, 3.0, 6.0]);
+    // Safety: the file is only removed once the mapped file is dropped
+    let mapped = unsafe { SafeTensors::open_mmap(&path)? };
+    assert_eq!(mapped.load::<CPUBackend>(Arc::clone(&ctx), "transposed")?.data()?, vec![1.0, 4.0, 2.0, 5.0, 3.0, 6.0]);
+    drop(mapped);
+    assert_eq!(safetensors::load_file::<CPUBackend>(Arc::clone(&ctx), &path)?.len(), tensors.len());
+    std::fs::remove_file(&path)?;
+
+    let duplicate = vec![tensors[0].clone(), tensors[0].clone()];
+    assert!(safetensors::serialize(&duplicate, None).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_safetensors_loads_in_place() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let matrix = T::new(Arc::clone(&ctx), Shape::new(vec![2, 3]), &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0])?;
+    let indices = T::from_slice(Arc::clone(&ctx), Shape::new(vec![2]), &[3i64, -4])?;
+    let path = temp_path("in-place.safetensors");
+    safetensors::save_file(&[("matrix".to_string(), matrix.clone()), ("indices".to_string(), indices)], None, &path)?;
+
+    // Loaded CPU tensors read the map instead of allocating buffers, and outlive the file handle.
+    // Safety: the file is only removed once every tensor loaded from it is dropped
+    let file = unsafe { SafeTensors::open_mmap(&path)? };
+    let before = ctx.memory_stats();
+    let loaded = file.load::<CPUBackend>(Arc::clone(&ctx), "matrix")?;
+    let loaded_indices = file.load::<CPUBackend>(Arc::clone(&ctx), "indices")?;
+    assert_eq!(ctx.memory_stats().allocations, before.allocations);
+    drop(file);
+    assert_eq!(loaded.data()?, matrix.data()?);
+    assert_eq!(loaded_indices.to_bytes()?, crate::dtype::to_bytes(&[3i64, -4]));
+    assert_eq!(loaded.transpose(0, 1)?.contiguous()?.data()?, vec![1.0, 4.0, 2.0, 5.0, 3.0, 6.0]);
+
+    // Writing copies the data out of the map, leaving the file and other loads untouched
+    let view = loaded.reshape(Shape::new(vec![6]))?;
+    loaded.scale_(2.0)?;
+    assert_eq!(view.data()?, vec![2.0, 4.0, 6.0, 8.0, 10.0, 12.0]);
+    let reloaded = SafeTensors::open(&path)?.load::<CPUBackend>(Arc::clone(&ctx), "matrix")?;
+    assert_eq!(reloaded.data()?, matrix.data()?);
+
+    drop((loaded, view, loaded_indices, reloaded));
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn test_safetensors_reads_reference_layout() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    // Written by the reference implementation: metadata, then tensors in any header order
+    let header = r#"{"b":{"dtype":"BF16","shape":[2],"data_offsets":[8,12]},"__metadata__":{"format":"pt"},"a":{"dtype":"F32","shape":[1,2],"data_offsets":[0,8]}}"#;
+    let data = [0, 0, 128, 63, 0, 0, 0, 192, 128, 63, 0, 64];
+    let file = SafeTensors::from_bytes(raw_file(header, &data))?;
+
+    assert_eq!(file.load::<CPUBackend>(Arc::clone(&ctx), "a")?.data()?, vec![1.0, -2.0]);
+    let b = file.load::<CPUBackend>(Arc::clone(&ctx), "b")?;
+    assert_eq!((b.dtype(), b.data()?), (DType::BF16, vec![1.0, 2.0]));
+    Ok(())
+}
+
+#[test]
+fn test_safetensors_rejects_malformed_files() {
+    let tensor = |entry: &str| format!(r#"{{"t":{}}}"#, entry);
+    let cases = [
+        (vec![1, 0, 0], "too short"),
+        (raw_file("{}", &[]).into_iter().chain([0]).collect(), "trailing data"),
+        ([u64::MAX.to_le_bytes().to_vec(), b"{}".to_vec()].concat(), "header length"),
+        (raw_file("[1, 2]", &[]), "not an object"),
+        (raw_file("{\"t\":", &[]), "truncated JSON"),
+        (raw_file(&tensor(r#"{"dtype":"F32","shape":[2],"data_offsets":[0,4]}"#), &[0; 4]), "size mismatch"),
+        (raw_file(&tensor(r#"{"dtype":"F32","shape":[1],"data_offsets":[0,4]}"#), &[0; 2]), "out of bounds"),
+        (raw_file(&tensor(r#"{"dtype":"F32","shape":[1],"data_offsets":[4,8]}"#), &[0; 8]), "gap"),
+        (raw_file(&tensor(r#"{"dtype":"F32","shape":[-1],"data_offsets":[0,4]}"#), &[0; 4]), "negative dim"),
+        (raw_file(&tensor(r#"{"dtype":"F32","shape":[1]}"#), &[0; 4]), "missing offsets"),
+        (raw_file(r#"{"__metadata__":{"step":3}}"#, &[]), "metadata value"),
+    ];
+    for (bytes, case) in cases {
+        let result = SafeTensors::from_bytes(bytes);
+        assert!(matches!(result, Err(FerroFlowError::FormatError(_))), "{}", case);
+    }
+
+    let unsupported = raw_file(&tensor(r#"{"dtype":"I8","shape":[1],"data_offsets":[0,1]}"#), &[0]);
+    assert!(matches!(SafeTensors::from_bytes(unsupported), Err(FerroFlowError::UnsupportedDType(_))));
+    assert!(matches!(SafeTensors::open(temp_path("missing.safetensors")), Err(FerroFlowError::IoError(_))));
+}
+
+#[test]
+fn test_module_state_dict_round_trip() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let model = || -> Result<Sequential<CPUBackend>> {
+        Ok(Sequential::new()
+            .add(Linear::new(Arc::clone(&ctx), 3, 4, true)?)
+            .add(Activation::new(UnaryOp::Relu))
+            .add(BatchNorm2d::new(Arc::clone(&ctx), 4, true)?))
+    };
+    let trained = model()?;
+    let x = T::rand(Arc::clone(&ctx), Shape::new(vec![2, 3]))?;
+    // Update the running statistics so they differ from a fresh model's
+    trained.get(2).unwrap().forward(&trained.get(0).unwrap().forward(&x)?.reshape(Shape::new(vec![2, 4, 1, 1]))?)?;
+
+    let names: Vec<String> = trained.state_dict().into_iter().map(|(name, _)| name).collect();
+    assert_eq!(names, ["0.weight", "0.bias", "2.weight", "2.bias", "2.running_mean", "2.running_var"]);
+
+    let path = temp_path("state-dict.safetensors");
+    safetensors::save_file(&trained.state_dict(), None, &path)?;
+    let state = safetensors::load_file::<CPUBackend>(Arc::clone(&ctx), &path)?;
+    std::fs::remove_file(&path)?;
+
+    let restored = model()?;
+    restored.load_state_dict(&state, true)?;
+    for ((name, expected), (_, actual)) in trained.state_dict().iter().zip(restored.state_dict()) {
+        assert_eq!(actual.data()?, expected.data()?, "{}", name);
+    }
+    // Loading writes into the parameters' buffers, which keep requiring grad
+    assert!(restored.parameters().iter().all(Tensor::needs_grad));
+
+    // Strict loading rejects missing and unexpected names, shape mismatches reject the whole load
+    let mut partial = state.clone();
+    partial.remove("0.bias");
+    assert!(restored.load_state_dict(&partial, true).is_err());
+    restored.load_state_dict(&partial, false)?;
+    let mut extra = state.clone();
+    extra.insert("3.weight".into(), T::zeros(Arc::clone(&ctx), Shape::new(vec![1]))?);
+    assert!(restored.load_state_dict(&extra, true).is_err());
+
+    let mut wrong = partial;
+    wrong.insert("0.weight".into(), T::zeros(Arc::clone(&ctx), Shape::new(vec![4, 3]))?.to_dtype(DType::F16)?);
+    wrong.insert("2.bias".into(), T::zeros(Arc::clone(&ctx), Shape::new(vec![5]))?);
+    assert!(matches!(restored.load_state_dict(&wrong, false), Err(FerroFlowError::ShapeMismatch(_))));
+    assert_eq!(restored.state_dict()[0].1.data()?, trained.state_dict()[0].1.data()?);
+
+    // Entries of another dtype are converted
+    wrong.remove("2.bias");
+    restored.load_state_dict(&wrong, false)?;
+    assert_eq!(restored.state_dict()[0].1.data()?, vec![0.0; 12]);
+
+    Ok(())
+}
+
+#[test]
+fn test_npy_round_trip() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let matrix = T::new(Arc::clone(&ctx), Shape::new(vec![2, 3]), &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0])?;
+    let tensors = [
+        matrix.clone(),
+        matrix.transpose(0, 1)?,
+        matrix.to_dtype(DType::F64)?,
+        T::from_slice(Arc::clone(&ctx), Shape::new(vec![2]), &[f16::from_f32(1.5), f16::from_f32(-2.0)])?,
+        T::from_slice(Arc::clone(&ctx), Shape::new(vec![1, 2]), &[-7i32, 1 << 30])?,
+        T::from_slice(Arc::clone(&ctx), Shape::new(vec![3]), &[7i64, -1, 1 << 40])?,
+        T::from_slice(Arc::clone(&ctx), Shape::new(vec![2, 1, 1]), &[0u8, 255])?,
+        T::from_slice(Arc::clone(&ctx), Shape::new(vec![]), &[true])?,
+        T::zeros(Arc::clone(&ctx), Shape::new(vec![0, 4]))?,
+    ];
+    for tensor in &tensors {
+        let bytes = npy::serialize(tensor)?;
+        let header_len = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+        assert_eq!((&bytes[..8], (10 + header_len) % 64), (&b"\x93NUMPY\x01\x00"[..], 0));
+        let restored = npy::deserialize::<CPUBackend>(Arc::clone(&ctx), &bytes)?;
+        assert_eq!((restored.dtype(), restored.shape()), (tensor.dtype(), tensor.shape()));
+        assert_eq!(restored.to_bytes()?, tensor.to_bytes()?);
+    }
+    let expected = "{'descr': '<f4', 'fortran_order': False, 'shape': (3, 2), }";
+    let header = &npy::serialize(&tensors[1])?[10..128];
+    assert_eq!(String::from_utf8_lossy(header), format!("{:<117}\n", expected));
+
+    let path = temp_path("round-trip.npy");
+    matrix.write_npy(&path)?;
+    assert_eq!(T::read_npy(Arc::clone(&ctx), &path)?.data()?, matrix.data()?);
+    std::fs::remove_file(&path)?;
+
+    assert!(matches!(npy::serialize(&matrix.to_dtype(DType::BF16)?), Err(FerroFlowError::UnsupportedDType(_))));
+    Ok(())
+}
+
+#[test]
+fn test_npy_reads_numpy_layouts() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let load = |bytes: Vec<u8>| npy::deserialize::<CPUBackend>(Arc::clone(&ctx), &bytes);
+
+    // Big-endian, column-major data of [[1, 2, 3], [4, 5, 6]]
+    let data: Vec<u8> = [1i32, 4, 2, 5, 3, 6].iter().flat_map(|v| v.to_be_bytes()).collect();
+    let fortran = load(raw_npy(1, "{'descr': '>i4', 'fortran_order': True, 'shape': (2, 3), }", &data))?;
+    assert_eq!((fortran.shape().dims(), fortran.to_vec::<i32>()?), (&[2, 3][..], vec![1, 2, 3, 4, 5, 6]));
+
+    // Version 2.0 header in another key order, with Python 2 long integers
+    let data: Vec<u8> = [0.5f64, -1.0].iter().flat_map(|v| v.to_le_bytes()).collect();
+    let v2 = load(raw_npy(2, "{\"shape\": (1L, 2L), \"fortran_order\": False, \"descr\": \"<f8\"}", &data))?;
+    assert_eq!((v2.dtype(), v2.shape().dims(), v2.data()?), (DType::F64, &[1, 2][..], vec![0.5, -1.0]));
+
+    let flags = load(raw_npy(1, "{'descr': '|b1', 'fortran_order': False, 'shape': (2,), }", &[1, 0]))?;
+    assert_eq!(flags.to_vec::<bool>()?, vec![true, false]);
+    Ok(())
+}
+
+#[test]
+fn test_npy_rejects_malformed_files() {
+    let ctx = CPUBackend::new().unwrap();
+    let header = |descr: &str, shape: &str| format!("{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}", descr, shape);
+    let cases = [
+        (b"\x93NUMPZ\x01\x00".to_vec(), "magic"),
+        (raw_npy(4, &header("<f4", "(1,)"), &[0; 4]), "version"),
+        (raw_npy(1, &header("<f4", "(1,)"), &[0; 4])[..20].to_vec(), "truncated header"),
+        (raw_npy(1, &header("<f4", "(2,)"), &[0; 4]), "size mismatch"),
+        (raw_npy(1, &header("<f4", "(-1,)"), &[]), "negative dim"),
+        (raw_npy(1, &header("<f4", "((((((((((1,),),),),),),),),),)"), &[0; 4]), "nesting"),
+        (raw_npy(1, "{'descr': '<f4', 'shape': (1,)}", &[0; 4]), "missing field"),
+        (raw_npy(1, "['<f4', False, (1,)]", &[0; 4]), "not a dict"),
+    ];
+    for (bytes, case) in cases {
+        let result = npy::deserialize::<CPUBackend>(Arc::clone(&ctx), &bytes);
+        assert!(matches!(result, Err(FerroFlowError::FormatError(_))), "{}", case);
+    }
+
+    for descr in ["<c8", "<u4"] {
+        let result = npy::deserialize::<CPUBackend>(Arc::clone(&ctx), &raw_npy(1, &header(descr, "(1,)"), &[0; 8]));
+        assert!(matches!(result, Err(FerroFlowError::UnsupportedDType(_))), "{}", descr);
+    }
+}
+
+#[test]
+fn test_npz_round_trip() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let tensors = vec![
+        ("weights".to_string(), T::rand(Arc::clone(&ctx), Shape::new(vec![16, 8]))?),
+        ("steps".to_string(), T::from_slice(Arc::clone(&ctx), Shape::new(vec![2]), &[3i64, 4])?),
+        ("zeros".to_string(), T::zeros(Arc::clone(&ctx), Shape::new(vec![64]))?),
+    ];
+
+    let mut sizes = Vec::new();
+    for compression in [Compression::Stored, Compression::Deflated] {
+        let path = temp_path(&format!("round-trip-{:?}.npz", compression));
+        npy::save_npz(&tensors, compression, &path)?;
+        sizes.push(std::fs::metadata(&path)?.len());
+        let loaded = npy::load_npz::<CPUBackend>(Arc::clone(&ctx), &path)?;
+        std::fs::remove_file(&path)?;
+
+        assert_eq!(loaded.len(), tensors.len());
+        for (name, tensor) in &tensors {
+            assert_eq!(loaded[name].to_bytes()?, tensor.to_bytes()?, "{}", name);
+        }
+    }
+    assert!(sizes[1] < sizes[0]);
+
+    let duplicate = vec![tensors[0].clone(), tensors[0].clone()];
+    assert!(npy::save_npz(&duplicate, Compression::Stored, temp_path("duplicate.npz")).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_npz_reads_numpy_archives() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    // A deflated `x.npy` holding [1.5, -2.0] as f32, written by Python's zipfile with the
+    // ZIP64 local headers numpy asks for
+    let archive = "504b03042d000000080000002100d6434b05ffffffffffffffff05001400782e6e70790100100088000000\
+        000000004b000000000000009bec17ea1b10c9c850c650ad9e925a9c5ca46ea5a06e9366a2aea3a09e965f5452\
+        9498179f5f94920a12774bcc294e058a17672416a402f91a463a9a3a0ab50a14002e068603f60c400200504b01\
+        022d032d000000080000002100d6434b054b00000088000000050000000000000000000000800100000000782e\
+        6e7079504b0506000000000100010033000000820000000000";
+    let archive: Vec<u8> = (0..archive.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&archive[i..i + 2], 16).unwrap())
+        .collect();
+    let path = temp_path("numpy.npz");
+    std::fs::write(&path, &archive)?;
+    let loaded = npy::load_npz::<CPUBackend>(Arc::clone(&ctx), &path)?;
+    assert_eq!(loaded["x"].data()?, vec![1.5, -2.0]);
+
+    // Flipping a byte of the compressed data fails the checksum or the decoder
+    let mut corrupt = archive;
+    corrupt[60] ^= 0xff;
+    std::fs::write(&path, &corrupt)?;
+    let result = npy::load_npz::<CPUBackend>(Arc::clone(&ctx), &path);
+    std::fs::remove_file(&path)?;
+    assert!(matches!(result, Err(FerroFlowError::FormatError(_))));
+    Ok(())
+}
+
+/// Appends a GGUF string
+fn gguf_string(out: &mut Vec<u8>, value: &str) {
+    out.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    out.extend_from_slice(value.as_bytes());
+}
+
+/// Builds a version 3 GGUF file from encoded metadata values, given with their type ids, and
+/// tensors given with their GGML dims, type id and data, laid out with 32-byte alignment
+fn gguf_file(metadata: &[(&str, u32, Vec<u8>)], tensors: &[(&str, Vec<u64>, u32, Vec<u8>)]) -> Vec<u8> {
+    let mut bytes = b"GGUF".to_vec();
+    bytes.extend_from_slice(&3u32.to_le_bytes());
+    bytes.extend_from_slice(&(tensors.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(&(metadata.len() as u64).to_le_bytes());
+    for (key, value_type, value) in metadata {
+        gguf_string(&mut bytes, key);
+        bytes.extend_from_slice(&value_type.to_le_bytes());
+        bytes.extend_from_slice(value);
+    }
+    let mut data = Vec::new();
+    for (name, dims, ggml_type, tensor_data) in tensors {
+        gguf_string(&mut bytes, name);
+        bytes.extend_from_slice(&(dims.len() as u32).to_le_bytes());
+        dims.iter().for_each(|dim| bytes.extend_from_slice(&dim.to_le_bytes()));
+        bytes.extend_from_slice(&ggml_type.to_le_bytes());
+        bytes.extend_from_slice(&(data.len() as u64).to_le_bytes());
+        data.extend_from_slice(tensor_data);
+        data.resize(data.len().next_multiple_of(32), 0);
+    }
+    bytes.resize(bytes.len().next_multiple_of(32), 0);
+    bytes.extend_from_slice(&data);
+    bytes
+}
+
+fn f16_bytes(value: f32) -> [u8; 2] {
+    f16::from_f32(value).to_le_bytes()
+}
+
+#[test]
+fn test_gguf_reads_metadata_and_tensors() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let mut tokens = vec![8, 0, 0, 0];
+    tokens.extend_from_slice(&2u64.to_le_bytes());
+    gguf_string(&mut tokens, "<s>");
+    gguf_string(&mut tokens, "hello");
+    let metadata = [
+        ("general.architecture", 8, [5u64.to_le_bytes().to_vec(), b"llama".to_vec()].concat()),
+        ("llama.context_length", 4, 4096u32.to_le_bytes().to_vec()),
+        ("llama.rope.freq_base", 6, 10000f32.to_le_bytes().to_vec()),
+        ("tokenizer.ggml.tokens", 9, tokens),
+        ("tokenizer.ggml.add_bos_token", 7, vec![1]),
+    ];
+
+    // Q8_0: scale 0.5, values -16..16
+    let mut q8 = f16_bytes(0.5).to_vec();
+    q8.extend((-16i8..16).map(|q| q as u8));
+    // Q4_0: scale 2, low nibbles 0..16 for the first half, high nibbles 15 for the second
+    let mut q4 = f16_bytes(2.0).to_vec();
+    q4.extend((0..16).map(|q| q | 0xF0));
+    // Q4_K: scale and min 1, sub-block j has scale j + 1 and minimum j, chunks hold nibbles
+    // l % 16 low and 15 - l % 16 high
+    let mut q4k = [f16_bytes(1.0), f16_bytes(1.0)].concat();
+    q4k.extend([1, 2, 3, 4, 0, 1, 2, 3, 5 | (4 << 4), 6 | (5 << 4), 7 | (6 << 4), 8 | (7 << 4)]);
+    q4k.extend((0..128).map(|l| (((15 - l % 16) << 4) | (l % 16)) as u8));
+    // Q6_K: low bits 1 and 2 from the nibbles, high bits 0 to 3 from the bit pairs of 0xE4,
+    // sub-block scales 1 to 16
+    let mut q6k = vec![0x21; 128];
+    q6k.extend([0xE4; 64]);
+    q6k.extend(1..=16u8);
+    q6k.extend(f16_bytes(1.0));
+
+    let tensors = [
+        ("token_embd.weight", vec![3, 2], 0, [1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0].iter().flat_map(|v| v.to_le_bytes()).collect()),
+        ("output_norm.weight", vec![2], 1, [f16_bytes(0.5), f16_bytes(-1.0)].concat()),
+        ("blk.0.attn_q.weight", vec![32, 1], 8, q8),
+        ("blk.0.attn_k.weight", vec![32], 2, q4),
+        ("blk.0.ffn_up.weight", vec![256, 1], 12, q4k),
+        ("output.weight", vec![256], 14, q6k),
+        ("blk.0.attn_v.weight", vec![32], 3, vec![0; 20]),
+    ];
+    let file = GgufFile::from_bytes(gguf_file(&metadata, &tensors))?;
+
+    assert_eq!(file.version(), 3);
+    let meta = file.metadata();
+    assert_eq!(meta["general.architecture"].as_str(), Some("llama"));
+    assert_eq!(meta["llama.context_length"].as_u64(), Some(4096));
+    assert_eq!(meta["llama.rope.freq_base"].as_f64(), Some(10000.0));
+    assert_eq!(meta["tokenizer.ggml.add_bos_token"], MetadataValue::Bool(true));
+    let tokens: Vec<&str> = meta["tokenizer.ggml.tokens"].as_array().unwrap().iter().filter_map(MetadataValue::as_str).collect();
+    assert_eq!(tokens, ["<s>", "hello"]);
+
+    let names: Vec<&str> = file.tensor_infos().iter().map(|info| info.name.as_str()).collect();
+    assert_eq!(names, tensors.iter().map(|t| t.0).collect::<Vec<_>>());
+    let embedding = file.load::<CPUBackend>(Arc::clone(&ctx), "token_embd.weight")?;
+    assert_eq!((embedding.shape().dims(), embedding.data()?), (&[2, 3][..], vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]));
+    let norm = file.load::<CPUBackend>(Arc::clone(&ctx), "output_norm.weight")?;
+    assert_eq!((norm.dtype(), norm.data()?), (DType::F16, vec![0.5, -1.0]));
+
+    let q8 = file.load::<CPUBackend>(Arc::clone(&ctx), "blk.0.attn_q.weight")?;
+    assert_eq!((q8.dtype(), q8.shape().dims()), (DType::F32, &[1, 32][..]));
+    assert_eq!(q8.data()?, (-16..16).map(|q| q as f32 * 0.5).collect::<Vec<_>>());
+    let q4 = file.load::<CPUBackend>(Arc::clone(&ctx), "blk.0.attn_k.weight")?.data()?;
+    assert_eq!(q4, (0..16).map(|q| (q - 8) as f32 * 2.0).chain([14.0; 16]).collect::<Vec<_>>());
+    let q4k = file.load::<CPUBackend>(Arc::clone(&ctx), "blk.0.ffn_up.weight")?.data()?;
+    assert_eq!((q4k[0], q4k[64 + 3], q4k[96 + 3], q4k[128 + 32], q4k[255]), (0.0, 7.0, 45.0, 85.0, -7.0));
+    let q6k = file.load::<CPUBackend>(Arc::clone(&ctx), "output.weight")?.data()?;
+    assert_eq!((q6k[0], q6k[32], q6k[64 + 17], q6k[128 + 96 + 31]), (-31.0, -45.0, 12.0, 288.0));
+
+    // Quantized tensors keep their blocks and multiply without dequantizing up front
+    let quantized = file.load_quantized("blk.0.ffn_up.weight")?;
+    assert_eq!((quantized.quant_type(), quantized.as_bytes()), (QuantType::Q4_K, file.raw_data("blk.0.ffn_up.weight")?));
+    let input = T::new(Arc::clone(&ctx), Shape::new(vec![1, 256]), &[1.0; 256])?;
+    assert_eq!(quantized.matmul(&input)?.data()?, vec![q4k.iter().sum::<f32>()]);
+    assert!(file.load_quantized("token_embd.weight").is_err());
+
+    let unsupported = file.info("blk.0.attn_v.weight")?;
+    assert_eq!((unsupported.ggml_type, &unsupported.shape[..]), (GgmlType::Unsupported(3), &[32][..]));
+    assert!(matches!(file.load::<CPUBackend>(Arc::clone(&ctx), "blk.0.attn_v.weight"), Err(FerroFlowError::UnsupportedDType(_))));
+    assert!(file.info("missing").is_err());
+
+    let path = temp_path("model.gguf");
+    std::fs::write(&path, gguf_file(&metadata, &tensors[..6]))?;
+    let loaded = GgufFile::open(&path)?.load_all::<CPUBackend>(Arc::clone(&ctx))?;
+    // Safety: the file is only removed once the mapped file is dropped
+    let mapped = unsafe { GgufFile::open_mmap(&path)? }.load_all::<CPUBackend>(Arc::clone(&ctx))?;
+    std::fs::remove_file(&path)?;
+    assert_eq!(mapped["output.weight"].data()?, q6k);
+    assert_eq!(loaded.len(), 6);
+    assert_eq!(loaded["output.weight"].data()?, q6k);
+    Ok(())
+}
+
+#[test]
+fn test_gguf_rejects_malformed_files() {
+    let tensor = |dims: Vec<u64>, ggml_type: u32, len: usize| vec![("t", dims, ggml_type, vec![0; len])];
+    let valid = gguf_file(&[], &tensor(vec![4], 0, 16));
+    assert!(GgufFile::from_bytes(valid.clone()).is_ok());
+
+    let with_header = |offset: usize, bytes: &[u8]| {
+        let mut file = valid.clone();
+        file[offset..offset + bytes.len()].copy_from_slice(bytes);
+        file
+    };
+    // The tensor's offset follows the 24-byte header and its name, rank, dims and type
+    let offset_position = 24 + 9 + 4 + 8 + 4;
+    let cases = [
+        (with_header(0, b"GGML"), "magic"),
+        (with_header(4, &1u32.to_le_bytes()), "version 1"),
+        (with_header(4, &3u32.to_be_bytes()), "big endian"),
+        (valid[..valid.len() - 17].to_vec(), "truncated data"),
+        (valid[..20].to_vec(), "truncated header"),
+        (gguf_file(&[], &tensor(vec![0], 0, 0))[..offset_position + 8].to_vec(), "truncated padding"),
+        (with_header(offset_position, &[4]), "misaligned offset"),
+        (gguf_file(&[], &tensor(vec![16], 2, 9)), "partial block"),
+        (gguf_file(&[], &[tensor(vec![1], 0, 4), tensor(vec![1], 0, 4)].concat()), "duplicate name"),
+        (gguf_file(&[("k", 13, vec![])], &[]), "unknown value type"),
+        (gguf_file(&[("k", 7, vec![2])], &[]), "bool"),
+        (gguf_file(&[("general.alignment", 4, 24u32.to_le_bytes().to_vec())], &[]), "alignment"),
+    ];
+    for (bytes, case) in cases {
+        let result = GgufFile::from_bytes(bytes);
+        assert!(matches!(result, Err(FerroFlowError::FormatError(_))), "{}", case);
+    }
+}
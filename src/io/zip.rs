@@ -0,0 +1,230 @@
+//! The subset of the ZIP format used by `.npz` archives: stored and deflated entries without
+//! encryption, with ZIP64 sizes and offsets when reading.
+
+use super::ByteReader;
+use crate::error::{Result, FerroFlowError};
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use std::io::{Read, Write};
+
+const LOCAL_HEADER: u32 = 0x0403_4b50;
+const CENTRAL_HEADER: u32 = 0x0201_4b50;
+const END_OF_DIRECTORY: u32 = 0x0605_4b50;
+const ZIP64_END_OF_DIRECTORY: u32 = 0x0606_4b50;
+const ZIP64_LOCATOR: u32 = 0x0706_4b50;
+const ZIP64_EXTRA: u16 = 0x0001;
+
+/// Name prefixed to errors, archives are only read as `.npz` files
+const FORMAT: &str = "npz";
+
+const LOCAL_HEADER_LEN: usize = 30;
+const CENTRAL_HEADER_LEN: usize = 46;
+const END_OF_DIRECTORY_LEN: usize = 22;
+
+/// Entries are written with a fixed timestamp of 1980-01-01, the earliest ZIP date
+const DOS_DATE: u16 = (1 << 5) | 1;
+/// Flag marking entry names as UTF-8
+const UTF8_NAMES: u16 = 1 << 11;
+
+/// How entries are stored in a written archive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// Uncompressed, as written by `numpy.savez`
+    Stored,
+    /// Deflate-compressed, as written by `numpy.savez_compressed`
+    Deflated,
+}
+
+impl Compression {
+    fn method(self) -> u16 {
+        match self {
+            Compression::Stored => 0,
+            Compression::Deflated => 8,
+        }
+    }
+}
+
+/// Reads a record signature, failing if it isn't `expected`
+fn signature(reader: &mut ByteReader<'_>, expected: u32, what: &str) -> Result<()> {
+    if reader.u32()? != expected {
+        return Err(format_error(format!("missing {} signature at byte {}", what, reader.position() - 4)));
+    }
+    Ok(())
+}
+
+/// Returns the name and uncompressed contents of every entry, in central directory order
+pub(crate) fn read_entries(bytes: &[u8]) -> Result<Vec<(String, Vec<u8>)>> {
+    let (count, directory_offset) = find_directory(bytes)?;
+    let mut directory = ByteReader::new(bytes, directory_offset, FORMAT);
+    // Each entry takes at least a central header, so a corrupt count can't over-allocate
+    let mut entries = Vec::with_capacity(count.min(bytes.len() / CENTRAL_HEADER_LEN));
+    for _ in 0..count {
+        signature(&mut directory, CENTRAL_HEADER, "central directory entry")?;
+        directory.take(4)?; // versions
+        let flags = directory.u16()?;
+        let method = directory.u16()?;
+        directory.take(4)?; // modification time and date
+        let crc = directory.u32()?;
+        let mut compressed_size = directory.u32()? as u64;
+        let mut size = directory.u32()? as u64;
+        let name_len = directory.u16()? as usize;
+        let extra_len = directory.u16()? as usize;
+        let comment_len = directory.u16()? as usize;
+        directory.take(8)?; // disk number and file attributes
+        let mut offset = directory.u32()? as u64;
+        let name = String::from_utf8(directory.take(name_len)?.to_vec())
+            .map_err(|_| format_error("entry name is not UTF-8"))?;
+        read_zip64_extra(directory.take(extra_len)?, &mut size, &mut compressed_size, &mut offset)?;
+        directory.take(comment_len)?;
+
+        if flags & 1 != 0 {
+            return Err(format_error(format!("entry {:?} is encrypted", name)));
+        }
+        let data = entry_data(bytes, offset, compressed_size)?;
+        let contents = match method {
+            0 => data.to_vec(),
+            8 => {
+                let mut contents = Vec::new();
+                DeflateDecoder::new(data)
+                    .take(size.saturating_add(1))
+                    .read_to_end(&mut contents)
+                    .map_err(|e| format_error(format!("entry {:?} is not valid deflate data: {}", name, e)))?;
+                contents
+            }
+            _ => return Err(format_error(format!("entry {:?} uses unsupported compression method {}", name, method))),
+        };
+        if contents.len() as u64 != size || crc32fast::hash(&contents) != crc {
+            return Err(format_error(format!("entry {:?} doesn't match its recorded size or checksum", name)));
+        }
+        entries.push((name, contents));
+    }
+    Ok(entries)
+}
+
+/// Locates the end of central directory record and returns the entry count and directory offset
+fn find_directory(bytes: &[u8]) -> Result<(usize, usize)> {
+    // The record ends the archive, followed only by a comment of at most 64 KiB
+    let last = bytes.len().checked_sub(END_OF_DIRECTORY_LEN).ok_or_else(|| format_error("archive is too short"))?;
+    let end = (last.saturating_sub(u16::MAX as usize)..=last)
+        .rev()
+        .find(|&i| bytes[i..i + 4] == END_OF_DIRECTORY.to_le_bytes())
+        .ok_or_else(|| format_error("end of central directory record not found"))?;
+
+    let mut record = ByteReader::new(bytes, end + 10, FORMAT);
+    let mut count = record.u16()? as u64;
+    record.take(4)?; // directory size
+    let mut offset = record.u32()? as u64;
+    if count == u16::MAX as u64 || offset == u32::MAX as u64 {
+        let locator_start = end.checked_sub(20).ok_or_else(|| format_error("ZIP64 locator not found"))?;
+        let mut locator = ByteReader::new(bytes, locator_start, FORMAT);
+        signature(&mut locator, ZIP64_LOCATOR, "ZIP64 locator")?;
+        locator.take(4)?; // disk number
+        let mut record = ByteReader::new(bytes, to_usize(locator.u64()?)?, FORMAT);
+        signature(&mut record, ZIP64_END_OF_DIRECTORY, "ZIP64 end of central directory")?;
+        record.take(28)?; // record size, versions, disk numbers and per-disk count
+        count = record.u64()?;
+        record.take(8)?; // directory size
+        offset = record.u64()?;
+    }
+    Ok((to_usize(count)?, to_usize(offset)?))
+}
+
+/// Replaces the sizes and offset saturated at `u32::MAX` with the values of a ZIP64 extra field
+fn read_zip64_extra(mut extra: &[u8], size: &mut u64, compressed_size: &mut u64, offset: &mut u64) -> Result<()> {
+    while extra.len() >= 4 {
+        let mut field = ByteReader::new(extra, 0, FORMAT);
+        let id = field.u16()?;
+        let len = field.u16()? as usize;
+        let mut values = ByteReader::new(field.take(len)?, 0, FORMAT);
+        if id == ZIP64_EXTRA {
+            for value in [&mut *size, &mut *compressed_size, &mut *offset] {
+                if *value == u32::MAX as u64 {
+                    *value = values.u64()?;
+                }
+            }
+            return Ok(());
+        }
+        extra = &extra[4 + len..];
+    }
+    Ok(())
+}
+
+/// Returns the `compressed_size` bytes following the local header at `offset`
+fn entry_data(bytes: &[u8], offset: u64, compressed_size: u64) -> Result<&[u8]> {
+    let mut header = ByteReader::new(bytes, to_usize(offset)?, FORMAT);
+    signature(&mut header, LOCAL_HEADER, "local file header")?;
+    header.take(LOCAL_HEADER_LEN - 8)?;
+    let name_len = header.u16()? as usize;
+    let extra_len = header.u16()? as usize;
+    header.take(name_len + extra_len)?;
+    header.take(to_usize(compressed_size)?)
+}
+
+/// Encodes `entries` as an archive. Archives needing ZIP64, beyond 4 GiB or 65535 entries,
+/// are rejected.
+pub(crate) fn write_archive(entries: &[(String, Vec<u8>)], compression: Compression) -> Result<Vec<u8>> {
+    let too_large = || FerroFlowError::InvalidOperation(
+        "Archives over 4 GiB or 65535 entries are not supported for writing".into()
+    );
+    let count = u16::try_from(entries.len()).map_err(|_| too_large())?;
+    let mut archive = Vec::new();
+    let mut directory = Vec::new();
+    for (name, contents) in entries {
+        let data = match compression {
+            Compression::Stored => contents.clone(),
+            Compression::Deflated => {
+                let mut encoder = DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(contents)?;
+                encoder.finish()?
+            }
+        };
+        let offset = u32::try_from(archive.len()).map_err(|_| too_large())?;
+        let size = u32::try_from(contents.len()).map_err(|_| too_large())?;
+        let compressed_size = u32::try_from(data.len()).map_err(|_| too_large())?;
+        let name_len = u16::try_from(name.len()).map_err(|_| too_large())?;
+        let flags = if name.is_ascii() { 0 } else { UTF8_NAMES };
+
+        // Fields shared by the local and central headers, from the version needed onwards
+        let mut common = Vec::with_capacity(26);
+        for value in [20, flags, compression.method(), 0, DOS_DATE] {
+            common.extend_from_slice(&u16::to_le_bytes(value));
+        }
+        for value in [crc32fast::hash(contents), compressed_size, size] {
+            common.extend_from_slice(&value.to_le_bytes());
+        }
+        common.extend_from_slice(&name_len.to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes()); // extra field length
+
+        archive.extend_from_slice(&LOCAL_HEADER.to_le_bytes());
+        archive.extend_from_slice(&common);
+        archive.extend_from_slice(name.as_bytes());
+        archive.extend_from_slice(&data);
+
+        directory.extend_from_slice(&CENTRAL_HEADER.to_le_bytes());
+        directory.extend_from_slice(&20u16.to_le_bytes()); // version made by
+        directory.extend_from_slice(&common);
+        directory.extend_from_slice(&[0; 10]); // comment length, disk number and file attributes
+        directory.extend_from_slice(&offset.to_le_bytes());
+        directory.extend_from_slice(name.as_bytes());
+    }
+
+    let directory_offset = u32::try_from(archive.len()).map_err(|_| too_large())?;
+    let directory_len = u32::try_from(directory.len()).map_err(|_| too_large())?;
+    archive.extend_from_slice(&directory);
+    archive.extend_from_slice(&END_OF_DIRECTORY.to_le_bytes());
+    archive.extend_from_slice(&[0; 4]); // disk numbers
+    archive.extend_from_slice(&count.to_le_bytes());
+    archive.extend_from_slice(&count.to_le_bytes());
+    archive.extend_from_slice(&directory_len.to_le_bytes());
+    archive.extend_from_slice(&directory_offset.to_le_bytes());
+    archive.extend_from_slice(&0u16.to_le_bytes()); // comment length
+    Ok(archive)
+}
+
+fn to_usize(value: u64) -> Result<usize> {
+    usize::try_from(value).map_err(|_| format_error(format!("offset {} doesn't fit in memory", value)))
+}
+
+fn format_error(message: impl Into<String>) -> FerroFlowError {
+    FerroFlowError::FormatError(format!("{}: {}", FORMAT, message.into()))
+}
@@ -2,14 +2,30 @@
 //! 
 //! This library provides a tensor computation framework that can leverage both CPU and Metal GPU
 //! backends for accelerated machine learning operations.
+//!
+//! The Metal backend is gated behind the `metal` cargo feature (enabled by default) and is
+//! only compiled on macOS. `CPUBackend` and the full `Tensor` API are available everywhere.
 
 pub mod tensor;
 pub mod compute;
+pub mod autograd;
+pub mod dtype;
+pub mod nn;
+pub mod loss;
+pub mod optim;
+pub mod data;
+pub mod io;
+pub mod onnx;
+#[cfg(all(feature = "metal", target_os = "macos"))]
 pub mod metal;
 pub mod error;
 
 pub use tensor::Tensor;
-pub use compute::{ComputeBackend, CPUBackend, MetalBackend};
+pub use autograd::no_grad;
+pub use dtype::DType;
+pub use compute::{ComputeBackend, CPUBackend};
+#[cfg(all(feature = "metal", target_os = "macos"))]
+pub use compute::MetalBackend;
 
 use tracing_subscriber::{fmt, EnvFilter};
 
@@ -0,0 +1,173 @@
+//! Loss functions.
+//!
+//! Each loss is evaluated by a fused, numerically stable backend kernel that produces one
+//! value per element (or per row for class losses), followed by the requested [`Reduction`].
+//! Class targets are i64 tensors of indices; the remaining losses take a target of the same
+//! shape as the input.
+
+use crate::compute::{ClassLossOp, ComputeBackend, PointwiseLossOp};
+use crate::error::{Result, FerroFlowError};
+use crate::tensor::{Shape, Tensor};
+
+/// How per-element losses are combined
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Reduction {
+    /// Keep one loss per element (per sample for class losses)
+    None,
+    /// Average the losses; class losses average over the targets that are not ignored and fail
+    /// when every target is ignored
+    #[default]
+    Mean,
+    Sum,
+}
+
+/// Options for [`cross_entropy`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CrossEntropyOptions {
+    pub reduction: Reduction,
+    /// Mixes the one-hot target with a uniform distribution: `(1 - ε) * one_hot + ε / classes`
+    pub label_smoothing: f32,
+    /// Targets equal to this value contribute neither loss nor gradient
+    pub ignore_index: Option<i64>,
+}
+
+impl Default for CrossEntropyOptions {
+    fn default() -> Self {
+        Self { reduction: Reduction::Mean, label_smoothing: 0.0, ignore_index: None }
+    }
+}
+
+/// Softmax cross-entropy of `[N, C]` or `[N, C, d1, ...]` logits against i64 class indices
+/// of shape `[N]` or `[N, d1, ...]`
+pub fn cross_entropy<B: ComputeBackend>(
+    logits: &Tensor<B>,
+    targets: &Tensor<B>,
+    options: CrossEntropyOptions
+) -> Result<Tensor<B>> {
+    let smoothing = options.label_smoothing;
+    if !(0.0..=1.0).contains(&smoothing) {
+        return Err(FerroFlowError::InvalidOperation(format!(
+            "Label smoothing must be in [0, 1], got {}", smoothing
+        )));
+    }
+    let op = ClassLossOp::CrossEntropy { label_smoothing: smoothing };
+    class_loss(logits, targets, op, options.reduction, options.ignore_index)
+}
+
+/// Negative log-likelihood of `[N, C]` or `[N, C, d1, ...]` log-probabilities, e.g. the output
+/// of `log_softmax(1)`, against i64 class indices of shape `[N]` or `[N, d1, ...]`
+pub fn nll_loss<B: ComputeBackend>(
+    log_probs: &Tensor<B>,
+    targets: &Tensor<B>,
+    reduction: Reduction,
+    ignore_index: Option<i64>
+) -> Result<Tensor<B>> {
+    class_loss(log_probs, targets, ClassLossOp::Nll, reduction, ignore_index)
+}
+
+/// Mean squared error, `(x - y)^2`
+pub fn mse_loss<B: ComputeBackend>(input: &Tensor<B>, target: &Tensor<B>, reduction: Reduction) -> Result<Tensor<B>> {
+    reduce(input.pointwise_loss(target, PointwiseLossOp::Mse)?, reduction)
+}
+
+/// Mean absolute error, `|x - y|`
+pub fn l1_loss<B: ComputeBackend>(input: &Tensor<B>, target: &Tensor<B>, reduction: Reduction) -> Result<Tensor<B>> {
+    reduce(input.pointwise_loss(target, PointwiseLossOp::L1)?, reduction)
+}
+
+/// Quadratic below `beta` and L1 above it, with slope 1 in the linear region
+pub fn smooth_l1_loss<B: ComputeBackend>(
+    input: &Tensor<B>,
+    target: &Tensor<B>,
+    beta: f32,
+    reduction: Reduction
+) -> Result<Tensor<B>> {
+    check_threshold("smooth_l1_loss beta", beta)?;
+    reduce(input.pointwise_loss(target, PointwiseLossOp::SmoothL1 { beta })?, reduction)
+}
+
+/// Quadratic below `delta` and linear with slope `delta` above it; `delta` times `smooth_l1_loss`
+pub fn huber_loss<B: ComputeBackend>(
+    input: &Tensor<B>,
+    target: &Tensor<B>,
+    delta: f32,
+    reduction: Reduction
+) -> Result<Tensor<B>> {
+    check_threshold("huber_loss delta", delta)?;
+    reduce(input.pointwise_loss(target, PointwiseLossOp::Huber { delta })?, reduction)
+}
+
+/// Binary cross-entropy between `sigmoid(logits)` and target probabilities, computed from the
+/// logits directly so it stays finite for large magnitudes
+pub fn binary_cross_entropy_with_logits<B: ComputeBackend>(
+    logits: &Tensor<B>,
+    targets: &Tensor<B>,
+    reduction: Reduction
+) -> Result<Tensor<B>> {
+    reduce(logits.pointwise_loss(targets, PointwiseLossOp::BceWithLogits)?, reduction)
+}
+
+fn check_threshold(name: &str, value: f32) -> Result<()> {
+    if value.is_nan() || value < 0.0 {
+        return Err(FerroFlowError::InvalidOperation(format!("{} must be non-negative, got {}", name, value)));
+    }
+    Ok(())
+}
+
+fn reduce<B: ComputeBackend>(losses: Tensor<B>, reduction: Reduction) -> Result<Tensor<B>> {
+    match reduction {
+        Reduction::None => Ok(losses),
+        Reduction::Mean => losses.mean_all(),
+        Reduction::Sum => losses.sum_all(),
+    }
+}
+
+/// Moves the class dimension of `[N, C, d1, ...]` scores last and flattens them to
+/// `[N * d1 * ..., C]` rows, evaluates `op` per row and applies the reduction
+fn class_loss<B: ComputeBackend>(
+    input: &Tensor<B>,
+    targets: &Tensor<B>,
+    op: ClassLossOp,
+    reduction: Reduction,
+    ignore_index: Option<i64>
+) -> Result<Tensor<B>> {
+    let dims = input.shape().dims();
+    if dims.len() < 2 {
+        return Err(FerroFlowError::ShapeMismatch(format!(
+            "Class losses expect [N, C, ...] scores, got shape {:?}", dims
+        )));
+    }
+    let mut target_dims = dims.to_vec();
+    let classes = target_dims.remove(1);
+    if targets.shape().dims() != target_dims {
+        return Err(FerroFlowError::ShapeMismatch(format!(
+            "Scores of shape {:?} need targets of shape {:?}, got {:?}", dims, target_dims, targets.shape().dims()
+        )));
+    }
+
+    let rows = targets.shape().size();
+    let scores = if dims.len() == 2 {
+        input.clone()
+    } else {
+        let mut order: Vec<usize> = (0..dims.len()).filter(|&d| d != 1).collect();
+        order.push(1);
+        input.permute(&order)?.reshape(Shape::new(vec![rows, classes]))?
+    };
+    let flat_targets = targets.reshape(Shape::new(vec![rows]))?;
+    let (losses, counted) = scores.class_loss(&flat_targets, op, ignore_index)?;
+
+    match reduction {
+        Reduction::None => losses.reshape(Shape::new(target_dims)),
+        Reduction::Sum => losses.sum_all(),
+        Reduction::Mean if rows == 0 => Err(FerroFlowError::InvalidOperation(
+            "The mean of a class loss over no targets is undefined".into()
+        )),
+        Reduction::Mean if counted == 0 => Err(FerroFlowError::InvalidOperation(
+            "The mean of a class loss is undefined when every target is ignored".into()
+        )),
+        Reduction::Mean => losses.sum_all()?.scalar_multiply(1.0 / counted as f32),
+    }
+}
+
+#[cfg(test)]
+mod tests;
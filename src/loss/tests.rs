@@ -0,0 +1,147 @@
+use super::*;
+use crate::autograd::tests::{assert_close, check_gradients, tensor as varied};
+use crate::compute::CPUBackend;
+use std::sync::Arc;
+
+type T = Tensor<CPUBackend>;
+
+fn tensor(ctx: &Arc<<CPUBackend as ComputeBackend>::Context>, dims: &[usize], data: &[f32]) -> T {
+    Tensor::new(Arc::clone(ctx), Shape::new(dims.to_vec()), data).unwrap()
+}
+
+fn indices(ctx: &Arc<<CPUBackend as ComputeBackend>::Context>, dims: &[usize], data: &[i64]) -> T {
+    Tensor::from_slice(Arc::clone(ctx), Shape::new(dims.to_vec()), data).unwrap()
+}
+
+const REDUCTIONS: [Reduction; 3] = [Reduction::None, Reduction::Mean, Reduction::Sum];
+
+#[test]
+fn test_cross_entropy() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let logits = tensor(&ctx, &[3, 4], &[1.0, 2.0, 3.0, 4.0, 0.0, 0.0, 0.0, 0.0, 1000.0, -1000.0, 0.0, 1000.0]);
+    let targets = indices(&ctx, &[3], &[3, 1, 1]);
+
+    // Matches -log_softmax at the target, without overflowing for large logits
+    let log_probs = logits.log_softmax(1)?.data()?;
+    let expected = [-log_probs[3], -log_probs[5], -log_probs[9]];
+    let none = CrossEntropyOptions { reduction: Reduction::None, ..Default::default() };
+    let losses = cross_entropy(&logits, &targets, none)?;
+    assert_eq!(losses.shape().dims(), &[3]);
+    assert_close(&losses.data()?, &expected, 1e-5);
+    assert_close(&cross_entropy(&logits, &targets, Default::default())?.data()?, &[expected.iter().sum::<f32>() / 3.0], 1e-5);
+
+    // nll_loss over log_softmax is the same loss
+    let nll = nll_loss(&logits.log_softmax(1)?, &targets, Reduction::Sum, None)?;
+    assert_close(&nll.data()?, &[expected.iter().sum()], 1e-5);
+
+    // Ignored targets are left out of both the sum and the mean's count
+    let ignored = indices(&ctx, &[3], &[3, -100, 1]);
+    let options = CrossEntropyOptions { ignore_index: Some(-100), ..Default::default() };
+    let mean = cross_entropy(&logits, &ignored, options)?.data()?;
+    assert_close(&mean, &[(expected[0] + expected[2]) / 2.0], 1e-5);
+
+    // The mean over no counted targets is undefined, while the sum is zero
+    let all_ignored = indices(&ctx, &[3], &[-100; 3]);
+    assert!(matches!(cross_entropy(&logits, &all_ignored, options), Err(FerroFlowError::InvalidOperation(_))));
+    assert!(nll_loss(&logits, &all_ignored, Reduction::Mean, Some(-100)).is_err());
+    let sum = CrossEntropyOptions { reduction: Reduction::Sum, ..options };
+    assert_eq!(cross_entropy(&logits, &all_ignored, sum)?.data()?, vec![0.0]);
+    let empty = cross_entropy(&tensor(&ctx, &[0, 4], &[]), &indices(&ctx, &[0], &[]), Default::default());
+    assert!(matches!(empty, Err(FerroFlowError::InvalidOperation(message)) if message.contains("no targets")));
+
+    // Label smoothing mixes in the mean of -log p over all classes
+    let smoothing = 0.1;
+    let options = CrossEntropyOptions { label_smoothing: smoothing, reduction: Reduction::None, ..Default::default() };
+    let smoothed = cross_entropy(&logits, &targets, options)?.data()?;
+    let uniform = -log_probs[..4].iter().sum::<f32>() / 4.0;
+    assert_close(&smoothed[..1], &[(1.0 - smoothing) * expected[0] + smoothing * uniform], 1e-5);
+
+    // Class scores in dimension 1 of [N, C, d] inputs
+    let spatial = logits.transpose(0, 1)?.reshape(Shape::new(vec![1, 4, 3]))?;
+    let losses = cross_entropy(&spatial, &indices(&ctx, &[1, 3], &[3, 1, 1]), none)?;
+    assert_eq!(losses.shape().dims(), &[1, 3]);
+    assert_close(&losses.data()?, &expected, 1e-5);
+
+    assert!(cross_entropy(&logits, &indices(&ctx, &[3], &[4, 0, 0]), none).is_err());
+    assert!(cross_entropy(&logits, &indices(&ctx, &[2], &[0, 0]), none).is_err());
+    assert!(cross_entropy(&logits, &tensor(&ctx, &[3], &[0.0, 1.0, 2.0]), none).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_regression_losses() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let input = tensor(&ctx, &[2, 2], &[0.0, 1.0, 3.0, -2.0]);
+    let target = tensor(&ctx, &[2, 2], &[0.5, 1.0, 0.0, 0.0]);
+
+    assert_eq!(mse_loss(&input, &target, Reduction::None)?.data()?, vec![0.25, 0.0, 9.0, 4.0]);
+    assert_eq!(mse_loss(&input, &target, Reduction::Mean)?.data()?, vec![13.25 / 4.0]);
+    assert_eq!(l1_loss(&input, &target, Reduction::Sum)?.data()?, vec![5.5]);
+    assert_eq!(smooth_l1_loss(&input, &target, 1.0, Reduction::None)?.data()?, vec![0.125, 0.0, 2.5, 1.5]);
+    assert_eq!(huber_loss(&input, &target, 2.0, Reduction::None)?.data()?, vec![0.125, 0.0, 4.0, 2.0]);
+    // With beta 0 smooth L1 is plain L1
+    assert_eq!(smooth_l1_loss(&input, &target, 0.0, Reduction::Sum)?.data()?, vec![5.5]);
+
+    assert!(mse_loss(&input, &target.reshape(Shape::new(vec![4]))?, Reduction::Mean).is_err());
+    assert!(huber_loss(&input, &target, -1.0, Reduction::Mean).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_binary_cross_entropy_with_logits() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let logits = tensor(&ctx, &[4], &[0.0, 2.0, -3.0, 200.0]);
+    let targets = tensor(&ctx, &[4], &[1.0, 0.0, 0.25, 0.0]);
+
+    let losses = binary_cross_entropy_with_logits(&logits, &targets, Reduction::None)?.data()?;
+    let reference = |x: f64, y: f64| {
+        let p = 1.0 / (1.0 + (-x).exp());
+        -(y * p.ln() + (1.0 - y) * (1.0 - p).ln())
+    };
+    for (i, (x, y)) in [(0.0, 1.0), (2.0, 0.0), (-3.0, 0.25)].into_iter().enumerate() {
+        assert!((losses[i] as f64 - reference(x, y)).abs() < 1e-6);
+    }
+    // A confidently wrong logit costs its magnitude instead of overflowing
+    assert_eq!(losses[3], 200.0);
+
+    Ok(())
+}
+
+#[test]
+fn test_class_loss_gradients() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let logits = varied(&ctx, &[4, 5], 0).scalar_multiply(3.0)?;
+    let targets = indices(&ctx, &[4], &[2, 0, -1, 4]);
+    for reduction in REDUCTIONS {
+        let options = CrossEntropyOptions { reduction, label_smoothing: 0.2, ignore_index: Some(-1) };
+        check_gradients(std::slice::from_ref(&logits), |t| cross_entropy(&t[0], &targets, options))?;
+        check_gradients(std::slice::from_ref(&logits), |t| nll_loss(&t[0].log_softmax(1)?, &targets, reduction, Some(-1)))?;
+    }
+
+    // Spatial scores [N, C, d]
+    let spatial = varied(&ctx, &[2, 3, 2], 4);
+    let spatial_targets = indices(&ctx, &[2, 2], &[0, 2, 1, 1]);
+    check_gradients(std::slice::from_ref(&spatial), |t| cross_entropy(&t[0], &spatial_targets, Default::default()))?;
+    check_gradients(&[spatial], |t| nll_loss(&t[0], &spatial_targets, Reduction::None, None))?;
+    Ok(())
+}
+
+#[test]
+fn test_pointwise_loss_gradients() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    // Inputs stay away from the kinks of the piecewise losses
+    let input = varied(&ctx, &[3, 4], 1).scalar_multiply(2.0)?;
+    let target = varied(&ctx, &[3, 4], 6).add(&Tensor::full(Arc::clone(&ctx), Shape::new(vec![3, 4]), 0.05)?)?;
+    let probabilities = varied(&ctx, &[3, 4], 2).scalar_multiply(0.4)?.add(&Tensor::full(Arc::clone(&ctx), Shape::new(vec![1]), 0.5)?)?;
+    let inputs = [input.clone(), target];
+    for reduction in REDUCTIONS {
+        check_gradients(&inputs, |t| mse_loss(&t[0], &t[1], reduction))?;
+        check_gradients(&inputs, |t| l1_loss(&t[0], &t[1], reduction))?;
+        check_gradients(&inputs, |t| smooth_l1_loss(&t[0], &t[1], 0.5, reduction))?;
+        check_gradients(&inputs, |t| huber_loss(&t[0], &t[1], 0.7, reduction))?;
+        check_gradients(&[input.clone(), probabilities.clone()], |t| binary_cross_entropy_with_logits(&t[0], &t[1], reduction))?;
+    }
+    Ok(())
+}
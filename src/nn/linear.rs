@@ -0,0 +1,116 @@
+use super::Module;
+use crate::compute::ComputeBackend;
+use crate::error::{Result, FerroFlowError};
+use crate::tensor::{Shape, Tensor};
+use rand::Rng;
+use std::sync::Arc;
+
+/// Applies `y = x W^T + b` to the last dimension of the input.
+/// The weight is stored as `[out_features, in_features]`, matching PyTorch.
+pub struct Linear<B: ComputeBackend> {
+    weight: Tensor<B>,
+    bias: Option<Tensor<B>>,
+    training: bool,
+}
+
+impl<B: ComputeBackend> Linear<B> {
+    /// Creates a layer with weights and bias drawn from `U(-1/sqrt(in), 1/sqrt(in))`
+    pub fn new(ctx: Arc<B::Context>, in_features: usize, out_features: usize, bias: bool) -> Result<Self> {
+        let bound = 1.0 / (in_features.max(1) as f32).sqrt();
+        let mut rng = rand::thread_rng();
+        let mut uniform = |len: usize| -> Vec<f32> { (0..len).map(|_| rng.gen_range(-bound..=bound)).collect() };
+
+        let weight = Tensor::new(
+            Arc::clone(&ctx),
+            Shape::new(vec![out_features, in_features]),
+            &uniform(out_features * in_features),
+        )?;
+        let bias = if bias {
+            Some(Tensor::new(ctx, Shape::new(vec![out_features]), &uniform(out_features))?)
+        } else {
+            None
+        };
+        Self::from_parameters(weight, bias)
+    }
+
+    /// Creates a layer from an existing `[out, in]` weight and optional `[out]` bias.
+    /// Both are marked as requiring gradients.
+    pub fn from_parameters(weight: Tensor<B>, bias: Option<Tensor<B>>) -> Result<Self> {
+        let dims = weight.shape().dims();
+        if dims.len() != 2 {
+            return Err(FerroFlowError::ShapeMismatch(
+                format!("Linear weight must be [out_features, in_features], got {:?}", dims)
+            ));
+        }
+        if let Some(bias) = &bias {
+            if bias.shape().dims() != [dims[0]] {
+                return Err(FerroFlowError::ShapeMismatch(
+                    format!("Linear bias must be [{}], got {:?}", dims[0], bias.shape().dims())
+                ));
+            }
+        }
+
+        Ok(Self {
+            weight: weight.requires_grad(),
+            bias: bias.map(Tensor::requires_grad),
+            training: true,
+        })
+    }
+
+    pub fn in_features(&self) -> usize {
+        self.weight.shape().dims()[1]
+    }
+
+    pub fn out_features(&self) -> usize {
+        self.weight.shape().dims()[0]
+    }
+
+    pub fn weight(&self) -> &Tensor<B> {
+        &self.weight
+    }
+
+    pub fn bias(&self) -> Option<&Tensor<B>> {
+        self.bias.as_ref()
+    }
+}
+
+impl<B: ComputeBackend> Module<B> for Linear<B> {
+    /// Accepts any input whose last dimension is `in_features`; leading dimensions are
+    /// flattened into one matmul and restored on the output.
+    fn forward(&self, input: &Tensor<B>) -> Result<Tensor<B>> {
+        let dims = input.shape().dims();
+        if dims.last() != Some(&self.in_features()) {
+            return Err(FerroFlowError::ShapeMismatch(format!(
+                "Linear layer expects inputs with {} features in the last dimension, got shape {:?}",
+                self.in_features(), dims
+            )));
+        }
+
+        let rows = dims[..dims.len() - 1].iter().product();
+        let matrix = input.reshape(Shape::new(vec![rows, self.in_features()]))?;
+        let mut output = matrix.matmul_transposed(&self.weight, false, true)?;
+        if let Some(bias) = &self.bias {
+            output = output.add(bias)?;
+        }
+
+        let mut output_dims = dims[..dims.len() - 1].to_vec();
+        output_dims.push(self.out_features());
+        output.reshape(Shape::new(output_dims))
+    }
+
+    fn named_parameters(&self) -> Vec<(String, Tensor<B>)> {
+        let mut named = vec![("weight".to_string(), self.weight.clone())];
+        if let Some(bias) = &self.bias {
+            named.push(("bias".to_string(), bias.clone()));
+        }
+        named
+    }
+
+    fn train(&mut self, mode: bool) {
+        self.training = mode;
+    }
+
+    fn is_training(&self) -> bool {
+        self.training
+    }
+}
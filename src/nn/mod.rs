@@ -0,0 +1,143 @@
+//! Neural network building blocks.
+//!
+//! Layers implement [`Module`], which exposes their forward pass and their trainable
+//! parameters. Parameters are returned as tensor clones sharing the layer's buffer and
+//! autograd node, so gradients computed through `forward` are visible to whoever holds them.
+//! Containers prefix the names of their children's parameters with the child's name,
+//! giving dotted paths such as `"0.weight"` or `"encoder.1.bias"`.
+//!
+//! [`Module::state_dict`] lists the parameters together with non-trainable buffers such as
+//! running statistics, and [`Module::load_state_dict`] copies saved values back into them,
+//! e.g. from a file read with [`crate::io::safetensors::load_file`].
+
+use crate::autograd;
+use crate::compute::{ComputeBackend, UnaryOp};
+use crate::error::{Result, FerroFlowError};
+use crate::tensor::Tensor;
+use std::collections::HashMap;
+
+mod linear;
+mod norm;
+mod sequential;
+
+pub use linear::Linear;
+pub use norm::{BatchNorm2d, GroupNorm, LayerNorm, RmsNorm};
+pub use sequential::Sequential;
+
+/// A layer or model that maps an input tensor to an output tensor.
+pub trait Module<B: ComputeBackend>: Send + Sync {
+    /// Runs the layer on `input`
+    fn forward(&self, input: &Tensor<B>) -> Result<Tensor<B>>;
+
+    /// Returns every trainable parameter with its dotted path relative to this module
+    fn named_parameters(&self) -> Vec<(String, Tensor<B>)>;
+
+    /// Returns every trainable parameter
+    fn parameters(&self) -> Vec<Tensor<B>> {
+        self.named_parameters().into_iter().map(|(_, parameter)| parameter).collect()
+    }
+
+    /// Returns non-trainable state, such as running statistics, named like the parameters
+    fn named_buffers(&self) -> Vec<(String, Tensor<B>)> {
+        Vec::new()
+    }
+
+    /// Returns the parameters followed by the buffers. The tensors share the module's storage,
+    /// so they reflect later updates until they are copied, e.g. by saving them.
+    fn state_dict(&self) -> Vec<(String, Tensor<B>)> {
+        let mut state = self.named_parameters();
+        state.extend(self.named_buffers());
+        state
+    }
+
+    /// Copies the entries of `state` into the parameters and buffers of the same name,
+    /// converting element types as needed. With `strict`, `state` must hold exactly the names
+    /// of `state_dict`. Nothing is copied unless every entry matches its target's shape.
+    fn load_state_dict(&self, state: &HashMap<String, Tensor<B>>, strict: bool) -> Result<()> {
+        let targets = self.state_dict();
+        if strict {
+            let mut missing: Vec<&str> = targets.iter().map(|(name, _)| name.as_str()).filter(|name| !state.contains_key(*name)).collect();
+            let mut unexpected: Vec<&str> = state.keys().map(String::as_str).filter(|name| !targets.iter().any(|(n, _)| n == name)).collect();
+            if !missing.is_empty() || !unexpected.is_empty() {
+                missing.sort_unstable();
+                unexpected.sort_unstable();
+                return Err(FerroFlowError::InvalidOperation(format!(
+                    "State dict doesn't match the module: missing {:?}, unexpected {:?}", missing, unexpected
+                )));
+            }
+        }
+
+        let pairs: Vec<_> = targets.iter().filter_map(|(name, target)| state.get(name).map(|source| (name, target, source))).collect();
+        for (name, target, source) in &pairs {
+            if source.shape() != target.shape() {
+                return Err(FerroFlowError::ShapeMismatch(format!(
+                    "{} has shape {:?} in the state dict and {:?} in the module", name, source.shape().dims(), target.shape().dims()
+                )));
+            }
+        }
+        autograd::no_grad(|| {
+            for (_, target, source) in pairs {
+                target.copy_from(&source.to_dtype(target.dtype())?)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Switches between training (`true`) and evaluation behaviour.
+    /// Containers forward the mode to all of their children.
+    fn train(&mut self, mode: bool);
+
+    /// Switches to evaluation behaviour; shorthand for `train(false)`
+    fn eval(&mut self) {
+        self.train(false);
+    }
+
+    fn is_training(&self) -> bool;
+
+    /// Clears the accumulated gradient of every parameter
+    fn zero_grad(&self) {
+        for parameter in self.parameters() {
+            parameter.zero_grad();
+        }
+    }
+}
+
+/// Prefixes the parameter names of a child module with `prefix` and a dot,
+/// for use in the `named_parameters` implementation of containers.
+pub fn prefixed<B: ComputeBackend>(prefix: &str, named: Vec<(String, Tensor<B>)>) -> Vec<(String, Tensor<B>)> {
+    named.into_iter().map(|(name, parameter)| (format!("{}.{}", prefix, name), parameter)).collect()
+}
+
+/// A parameter-free layer applying an element-wise activation, e.g. `Activation::new(UnaryOp::Relu)`
+#[derive(Debug, Clone)]
+pub struct Activation {
+    op: UnaryOp,
+    training: bool,
+}
+
+impl Activation {
+    pub fn new(op: UnaryOp) -> Self {
+        Self { op, training: true }
+    }
+}
+
+impl<B: ComputeBackend> Module<B> for Activation {
+    fn forward(&self, input: &Tensor<B>) -> Result<Tensor<B>> {
+        input.unary_op(self.op)
+    }
+
+    fn named_parameters(&self) -> Vec<(String, Tensor<B>)> {
+        Vec::new()
+    }
+
+    fn train(&mut self, mode: bool) {
+        self.training = mode;
+    }
+
+    fn is_training(&self) -> bool {
+        self.training
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,347 @@
+use super::Module;
+use crate::autograd;
+use crate::compute::{ComputeBackend, NormOp};
+use crate::error::{Result, FerroFlowError};
+use crate::optim::check_hyperparameter;
+use crate::tensor::{Shape, Tensor};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+/// Creates the affine parameters of a normalization layer: a weight of ones and a bias of zeros
+fn affine_parameters<B: ComputeBackend>(
+    ctx: &Arc<B::Context>,
+    shape: &[usize],
+    bias: bool
+) -> Result<(Tensor<B>, Option<Tensor<B>>)> {
+    let weight = Tensor::full(Arc::clone(ctx), Shape::new(shape.to_vec()), 1.0)?.requires_grad();
+    let bias = if bias {
+        Some(Tensor::zeros(Arc::clone(ctx), Shape::new(shape.to_vec()))?.requires_grad())
+    } else {
+        None
+    };
+    Ok((weight, bias))
+}
+
+fn named_affine<B: ComputeBackend>(weight: Option<&Tensor<B>>, bias: Option<&Tensor<B>>) -> Vec<(String, Tensor<B>)> {
+    let mut named = Vec::new();
+    if let Some(weight) = weight {
+        named.push(("weight".to_string(), weight.clone()));
+    }
+    if let Some(bias) = bias {
+        named.push(("bias".to_string(), bias.clone()));
+    }
+    named
+}
+
+/// Normalizes over the trailing `normalized_shape` dimensions, then applies a learned
+/// element-wise scale and shift
+pub struct LayerNorm<B: ComputeBackend> {
+    normalized_shape: Vec<usize>,
+    weight: Option<Tensor<B>>,
+    bias: Option<Tensor<B>>,
+    eps: f32,
+    training: bool,
+}
+
+impl<B: ComputeBackend> LayerNorm<B> {
+    /// Creates a layer with `eps = 1e-5`; `elementwise_affine` adds a weight and bias
+    /// initialized to ones and zeros
+    pub fn new(ctx: Arc<B::Context>, normalized_shape: &[usize], elementwise_affine: bool) -> Result<Self> {
+        let (weight, bias) = if elementwise_affine {
+            let (weight, bias) = affine_parameters(&ctx, normalized_shape, true)?;
+            (Some(weight), bias)
+        } else {
+            (None, None)
+        };
+        Ok(Self { normalized_shape: normalized_shape.to_vec(), weight, bias, eps: 1e-5, training: true })
+    }
+
+    /// Sets the value added to the variance for numerical stability
+    pub fn with_eps(mut self, eps: f32) -> Self {
+        self.eps = eps;
+        self
+    }
+
+    pub fn weight(&self) -> Option<&Tensor<B>> {
+        self.weight.as_ref()
+    }
+
+    pub fn bias(&self) -> Option<&Tensor<B>> {
+        self.bias.as_ref()
+    }
+}
+
+impl<B: ComputeBackend> Module<B> for LayerNorm<B> {
+    fn forward(&self, input: &Tensor<B>) -> Result<Tensor<B>> {
+        input.layer_norm(&self.normalized_shape, self.weight.as_ref(), self.bias.as_ref(), self.eps)
+    }
+
+    fn named_parameters(&self) -> Vec<(String, Tensor<B>)> {
+        named_affine(self.weight.as_ref(), self.bias.as_ref())
+    }
+
+    fn train(&mut self, mode: bool) {
+        self.training = mode;
+    }
+
+    fn is_training(&self) -> bool {
+        self.training
+    }
+}
+
+/// Scales inputs by the reciprocal root mean square of the trailing `normalized_shape`
+/// dimensions, without centering, then applies a learned element-wise scale
+pub struct RmsNorm<B: ComputeBackend> {
+    normalized_shape: Vec<usize>,
+    weight: Option<Tensor<B>>,
+    eps: f32,
+    training: bool,
+}
+
+impl<B: ComputeBackend> RmsNorm<B> {
+    /// Creates a layer with `eps = 1e-6`; `elementwise_affine` adds a weight initialized to ones
+    pub fn new(ctx: Arc<B::Context>, normalized_shape: &[usize], elementwise_affine: bool) -> Result<Self> {
+        let weight = if elementwise_affine {
+            Some(affine_parameters(&ctx, normalized_shape, false)?.0)
+        } else {
+            None
+        };
+        Ok(Self { normalized_shape: normalized_shape.to_vec(), weight, eps: 1e-6, training: true })
+    }
+
+    /// Sets the value added to the mean square for numerical stability
+    pub fn with_eps(mut self, eps: f32) -> Self {
+        self.eps = eps;
+        self
+    }
+
+    pub fn weight(&self) -> Option<&Tensor<B>> {
+        self.weight.as_ref()
+    }
+}
+
+impl<B: ComputeBackend> Module<B> for RmsNorm<B> {
+    fn forward(&self, input: &Tensor<B>) -> Result<Tensor<B>> {
+        input.rms_norm(&self.normalized_shape, self.weight.as_ref(), self.eps)
+    }
+
+    fn named_parameters(&self) -> Vec<(String, Tensor<B>)> {
+        named_affine(self.weight.as_ref(), None)
+    }
+
+    fn train(&mut self, mode: bool) {
+        self.training = mode;
+    }
+
+    fn is_training(&self) -> bool {
+        self.training
+    }
+}
+
+/// Normalizes `[N, C, *]` inputs over groups of channels and all spatial positions,
+/// then applies a learned per-channel scale and shift
+pub struct GroupNorm<B: ComputeBackend> {
+    num_groups: usize,
+    num_channels: usize,
+    weight: Option<Tensor<B>>,
+    bias: Option<Tensor<B>>,
+    eps: f32,
+    training: bool,
+}
+
+impl<B: ComputeBackend> GroupNorm<B> {
+    /// Creates a layer with `eps = 1e-5`; `affine` adds a `[num_channels]` weight and bias
+    /// initialized to ones and zeros
+    pub fn new(ctx: Arc<B::Context>, num_groups: usize, num_channels: usize, affine: bool) -> Result<Self> {
+        if num_groups == 0 || !num_channels.is_multiple_of(num_groups) {
+            return Err(FerroFlowError::InvalidOperation(format!(
+                "{} groups do not evenly divide {} channels", num_groups, num_channels
+            )));
+        }
+        let (weight, bias) = if affine {
+            let (weight, bias) = affine_parameters(&ctx, &[num_channels], true)?;
+            (Some(weight), bias)
+        } else {
+            (None, None)
+        };
+        Ok(Self { num_groups, num_channels, weight, bias, eps: 1e-5, training: true })
+    }
+
+    /// Sets the value added to the variance for numerical stability
+    pub fn with_eps(mut self, eps: f32) -> Self {
+        self.eps = eps;
+        self
+    }
+}
+
+impl<B: ComputeBackend> Module<B> for GroupNorm<B> {
+    fn forward(&self, input: &Tensor<B>) -> Result<Tensor<B>> {
+        let dims = input.shape().dims();
+        if dims.len() < 2 || dims[1] != self.num_channels {
+            return Err(FerroFlowError::ShapeMismatch(format!(
+                "GroupNorm expects [N, {}, *] inputs, got shape {:?}", self.num_channels, dims
+            )));
+        }
+        input.group_norm(self.num_groups, self.weight.as_ref(), self.bias.as_ref(), self.eps)
+    }
+
+    fn named_parameters(&self) -> Vec<(String, Tensor<B>)> {
+        named_affine(self.weight.as_ref(), self.bias.as_ref())
+    }
+
+    fn train(&mut self, mode: bool) {
+        self.training = mode;
+    }
+
+    fn is_training(&self) -> bool {
+        self.training
+    }
+}
+
+/// Per-channel statistics tracked by [`BatchNorm2d`] during training
+struct RunningStats<B: ComputeBackend> {
+    mean: Tensor<B>,
+    var: Tensor<B>,
+}
+
+/// Normalizes `[N, C, H, W]` inputs per channel. In training mode the statistics of the
+/// batch are used and folded into running estimates, which evaluation mode uses instead.
+pub struct BatchNorm2d<B: ComputeBackend> {
+    ctx: Arc<B::Context>,
+    num_features: usize,
+    weight: Option<Tensor<B>>,
+    bias: Option<Tensor<B>>,
+    running: Mutex<RunningStats<B>>,
+    eps: f32,
+    momentum: f32,
+    training: bool,
+}
+
+impl<B: ComputeBackend> BatchNorm2d<B> {
+    /// Creates a layer with `eps = 1e-5` and `momentum = 0.1`; `affine` adds a `[num_features]`
+    /// weight and bias initialized to ones and zeros. Running statistics start at mean 0, variance 1.
+    pub fn new(ctx: Arc<B::Context>, num_features: usize, affine: bool) -> Result<Self> {
+        let (weight, bias) = if affine {
+            let (weight, bias) = affine_parameters(&ctx, &[num_features], true)?;
+            (Some(weight), bias)
+        } else {
+            (None, None)
+        };
+        let running = RunningStats {
+            mean: Tensor::zeros(Arc::clone(&ctx), Shape::new(vec![num_features]))?,
+            var: Tensor::full(Arc::clone(&ctx), Shape::new(vec![num_features]), 1.0)?,
+        };
+        Ok(Self {
+            ctx,
+            num_features,
+            weight,
+            bias,
+            running: Mutex::new(running),
+            eps: 1e-5,
+            momentum: 0.1,
+            training: true,
+        })
+    }
+
+    /// Sets the value added to the variance for numerical stability
+    pub fn with_eps(mut self, eps: f32) -> Self {
+        self.eps = eps;
+        self
+    }
+
+    /// Sets the weight of each new batch in the running statistics,
+    /// `running = (1 - momentum) * running + momentum * batch`. Fails unless it lies in `[0, 1]`.
+    pub fn with_momentum(mut self, momentum: f32) -> Result<Self> {
+        check_hyperparameter("BatchNorm momentum", momentum, 0.0..=1.0)?;
+        self.momentum = momentum;
+        Ok(self)
+    }
+
+    /// Locks the running statistics so concurrent training passes don't interleave their
+    /// updates, ignoring poisoning for the same reason as `Tensor::buffer`
+    fn running(&self) -> MutexGuard<'_, RunningStats<B>> {
+        self.running.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Returns the running per-channel mean
+    pub fn running_mean(&self) -> Tensor<B> {
+        self.running().mean.clone()
+    }
+
+    /// Returns the running per-channel (unbiased) variance
+    pub fn running_var(&self) -> Tensor<B> {
+        self.running().var.clone()
+    }
+
+    /// Normalizes with the statistics of the batch and updates the running estimates
+    fn forward_train(&self, input: &Tensor<B>) -> Result<Tensor<B>> {
+        let dims = input.shape().dims();
+        let per_channel = dims[0] * dims[2] * dims[3];
+        if per_channel < 2 {
+            return Err(FerroFlowError::InvalidOperation(format!(
+                "BatchNorm2d needs more than one value per channel in training, got shape {:?}", dims
+            )));
+        }
+
+        // With channels first, each channel's values form one contiguous row
+        let channels_first = input.transpose(0, 1)?;
+        let output = channels_first.normalize(NormOp::LayerNorm, per_channel, self.eps)?.transpose(0, 1)?;
+
+        autograd::no_grad(|| -> Result<()> {
+            let rows = channels_first.reshape(Shape::new(vec![self.num_features, per_channel]))?;
+            let (mean, var) = (rows.mean(1, false)?, rows.var(1, 1, false)?);
+            // Updated in place, so tensors handed out by `named_buffers` see the new estimates
+            let blend = |running: &Tensor<B>, batch: &Tensor<B>| -> Result<()> {
+                running.scale_(1.0 - self.momentum)?;
+                running.add_(&batch.scalar_multiply(self.momentum)?)
+            };
+            let running = self.running();
+            blend(&running.mean, &mean)?;
+            blend(&running.var, &var)
+        })?;
+        Ok(output)
+    }
+
+    /// Normalizes with the running estimates
+    fn forward_eval(&self, input: &Tensor<B>) -> Result<Tensor<B>> {
+        let channel_shape = Shape::new(Tensor::<B>::channel_shape(input.shape().dims()));
+        let (mean, var) = {
+            let running = self.running();
+            (running.mean.clone(), running.var.clone())
+        };
+        let eps = Tensor::full(Arc::clone(&self.ctx), Shape::new(vec![self.num_features]), self.eps)?;
+        let scale = var.add(&eps)?.rsqrt()?.reshape(channel_shape.clone())?;
+        input.sub(&mean.reshape(channel_shape)?)?.multiply(&scale)
+    }
+}
+
+impl<B: ComputeBackend> Module<B> for BatchNorm2d<B> {
+    fn forward(&self, input: &Tensor<B>) -> Result<Tensor<B>> {
+        let dims = input.shape().dims();
+        if dims.len() != 4 || dims[1] != self.num_features {
+            return Err(FerroFlowError::ShapeMismatch(format!(
+                "BatchNorm2d expects [N, {}, H, W] inputs, got shape {:?}", self.num_features, dims
+            )));
+        }
+
+        let normalized = if self.training { self.forward_train(input)? } else { self.forward_eval(input)? };
+        let channel_shape = Tensor::<B>::channel_shape(dims);
+        normalized.affine(self.weight.as_ref(), self.bias.as_ref(), &[self.num_features], &channel_shape)
+    }
+
+    fn named_parameters(&self) -> Vec<(String, Tensor<B>)> {
+        named_affine(self.weight.as_ref(), self.bias.as_ref())
+    }
+
+    fn named_buffers(&self) -> Vec<(String, Tensor<B>)> {
+        let running = self.running();
+        vec![("running_mean".into(), running.mean.clone()), ("running_var".into(), running.var.clone())]
+    }
+
+    fn train(&mut self, mode: bool) {
+        self.training = mode;
+    }
+
+    fn is_training(&self) -> bool {
+        self.training
+    }
+}
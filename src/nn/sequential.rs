@@ -0,0 +1,86 @@
+use super::{prefixed, Module};
+use crate::compute::ComputeBackend;
+use crate::error::Result;
+use crate::tensor::Tensor;
+
+/// Chains modules, feeding each one's output into the next.
+/// Parameters are named by the child's position, e.g. `"0.weight"`.
+pub struct Sequential<B: ComputeBackend> {
+    layers: Vec<Box<dyn Module<B>>>,
+    training: bool,
+}
+
+impl<B: ComputeBackend> Sequential<B> {
+    pub fn new() -> Self {
+        Self { layers: Vec::new(), training: true }
+    }
+
+    /// Appends a module, builder style
+    #[allow(clippy::should_implement_trait)]
+    pub fn add(mut self, module: impl Module<B> + 'static) -> Self {
+        self.push(Box::new(module));
+        self
+    }
+
+    /// Appends a boxed module, switching it to the container's mode
+    pub fn push(&mut self, mut module: Box<dyn Module<B>>) {
+        module.train(self.training);
+        self.layers.push(module);
+    }
+
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// Returns the module at `index`
+    pub fn get(&self, index: usize) -> Option<&dyn Module<B>> {
+        self.layers.get(index).map(|layer| layer.as_ref())
+    }
+}
+
+impl<B: ComputeBackend> Default for Sequential<B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<B: ComputeBackend> Module<B> for Sequential<B> {
+    fn forward(&self, input: &Tensor<B>) -> Result<Tensor<B>> {
+        let mut output = input.clone();
+        for layer in &self.layers {
+            output = layer.forward(&output)?;
+        }
+        Ok(output)
+    }
+
+    fn named_parameters(&self) -> Vec<(String, Tensor<B>)> {
+        self.layers
+            .iter()
+            .enumerate()
+            .flat_map(|(index, layer)| prefixed(&index.to_string(), layer.named_parameters()))
+            .collect()
+    }
+
+    fn named_buffers(&self) -> Vec<(String, Tensor<B>)> {
+        self.layers
+            .iter()
+            .enumerate()
+            .flat_map(|(index, layer)| prefixed(&index.to_string(), layer.named_buffers()))
+            .collect()
+    }
+
+    fn train(&mut self, mode: bool) {
+        self.training = mode;
+        for layer in &mut self.layers {
+            layer.train(mode);
+        }
+    }
+
+    fn is_training(&self) -> bool {
+        self.training
+    }
+}
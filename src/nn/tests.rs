@@ -0,0 +1,181 @@
+use super::*;
+use crate::compute::CPUBackend;
+use crate::error::FerroFlowError;
+use crate::tensor::Shape;
+use std::sync::Arc;
+
+fn linear(ctx: &Arc<<CPUBackend as ComputeBackend>::Context>) -> Result<Linear<CPUBackend>> {
+    let weight = Tensor::new(Arc::clone(ctx), Shape::new(vec![2, 3]), &[1.0, 0.0, -1.0, 0.5, 0.5, 0.5])?;
+    let bias = Tensor::new(Arc::clone(ctx), Shape::new(vec![2]), &[0.1, -0.2])?;
+    Linear::from_parameters(weight, Some(bias))
+}
+
+#[test]
+fn test_linear_forward() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let layer = linear(&ctx)?;
+    assert_eq!((layer.in_features(), layer.out_features()), (3, 2));
+
+    let x = Tensor::new(Arc::clone(&ctx), Shape::new(vec![2, 3]), &[1.0, 2.0, 3.0, -1.0, 0.0, 1.0])?;
+    let y = layer.forward(&x)?;
+    assert_eq!(y.shape().dims(), &[2, 2]);
+    assert_eq!(y.data()?, vec![-1.9, 2.8, -1.9, -0.2]);
+
+    // Leading dimensions are flattened and restored
+    let batched = x.reshape(Shape::new(vec![2, 1, 3]))?;
+    let y = layer.forward(&batched)?;
+    assert_eq!(y.shape().dims(), &[2, 1, 2]);
+    assert_eq!(y.data()?, vec![-1.9, 2.8, -1.9, -0.2]);
+
+    let wrong = Tensor::zeros(Arc::clone(&ctx), Shape::new(vec![2, 4]))?;
+    assert!(matches!(layer.forward(&wrong), Err(FerroFlowError::ShapeMismatch(_))));
+
+    let random = Linear::<CPUBackend>::new(Arc::clone(&ctx), 16, 4, false)?;
+    assert!(random.bias().is_none());
+    assert!(random.weight().data()?.iter().all(|w| w.abs() <= 0.25));
+
+    // A layer without input features outputs its bias
+    let empty = Linear::<CPUBackend>::new(Arc::clone(&ctx), 0, 2, true)?;
+    let y = empty.forward(&Tensor::zeros(Arc::clone(&ctx), Shape::new(vec![3, 0]))?)?;
+    assert_eq!(y.shape().dims(), &[3, 2]);
+    assert_eq!(y.data()?, empty.bias().unwrap().data()?.repeat(3));
+
+    Ok(())
+}
+
+#[test]
+fn test_sequential_parameter_names() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let encoder = Sequential::new()
+        .add(Linear::<CPUBackend>::new(Arc::clone(&ctx), 4, 8, true)?)
+        .add(Activation::new(UnaryOp::Relu));
+    let model = Sequential::new()
+        .add(encoder)
+        .add(Linear::<CPUBackend>::new(Arc::clone(&ctx), 8, 2, false)?);
+
+    let names: Vec<String> = model.named_parameters().into_iter().map(|(name, _)| name).collect();
+    assert_eq!(names, vec!["0.0.weight", "0.0.bias", "1.weight"]);
+    assert_eq!(model.parameters().len(), 3);
+    assert_eq!(model.len(), 2);
+
+    let output = model.forward(&Tensor::rand(Arc::clone(&ctx), Shape::new(vec![5, 4]))?)?;
+    assert_eq!(output.shape().dims(), &[5, 2]);
+
+    Ok(())
+}
+
+#[test]
+fn test_train_eval_propagates() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let mut model = Sequential::new()
+        .add(Sequential::new().add(Linear::<CPUBackend>::new(Arc::clone(&ctx), 2, 2, true)?))
+        .add(Activation::new(UnaryOp::Tanh));
+    assert!(model.is_training());
+
+    model.eval();
+    assert!(!model.is_training());
+    assert!(!model.get(0).unwrap().is_training());
+    assert!(!model.get(1).unwrap().is_training());
+
+    // Modules added later adopt the container's mode
+    model.push(Box::new(Activation::new(UnaryOp::Relu)));
+    assert!(!model.get(2).unwrap().is_training());
+
+    model.train(true);
+    assert!(model.get(0).unwrap().is_training());
+
+    Ok(())
+}
+
+#[test]
+fn test_parameters_receive_gradients() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let model = Sequential::new()
+        .add(linear(&ctx)?)
+        .add(Activation::new(UnaryOp::Relu));
+
+    let x = Tensor::new(Arc::clone(&ctx), Shape::new(vec![1, 3]), &[1.0, 2.0, 3.0])?;
+    model.forward(&x)?.sum_all()?.backward()?;
+
+    // Only the second output unit is active after the relu
+    let named = model.named_parameters();
+    assert_eq!(named[0].1.grad().unwrap().data()?, vec![0.0, 0.0, 0.0, 1.0, 2.0, 3.0]);
+    assert_eq!(named[1].1.grad().unwrap().data()?, vec![0.0, 1.0]);
+
+    model.zero_grad();
+    assert!(model.parameters().iter().all(|p| p.grad().is_none()));
+
+    Ok(())
+}
+
+#[test]
+fn test_normalization_layers() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let x = Tensor::rand(Arc::clone(&ctx), Shape::new(vec![3, 2, 4, 5]))?;
+
+    let layer_norm = LayerNorm::<CPUBackend>::new(Arc::clone(&ctx), &[4, 5], true)?;
+    let names: Vec<String> = layer_norm.named_parameters().into_iter().map(|(name, _)| name).collect();
+    assert_eq!(names, vec!["weight", "bias"]);
+    assert_eq!(layer_norm.forward(&x)?.shape().dims(), &[3, 2, 4, 5]);
+
+    let rms_norm = RmsNorm::<CPUBackend>::new(Arc::clone(&ctx), &[5], true)?.with_eps(1e-5);
+    assert_eq!(rms_norm.parameters().len(), 1);
+    assert_eq!(rms_norm.forward(&x)?.shape().dims(), &[3, 2, 4, 5]);
+
+    let group_norm = GroupNorm::<CPUBackend>::new(Arc::clone(&ctx), 1, 2, false)?;
+    assert!(group_norm.parameters().is_empty());
+    assert!(group_norm.forward(&x.transpose(1, 2)?).is_err());
+    assert!(GroupNorm::<CPUBackend>::new(Arc::clone(&ctx), 3, 2, true).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_batch_norm_running_statistics() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    // Channel 0 holds 1..=4 and channel 1 holds 10, 20, 30, 40 across a batch of two 1x2 images
+    let x = Tensor::new(
+        Arc::clone(&ctx),
+        Shape::new(vec![2, 2, 1, 2]),
+        &[1.0, 2.0, 10.0, 20.0, 3.0, 4.0, 30.0, 40.0],
+    )?;
+    let mut layer = BatchNorm2d::<CPUBackend>::new(Arc::clone(&ctx), 2, true)?.with_eps(0.0).with_momentum(0.5)?;
+    let state = layer.state_dict();
+
+    // Training normalizes with the biased batch variance
+    let y = layer.forward(&x)?.data()?;
+    let inv = 1.0 / 1.25f32.sqrt();
+    let expected = [-1.5 * inv, -0.5 * inv, -1.5 * inv, -0.5 * inv, 0.5 * inv, 1.5 * inv, 0.5 * inv, 1.5 * inv];
+    for (a, e) in y.iter().zip(expected) {
+        assert!((a - e).abs() < 1e-5, "{} vs {}", a, e);
+    }
+
+    // ... and folds the unbiased variance into the running estimate
+    let (mean, var) = (layer.running_mean().data()?, layer.running_var().data()?);
+    assert_eq!(mean, vec![1.25, 12.5]);
+    assert!((var[0] - (0.5 + 0.5 * 5.0 / 3.0)).abs() < 1e-5);
+    assert!((var[1] - (0.5 + 0.5 * 500.0 / 3.0)).abs() < 1e-3);
+
+    // The estimates are updated in place, so a state dict taken earlier sees them
+    let buffer = |name: &str| state.iter().find(|(n, _)| n == name).map(|(_, tensor)| tensor.data());
+    assert_eq!(buffer("running_mean").transpose()?, Some(mean.clone()));
+    assert_eq!(buffer("running_var").transpose()?, Some(var.clone()));
+
+    // Evaluation uses the running estimates and leaves them untouched
+    layer.eval();
+    let y = layer.forward(&x)?.data()?;
+    assert!((y[0] - (1.0 - 1.25) / var[0].sqrt()).abs() < 1e-5);
+    assert_eq!(layer.running_mean().data()?, mean);
+
+    // Gradients reach the affine parameters in training mode
+    layer.train(true);
+    layer.forward(&x)?.multiply(&x)?.sum_all()?.backward()?;
+    assert!(layer.parameters().iter().all(|p| p.grad().is_some()));
+
+    assert!(layer.forward(&x.reshape(Shape::new(vec![2, 4]))?).is_err());
+    let single = Tensor::zeros(Arc::clone(&ctx), Shape::new(vec![1, 2, 1, 1]))?;
+    assert!(layer.forward(&single).is_err());
+    assert!(BatchNorm2d::<CPUBackend>::new(Arc::clone(&ctx), 2, true)?.with_momentum(1.5).is_err());
+
+    Ok(())
+}
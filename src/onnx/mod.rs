@@ -0,0 +1,199 @@
+//! Importing ONNX models for inference.
+//!
+//! [`OnnxModel`] decodes the graph of an `.onnx` file, loads its initializers as tensors and
+//! runs its nodes in order with tensor operations. The importer covers the operators of common
+//! MLPs and CNNs: `MatMul`, `Gemm`, elementwise arithmetic and activations, `Softmax`, `Conv`,
+//! pooling, inference-mode `BatchNormalization` and the shape operators. Models using anything
+//! else fail to load with [`FerroFlowError::UnsupportedOperator`] listing every such node.
+
+use crate::compute::ComputeBackend;
+use crate::dtype::{DType, Element};
+use crate::error::{Result, FerroFlowError};
+use crate::io::convert_endianness;
+use crate::tensor::{Shape, Tensor};
+use ops::Op;
+use proto::{format_error, Attribute, ModelProto, NodeProto, TensorProto};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Arc;
+
+mod ops;
+mod proto;
+#[cfg(test)]
+mod tests;
+
+/// A node of the imported graph
+struct Node {
+    op: Op,
+    /// Input names, empty for omitted optional inputs
+    inputs: Vec<String>,
+    output: String,
+}
+
+/// An ONNX inference graph whose weights live in tensors of backend `B`
+pub struct OnnxModel<B: ComputeBackend> {
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+    /// Initializers and the values of `Constant` nodes
+    constants: HashMap<String, Tensor<B>>,
+    nodes: Vec<Node>,
+}
+
+impl<B: ComputeBackend> OnnxModel<B> {
+    /// Reads and imports the model at `path`. Weights stored in external files are not supported.
+    pub fn load(ctx: Arc<B::Context>, path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_bytes(ctx, &std::fs::read(path)?)
+    }
+
+    /// Imports a serialized `ModelProto`
+    pub fn from_bytes(ctx: Arc<B::Context>, bytes: &[u8]) -> Result<Self> {
+        let model = ModelProto::decode(bytes)?;
+        let opset = model.opset_import
+            .iter()
+            .find(|(domain, _)| domain.is_empty() || domain == "ai.onnx")
+            .map(|&(_, version)| version)
+            .ok_or_else(|| format_error("model doesn't import the default operator set"))?;
+        let graph = model.graph;
+
+        let mut constants = HashMap::new();
+        for initializer in &graph.initializers {
+            constants.insert(initializer.name.clone(), tensor_from_proto(Arc::clone(&ctx), initializer)?);
+        }
+        let inputs: Vec<String> = graph.inputs.into_iter().filter(|name| !constants.contains_key(name)).collect();
+
+        // Unsupported nodes are collected so a single error names all of them
+        let mut nodes = Vec::with_capacity(graph.nodes.len());
+        let mut unsupported = Vec::new();
+        for node in &graph.nodes {
+            let output = node.outputs.first().cloned().unwrap_or_default();
+            if node.op_type == "Constant" && (node.domain.is_empty() || node.domain == "ai.onnx") {
+                constants.insert(output, constant(Arc::clone(&ctx), node)?);
+                continue;
+            }
+            match Op::parse(node, opset) {
+                Ok(op) => nodes.push(Node { op, inputs: node.inputs.clone(), output }),
+                Err(FerroFlowError::UnsupportedOperator(message)) => unsupported.push(message),
+                Err(e) => return Err(e),
+            }
+        }
+        if !unsupported.is_empty() {
+            return Err(FerroFlowError::UnsupportedOperator(unsupported.join(", ")));
+        }
+
+        // Nodes must be topologically sorted, as the ONNX spec requires
+        let mut available: HashSet<&str> = inputs.iter().chain(constants.keys()).map(String::as_str).collect();
+        for node in &nodes {
+            if let Some(name) = node.inputs.iter().find(|name| !name.is_empty() && !available.contains(name.as_str())) {
+                return Err(format_error(format!("value {:?} is used before any node produces it", name)));
+            }
+            available.insert(&node.output);
+        }
+        if let Some(name) = graph.outputs.iter().find(|name| !available.contains(name.as_str())) {
+            return Err(format_error(format!("graph output {:?} is never produced", name)));
+        }
+
+        Ok(Self { inputs, outputs: graph.outputs, constants, nodes })
+    }
+
+    /// Returns the names of the inputs [`run`](Self::run) expects, excluding initializers
+    pub fn input_names(&self) -> &[String] {
+        &self.inputs
+    }
+
+    pub fn output_names(&self) -> &[String] {
+        &self.outputs
+    }
+
+    /// Runs the graph on tensors keyed by input name and returns the outputs in the order of
+    /// [`output_names`](Self::output_names). Nothing is recorded for autograd.
+    pub fn run(&self, inputs: &HashMap<String, Tensor<B>>) -> Result<Vec<Tensor<B>>> {
+        if let Some(name) = inputs.keys().find(|name| !self.inputs.contains(name)) {
+            return Err(FerroFlowError::InvalidOperation(format!("The model has no input named {:?}", name)));
+        }
+        crate::no_grad(|| {
+            let mut values: HashMap<&str, Tensor<B>> = HashMap::new();
+            for name in &self.inputs {
+                let tensor = inputs.get(name).ok_or_else(|| {
+                    FerroFlowError::InvalidOperation(format!("Missing model input {:?}", name))
+                })?;
+                values.insert(name, tensor.clone());
+            }
+            for node in &self.nodes {
+                let args: Vec<Option<&Tensor<B>>> = node.inputs
+                    .iter()
+                    .map(|name| values.get(name.as_str()).or_else(|| self.constants.get(name)))
+                    .collect();
+                let output = node.op.run(&args)?;
+                values.insert(&node.output, output);
+            }
+            Ok(self.outputs
+                .iter()
+                .map(|name| values.get(name.as_str()).unwrap_or_else(|| &self.constants[name]).clone())
+                .collect())
+        })
+    }
+}
+
+/// Decodes a serialized `TensorProto`, such as the `.pb` inputs and outputs of the ONNX test
+/// data sets, into a tensor of backend `B`
+pub fn load_tensor<B: ComputeBackend>(ctx: Arc<B::Context>, bytes: &[u8]) -> Result<Tensor<B>> {
+    tensor_from_proto(ctx, &TensorProto::decode(bytes)?)
+}
+
+fn tensor_from_proto<B: ComputeBackend>(ctx: Arc<B::Context>, proto: &TensorProto) -> Result<Tensor<B>> {
+    if proto.external {
+        return Err(format_error(format!("tensor {:?} keeps its data in an external file", proto.name)));
+    }
+    let dtype = match proto.data_type {
+        1 => DType::F32,
+        2 => DType::U8,
+        6 => DType::I32,
+        7 => DType::I64,
+        9 => DType::Bool,
+        10 => DType::F16,
+        11 => DType::F64,
+        16 => DType::BF16,
+        other => return Err(FerroFlowError::UnsupportedDType(format!(
+            "ONNX data type {} of tensor {:?}", other, proto.name
+        ))),
+    };
+    let dims = proto.dims
+        .iter()
+        .map(|&dim| usize::try_from(dim).map_err(|_| format_error(format!("tensor {:?} has dimension {}", proto.name, dim))))
+        .collect::<Result<Vec<_>>>()?;
+
+    let bytes = match &proto.raw_data {
+        Some(raw) => convert_endianness(raw, dtype.size_in_bytes(), true).into_owned(),
+        None => {
+            // Types narrower than 32 bits are widened into `int32_data`, f16 and bf16 as their bits
+            let mut bytes = Vec::new();
+            match dtype {
+                DType::F32 => proto.float_data.iter().for_each(|v| v.extend_ne_bytes(&mut bytes)),
+                DType::F64 => proto.double_data.iter().for_each(|v| v.extend_ne_bytes(&mut bytes)),
+                DType::I64 => proto.int64_data.iter().for_each(|v| v.extend_ne_bytes(&mut bytes)),
+                DType::I32 => proto.int32_data.iter().for_each(|&v| (v as i32).extend_ne_bytes(&mut bytes)),
+                DType::U8 | DType::Bool => bytes.extend(proto.int32_data.iter().map(|&v| v as u8)),
+                DType::F16 | DType::BF16 => {
+                    proto.int32_data.iter().for_each(|&v| bytes.extend_from_slice(&(v as u16).to_ne_bytes()))
+                }
+            }
+            bytes
+        }
+    };
+    Tensor::from_bytes(ctx, Shape::new(dims), dtype, &bytes)
+}
+
+/// Evaluates a `Constant` node
+fn constant<B: ComputeBackend>(ctx: Arc<B::Context>, node: &NodeProto) -> Result<Tensor<B>> {
+    let [attribute] = &node.attributes[..] else {
+        return Err(format_error(format!("Constant node {:?} must have exactly one attribute", node.name)));
+    };
+    match (attribute.name.as_str(), &attribute.value) {
+        ("value", Attribute::Tensor(proto)) => tensor_from_proto(ctx, proto),
+        ("value_float", Attribute::Float(value)) => Tensor::from_slice(ctx, Shape::new(vec![]), &[*value]),
+        ("value_floats", Attribute::Floats(values)) => Tensor::from_slice(ctx, Shape::new(vec![values.len()]), values),
+        ("value_int", Attribute::Int(value)) => Tensor::from_slice(ctx, Shape::new(vec![]), &[*value]),
+        ("value_ints", Attribute::Ints(values)) => Tensor::from_slice(ctx, Shape::new(vec![values.len()]), values),
+        (name, _) => Err(ops::unsupported(node, Some(format!("attribute {:?}", name)))),
+    }
+}
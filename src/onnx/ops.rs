@@ -0,0 +1,458 @@
+//! The supported ONNX operators, parsed from their nodes once at import and run on tensors.
+
+use super::proto::{format_error, Attribute, NodeProto};
+use crate::compute::{ComputeBackend, UnaryOp};
+use crate::dtype::DType;
+use crate::error::{Result, FerroFlowError};
+use crate::tensor::{Conv2dOptions, Pool2dOptions, Shape, Tensor};
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Binary {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+/// An operator with its attributes resolved for the model's opset version
+#[derive(Debug, Clone)]
+pub(crate) enum Op {
+    /// `Identity`, and `Dropout` which is the identity at inference
+    Identity,
+    Binary(Binary),
+    Unary(UnaryOp),
+    Neg,
+    LeakyRelu { alpha: f32 },
+    /// Bounds from attributes before opset 11, replaced by the optional inputs from then on
+    Clip { min: f32, max: f32 },
+    MatMul,
+    Gemm { alpha: f32, beta: f32, trans_a: bool, trans_b: bool },
+    /// Before opset 13 the input is flattened to 2D around `axis`
+    Softmax { axis: i64, log: bool, flatten: bool },
+    Conv(Conv2dOptions),
+    MaxPool(Pool2dOptions),
+    AveragePool(Pool2dOptions),
+    GlobalAveragePool,
+    GlobalMaxPool,
+    BatchNormalization { epsilon: f32 },
+    Flatten { axis: i64 },
+    Reshape { allow_zero: bool },
+    Transpose { perm: Option<Vec<usize>> },
+    /// Axes from the attribute before opset 13, or from the second input when None
+    Squeeze { axes: Option<Vec<i64>> },
+    Unsqueeze { axes: Option<Vec<i64>> },
+}
+
+/// Typed access to the attributes of a node
+struct Attributes<'a>(&'a NodeProto);
+
+impl<'a> Attributes<'a> {
+    fn get(&self, name: &str) -> Option<&'a Attribute> {
+        self.0.attributes.iter().find(|a| a.name == name).map(|a| &a.value)
+    }
+
+    fn wrong_type(&self, name: &str) -> FerroFlowError {
+        format_error(format!("attribute {:?} of {} node {:?} has the wrong type", name, self.0.op_type, self.0.name))
+    }
+
+    fn int(&self, name: &str, default: i64) -> Result<i64> {
+        match self.get(name) {
+            None => Ok(default),
+            Some(Attribute::Int(value)) => Ok(*value),
+            Some(_) => Err(self.wrong_type(name)),
+        }
+    }
+
+    fn float(&self, name: &str, default: f32) -> Result<f32> {
+        match self.get(name) {
+            None => Ok(default),
+            Some(Attribute::Float(value)) => Ok(*value),
+            Some(_) => Err(self.wrong_type(name)),
+        }
+    }
+
+    fn ints(&self, name: &str) -> Result<Option<&'a [i64]>> {
+        match self.get(name) {
+            None => Ok(None),
+            Some(Attribute::Ints(values)) => Ok(Some(values)),
+            Some(_) => Err(self.wrong_type(name)),
+        }
+    }
+
+    fn string(&self, name: &str, default: &str) -> Result<String> {
+        match self.get(name) {
+            None => Ok(default.to_string()),
+            Some(Attribute::String(value)) => String::from_utf8(value.clone()).map_err(|_| self.wrong_type(name)),
+            Some(_) => Err(self.wrong_type(name)),
+        }
+    }
+}
+
+/// Returns the error for a node the importer can't run, described by `what` if its operator is
+/// supported in other forms
+pub(crate) fn unsupported(node: &NodeProto, what: Option<String>) -> FerroFlowError {
+    let op = if node.domain.is_empty() { node.op_type.clone() } else { format!("{}.{}", node.domain, node.op_type) };
+    match what {
+        Some(what) => FerroFlowError::UnsupportedOperator(format!("{} (node {:?}) with {}", op, node.name, what)),
+        None => FerroFlowError::UnsupportedOperator(format!("{} (node {:?})", op, node.name)),
+    }
+}
+
+impl Op {
+    /// Parses a node of the default domain for opset version `opset`
+    pub(crate) fn parse(node: &NodeProto, opset: i64) -> Result<Self> {
+        if !node.domain.is_empty() && node.domain != "ai.onnx" {
+            return Err(unsupported(node, None));
+        }
+        let attrs = Attributes(node);
+        let op = match node.op_type.as_str() {
+            "Identity" => Op::Identity,
+            "Dropout" => {
+                if node.outputs.get(1).is_some_and(|mask| !mask.is_empty()) {
+                    return Err(unsupported(node, Some("a mask output".into())));
+                }
+                Op::Identity
+            }
+            "Add" => Op::Binary(Binary::Add),
+            "Sub" => Op::Binary(Binary::Sub),
+            "Mul" => Op::Binary(Binary::Mul),
+            "Div" => Op::Binary(Binary::Div),
+            "Relu" => Op::Unary(UnaryOp::Relu),
+            "Sigmoid" => Op::Unary(UnaryOp::Sigmoid),
+            "Tanh" => Op::Unary(UnaryOp::Tanh),
+            "Exp" => Op::Unary(UnaryOp::Exp),
+            "Log" => Op::Unary(UnaryOp::Log),
+            "Sqrt" => Op::Unary(UnaryOp::Sqrt),
+            "Abs" => Op::Unary(UnaryOp::Abs),
+            "Neg" => Op::Neg,
+            "Gelu" => match attrs.string("approximate", "none")?.as_str() {
+                "none" => Op::Unary(UnaryOp::Gelu),
+                "tanh" => Op::Unary(UnaryOp::GeluTanh),
+                other => return Err(unsupported(node, Some(format!("approximate {:?}", other)))),
+            },
+            "LeakyRelu" => Op::LeakyRelu { alpha: attrs.float("alpha", 0.01)? },
+            "Clip" => Op::Clip {
+                min: if opset < 11 { attrs.float("min", f32::NEG_INFINITY)? } else { f32::NEG_INFINITY },
+                max: if opset < 11 { attrs.float("max", f32::INFINITY)? } else { f32::INFINITY },
+            },
+            "MatMul" => Op::MatMul,
+            "Gemm" => Op::Gemm {
+                alpha: attrs.float("alpha", 1.0)?,
+                beta: attrs.float("beta", 1.0)?,
+                trans_a: attrs.int("transA", 0)? != 0,
+                trans_b: attrs.int("transB", 0)? != 0,
+            },
+            "Softmax" | "LogSoftmax" => Op::Softmax {
+                axis: attrs.int("axis", if opset < 13 { 1 } else { -1 })?,
+                log: node.op_type == "LogSoftmax",
+                flatten: opset < 13,
+            },
+            "Conv" => {
+                if attrs.ints("kernel_shape")?.is_some_and(|kernel| kernel.len() != 2) {
+                    return Err(unsupported(node, Some("a kernel that is not 2D".into())));
+                }
+                Op::Conv(Conv2dOptions {
+                    stride: pair(node, &attrs, "strides")?,
+                    padding: padding(node, &attrs)?,
+                    dilation: pair(node, &attrs, "dilations")?,
+                    groups: usize::try_from(attrs.int("group", 1)?).map_err(|_| attrs.wrong_type("group"))?,
+                    output_padding: (0, 0),
+                })
+            }
+            "MaxPool" | "AveragePool" => {
+                let kernel_size = match attrs.ints("kernel_shape")? {
+                    Some(&[h, w]) if h > 0 && w > 0 => (h as usize, w as usize),
+                    Some(kernel) => return Err(unsupported(node, Some(format!("kernel_shape {:?}", kernel)))),
+                    None => return Err(format_error(format!("{} node {:?} has no kernel_shape", node.op_type, node.name))),
+                };
+                if attrs.int("ceil_mode", 0)? != 0 {
+                    return Err(unsupported(node, Some("ceil_mode".into())));
+                }
+                if pair(node, &attrs, "dilations")? != (1, 1) {
+                    return Err(unsupported(node, Some("dilations".into())));
+                }
+                let options = Pool2dOptions {
+                    kernel_size,
+                    stride: pair(node, &attrs, "strides")?,
+                    padding: padding(node, &attrs)?,
+                    count_include_pad: attrs.int("count_include_pad", 0)? != 0,
+                };
+                if node.op_type == "MaxPool" { Op::MaxPool(options) } else { Op::AveragePool(options) }
+            }
+            "GlobalAveragePool" => Op::GlobalAveragePool,
+            "GlobalMaxPool" => Op::GlobalMaxPool,
+            "BatchNormalization" => {
+                if attrs.int("training_mode", 0)? != 0 {
+                    return Err(unsupported(node, Some("training mode".into())));
+                }
+                Op::BatchNormalization { epsilon: attrs.float("epsilon", 1e-5)? }
+            }
+            "Flatten" => Op::Flatten { axis: attrs.int("axis", 1)? },
+            "Reshape" => Op::Reshape { allow_zero: attrs.int("allowzero", 0)? != 0 },
+            "Transpose" => Op::Transpose {
+                perm: attrs.ints("perm")?
+                    .map(|perm| perm.iter().map(|&p| usize::try_from(p).map_err(|_| attrs.wrong_type("perm"))).collect())
+                    .transpose()?,
+            },
+            "Squeeze" | "Unsqueeze" => {
+                let axes = if opset < 13 { attrs.ints("axes")?.map(<[i64]>::to_vec) } else { None };
+                if node.op_type == "Squeeze" { Op::Squeeze { axes } } else { Op::Unsqueeze { axes } }
+            }
+            _ => return Err(unsupported(node, None)),
+        };
+        // Every supported operator produces one output, such as MaxPool without its indices
+        if node.op_type != "Dropout" && node.outputs.iter().skip(1).any(|output| !output.is_empty()) {
+            return Err(unsupported(node, Some("several outputs".into())));
+        }
+        Ok(op)
+    }
+
+    /// Runs the operator on its inputs, where omitted optional inputs are None
+    pub(crate) fn run<B: ComputeBackend>(&self, inputs: &[Option<&Tensor<B>>]) -> Result<Tensor<B>> {
+        let input = |i: usize| {
+            inputs.get(i).copied().flatten().ok_or_else(|| {
+                FerroFlowError::InvalidOperation(format!("Input {} of {:?} is missing", i, self))
+            })
+        };
+        let optional = |i: usize| inputs.get(i).copied().flatten();
+        let x = input(0)?;
+        match self {
+            Op::Identity => Ok(x.clone()),
+            Op::Binary(Binary::Add) => x.add(input(1)?),
+            Op::Binary(Binary::Sub) => x.sub(input(1)?),
+            Op::Binary(Binary::Mul) => x.multiply(input(1)?),
+            Op::Binary(Binary::Div) => x.divide(input(1)?),
+            Op::Unary(op) => x.unary_op(*op),
+            Op::Neg => x.scalar_multiply(-1.0),
+            Op::LeakyRelu { alpha } => x.relu()?.sub(&x.scalar_multiply(-1.0)?.relu()?.scalar_multiply(*alpha)?),
+            Op::Clip { min, max } => {
+                let min = optional(1).map(scalar).transpose()?.unwrap_or(*min);
+                let max = optional(2).map(scalar).transpose()?.unwrap_or(*max);
+                x.clamp(min, max)
+            }
+            Op::MatMul => matmul(x, input(1)?),
+            Op::Gemm { alpha, beta, trans_a, trans_b } => {
+                let product = x.matmul_transposed(input(1)?, *trans_a, *trans_b)?;
+                let product = if *alpha == 1.0 { product } else { product.scalar_multiply(*alpha)? };
+                match optional(2) {
+                    Some(c) if *beta == 1.0 => product.add(c),
+                    Some(c) => product.add(&c.scalar_multiply(*beta)?),
+                    None => Ok(product),
+                }
+            }
+            Op::Softmax { axis, log, flatten } => {
+                let dims = x.shape().dims().to_vec();
+                let axis = normalize_axis(*axis, dims.len())?;
+                let softmax = |x: &Tensor<B>, dim| if *log { x.log_softmax(dim) } else { x.softmax(dim) };
+                if *flatten {
+                    let rows = dims[..axis].iter().product();
+                    let flat = x.reshape(Shape::new(vec![rows, x.shape().size() / rows.max(1)]))?;
+                    softmax(&flat, 1)?.reshape(Shape::new(dims))
+                } else {
+                    softmax(x, axis)
+                }
+            }
+            Op::Conv(options) => x.conv2d(input(1)?, optional(2), *options),
+            Op::MaxPool(options) => Ok(x.max_pool2d(*options)?.0),
+            Op::AveragePool(options) => x.avg_pool2d(*options),
+            Op::GlobalAveragePool => x.adaptive_avg_pool2d((1, 1)),
+            Op::GlobalMaxPool => Ok(x.adaptive_max_pool2d((1, 1))?.0),
+            Op::BatchNormalization { epsilon } => {
+                let (scale, bias, mean, var) = (input(1)?, input(2)?, input(3)?, input(4)?);
+                let rank = x.shape().dims().len();
+                if rank < 2 {
+                    return Err(FerroFlowError::ShapeMismatch(format!(
+                        "BatchNormalization needs an input with a channel dimension, got shape {:?}", x.shape().dims()
+                    )));
+                }
+                // Folds the statistics into a per-channel affine map broadcast over [N, C, ...]
+                let mut param_dims = vec![x.shape().dims()[1]];
+                param_dims.resize(rank - 1, 1);
+                let epsilon = Tensor::full(Arc::clone(x.context()), Shape::new(vec![1]), *epsilon)?;
+                let factor = scale.divide(&var.add(&epsilon)?.sqrt()?)?;
+                let shift = bias.sub(&mean.multiply(&factor)?)?;
+                x.multiply(&factor.reshape(Shape::new(param_dims.clone()))?)?
+                    .add(&shift.reshape(Shape::new(param_dims))?)
+            }
+            Op::Flatten { axis } => {
+                // The axis may also be the rank itself, flattening into a single row, but a
+                // negative axis counts back from the rank as usual
+                let dims = x.shape().dims();
+                let axis = if *axis < 0 { normalize_axis(*axis, dims.len()) } else { normalize_axis(*axis, dims.len() + 1) }?;
+                let rows = dims[..axis].iter().product();
+                x.reshape(Shape::new(vec![rows, dims[axis..].iter().product()]))
+            }
+            Op::Reshape { allow_zero } => {
+                let target = reshape_dims(x.shape().dims(), &integers(input(1)?)?, *allow_zero)?;
+                x.reshape(Shape::new(target))
+            }
+            Op::Transpose { perm } => match perm {
+                Some(perm) => x.permute(perm),
+                None => x.permute(&(0..x.shape().dims().len()).rev().collect::<Vec<_>>()),
+            },
+            Op::Squeeze { axes } => {
+                let dims = x.shape().dims();
+                let axes = match axes {
+                    Some(axes) => Some(axes.clone()),
+                    None => optional(1).map(integers).transpose()?,
+                };
+                let squeezed = match axes {
+                    None => dims.iter().copied().filter(|&d| d != 1).collect(),
+                    Some(axes) => {
+                        let axes = axes.iter().map(|&a| normalize_axis(a, dims.len())).collect::<Result<Vec<_>>>()?;
+                        if let Some(&axis) = axes.iter().find(|&&a| dims[a] != 1) {
+                            return Err(FerroFlowError::ShapeMismatch(format!(
+                                "Cannot squeeze dimension {} of shape {:?}, which is not 1", axis, dims
+                            )));
+                        }
+                        dims.iter().enumerate().filter(|(i, _)| !axes.contains(i)).map(|(_, &d)| d).collect()
+                    }
+                };
+                x.reshape(Shape::new(squeezed))
+            }
+            Op::Unsqueeze { axes } => {
+                let axes = match axes {
+                    Some(axes) => axes.clone(),
+                    None => integers(input(1)?)?,
+                };
+                let rank = x.shape().dims().len() + axes.len();
+                let mut axes = axes.iter().map(|&a| normalize_axis(a, rank)).collect::<Result<Vec<_>>>()?;
+                axes.sort_unstable();
+                let mut dims = x.shape().dims().to_vec();
+                for axis in axes {
+                    dims.insert(axis, 1);
+                }
+                x.reshape(Shape::new(dims))
+            }
+        }
+    }
+}
+
+/// Reads a `(height, width)` attribute such as `strides`, which defaults to ones
+fn pair(node: &NodeProto, attrs: &Attributes<'_>, name: &str) -> Result<(usize, usize)> {
+    match attrs.ints(name)? {
+        None => Ok((1, 1)),
+        Some(&[h, w]) if h > 0 && w > 0 => Ok((h as usize, w as usize)),
+        Some(values) => Err(unsupported(node, Some(format!("{} {:?}", name, values)))),
+    }
+}
+
+/// Reads `auto_pad` and `pads`, which must pad both sides of each dimension equally
+fn padding(node: &NodeProto, attrs: &Attributes<'_>) -> Result<(usize, usize)> {
+    match attrs.string("auto_pad", "NOTSET")?.as_str() {
+        "NOTSET" => {}
+        "VALID" => return Ok((0, 0)),
+        other => return Err(unsupported(node, Some(format!("auto_pad {}", other)))),
+    }
+    match attrs.ints("pads")? {
+        None => Ok((0, 0)),
+        Some(&[top, left, bottom, right]) if top == bottom && left == right && top >= 0 && left >= 0 => {
+            Ok((top as usize, left as usize))
+        }
+        Some(pads) => Err(unsupported(node, Some(format!("pads {:?}", pads)))),
+    }
+}
+
+/// Maps a possibly negative axis onto `0..rank`
+fn normalize_axis(axis: i64, rank: usize) -> Result<usize> {
+    let normalized = if axis < 0 { axis + rank as i64 } else { axis };
+    usize::try_from(normalized).ok().filter(|&a| a < rank).ok_or_else(|| {
+        FerroFlowError::InvalidOperation(format!("Axis {} is out of range for rank {}", axis, rank))
+    })
+}
+
+/// Reads the single value of a scalar input, such as the bounds of `Clip`
+fn scalar<B: ComputeBackend>(tensor: &Tensor<B>) -> Result<f32> {
+    match tensor.data()?[..] {
+        [value] => Ok(value),
+        _ => Err(FerroFlowError::ShapeMismatch(format!(
+            "Expected a scalar, got shape {:?}", tensor.shape().dims()
+        ))),
+    }
+}
+
+/// Reads an i64 tensor of shapes or axes
+fn integers<B: ComputeBackend>(tensor: &Tensor<B>) -> Result<Vec<i64>> {
+    if tensor.dtype() != DType::I64 {
+        return Err(FerroFlowError::DTypeMismatch(format!(
+            "Shapes and axes must be i64 tensors, got {}", tensor.dtype()
+        )));
+    }
+    tensor.to_vec::<i64>()
+}
+
+/// Resolves the target of `Reshape`, where -1 is inferred and 0 copies the input dimension
+/// unless `allow_zero` is set
+fn reshape_dims(dims: &[usize], target: &[i64], allow_zero: bool) -> Result<Vec<usize>> {
+    let invalid = || FerroFlowError::ShapeMismatch(format!("Cannot reshape shape {:?} to {:?}", dims, target));
+    let mut inferred = None;
+    let mut output = Vec::with_capacity(target.len());
+    for (i, &dim) in target.iter().enumerate() {
+        output.push(match dim {
+            -1 if inferred.is_none() => {
+                inferred = Some(i);
+                1
+            }
+            0 if !allow_zero => *dims.get(i).ok_or_else(invalid)?,
+            dim => usize::try_from(dim).map_err(|_| invalid())?,
+        });
+    }
+    if let Some(i) = inferred {
+        let known: usize = output.iter().product();
+        let size: usize = dims.iter().product();
+        if known == 0 || !size.is_multiple_of(known) {
+            return Err(invalid());
+        }
+        output[i] = size / known;
+    }
+    Ok(output)
+}
+
+/// Matrix product with NumPy semantics: 1D operands gain a dimension that is removed again,
+/// and batch dimensions broadcast
+fn matmul<B: ComputeBackend>(a: &Tensor<B>, b: &Tensor<B>) -> Result<Tensor<B>> {
+    let (a_dims, b_dims) = (a.shape().dims().to_vec(), b.shape().dims().to_vec());
+    if a_dims.is_empty() || b_dims.is_empty() {
+        return Err(FerroFlowError::ShapeMismatch("MatMul operands must not be scalars".into()));
+    }
+    let a = if a_dims.len() == 1 { a.reshape(Shape::new(vec![1, a_dims[0]]))? } else { a.clone() };
+    let b = if b_dims.len() == 1 { b.reshape(Shape::new(vec![b_dims[0], 1]))? } else { b.clone() };
+    let (a_rank, b_rank) = (a.shape().dims().len(), b.shape().dims().len());
+    let (m, k) = (a.shape().dims()[a_rank - 2], a.shape().dims()[a_rank - 1]);
+    let n = b.shape().dims()[b_rank - 1];
+
+    let product = if a_rank == 2 && b_rank == 2 {
+        a.matmul(&b)?
+    } else if b_rank == 2 {
+        // Stacked inputs times one matrix is a single product over all rows
+        let batch = a.shape().dims()[..a_rank - 2].to_vec();
+        let rows = a.reshape(Shape::new(vec![batch.iter().product::<usize>() * m, k]))?;
+        let mut dims = batch;
+        dims.extend([m, n]);
+        rows.matmul(&b)?.reshape(Shape::new(dims))?
+    } else {
+        let a_batch = Shape::new(a.shape().dims()[..a_rank - 2].to_vec());
+        let b_batch = Shape::new(b.shape().dims()[..b_rank - 2].to_vec());
+        let batch = a_batch.broadcast_with(&b_batch)?.dims().to_vec();
+        let batch_size = batch.iter().product();
+        let flatten = |t: &Tensor<B>, rows: usize, cols: usize| -> Result<Tensor<B>> {
+            let mut dims = batch.clone();
+            dims.extend([rows, cols]);
+            t.broadcast_to(&Shape::new(dims))?.reshape(Shape::new_batched(batch_size, rows, cols))
+        };
+        let mut dims = batch.clone();
+        dims.extend([m, n]);
+        flatten(&a, m, k)?.matmul(&flatten(&b, k, n)?)?.reshape(Shape::new(dims))?
+    };
+
+    // Drops the dimensions added to 1D operands
+    let mut dims = product.shape().dims().to_vec();
+    if b_dims.len() == 1 {
+        dims.pop();
+    }
+    if a_dims.len() == 1 {
+        dims.remove(dims.len() - 1 - usize::from(b_dims.len() != 1));
+    }
+    product.reshape(Shape::new(dims))
+}
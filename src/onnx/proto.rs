@@ -0,0 +1,329 @@
+//! Decoding of the protobuf messages of `onnx.proto` that describe inference graphs. Fields
+//! the importer doesn't use are skipped, as protobuf readers do for unknown fields.
+
+use crate::error::{Result, FerroFlowError};
+use crate::io::ByteReader;
+
+const FORMAT: &str = "onnx";
+
+/// A field value in protobuf wire format
+#[derive(Clone, Copy)]
+enum Field<'a> {
+    Varint(u64),
+    Fixed64(u64),
+    Bytes(&'a [u8]),
+    Fixed32(u32),
+}
+
+impl<'a> Field<'a> {
+    fn varint(self) -> Result<u64> {
+        match self {
+            Field::Varint(value) => Ok(value),
+            _ => Err(wire_error("varint")),
+        }
+    }
+
+    /// Returns an `int64` or `int32` value, which negative values sign-extend to 64 bits
+    fn int(self) -> Result<i64> {
+        self.varint().map(|value| value as i64)
+    }
+
+    fn bytes(self) -> Result<&'a [u8]> {
+        match self {
+            Field::Bytes(bytes) => Ok(bytes),
+            _ => Err(wire_error("length-delimited")),
+        }
+    }
+
+    fn string(self) -> Result<String> {
+        String::from_utf8(self.bytes()?.to_vec()).map_err(|_| format_error("string field is not UTF-8"))
+    }
+
+    fn float(self) -> Result<f32> {
+        match self {
+            Field::Fixed32(bits) => Ok(f32::from_bits(bits)),
+            _ => Err(wire_error("32-bit")),
+        }
+    }
+
+    /// Appends a repeated integer field, packed or not
+    fn push_ints(self, values: &mut Vec<i64>) -> Result<()> {
+        match self {
+            Field::Bytes(bytes) => {
+                let mut reader = ByteReader::new(bytes, 0, FORMAT);
+                while reader.remaining() > 0 {
+                    values.push(read_varint(&mut reader)? as i64);
+                }
+            }
+            field => values.push(field.int()?),
+        }
+        Ok(())
+    }
+
+    /// Appends a repeated `float` field, packed or not
+    fn push_floats(self, values: &mut Vec<f32>) -> Result<()> {
+        match self {
+            Field::Bytes(bytes) if bytes.len() % 4 == 0 => {
+                values.extend(bytes.chunks_exact(4).map(|b| f32::from_le_bytes(b.try_into().unwrap())));
+            }
+            field => values.push(field.float()?),
+        }
+        Ok(())
+    }
+
+    /// Appends a repeated `double` field, packed or not
+    fn push_doubles(self, values: &mut Vec<f64>) -> Result<()> {
+        match self {
+            Field::Bytes(bytes) if bytes.len() % 8 == 0 => {
+                values.extend(bytes.chunks_exact(8).map(|b| f64::from_le_bytes(b.try_into().unwrap())));
+            }
+            Field::Fixed64(bits) => values.push(f64::from_bits(bits)),
+            _ => return Err(wire_error("64-bit")),
+        }
+        Ok(())
+    }
+}
+
+fn read_varint(reader: &mut ByteReader<'_>) -> Result<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = reader.u8()?;
+        value |= u64::from(byte & 0x7F) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(format_error("varint is longer than 10 bytes"))
+}
+
+/// Calls `visit` with the number and value of every field of the message in `bytes`
+fn for_each_field<'a>(bytes: &'a [u8], mut visit: impl FnMut(u64, Field<'a>) -> Result<()>) -> Result<()> {
+    let mut reader = ByteReader::new(bytes, 0, FORMAT);
+    while reader.remaining() > 0 {
+        let key = read_varint(&mut reader)?;
+        let field = match key & 7 {
+            0 => Field::Varint(read_varint(&mut reader)?),
+            1 => Field::Fixed64(reader.u64()?),
+            2 => {
+                let len = usize::try_from(read_varint(&mut reader)?).unwrap_or(usize::MAX);
+                Field::Bytes(reader.take(len)?)
+            }
+            5 => Field::Fixed32(reader.u32()?),
+            wire_type => return Err(format_error(format!("unsupported wire type {}", wire_type))),
+        };
+        visit(key >> 3, field)?;
+    }
+    Ok(())
+}
+
+/// `ModelProto`
+#[derive(Debug, Default)]
+pub(crate) struct ModelProto {
+    pub(crate) ir_version: i64,
+    /// `(domain, version)` pairs, the default domain is empty
+    pub(crate) opset_import: Vec<(String, i64)>,
+    pub(crate) graph: GraphProto,
+}
+
+impl ModelProto {
+    pub(crate) fn decode(bytes: &[u8]) -> Result<Self> {
+        let mut model = Self::default();
+        let mut has_graph = false;
+        for_each_field(bytes, |number, field| {
+            match number {
+                1 => model.ir_version = field.int()?,
+                7 => {
+                    model.graph = GraphProto::decode(field.bytes()?)?;
+                    has_graph = true;
+                }
+                8 => {
+                    let mut opset = (String::new(), 0);
+                    for_each_field(field.bytes()?, |number, field| {
+                        match number {
+                            1 => opset.0 = field.string()?,
+                            2 => opset.1 = field.int()?,
+                            _ => {}
+                        }
+                        Ok(())
+                    })?;
+                    model.opset_import.push(opset);
+                }
+                _ => {}
+            }
+            Ok(())
+        })?;
+        if !has_graph {
+            return Err(format_error("model has no graph"));
+        }
+        Ok(model)
+    }
+}
+
+/// `GraphProto`
+#[derive(Debug, Default)]
+pub(crate) struct GraphProto {
+    pub(crate) name: String,
+    pub(crate) nodes: Vec<NodeProto>,
+    pub(crate) initializers: Vec<TensorProto>,
+    /// Names of the `ValueInfoProto` inputs, which may include initializers
+    pub(crate) inputs: Vec<String>,
+    pub(crate) outputs: Vec<String>,
+}
+
+impl GraphProto {
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        let mut graph = Self::default();
+        let value_name = |field: Field<'_>| -> Result<String> {
+            let mut name = String::new();
+            for_each_field(field.bytes()?, |number, field| {
+                if number == 1 {
+                    name = field.string()?;
+                }
+                Ok(())
+            })?;
+            Ok(name)
+        };
+        for_each_field(bytes, |number, field| {
+            match number {
+                1 => graph.nodes.push(NodeProto::decode(field.bytes()?)?),
+                2 => graph.name = field.string()?,
+                5 => graph.initializers.push(TensorProto::decode(field.bytes()?)?),
+                11 => graph.inputs.push(value_name(field)?),
+                12 => graph.outputs.push(value_name(field)?),
+                15 => return Err(format_error("sparse initializers are not supported")),
+                _ => {}
+            }
+            Ok(())
+        })?;
+        Ok(graph)
+    }
+}
+
+/// `NodeProto`
+#[derive(Debug, Default)]
+pub(crate) struct NodeProto {
+    pub(crate) name: String,
+    pub(crate) op_type: String,
+    pub(crate) domain: String,
+    /// Input names, an empty name marks an omitted optional input
+    pub(crate) inputs: Vec<String>,
+    pub(crate) outputs: Vec<String>,
+    pub(crate) attributes: Vec<AttributeProto>,
+}
+
+impl NodeProto {
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        let mut node = Self::default();
+        for_each_field(bytes, |number, field| {
+            match number {
+                1 => node.inputs.push(field.string()?),
+                2 => node.outputs.push(field.string()?),
+                3 => node.name = field.string()?,
+                4 => node.op_type = field.string()?,
+                5 => node.attributes.push(AttributeProto::decode(field.bytes()?)?),
+                7 => node.domain = field.string()?,
+                _ => {}
+            }
+            Ok(())
+        })?;
+        Ok(node)
+    }
+}
+
+/// `AttributeProto`, holding the value of its `type`
+#[derive(Debug)]
+pub(crate) struct AttributeProto {
+    pub(crate) name: String,
+    pub(crate) value: Attribute,
+}
+
+/// The value of an attribute
+#[derive(Debug)]
+pub(crate) enum Attribute {
+    Float(f32),
+    Int(i64),
+    String(Vec<u8>),
+    Tensor(TensorProto),
+    Floats(Vec<f32>),
+    Ints(Vec<i64>),
+    /// String lists, graphs, sparse tensors and type protos, which no supported operator takes
+    Other,
+}
+
+impl AttributeProto {
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        let (mut name, mut attribute_type) = (String::new(), 0);
+        let (mut float, mut int, mut string, mut tensor) = (0.0, 0, Vec::new(), None);
+        let (mut floats, mut ints) = (Vec::new(), Vec::new());
+        for_each_field(bytes, |number, field| {
+            match number {
+                1 => name = field.string()?,
+                2 => float = field.float()?,
+                3 => int = field.int()?,
+                4 => string = field.bytes()?.to_vec(),
+                5 => tensor = Some(TensorProto::decode(field.bytes()?)?),
+                7 => field.push_floats(&mut floats)?,
+                8 => field.push_ints(&mut ints)?,
+                20 => attribute_type = field.int()?,
+                _ => {}
+            }
+            Ok(())
+        })?;
+        let value = match attribute_type {
+            1 => Attribute::Float(float),
+            2 => Attribute::Int(int),
+            3 => Attribute::String(string),
+            4 => Attribute::Tensor(tensor.ok_or_else(|| format_error(format!("tensor attribute {:?} has no tensor", name)))?),
+            6 => Attribute::Floats(floats),
+            7 => Attribute::Ints(ints),
+            _ => Attribute::Other,
+        };
+        Ok(Self { name, value })
+    }
+}
+
+/// `TensorProto`, with its data in whichever field the writer used
+#[derive(Debug, Default)]
+pub(crate) struct TensorProto {
+    pub(crate) name: String,
+    pub(crate) dims: Vec<i64>,
+    pub(crate) data_type: i64,
+    pub(crate) raw_data: Option<Vec<u8>>,
+    pub(crate) float_data: Vec<f32>,
+    pub(crate) double_data: Vec<f64>,
+    /// Also holds the bits of 8- and 16-bit types
+    pub(crate) int32_data: Vec<i64>,
+    pub(crate) int64_data: Vec<i64>,
+    pub(crate) external: bool,
+}
+
+impl TensorProto {
+    pub(crate) fn decode(bytes: &[u8]) -> Result<Self> {
+        let mut tensor = Self::default();
+        for_each_field(bytes, |number, field| {
+            match number {
+                1 => field.push_ints(&mut tensor.dims)?,
+                2 => tensor.data_type = field.int()?,
+                3 => return Err(format_error("segmented tensors are not supported")),
+                4 => field.push_floats(&mut tensor.float_data)?,
+                5 => field.push_ints(&mut tensor.int32_data)?,
+                7 => field.push_ints(&mut tensor.int64_data)?,
+                8 => tensor.name = field.string()?,
+                9 => tensor.raw_data = Some(field.bytes()?.to_vec()),
+                10 => field.push_doubles(&mut tensor.double_data)?,
+                14 => tensor.external = field.varint()? == 1,
+                _ => {}
+            }
+            Ok(())
+        })?;
+        Ok(tensor)
+    }
+}
+
+fn wire_error(expected: &str) -> FerroFlowError {
+    format_error(format!("expected a {} field", expected))
+}
+
+pub(crate) fn format_error(message: impl Into<String>) -> FerroFlowError {
+    FerroFlowError::FormatError(format!("{}: {}", FORMAT, message.into()))
+}
@@ -0,0 +1,87 @@
+use super::{load_tensor, OnnxModel};
+use crate::compute::{CPUBackend, ComputeBackend};
+use crate::error::{Result, FerroFlowError};
+use crate::tensor::Tensor;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Returns the directory of a model written by `tests/fixtures/onnx/generate.py`
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/onnx").join(name)
+}
+
+/// Runs a fixture model on its test data set and compares every output with the expected one
+fn check_fixture(name: &str) -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let dir = fixture(name);
+    let model = OnnxModel::<CPUBackend>::load(Arc::clone(&ctx), dir.join("model.onnx"))?;
+    let data = dir.join("test_data_set_0");
+    let read = |file: String| -> Result<Tensor<CPUBackend>> {
+        load_tensor(Arc::clone(&ctx), &std::fs::read(data.join(file))?)
+    };
+
+    let mut inputs = HashMap::new();
+    for (i, name) in model.input_names().iter().enumerate() {
+        inputs.insert(name.clone(), read(format!("input_{}.pb", i))?);
+    }
+    let outputs = model.run(&inputs)?;
+    assert_eq!(outputs.len(), model.output_names().len());
+    for (i, output) in outputs.iter().enumerate() {
+        let expected = read(format!("output_{}.pb", i))?;
+        let name = &model.output_names()[i];
+        assert_eq!(output.shape(), expected.shape(), "shape of {} output {}", name, i);
+        for (actual, expected) in output.data()?.iter().zip(expected.data()?) {
+            assert!((actual - expected).abs() < 1e-4, "{} output {}: {} != {}", name, i, actual, expected);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_onnx_mlp() -> Result<()> {
+    check_fixture("mlp")
+}
+
+#[test]
+fn test_onnx_cnn() -> Result<()> {
+    check_fixture("cnn")
+}
+
+#[test]
+fn test_onnx_opset_11_operators() -> Result<()> {
+    check_fixture("ops")
+}
+
+#[test]
+fn test_onnx_reports_unsupported_operators() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    match OnnxModel::<CPUBackend>::load(ctx, fixture("unsupported").join("model.onnx")) {
+        Err(FerroFlowError::UnsupportedOperator(message)) => {
+            assert_eq!(message, r#"Einsum (node "outer"), com.microsoft.FusedGemm (node "fused")"#);
+        }
+        Err(e) => panic!("expected an unsupported operator error, got {}", e),
+        Ok(_) => panic!("expected an unsupported operator error"),
+    }
+    Ok(())
+}
+
+#[test]
+fn test_onnx_rejects_invalid_models_and_inputs() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let bytes = std::fs::read(fixture("mlp").join("model.onnx"))?;
+    let model = OnnxModel::<CPUBackend>::from_bytes(Arc::clone(&ctx), &bytes)?;
+    assert_eq!(model.input_names(), ["x"]);
+    assert_eq!(model.output_names(), ["probs"]);
+
+    let x = Tensor::<CPUBackend>::zeros(Arc::clone(&ctx), crate::tensor::Shape::new(vec![2, 4]))?;
+    assert!(matches!(model.run(&HashMap::new()), Err(FerroFlowError::InvalidOperation(_))));
+    let extra = HashMap::from([("x".to_string(), x.clone()), ("w1".to_string(), x)]);
+    assert!(matches!(model.run(&extra), Err(FerroFlowError::InvalidOperation(_))));
+
+    let truncated = OnnxModel::<CPUBackend>::from_bytes(Arc::clone(&ctx), &bytes[..bytes.len() - 5]);
+    assert!(matches!(truncated, Err(FerroFlowError::FormatError(_))));
+    let no_graph = OnnxModel::<CPUBackend>::from_bytes(ctx, &[0x08, 0x08]);
+    assert!(matches!(no_graph, Err(FerroFlowError::FormatError(_))));
+    Ok(())
+}
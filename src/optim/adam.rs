@@ -0,0 +1,82 @@
+use super::{add_group, check_hyperparameter, load_groups, Optimizer, OptimizerState, ParamGroup};
+use crate::compute::{ComputeBackend, OptimizerUpdate};
+use crate::error::Result;
+use crate::tensor::Tensor;
+
+/// Hyperparameters of [`Adam`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdamOptions {
+    pub lr: f32,
+    /// Decay rates of the running averages of the gradient and of its square
+    pub betas: (f32, f32),
+    /// Added to the denominator for numerical stability
+    pub eps: f32,
+    pub weight_decay: f32,
+    /// Shrinks the parameters by `lr * weight_decay` directly instead of adding an L2 penalty
+    /// to the gradient, which turns Adam into AdamW
+    pub decoupled_weight_decay: bool,
+}
+
+impl AdamOptions {
+    /// Adam with learning rate `lr`, betas `(0.9, 0.999)` and no weight decay
+    pub fn new(lr: f32) -> Self {
+        Self { lr, betas: (0.9, 0.999), eps: 1e-8, weight_decay: 0.0, decoupled_weight_decay: false }
+    }
+
+    /// AdamW with learning rate `lr` and decoupled weight decay of 0.01
+    pub fn adamw(lr: f32) -> Self {
+        Self { weight_decay: 0.01, decoupled_weight_decay: true, ..Self::new(lr) }
+    }
+
+    fn update(&self, lr: f32, weight_decay: f32, step: u64) -> OptimizerUpdate {
+        let AdamOptions { betas: (beta1, beta2), eps, decoupled_weight_decay: decoupled, .. } = *self;
+        OptimizerUpdate::Adam { lr, beta1, beta2, eps, weight_decay, decoupled, step }
+    }
+}
+
+/// Adam with bias-corrected moment estimates, or AdamW with
+/// [`AdamOptions::decoupled_weight_decay`], matching PyTorch's `torch.optim.Adam` and `AdamW`
+pub struct Adam<B: ComputeBackend> {
+    groups: Vec<ParamGroup<B>>,
+    options: AdamOptions,
+}
+
+impl<B: ComputeBackend> Adam<B> {
+    /// Creates an optimizer with a single group of `params` using the options' learning rate
+    /// and weight decay
+    pub fn new(params: Vec<Tensor<B>>, options: AdamOptions) -> Result<Self> {
+        check_hyperparameter("Adam beta1", options.betas.0, 0.0..1.0)?;
+        check_hyperparameter("Adam beta2", options.betas.1, 0.0..1.0)?;
+        check_hyperparameter("Adam eps", options.eps, 0.0..f32::INFINITY)?;
+        let mut optimizer = Self { groups: Vec::new(), options };
+        optimizer.add_param_group(ParamGroup::new(params, options.lr, options.weight_decay))?;
+        Ok(optimizer)
+    }
+}
+
+impl<B: ComputeBackend> Optimizer<B> for Adam<B> {
+    fn step(&mut self) -> Result<()> {
+        let options = self.options;
+        for group in &mut self.groups {
+            group.step(|lr, weight_decay, step| options.update(lr, weight_decay, step))?;
+        }
+        Ok(())
+    }
+
+    fn param_groups(&self) -> &[ParamGroup<B>] {
+        &self.groups
+    }
+
+    fn param_groups_mut(&mut self) -> &mut [ParamGroup<B>] {
+        &mut self.groups
+    }
+
+    fn add_param_group(&mut self, group: ParamGroup<B>) -> Result<()> {
+        add_group(&mut self.groups, group)
+    }
+
+    fn load_state_dict(&mut self, state: &OptimizerState) -> Result<()> {
+        let state_len = self.options.update(0.0, 0.0, 1).state_len();
+        load_groups(&mut self.groups, state, state_len)
+    }
+}
@@ -0,0 +1,80 @@
+use crate::autograd;
+use crate::compute::ComputeBackend;
+use crate::error::{Result, FerroFlowError};
+use crate::tensor::{Shape, Tensor};
+use std::sync::Arc;
+
+/// Scales the gradients of `params` so that their combined `norm_type`-norm, taken over all
+/// gradients as if concatenated, is at most `max_norm`. `norm_type` is a `p >= 1` or
+/// `f32::INFINITY` for the largest magnitude.
+///
+/// Returns the norm before clipping as a tensor of shape `[]`, or `None` if no parameter has a
+/// gradient. Everything is computed on the backend, so the norm is only read back on request.
+pub fn clip_grad_norm<B: ComputeBackend>(params: &[Tensor<B>], max_norm: f32, norm_type: f32) -> Result<Option<Tensor<B>>> {
+    if max_norm.is_nan() || max_norm < 0.0 || norm_type.is_nan() || norm_type < 1.0 {
+        return Err(FerroFlowError::InvalidOperation(format!(
+            "Gradient clipping needs max_norm >= 0 and norm_type >= 1, got {} and {}", max_norm, norm_type
+        )));
+    }
+    autograd::no_grad(|| {
+        let grads: Vec<_> = params.iter().filter_map(|param| param.grad().map(|grad| (param, grad))).collect();
+        let Some((first, _)) = grads.first() else {
+            return Ok(None);
+        };
+
+        let mut total: Option<Tensor<B>> = None;
+        for (_, grad) in grads.iter().filter(|(_, grad)| grad.shape().size() > 0) {
+            let flat = grad.reshape(Shape::new(vec![grad.shape().size()]))?;
+            let partial = if norm_type == f32::INFINITY {
+                flat.abs()?.max(0, false)?
+            } else if norm_type == 2.0 {
+                flat.multiply(&flat)?.sum(0, false)?
+            } else {
+                flat.abs()?.pow(norm_type)?.sum(0, false)?
+            };
+            total = Some(match total {
+                None => partial,
+                // max(a, b) = a + relu(b - a)
+                Some(total) if norm_type == f32::INFINITY => total.add(&partial.sub(&total)?.relu()?)?,
+                Some(total) => total.add(&partial)?,
+            });
+        }
+
+        let scalar = |value: f32| Tensor::full(Arc::clone(first.context()), Shape::new(Vec::new()), value);
+        let total = match total {
+            None => scalar(0.0)?,
+            Some(total) if norm_type == f32::INFINITY => total,
+            Some(total) => total.pow(1.0 / norm_type)?,
+        };
+
+        // Matches PyTorch: the coefficient is max_norm / (norm + 1e-6), capped at 1
+        let coefficient = scalar(max_norm)?.divide(&total.add(&scalar(1e-6)?)?)?.clamp(0.0, 1.0)?;
+        for (param, grad) in &grads {
+            set_grad(param, grad.multiply(&coefficient)?);
+        }
+        Ok(Some(total))
+    })
+}
+
+/// Clamps every gradient element of `params` to `[-clip_value, clip_value]`
+pub fn clip_grad_value<B: ComputeBackend>(params: &[Tensor<B>], clip_value: f32) -> Result<()> {
+    if clip_value.is_nan() || clip_value < 0.0 {
+        return Err(FerroFlowError::InvalidOperation(format!(
+            "Gradient clip value must be non-negative, got {}", clip_value
+        )));
+    }
+    autograd::no_grad(|| {
+        for param in params {
+            if let Some(grad) = param.grad() {
+                set_grad(param, grad.clamp(-clip_value, clip_value)?);
+            }
+        }
+        Ok(())
+    })
+}
+
+fn set_grad<B: ComputeBackend>(param: &Tensor<B>, grad: Tensor<B>) {
+    if let Some(node) = param.autograd_node() {
+        node.set_grad(Some(grad));
+    }
+}
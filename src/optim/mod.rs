@@ -0,0 +1,223 @@
+//! Optimizers that update parameters in place from their accumulated gradients.
+//!
+//! Parameters are grouped into [`ParamGroup`]s, each with its own learning rate and weight
+//! decay. A step runs one fused backend kernel per parameter, which updates the parameter's
+//! buffer and the optimizer's per-parameter state directly, so every clone of the parameter
+//! (such as the one held by its module) sees the new values. Graphs that saved a parameter and
+//! whose backward pass has not run yet are invalidated, and their backward pass fails. Parameters
+//! without a gradient are skipped. The state can be copied to the host with [`Optimizer::state_dict`] and
+//! restored with [`Optimizer::load_state_dict`].
+//!
+//! [`LrScheduler`] adjusts the groups' learning rates over the course of training, and
+//! [`clip_grad_norm`] and [`clip_grad_value`] limit the gradients before a step.
+
+use crate::compute::{ComputeBackend, OptimizerUpdate};
+use crate::error::{Result, FerroFlowError};
+use crate::tensor::Tensor;
+use std::ops::{Bound, RangeBounds};
+use std::sync::Arc;
+
+mod adam;
+mod clip;
+mod rmsprop;
+mod scheduler;
+mod sgd;
+
+pub use adam::{Adam, AdamOptions};
+pub use clip::{clip_grad_norm, clip_grad_value};
+pub use rmsprop::{RmsProp, RmsPropOptions};
+pub use scheduler::{ConstantLr, CosineAnnealingLr, LinearWarmup, LrSchedule, LrScheduler, OneCycleLr, StepLr};
+pub use sgd::{Sgd, SgdOptions};
+
+/// Updates parameters from their gradients.
+pub trait Optimizer<B: ComputeBackend>: Send + Sync {
+    /// Updates every parameter that has a gradient.
+    ///
+    /// Graphs recorded before the step saved the parameters' old values, so their backward
+    /// pass fails afterwards. Call `backward` before `step`, and record a new graph after it.
+    fn step(&mut self) -> Result<()>;
+
+    fn param_groups(&self) -> &[ParamGroup<B>];
+
+    /// Gives access to the groups' hyperparameters, e.g. for learning-rate schedules
+    fn param_groups_mut(&mut self) -> &mut [ParamGroup<B>];
+
+    /// Adds parameters with their own learning rate and weight decay
+    fn add_param_group(&mut self, group: ParamGroup<B>) -> Result<()>;
+
+    /// Copies the hyperparameters of each group and the state of each parameter to the host
+    fn state_dict(&self) -> Result<OptimizerState> {
+        self.param_groups().iter().map(ParamGroup::state).collect::<Result<_>>().map(|groups| OptimizerState { groups })
+    }
+
+    /// Restores a state produced by `state_dict` of an optimizer of the same kind over
+    /// parameters of the same shapes
+    fn load_state_dict(&mut self, state: &OptimizerState) -> Result<()>;
+
+    /// Clears the accumulated gradient of every parameter
+    fn zero_grad(&self) {
+        for group in self.param_groups() {
+            for param in group.params() {
+                param.zero_grad();
+            }
+        }
+    }
+}
+
+/// Parameters that share a learning rate and weight decay
+pub struct ParamGroup<B: ComputeBackend> {
+    params: Vec<Tensor<B>>,
+    state: Vec<ParamBuffers<B>>,
+    pub lr: f32,
+    pub weight_decay: f32,
+}
+
+/// Update count and state tensors of one parameter; the tensors are allocated on its first step
+struct ParamBuffers<B: ComputeBackend> {
+    step: u64,
+    buffers: Vec<Tensor<B>>,
+}
+
+impl<B: ComputeBackend> ParamGroup<B> {
+    pub fn new(params: Vec<Tensor<B>>, lr: f32, weight_decay: f32) -> Self {
+        let state = params.iter().map(|_| ParamBuffers { step: 0, buffers: Vec::new() }).collect();
+        Self { params, state, lr, weight_decay }
+    }
+
+    pub fn params(&self) -> &[Tensor<B>] {
+        &self.params
+    }
+
+    fn check(&self) -> Result<()> {
+        check_group_hyperparameters(self.lr, self.weight_decay)
+    }
+
+    /// Applies the update that `update` builds from the group's `(lr, weight_decay)` and a
+    /// parameter's step count to every parameter with a gradient
+    fn step(&mut self, update: impl Fn(f32, f32, u64) -> OptimizerUpdate) -> Result<()> {
+        self.check()?;
+        for (param, state) in self.params.iter().zip(&mut self.state) {
+            let Some(grad) = param.grad() else {
+                continue;
+            };
+            let update = update(self.lr, self.weight_decay, state.step + 1);
+            if state.buffers.is_empty() {
+                state.buffers = (0..update.state_len())
+                    .map(|_| Tensor::zeros(Arc::clone(param.context()), param.shape().clone()))
+                    .collect::<Result<_>>()?;
+            }
+            param.apply_optimizer_update(&update, &grad, &state.buffers)?;
+            state.step += 1;
+        }
+        Ok(())
+    }
+
+    fn state(&self) -> Result<GroupState> {
+        let params = self.state.iter().map(|state| {
+            let buffers = state.buffers.iter().map(Tensor::data).collect::<Result<_>>()?;
+            Ok(ParamState { step: state.step, buffers })
+        });
+        Ok(GroupState { lr: self.lr, weight_decay: self.weight_decay, params: params.collect::<Result<_>>()? })
+    }
+
+    /// Rebuilds the parameter state saved in `state`, whose parameters must each hold
+    /// either no buffers or `state_len` buffers of the parameter's size
+    fn restore(&self, state: &GroupState, state_len: usize) -> Result<Vec<ParamBuffers<B>>> {
+        check_group_hyperparameters(state.lr, state.weight_decay)?;
+        if state.params.len() != self.params.len() {
+            return Err(FerroFlowError::InvalidOperation(format!(
+                "Saved group has state for {} parameters, the optimizer has {}", state.params.len(), self.params.len()
+            )));
+        }
+        self.params.iter().zip(&state.params).map(|(param, saved)| {
+            let size = param.shape().size();
+            let buffer_count = saved.buffers.len();
+            if (buffer_count != 0 && buffer_count != state_len) || saved.buffers.iter().any(|b| b.len() != size) {
+                return Err(FerroFlowError::ShapeMismatch(format!(
+                    "Expected 0 or {} state buffers of {} elements for a parameter of shape {:?}",
+                    state_len, size, param.shape().dims()
+                )));
+            }
+            let buffers = saved.buffers.iter()
+                .map(|values| Tensor::new(Arc::clone(param.context()), param.shape().clone(), values))
+                .collect::<Result<_>>()?;
+            Ok(ParamBuffers { step: saved.step, buffers })
+        }).collect()
+    }
+}
+
+/// Host copy of an optimizer's state, see [`Optimizer::state_dict`]
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct OptimizerState {
+    pub groups: Vec<GroupState>,
+}
+
+/// Hyperparameters of a parameter group and the state of each of its parameters, in order
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroupState {
+    pub lr: f32,
+    pub weight_decay: f32,
+    pub params: Vec<ParamState>,
+}
+
+/// Number of updates applied to a parameter and the values of its state buffers.
+/// Parameters that have not been updated yet have no buffers.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ParamState {
+    pub step: u64,
+    pub buffers: Vec<Vec<f32>>,
+}
+
+/// Checks that a new group is valid and adds it, for use in `add_param_group` implementations
+fn add_group<B: ComputeBackend>(groups: &mut Vec<ParamGroup<B>>, group: ParamGroup<B>) -> Result<()> {
+    group.check()?;
+    groups.push(group);
+    Ok(())
+}
+
+/// Restores every group from `state`, for use in `load_state_dict` implementations
+fn load_groups<B: ComputeBackend>(groups: &mut [ParamGroup<B>], state: &OptimizerState, state_len: usize) -> Result<()> {
+    if state.groups.len() != groups.len() {
+        return Err(FerroFlowError::InvalidOperation(format!(
+            "Saved state has {} parameter groups, the optimizer has {}", state.groups.len(), groups.len()
+        )));
+    }
+    // Everything is validated before any group changes
+    let restored = groups.iter().zip(&state.groups)
+        .map(|(group, saved)| group.restore(saved, state_len))
+        .collect::<Result<Vec<_>>>()?;
+    for ((group, saved), buffers) in groups.iter_mut().zip(&state.groups).zip(restored) {
+        group.state = buffers;
+        group.lr = saved.lr;
+        group.weight_decay = saved.weight_decay;
+    }
+    Ok(())
+}
+
+fn check_group_hyperparameters(lr: f32, weight_decay: f32) -> Result<()> {
+    check_hyperparameter("Learning rate", lr, 0.0..f32::INFINITY)?;
+    check_hyperparameter("Weight decay", weight_decay, 0.0..f32::INFINITY)
+}
+
+/// Fails with the name and bounds of a hyperparameter that lies outside `range`
+pub(crate) fn check_hyperparameter(name: &str, value: f32, range: impl RangeBounds<f32>) -> Result<()> {
+    if !range.contains(&value) {
+        let (open, start) = match range.start_bound() {
+            Bound::Included(&start) => ('[', start),
+            Bound::Excluded(&start) => ('(', start),
+            Bound::Unbounded => ('(', f32::NEG_INFINITY),
+        };
+        let (end, close) = match range.end_bound() {
+            Bound::Included(&end) => (end, ']'),
+            Bound::Excluded(&end) => (end, ')'),
+            Bound::Unbounded => (f32::INFINITY, ')'),
+        };
+        return Err(FerroFlowError::InvalidOperation(format!(
+            "{} must be in {}{}, {}{}, got {}", name, open, start, end, close, value
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,78 @@
+use super::{add_group, check_hyperparameter, load_groups, Optimizer, OptimizerState, ParamGroup};
+use crate::compute::{ComputeBackend, OptimizerUpdate};
+use crate::error::Result;
+use crate::tensor::Tensor;
+
+/// Hyperparameters of [`RmsProp`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RmsPropOptions {
+    pub lr: f32,
+    /// Decay rate of the running average of the squared gradient
+    pub alpha: f32,
+    /// Added to the denominator for numerical stability
+    pub eps: f32,
+    /// L2 penalty added to the gradient
+    pub weight_decay: f32,
+    /// Momentum applied to the normalized update; 0 disables it
+    pub momentum: f32,
+    /// Normalizes by an estimate of the gradient's variance instead of its second moment
+    pub centered: bool,
+}
+
+impl RmsPropOptions {
+    /// RMSProp with learning rate `lr` and `alpha` 0.99, without momentum
+    pub fn new(lr: f32) -> Self {
+        Self { lr, alpha: 0.99, eps: 1e-8, weight_decay: 0.0, momentum: 0.0, centered: false }
+    }
+
+    fn update(&self, lr: f32, weight_decay: f32) -> OptimizerUpdate {
+        let RmsPropOptions { alpha, eps, momentum, centered, .. } = *self;
+        OptimizerUpdate::RmsProp { lr, alpha, eps, weight_decay, momentum, centered }
+    }
+}
+
+/// RMSProp, matching PyTorch's `torch.optim.RMSprop`
+pub struct RmsProp<B: ComputeBackend> {
+    groups: Vec<ParamGroup<B>>,
+    options: RmsPropOptions,
+}
+
+impl<B: ComputeBackend> RmsProp<B> {
+    /// Creates an optimizer with a single group of `params` using the options' learning rate
+    /// and weight decay
+    pub fn new(params: Vec<Tensor<B>>, options: RmsPropOptions) -> Result<Self> {
+        check_hyperparameter("RMSProp alpha", options.alpha, 0.0..1.0)?;
+        check_hyperparameter("RMSProp eps", options.eps, 0.0..f32::INFINITY)?;
+        check_hyperparameter("Momentum", options.momentum, 0.0..f32::INFINITY)?;
+        let mut optimizer = Self { groups: Vec::new(), options };
+        optimizer.add_param_group(ParamGroup::new(params, options.lr, options.weight_decay))?;
+        Ok(optimizer)
+    }
+}
+
+impl<B: ComputeBackend> Optimizer<B> for RmsProp<B> {
+    fn step(&mut self) -> Result<()> {
+        let options = self.options;
+        for group in &mut self.groups {
+            group.step(|lr, weight_decay, _| options.update(lr, weight_decay))?;
+        }
+        Ok(())
+    }
+
+    fn param_groups(&self) -> &[ParamGroup<B>] {
+        &self.groups
+    }
+
+    fn param_groups_mut(&mut self) -> &mut [ParamGroup<B>] {
+        &mut self.groups
+    }
+
+    fn add_param_group(&mut self, group: ParamGroup<B>) -> Result<()> {
+        add_group(&mut self.groups, group)
+    }
+
+    fn load_state_dict(&mut self, state: &OptimizerState) -> Result<()> {
+        let state_len = self.options.update(0.0, 0.0).state_len();
+        load_groups(&mut self.groups, state, state_len)
+    }
+}
@@ -0,0 +1,206 @@
+use super::{check_hyperparameter, Optimizer};
+use crate::compute::ComputeBackend;
+use crate::error::{Result, FerroFlowError};
+use std::f32::consts::PI;
+
+/// A learning rate as a function of the number of scheduler steps taken
+pub trait LrSchedule: Send + Sync {
+    /// Returns the learning rate after `step` steps for a group whose initial rate is `base_lr`
+    fn lr(&self, base_lr: f32, step: usize) -> f32;
+
+    /// Checks the schedule's hyperparameters
+    fn check(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Drives the learning rates of an optimizer's parameter groups with an [`LrSchedule`].
+/// The scheduler does not borrow the optimizer, which is passed to each call instead.
+pub struct LrScheduler<S: LrSchedule> {
+    schedule: S,
+    base_lrs: Vec<f32>,
+    step: usize,
+}
+
+impl<S: LrSchedule> LrScheduler<S> {
+    /// Takes the current learning rate of each group as its base rate and sets the groups
+    /// to the schedule's value at step 0
+    pub fn new<B: ComputeBackend>(optimizer: &mut dyn Optimizer<B>, schedule: S) -> Result<Self> {
+        schedule.check()?;
+        let base_lrs = optimizer.param_groups().iter().map(|group| group.lr).collect();
+        let scheduler = Self { schedule, base_lrs, step: 0 };
+        scheduler.apply(optimizer)?;
+        Ok(scheduler)
+    }
+
+    /// Advances the schedule by one step, usually after `optimizer.step()` once per batch or epoch
+    pub fn step<B: ComputeBackend>(&mut self, optimizer: &mut dyn Optimizer<B>) -> Result<()> {
+        self.step += 1;
+        self.apply(optimizer)
+    }
+
+    /// Returns the number of steps taken
+    pub fn current_step(&self) -> usize {
+        self.step
+    }
+
+    /// Returns the learning rate the schedule assigns to each group at the current step
+    pub fn lrs(&self) -> Vec<f32> {
+        self.base_lrs.iter().map(|&base_lr| self.schedule.lr(base_lr, self.step)).collect()
+    }
+
+    pub fn schedule(&self) -> &S {
+        &self.schedule
+    }
+
+    fn apply<B: ComputeBackend>(&self, optimizer: &mut dyn Optimizer<B>) -> Result<()> {
+        let groups = optimizer.param_groups_mut();
+        if groups.len() != self.base_lrs.len() {
+            return Err(FerroFlowError::InvalidOperation(format!(
+                "Scheduler was created for {} parameter groups, the optimizer has {}", self.base_lrs.len(), groups.len()
+            )));
+        }
+        for (group, lr) in groups.iter_mut().zip(self.lrs()) {
+            group.lr = lr;
+        }
+        Ok(())
+    }
+}
+
+/// Keeps the base learning rate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ConstantLr;
+
+impl LrSchedule for ConstantLr {
+    fn lr(&self, base_lr: f32, _step: usize) -> f32 {
+        base_lr
+    }
+}
+
+/// Multiplies the learning rate by `gamma` every `step_size` steps
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StepLr {
+    pub step_size: usize,
+    pub gamma: f32,
+}
+
+impl LrSchedule for StepLr {
+    fn lr(&self, base_lr: f32, step: usize) -> f32 {
+        base_lr * self.gamma.powi((step / self.step_size) as i32)
+    }
+
+    fn check(&self) -> Result<()> {
+        check_steps("StepLr step size", self.step_size)?;
+        check_hyperparameter("StepLr gamma", self.gamma, 0.0..f32::INFINITY)
+    }
+}
+
+/// Anneals from the base learning rate to `eta_min` along half a cosine over `t_max` steps,
+/// then stays at `eta_min`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CosineAnnealingLr {
+    pub t_max: usize,
+    pub eta_min: f32,
+}
+
+impl LrSchedule for CosineAnnealingLr {
+    fn lr(&self, base_lr: f32, step: usize) -> f32 {
+        let progress = step.min(self.t_max) as f32 / self.t_max as f32;
+        cosine_anneal(base_lr, self.eta_min, progress)
+    }
+
+    fn check(&self) -> Result<()> {
+        check_steps("CosineAnnealingLr t_max", self.t_max)?;
+        check_hyperparameter("CosineAnnealingLr eta_min", self.eta_min, 0.0..f32::INFINITY)
+    }
+}
+
+/// The one-cycle policy: rises from `base_lr / div_factor` to the base rate over the first
+/// `pct_start` of `total_steps`, then anneals to `base_lr / (div_factor * final_div_factor)`,
+/// both along half a cosine. Each group's base rate is its peak rate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OneCycleLr {
+    pub total_steps: usize,
+    pub pct_start: f32,
+    pub div_factor: f32,
+    pub final_div_factor: f32,
+}
+
+impl OneCycleLr {
+    /// A cycle of `total_steps` with PyTorch's defaults: 30% warmup from a 25th of the peak rate
+    pub fn new(total_steps: usize) -> Self {
+        Self { total_steps, pct_start: 0.3, div_factor: 25.0, final_div_factor: 1e4 }
+    }
+}
+
+impl LrSchedule for OneCycleLr {
+    fn lr(&self, base_lr: f32, step: usize) -> f32 {
+        let initial_lr = base_lr / self.div_factor;
+        let min_lr = initial_lr / self.final_div_factor;
+        // The peak is reached at step `warmup_end` and the minimum at the last step
+        let last_step = (self.total_steps - 1) as f32;
+        let warmup_end = (self.pct_start * self.total_steps as f32 - 1.0).clamp(0.0, last_step);
+        let step = (step as f32).min(last_step);
+        if step < warmup_end {
+            cosine_anneal(initial_lr, base_lr, step / warmup_end)
+        } else if warmup_end < last_step {
+            cosine_anneal(base_lr, min_lr, (step - warmup_end) / (last_step - warmup_end))
+        } else {
+            base_lr
+        }
+    }
+
+    fn check(&self) -> Result<()> {
+        if self.total_steps < 2 {
+            return Err(FerroFlowError::InvalidOperation(format!(
+                "OneCycleLr needs at least 2 total steps, got {}", self.total_steps
+            )));
+        }
+        check_hyperparameter("OneCycleLr pct_start", self.pct_start, 0.0..1.0)?;
+        check_hyperparameter("OneCycleLr div_factor", self.div_factor, f32::MIN_POSITIVE..f32::INFINITY)?;
+        check_hyperparameter("OneCycleLr final_div_factor", self.final_div_factor, f32::MIN_POSITIVE..f32::INFINITY)
+    }
+}
+
+/// Ramps linearly from `start_factor * base_lr` to the base rate over `warmup_steps`, then
+/// follows `schedule` with its steps counted from the end of the warmup
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LinearWarmup<S = ConstantLr> {
+    pub warmup_steps: usize,
+    pub start_factor: f32,
+    pub schedule: S,
+}
+
+impl<S: LrSchedule> LinearWarmup<S> {
+    /// Warms up from a learning rate of 0 before following `schedule`
+    pub fn new(warmup_steps: usize, schedule: S) -> Self {
+        Self { warmup_steps, start_factor: 0.0, schedule }
+    }
+}
+
+impl<S: LrSchedule> LrSchedule for LinearWarmup<S> {
+    fn lr(&self, base_lr: f32, step: usize) -> f32 {
+        if step >= self.warmup_steps {
+            return self.schedule.lr(base_lr, step - self.warmup_steps);
+        }
+        let progress = step as f32 / self.warmup_steps as f32;
+        base_lr * (self.start_factor + (1.0 - self.start_factor) * progress)
+    }
+
+    fn check(&self) -> Result<()> {
+        check_hyperparameter("LinearWarmup start_factor", self.start_factor, 0.0..1.0)?;
+        self.schedule.check()
+    }
+}
+
+/// Interpolates from `start` at `progress` 0 to `end` at `progress` 1 along half a cosine
+fn cosine_anneal(start: f32, end: f32, progress: f32) -> f32 {
+    end + (start - end) * 0.5 * (1.0 + (PI * progress).cos())
+}
+
+fn check_steps(name: &str, steps: usize) -> Result<()> {
+    if steps == 0 {
+        return Err(FerroFlowError::InvalidOperation(format!("{} must be positive", name)));
+    }
+    Ok(())
+}
@@ -0,0 +1,81 @@
+use super::{add_group, check_hyperparameter, load_groups, Optimizer, OptimizerState, ParamGroup};
+use crate::compute::{ComputeBackend, OptimizerUpdate};
+use crate::error::{Result, FerroFlowError};
+use crate::tensor::Tensor;
+
+/// Hyperparameters of [`Sgd`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SgdOptions {
+    pub lr: f32,
+    /// Factor of the previous update kept in the momentum buffer; 0 disables momentum
+    pub momentum: f32,
+    /// Scales down the gradient added to the momentum buffer by `1 - dampening`
+    pub dampening: f32,
+    /// L2 penalty added to the gradient
+    pub weight_decay: f32,
+    /// Uses Nesterov momentum, which needs a non-zero momentum and no dampening
+    pub nesterov: bool,
+}
+
+impl SgdOptions {
+    /// Plain gradient descent with learning rate `lr`
+    pub fn new(lr: f32) -> Self {
+        Self { lr, momentum: 0.0, dampening: 0.0, weight_decay: 0.0, nesterov: false }
+    }
+
+    fn update(&self, lr: f32, weight_decay: f32, step: u64) -> OptimizerUpdate {
+        let SgdOptions { momentum, dampening, nesterov, .. } = *self;
+        OptimizerUpdate::Sgd { lr, momentum, dampening, weight_decay, nesterov, step }
+    }
+}
+
+/// Stochastic gradient descent with optional momentum and Nesterov momentum, matching
+/// PyTorch's `torch.optim.SGD`
+pub struct Sgd<B: ComputeBackend> {
+    groups: Vec<ParamGroup<B>>,
+    options: SgdOptions,
+}
+
+impl<B: ComputeBackend> Sgd<B> {
+    /// Creates an optimizer with a single group of `params` using the options' learning rate
+    /// and weight decay
+    pub fn new(params: Vec<Tensor<B>>, options: SgdOptions) -> Result<Self> {
+        check_hyperparameter("Momentum", options.momentum, 0.0..f32::INFINITY)?;
+        check_hyperparameter("Dampening", options.dampening, 0.0..f32::INFINITY)?;
+        if options.nesterov && (options.momentum == 0.0 || options.dampening != 0.0) {
+            return Err(FerroFlowError::InvalidOperation(
+                "Nesterov momentum requires a non-zero momentum and zero dampening".into()
+            ));
+        }
+        let mut optimizer = Self { groups: Vec::new(), options };
+        optimizer.add_param_group(ParamGroup::new(params, options.lr, options.weight_decay))?;
+        Ok(optimizer)
+    }
+}
+
+impl<B: ComputeBackend> Optimizer<B> for Sgd<B> {
+    fn step(&mut self) -> Result<()> {
+        let options = self.options;
+        for group in &mut self.groups {
+            group.step(|lr, weight_decay, step| options.update(lr, weight_decay, step))?;
+        }
+        Ok(())
+    }
+
+    fn param_groups(&self) -> &[ParamGroup<B>] {
+        &self.groups
+    }
+
+    fn param_groups_mut(&mut self) -> &mut [ParamGroup<B>] {
+        &mut self.groups
+    }
+
+    fn add_param_group(&mut self, group: ParamGroup<B>) -> Result<()> {
+        add_group(&mut self.groups, group)
+    }
+
+    fn load_state_dict(&mut self, state: &OptimizerState) -> Result<()> {
+        let state_len = self.options.update(0.0, 0.0, 1).state_len();
+        load_groups(&mut self.groups, state, state_len)
+    }
+}
@@ -0,0 +1,348 @@
+use super::*;
+use crate::autograd::tests::assert_close;
+use crate::compute::CPUBackend;
+use crate::nn::{Linear, Module};
+use crate::tensor::Shape;
+
+type T = Tensor<CPUBackend>;
+
+const INITIAL: [f32; 4] = [1.0, -2.0, 0.5, 3.0];
+
+fn parameter(ctx: &Arc<<CPUBackend as ComputeBackend>::Context>, data: &[f32]) -> T {
+    Tensor::new(Arc::clone(ctx), Shape::new(vec![data.len()]), data).unwrap().requires_grad()
+}
+
+/// Runs `steps` optimizer steps on `0.5 * sum(p^2)`, whose gradient is `p` itself
+fn minimize(optimizer: &mut dyn Optimizer<CPUBackend>, params: &[&T], steps: usize) -> Result<()> {
+    for _ in 0..steps {
+        optimizer.zero_grad();
+        for param in params {
+            param.multiply(param)?.sum_all()?.scalar_multiply(0.5)?.backward()?;
+        }
+        optimizer.step()?;
+    }
+    Ok(())
+}
+
+/// Applies `update(p, g, step)` to each initial value `steps` times with `g = p`, in f64
+fn reference<S: Default>(steps: usize, mut update: impl FnMut(&mut f64, f64, u64, &mut S)) -> Vec<f32> {
+    INITIAL.iter().map(|&p| {
+        let (mut p, mut state) = (p as f64, S::default());
+        for step in 1..=steps as u64 {
+            let g = p;
+            update(&mut p, g, step, &mut state);
+        }
+        p as f32
+    }).collect()
+}
+
+#[test]
+fn test_sgd() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let variants = [
+        SgdOptions::new(0.1),
+        SgdOptions { momentum: 0.9, dampening: 0.5, weight_decay: 0.01, ..SgdOptions::new(0.1) },
+        SgdOptions { momentum: 0.9, nesterov: true, ..SgdOptions::new(0.1) },
+    ];
+    for options in variants {
+        let param = parameter(&ctx, &INITIAL);
+        let mut sgd = Sgd::new(vec![param.clone()], options)?;
+        minimize(&mut sgd, &[&param], 5)?;
+
+        let SgdOptions { lr, momentum, dampening, weight_decay, nesterov } = options;
+        let (lr, momentum, dampening, weight_decay) = (lr as f64, momentum as f64, dampening as f64, weight_decay as f64);
+        let expected = reference(5, |p, g, step, buf: &mut f64| {
+            let mut g = g + weight_decay * *p;
+            if momentum != 0.0 {
+                *buf = if step == 1 { g } else { momentum * *buf + (1.0 - dampening) * g };
+                g = if nesterov { g + momentum * *buf } else { *buf };
+            }
+            *p -= lr * g;
+        });
+        assert_close(&param.data()?, &expected, 1e-5);
+    }
+
+    let nesterov = SgdOptions { nesterov: true, ..SgdOptions::new(0.1) };
+    assert!(Sgd::new(vec![parameter(&ctx, &INITIAL)], nesterov).is_err());
+    assert!(Sgd::new(vec![parameter(&ctx, &INITIAL)], SgdOptions::new(-0.1)).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_adam() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let variants = [
+        AdamOptions::new(0.01),
+        AdamOptions { weight_decay: 0.1, ..AdamOptions::new(0.01) },
+        AdamOptions { weight_decay: 0.1, ..AdamOptions::adamw(0.01) },
+    ];
+    for options in variants {
+        let param = parameter(&ctx, &INITIAL);
+        let mut adam = Adam::new(vec![param.clone()], options)?;
+        minimize(&mut adam, &[&param], 5)?;
+
+        let AdamOptions { lr, betas: (beta1, beta2), eps, weight_decay, decoupled_weight_decay } = options;
+        let (lr, beta1, beta2) = (lr as f64, beta1 as f64, beta2 as f64);
+        let (eps, weight_decay) = (eps as f64, weight_decay as f64);
+        let expected = reference(5, |p, g, step, (m, v): &mut (f64, f64)| {
+            let g = if decoupled_weight_decay {
+                *p *= 1.0 - lr * weight_decay;
+                g
+            } else {
+                g + weight_decay * *p
+            };
+            *m = beta1 * *m + (1.0 - beta1) * g;
+            *v = beta2 * *v + (1.0 - beta2) * g * g;
+            let m_hat = *m / (1.0 - beta1.powi(step as i32));
+            let v_hat = *v / (1.0 - beta2.powi(step as i32));
+            *p -= lr * m_hat / (v_hat.sqrt() + eps);
+        });
+        assert_close(&param.data()?, &expected, 1e-5);
+    }
+
+    let betas = AdamOptions { betas: (0.9, 1.0), ..AdamOptions::new(0.01) };
+    assert!(Adam::new(vec![parameter(&ctx, &INITIAL)], betas).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_rmsprop() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let variants = [
+        RmsPropOptions::new(0.01),
+        RmsPropOptions { momentum: 0.9, centered: true, weight_decay: 0.1, ..RmsPropOptions::new(0.01) },
+    ];
+    for options in variants {
+        let param = parameter(&ctx, &INITIAL);
+        let mut rmsprop = RmsProp::new(vec![param.clone()], options)?;
+        minimize(&mut rmsprop, &[&param], 5)?;
+
+        let RmsPropOptions { lr, alpha, eps, weight_decay, momentum, centered } = options;
+        let (lr, alpha, eps) = (lr as f64, alpha as f64, eps as f64);
+        let (weight_decay, momentum) = (weight_decay as f64, momentum as f64);
+        let expected = reference(5, |p, g, _, (square_avg, grad_avg, buf): &mut (f64, f64, f64)| {
+            let g = g + weight_decay * *p;
+            *square_avg = alpha * *square_avg + (1.0 - alpha) * g * g;
+            let mut variance = *square_avg;
+            if centered {
+                *grad_avg = alpha * *grad_avg + (1.0 - alpha) * g;
+                variance -= *grad_avg * *grad_avg;
+            }
+            let update = g / (variance.sqrt() + eps);
+            if momentum != 0.0 {
+                *buf = momentum * *buf + update;
+                *p -= lr * *buf;
+            } else {
+                *p -= lr * update;
+            }
+        });
+        assert_close(&param.data()?, &expected, 1e-5);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_param_groups() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let (first, second, frozen) = (parameter(&ctx, &[1.0, 2.0]), parameter(&ctx, &[1.0, 2.0]), parameter(&ctx, &[1.0]));
+    let mut sgd = Sgd::new(vec![first.clone(), frozen.clone()], SgdOptions::new(0.5))?;
+    sgd.add_param_group(ParamGroup::new(vec![second.clone()], 0.25, 1.0))?;
+    assert!(sgd.add_param_group(ParamGroup::new(Vec::new(), f32::NAN, 0.0)).is_err());
+    assert_eq!(sgd.param_groups().len(), 2);
+
+    // Each group uses its own learning rate and weight decay; parameters without a gradient are skipped
+    minimize(&mut sgd, &[&first, &second], 1)?;
+    assert_eq!(first.data()?, vec![0.5, 1.0]);
+    assert_eq!(second.data()?, vec![0.5, 1.0]);
+    assert_eq!(frozen.data()?, vec![1.0]);
+
+    sgd.param_groups_mut()[0].lr = 1.0;
+    minimize(&mut sgd, &[&first], 1)?;
+    assert_eq!(first.data()?, vec![0.0, 0.0]);
+
+    sgd.zero_grad();
+    assert!(first.grad().is_none() && second.grad().is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_state_dict() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let options = AdamOptions::adamw(0.01);
+    let param = parameter(&ctx, &INITIAL);
+    let mut adam = Adam::new(vec![param.clone()], options)?;
+    let untouched = adam.state_dict()?;
+    assert_eq!(untouched.groups[0].params, vec![ParamState::default()]);
+
+    minimize(&mut adam, &[&param], 3)?;
+    let state = adam.state_dict()?;
+    assert_eq!(state.groups[0].params[0].step, 3);
+    assert_eq!(state.groups[0].params[0].buffers.len(), 2);
+
+    // A fresh optimizer restored from the state continues exactly where the first left off
+    let restored = parameter(&ctx, &param.data()?);
+    let mut resumed = Adam::new(vec![restored.clone()], AdamOptions::adamw(1.0))?;
+    resumed.load_state_dict(&state)?;
+    assert_eq!(resumed.state_dict()?, state);
+    minimize(&mut adam, &[&param], 2)?;
+    minimize(&mut resumed, &[&restored], 2)?;
+    assert_eq!(restored.data()?, param.data()?);
+
+    // Mismatched states are rejected without changing the optimizer
+    let before = resumed.state_dict()?;
+    let mut wrong_size = state.clone();
+    wrong_size.groups[0].params[0].buffers[1].pop();
+    assert!(resumed.load_state_dict(&wrong_size).is_err());
+    assert!(resumed.load_state_dict(&OptimizerState::default()).is_err());
+    let mut sgd = Sgd::new(vec![restored.clone()], SgdOptions { momentum: 0.9, ..SgdOptions::new(0.1) })?;
+    assert!(sgd.load_state_dict(&state).is_err());
+    assert_eq!(resumed.state_dict()?, before);
+
+    Ok(())
+}
+
+#[test]
+fn test_step_updates_shared_buffers() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+
+    // Parameters returned by a module share its buffers, so the layer sees the update
+    let weight = T::new(Arc::clone(&ctx), Shape::new(vec![1, 2]), &[1.0, -1.0])?;
+    let layer = Linear::from_parameters(weight, None)?;
+    let mut sgd = Sgd::new(layer.parameters(), SgdOptions::new(0.5))?;
+    let x = Tensor::new(Arc::clone(&ctx), Shape::new(vec![1, 2]), &[2.0, 4.0])?;
+    layer.forward(&x)?.sum_all()?.backward()?;
+    sgd.step()?;
+    assert_eq!(layer.weight().data()?, vec![0.0, -3.0]);
+
+    // A graph recorded before the step saved the weight, so its backward pass is rejected
+    let input = Tensor::new(Arc::clone(&ctx), Shape::new(vec![1, 2]), &[1.0, 1.0])?.requires_grad();
+    let output = layer.forward(&input)?.sum_all()?;
+    sgd.step()?;
+    assert_eq!(layer.weight().data()?, vec![-1.0, -5.0]);
+    assert!(matches!(output.backward(), Err(FerroFlowError::InvalidOperation(_))));
+    assert!(input.grad().is_none());
+
+    // A gradient that shares the parameter's own buffer is read before the parameter is written
+    let param = parameter(&ctx, &[2.0, 4.0]);
+    param.backward_with_grad(&param)?;
+    let mut sgd = Sgd::new(vec![param.clone()], SgdOptions::new(0.25))?;
+    sgd.step()?;
+    assert_eq!(param.data()?, vec![1.5, 3.0]);
+
+    Ok(())
+}
+
+#[test]
+fn test_train_step_releases_every_buffer() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    {
+        let layer = Linear::new(Arc::clone(&ctx), 6, 3, true)?;
+        let mut adam = Adam::new(layer.parameters(), AdamOptions::new(0.01))?;
+        let x = T::new(Arc::clone(&ctx), Shape::new(vec![4, 6]), &(0..24).map(|i| (i % 7) as f32 - 3.0).collect::<Vec<_>>())?;
+        let target = Tensor::from_slice(Arc::clone(&ctx), Shape::new(vec![4]), &[0i64, 2, 1, 2])?;
+        for _ in 0..2 {
+            adam.zero_grad();
+            crate::loss::cross_entropy(&layer.forward(&x)?, &target, crate::loss::CrossEntropyOptions::default())?.backward()?;
+            adam.step()?;
+        }
+        assert!(ctx.memory_stats().live_bytes > 0);
+    }
+
+    // Every buffer the step allocated, including gradients and optimizer state, went back to the cache
+    let stats = ctx.memory_stats();
+    assert_eq!(stats.live_bytes, 0, "{:?}", stats);
+    assert_eq!(stats.releases, stats.allocations);
+    Ok(())
+}
+
+#[test]
+fn test_lr_schedules() -> Result<()> {
+    let step = StepLr { step_size: 2, gamma: 0.5 };
+    assert_eq!((0..5).map(|s| step.lr(1.0, s)).collect::<Vec<_>>(), vec![1.0, 1.0, 0.5, 0.5, 0.25]);
+
+    let cosine = CosineAnnealingLr { t_max: 4, eta_min: 0.1 };
+    assert_close(&[0, 2, 4, 10].map(|s| cosine.lr(1.0, s)), &[1.0, 0.55, 0.1, 0.1], 1e-5);
+
+    // 10 steps warming up over the first 30%: the peak is at step 2 and the minimum at step 9
+    let one_cycle = OneCycleLr::new(10);
+    let lrs: Vec<f32> = (0..12).map(|s| one_cycle.lr(1.0, s)).collect();
+    assert_close(&[lrs[0], lrs[1], lrs[2], lrs[9], lrs[11]], &[0.04, 0.52, 1.0, 4e-6, 4e-6], 1e-5);
+    assert!(lrs[2..10].windows(2).all(|pair| pair[1] < pair[0]));
+
+    let warmup = LinearWarmup::new(4, cosine);
+    assert_close(&[0, 1, 4, 6].map(|s| warmup.lr(2.0, s)), &[0.0, 0.5, 2.0, 1.05], 1e-5);
+    assert_eq!(LinearWarmup::new(2, ConstantLr).lr(2.0, 7), 2.0);
+
+    // The scheduler scales each group's own initial rate
+    let ctx = CPUBackend::new()?;
+    let mut sgd = Sgd::new(vec![parameter(&ctx, &INITIAL)], SgdOptions::new(0.1))?;
+    sgd.add_param_group(ParamGroup::new(vec![parameter(&ctx, &INITIAL)], 1.0, 0.0))?;
+    let mut scheduler = LrScheduler::new(&mut sgd, step)?;
+    for _ in 0..2 {
+        scheduler.step(&mut sgd)?;
+    }
+    assert_eq!(scheduler.current_step(), 2);
+    assert_eq!(scheduler.lrs(), vec![0.05, 0.5]);
+    assert_eq!(sgd.param_groups().iter().map(|group| group.lr).collect::<Vec<_>>(), vec![0.05, 0.5]);
+
+    sgd.add_param_group(ParamGroup::new(Vec::new(), 1.0, 0.0))?;
+    assert!(scheduler.step(&mut sgd).is_err());
+    assert!(LrScheduler::new(&mut sgd, StepLr { step_size: 0, gamma: 0.5 }).is_err());
+    assert!(LrScheduler::new(&mut sgd, OneCycleLr::new(1)).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_clip_grad_norm() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let backward = |params: &[T], grads: [&[f32]; 2]| -> Result<()> {
+        for (param, grad) in params.iter().zip(grads) {
+            param.zero_grad();
+            param.backward_with_grad(&Tensor::new(Arc::clone(&ctx), param.shape().clone(), grad)?)?;
+        }
+        Ok(())
+    };
+    let params = [parameter(&ctx, &[0.0, 0.0]), parameter(&ctx, &[0.0]), parameter(&ctx, &[0.0])];
+
+    // The 2-norm of [3, 4, 12] is 13; the parameter without a gradient is left alone
+    backward(&params, [&[3.0, 4.0], &[12.0]])?;
+    let norm = clip_grad_norm(&params, 6.5, 2.0)?.unwrap();
+    assert_eq!(norm.shape().dims(), &[] as &[usize]);
+    assert_close(&norm.data()?, &[13.0], 1e-5);
+    assert_close(&params[0].grad().unwrap().data()?, &[1.5, 2.0], 1e-5);
+    assert_close(&params[1].grad().unwrap().data()?, &[6.0], 1e-5);
+    assert!(params[2].grad().is_none());
+
+    // Gradients within the limit are unchanged
+    backward(&params, [&[3.0, 4.0], &[12.0]])?;
+    clip_grad_norm(&params, 20.0, 2.0)?;
+    assert_eq!(params[0].grad().unwrap().data()?, vec![3.0, 4.0]);
+
+    backward(&params, [&[3.0, -4.0], &[-12.0]])?;
+    assert_close(&clip_grad_norm(&params, 100.0, 1.0)?.unwrap().data()?, &[19.0], 1e-5);
+    let norm = clip_grad_norm(&params, 6.0, f32::INFINITY)?.unwrap();
+    assert_eq!(norm.data()?, vec![12.0]);
+    assert_close(&params[1].grad().unwrap().data()?, &[-6.0], 1e-5);
+
+    assert!(clip_grad_norm(&params[2..], 1.0, 2.0)?.is_none());
+    assert!(clip_grad_norm(&params, 1.0, 0.5).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_clip_grad_value() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let param = parameter(&ctx, &[0.0, 0.0, 0.0]);
+    param.backward_with_grad(&T::new(Arc::clone(&ctx), Shape::new(vec![3]), &[-3.0, 0.5, 2.0])?)?;
+    clip_grad_value(std::slice::from_ref(&param), 1.0)?;
+    assert_eq!(param.grad().unwrap().data()?, vec![-1.0, 0.5, 1.0]);
+    assert!(clip_grad_value(std::slice::from_ref(&param), -1.0).is_err());
+
+    Ok(())
+}
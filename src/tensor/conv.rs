@@ -0,0 +1,199 @@
+use super::{Shape, Tensor};
+use crate::autograd;
+use crate::compute::{ComputeBackend, Conv2dParams};
+use crate::error::{Result, FerroFlowError};
+use std::sync::Arc;
+
+/// Hyper-parameters shared by [`Tensor::conv2d`] and [`Tensor::conv_transpose2d`],
+/// given as `(height, width)` pairs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Conv2dOptions {
+    pub stride: (usize, usize),
+    pub padding: (usize, usize),
+    pub dilation: (usize, usize),
+    pub groups: usize,
+    /// Extra size added to one side of a transposed convolution's output to pick among the
+    /// shapes a strided convolution maps to the same size. Must be smaller than the stride.
+    /// Ignored by `conv2d`.
+    pub output_padding: (usize, usize),
+}
+
+impl Default for Conv2dOptions {
+    fn default() -> Self {
+        Self { stride: (1, 1), padding: (0, 0), dilation: (1, 1), groups: 1, output_padding: (0, 0) }
+    }
+}
+
+impl<B: ComputeBackend> Tensor<B> {
+    /// 2D convolution of an NCHW input with a `[C_out, C_in / groups, kh, kw]` weight
+    /// and an optional `[C_out]` bias.
+    pub fn conv2d(&self, weight: &Self, bias: Option<&Self>, options: Conv2dOptions) -> Result<Self> {
+        let [batch_size, in_channels, height, width] = self.nchw_dims("conv2d input")?;
+        let [out_channels, group_channels, kh, kw] = weight.nchw_dims("conv2d weight")?;
+        check_channels(in_channels, group_channels, out_channels, options.groups)?;
+
+        let params = Conv2dParams {
+            batch_size,
+            in_channels,
+            out_channels,
+            input_size: (height, width),
+            kernel_size: (kh, kw),
+            stride: options.stride,
+            padding: options.padding,
+            dilation: options.dilation,
+            groups: options.groups,
+        };
+        let (out_h, out_w) = check_geometry(&params)?;
+
+        let (input, weight_dense) = (self.materialize()?, weight.materialize()?);
+        let buffer = input.with_buffers(&weight_dense, |input, weight| B::conv2d(&self.ctx, input, weight, &params))?;
+        let output = Self::from_buffer(Arc::clone(&self.ctx), buffer, Shape::new(vec![batch_size, out_channels, out_h, out_w]));
+
+        let (input_needs_grad, weight_needs_grad) = (self.needs_grad(), weight.needs_grad());
+        let saved = vec![input.saved_version(), weight_dense.saved_version()];
+        let output = autograd::record("conv2d", output, &[self, weight], saved, move |grad| {
+            let grad = grad.materialize()?;
+            let grad_input = input_needs_grad.then(|| -> Result<Self> {
+                let buffer = grad.with_buffers(&weight_dense, |grad_buffer, weight| B::conv2d_backward_input(&grad.ctx, grad_buffer, weight, &params))?;
+                Ok(Self::from_buffer(Arc::clone(&grad.ctx), buffer, input.shape().clone()))
+            }).transpose()?;
+            let grad_weight = weight_needs_grad.then(|| -> Result<Self> {
+                let buffer = input.with_buffers(&grad, |input, grad_buffer| B::conv2d_backward_weight(&grad.ctx, input, grad_buffer, &params))?;
+                Ok(Self::from_buffer(Arc::clone(&grad.ctx), buffer, weight_dense.shape().clone()))
+            }).transpose()?;
+            Ok(vec![grad_input, grad_weight])
+        });
+
+        output.add_channel_bias(bias, out_channels)
+    }
+
+    /// 2D transposed convolution ("deconvolution") of an NCHW input with a
+    /// `[C_in, C_out / groups, kh, kw]` weight and an optional `[C_out]` bias.
+    /// It is the gradient of `conv2d` with respect to its input, so with matching options it
+    /// maps a convolution's output shape back to its input shape.
+    pub fn conv_transpose2d(&self, weight: &Self, bias: Option<&Self>, options: Conv2dOptions) -> Result<Self> {
+        let [batch_size, in_channels, height, width] = self.nchw_dims("conv_transpose2d input")?;
+        let [weight_in, group_channels, kh, kw] = weight.nchw_dims("conv_transpose2d weight")?;
+        if weight_in != in_channels {
+            return Err(FerroFlowError::ShapeMismatch(format!(
+                "conv_transpose2d weight expects {} input channels, got {}", weight_in, in_channels
+            )));
+        }
+        let out_channels = group_channels * options.groups;
+        if options.output_padding.0 >= options.stride.0.max(1) || options.output_padding.1 >= options.stride.1.max(1) {
+            return Err(FerroFlowError::InvalidOperation(format!(
+                "output_padding {:?} must be smaller than the stride {:?}", options.output_padding, options.stride
+            )));
+        }
+
+        let span = |input: usize, kernel: usize, stride: usize, padding: usize, dilation: usize, extra: usize| {
+            ((input.saturating_sub(1) * stride + dilation * kernel.saturating_sub(1) + extra + 1).checked_sub(2 * padding))
+                .filter(|&size| size > 0)
+        };
+        let (Some(out_h), Some(out_w)) = (
+            span(height, kh, options.stride.0, options.padding.0, options.dilation.0, options.output_padding.0),
+            span(width, kw, options.stride.1, options.padding.1, options.dilation.1, options.output_padding.1),
+        ) else {
+            return Err(FerroFlowError::ShapeMismatch(format!(
+                "Padding {:?} leaves no output for a {}x{} input and {}x{} kernel", options.padding, height, width, kh, kw
+            )));
+        };
+
+        // The convolution whose input gradient this operation computes
+        let params = Conv2dParams {
+            batch_size,
+            in_channels: out_channels,
+            out_channels: in_channels,
+            input_size: (out_h, out_w),
+            kernel_size: (kh, kw),
+            stride: options.stride,
+            padding: options.padding,
+            dilation: options.dilation,
+            groups: options.groups,
+        };
+        check_channels(out_channels, group_channels, in_channels, options.groups)?;
+        if check_geometry(&params)? != (height, width) {
+            return Err(FerroFlowError::ShapeMismatch(format!(
+                "conv_transpose2d options {:?} are inconsistent with a {}x{} input", options, height, width
+            )));
+        }
+
+        let (input, weight_dense) = (self.materialize()?, weight.materialize()?);
+        let buffer = input.with_buffers(&weight_dense, |input, weight| B::conv2d_backward_input(&self.ctx, input, weight, &params))?;
+        let output = Self::from_buffer(Arc::clone(&self.ctx), buffer, Shape::new(vec![batch_size, out_channels, out_h, out_w]));
+
+        let (input_needs_grad, weight_needs_grad) = (self.needs_grad(), weight.needs_grad());
+        let saved = vec![input.saved_version(), weight_dense.saved_version()];
+        let output = autograd::record("conv_transpose2d", output, &[self, weight], saved, move |grad| {
+            let grad = grad.materialize()?;
+            let grad_input = input_needs_grad.then(|| -> Result<Self> {
+                let buffer = grad.with_buffers(&weight_dense, |grad_buffer, weight| B::conv2d(&grad.ctx, grad_buffer, weight, &params))?;
+                Ok(Self::from_buffer(Arc::clone(&grad.ctx), buffer, input.shape().clone()))
+            }).transpose()?;
+            let grad_weight = weight_needs_grad.then(|| -> Result<Self> {
+                let buffer = grad.with_buffers(&input, |grad_buffer, input| B::conv2d_backward_weight(&grad.ctx, grad_buffer, input, &params))?;
+                Ok(Self::from_buffer(Arc::clone(&grad.ctx), buffer, weight_dense.shape().clone()))
+            }).transpose()?;
+            Ok(vec![grad_input, grad_weight])
+        });
+
+        output.add_channel_bias(bias, out_channels)
+    }
+
+    /// Returns the four dimensions of an NCHW tensor or convolution weight.
+    pub(super) fn nchw_dims(&self, name: &str) -> Result<[usize; 4]> {
+        self.check_f32(&[], name)?;
+        self.shape().dims().try_into().map_err(|_| FerroFlowError::ShapeMismatch(
+            format!("{} must have 4 dimensions, got shape {:?}", name, self.shape().dims())
+        ))
+    }
+
+    /// Adds a `[channels]` bias to every pixel of an NCHW tensor.
+    fn add_channel_bias(self, bias: Option<&Self>, channels: usize) -> Result<Self> {
+        let Some(bias) = bias else {
+            return Ok(self);
+        };
+        if bias.shape().dims() != [channels] {
+            return Err(FerroFlowError::ShapeMismatch(format!(
+                "Convolution bias must have shape [{}], got {:?}", channels, bias.shape().dims()
+            )));
+        }
+        self.add(&bias.reshape(Shape::new(vec![1, channels, 1, 1]))?)
+    }
+}
+
+fn check_channels(in_channels: usize, group_channels: usize, out_channels: usize, groups: usize) -> Result<()> {
+    if groups == 0 || !in_channels.is_multiple_of(groups) || !out_channels.is_multiple_of(groups) {
+        return Err(FerroFlowError::InvalidOperation(format!(
+            "{} groups must divide both {} input and {} output channels", groups, in_channels, out_channels
+        )));
+    }
+    if in_channels / groups != group_channels {
+        return Err(FerroFlowError::ShapeMismatch(format!(
+            "Weight expects {} channels per group, but {} input channels in {} groups give {}",
+            group_channels, in_channels, groups, in_channels / groups
+        )));
+    }
+    Ok(())
+}
+
+/// Validates strides, dilations and kernel sizes and returns the convolution's output size.
+fn check_geometry(params: &Conv2dParams) -> Result<(usize, usize)> {
+    let (stride, dilation, kernel) = (params.stride, params.dilation, params.kernel_size);
+    if stride.0 == 0 || stride.1 == 0 || dilation.0 == 0 || dilation.1 == 0 {
+        return Err(FerroFlowError::InvalidOperation(format!(
+            "Stride {:?} and dilation {:?} must be positive", stride, dilation
+        )));
+    }
+    if kernel.0 == 0 || kernel.1 == 0 {
+        return Err(FerroFlowError::ShapeMismatch(format!("Kernel size {:?} must be positive", kernel)));
+    }
+
+    match params.output_size() {
+        (0, _) | (_, 0) => Err(FerroFlowError::ShapeMismatch(format!(
+            "Input of size {:?} with padding {:?} is smaller than the {:?} kernel with dilation {:?}",
+            params.input_size, params.padding, kernel, dilation
+        ))),
+        size => Ok(size),
+    }
+}
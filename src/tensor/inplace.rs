@@ -0,0 +1,181 @@
+use super::{MatmulPlan, Shape, Tensor};
+use crate::autograd;
+use crate::compute::{BinaryOp, BufferView, ComputeBackend};
+use crate::error::{Result, FerroFlowError};
+
+/// In-place operations and `*_into` variants, which write into the buffer of an existing dense
+/// tensor instead of allocating a new one.
+///
+/// These writes are not recorded for autograd, so while gradients are enabled they reject
+/// operands that require grad. They are visible through every tensor sharing the written
+/// buffer, and a backward pass that saved one of those tensors fails instead of computing
+/// gradients from the new values.
+impl<B: ComputeBackend> Tensor<B> {
+    /// Adds `other`, broadcast to this tensor's shape, in place
+    pub fn add_(&self, other: &Self) -> Result<()> {
+        self.binary_assign(other, BinaryOp::Add, "add_")
+    }
+
+    /// Subtracts `other`, broadcast to this tensor's shape, in place
+    pub fn sub_(&self, other: &Self) -> Result<()> {
+        self.binary_assign(other, BinaryOp::Subtract, "sub_")
+    }
+
+    /// Multiplies by `other`, broadcast to this tensor's shape, in place
+    pub fn mul_(&self, other: &Self) -> Result<()> {
+        self.binary_assign(other, BinaryOp::Multiply, "mul_")
+    }
+
+    /// Divides by `other`, broadcast to this tensor's shape, in place
+    pub fn div_(&self, other: &Self) -> Result<()> {
+        self.binary_assign(other, BinaryOp::Divide, "div_")
+    }
+
+    /// Multiplies every element by `scalar` in place
+    pub fn scale_(&self, scalar: f32) -> Result<()> {
+        self.check_f32(&[], "scale_")?;
+        self.check_writable(&[], "scale_")?;
+        B::scalar_multiply_assign(&self.ctx, &mut self.buffer_mut(), scalar, self.shape().size())
+    }
+
+    /// Overwrites this tensor with the elements of `src`, broadcast to this tensor's shape.
+    /// Both tensors must have the same element type.
+    pub fn copy_from(&self, src: &Self) -> Result<()> {
+        if src.dtype != self.dtype {
+            return Err(FerroFlowError::DTypeMismatch(format!(
+                "Cannot copy a {} tensor into a {} tensor", src.dtype, self.dtype
+            )));
+        }
+        self.check_writable(&[src], "copy_from")?;
+        let src = self.unaliased(src)?;
+        let layout = src.layout.broadcast_to(self.shape())?;
+        let src_buffer = src.buffer();
+        B::copy_strided_into(
+            &self.ctx,
+            &src_buffer,
+            self.dtype,
+            self.shape().dims(),
+            layout.strides(),
+            layout.offset(),
+            &mut self.buffer_mut(),
+        )
+    }
+
+    /// Writes `self + other`, with broadcasting, into `out`
+    pub fn add_into(&self, other: &Self, out: &Self) -> Result<()> {
+        self.binary_into(other, BinaryOp::Add, out, "add_into")
+    }
+
+    /// Writes `self - other`, with broadcasting, into `out`
+    pub fn sub_into(&self, other: &Self, out: &Self) -> Result<()> {
+        self.binary_into(other, BinaryOp::Subtract, out, "sub_into")
+    }
+
+    /// Writes `self * other`, with broadcasting, into `out`
+    pub fn mul_into(&self, other: &Self, out: &Self) -> Result<()> {
+        self.binary_into(other, BinaryOp::Multiply, out, "mul_into")
+    }
+
+    /// Writes `self / other`, with broadcasting, into `out`
+    pub fn div_into(&self, other: &Self, out: &Self) -> Result<()> {
+        self.binary_into(other, BinaryOp::Divide, out, "div_into")
+    }
+
+    /// Writes `self * scalar` into `out`, which must have the same shape
+    pub fn scale_into(&self, scalar: f32, out: &Self) -> Result<()> {
+        self.check_f32(&[out], "scale_into")?;
+        out.check_output(self.shape(), &[self], "scale_into")?;
+        let input = out.unaliased(self)?.materialize()?;
+        let input_buffer = input.buffer();
+        B::scalar_multiply_into(&self.ctx, &input_buffer, scalar, self.shape().size(), &mut out.buffer_mut())
+    }
+
+    /// Writes the matrix product `self x other` into `out`, which must have the shape
+    /// `matmul` would return. Batched operands are supported like in `matmul`.
+    pub fn matmul_into(&self, other: &Self, out: &Self) -> Result<()> {
+        let MatmulPlan { lhs, rhs, batch_size, m, n, k, transpose_a, transpose_b } = self.plan_matmul(other, false, false)?;
+        let shape = match batch_size {
+            Some(batch_size) => Shape::new_batched(batch_size, m, n),
+            None => Shape::new(vec![m, n]),
+        };
+        self.check_f32(&[out], "matmul_into")?;
+        out.check_output(&shape, &[self, other], "matmul_into")?;
+
+        let (lhs, rhs) = (out.unaliased(&lhs)?, out.unaliased(&rhs)?);
+        lhs.with_buffers(&rhs, |lhs_buffer, rhs_buffer| B::matmul_into(
+            &self.ctx,
+            lhs_buffer,
+            rhs_buffer,
+            &mut out.buffer_mut(),
+            batch_size.unwrap_or(1),
+            m,
+            n,
+            k,
+            transpose_a,
+            transpose_b,
+        ))
+    }
+
+    fn binary_assign(&self, other: &Self, op: BinaryOp, name: &str) -> Result<()> {
+        self.check_f32(&[other], name)?;
+        self.check_writable(&[other], name)?;
+        let other = self.unaliased(other)?;
+        let layout = other.layout.broadcast_to(self.shape())?;
+        let other_buffer = other.buffer();
+        let view = BufferView { buffer: &*other_buffer, strides: layout.strides(), offset: layout.offset() };
+        B::binary_op_assign(&self.ctx, op, &mut self.buffer_mut(), view, self.shape().dims())
+    }
+
+    fn binary_into(&self, other: &Self, op: BinaryOp, out: &Self, name: &str) -> Result<()> {
+        self.check_f32(&[other, out], name)?;
+        let shape = self.shape().broadcast_with(other.shape())?;
+        out.check_output(&shape, &[self, other], name)?;
+
+        let (lhs, rhs) = (out.unaliased(self)?, out.unaliased(other)?);
+        let (lhs_layout, rhs_layout) = (lhs.layout.broadcast_to(&shape)?, rhs.layout.broadcast_to(&shape)?);
+        lhs.with_buffers(&rhs, |lhs_buffer, rhs_buffer| B::binary_op_into(
+            &self.ctx,
+            op,
+            BufferView { buffer: lhs_buffer, strides: lhs_layout.strides(), offset: lhs_layout.offset() },
+            BufferView { buffer: rhs_buffer, strides: rhs_layout.strides(), offset: rhs_layout.offset() },
+            shape.dims(),
+            &mut out.buffer_mut(),
+        ))
+    }
+
+    /// Checks that this tensor can be written in place: it must be dense, and with gradients
+    /// enabled neither it nor the `operands` may require grad
+    fn check_writable(&self, operands: &[&Self], name: &str) -> Result<()> {
+        if !self.is_dense() {
+            return Err(FerroFlowError::InvalidOperation(format!(
+                "{} writes in place and needs a dense tensor, got layout {:?}", name, self.layout
+            )));
+        }
+        if autograd::is_grad_enabled() && (self.needs_grad() || operands.iter().any(|t| t.needs_grad())) {
+            return Err(FerroFlowError::InvalidOperation(format!(
+                "{} is not recorded for autograd; use it on tensors that require grad only inside no_grad", name
+            )));
+        }
+        Ok(())
+    }
+
+    /// Checks that this tensor can receive a result of `shape` computed from `operands`
+    fn check_output(&self, shape: &Shape, operands: &[&Self], name: &str) -> Result<()> {
+        if self.shape() != shape {
+            return Err(FerroFlowError::ShapeMismatch(format!(
+                "{} produces shape {:?} but the output has shape {:?}", name, shape.dims(), self.shape().dims()
+            )));
+        }
+        self.check_writable(operands, name)
+    }
+
+    /// Returns `operand`, copied if it shares this tensor's buffer. Reading it while this
+    /// tensor is written would otherwise need both locks on the same buffer.
+    fn unaliased(&self, operand: &Self) -> Result<Self> {
+        if operand.shares_buffer(self) {
+            operand.copy_untracked()
+        } else {
+            Ok(operand.detach())
+        }
+    }
+}
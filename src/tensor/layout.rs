@@ -0,0 +1,147 @@
+use super::Shape;
+use crate::compute::strided;
+use crate::error::{Result, FerroFlowError};
+
+/// Describes how a tensor's logical elements map onto its backend buffer.
+/// Strides and offset are measured in elements, so several views can share one buffer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layout {
+    shape: Shape,
+    strides: Vec<usize>,
+    offset: usize,
+}
+
+impl Layout {
+    /// Creates a dense row-major layout starting at the beginning of the buffer
+    pub fn contiguous(shape: Shape) -> Self {
+        let strides = Self::contiguous_strides(shape.dims());
+        Self { shape, strides, offset: 0 }
+    }
+
+    fn contiguous_strides(dims: &[usize]) -> Vec<usize> {
+        let mut strides = vec![1; dims.len()];
+        for i in (0..dims.len().saturating_sub(1)).rev() {
+            strides[i] = strides[i + 1] * dims[i + 1];
+        }
+        strides
+    }
+
+    pub fn shape(&self) -> &Shape {
+        &self.shape
+    }
+
+    pub fn strides(&self) -> &[usize] {
+        &self.strides
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn rank(&self) -> usize {
+        self.strides.len()
+    }
+
+    /// Returns true if elements are laid out densely in row-major order.
+    /// Dimensions of size 1 are ignored since their stride is never used.
+    pub fn is_contiguous(&self) -> bool {
+        strided::is_row_major(self.shape.dims(), &self.strides)
+    }
+
+    fn check_dim(&self, dim: usize) -> Result<()> {
+        if dim >= self.rank() {
+            return Err(FerroFlowError::InvalidOperation(
+                format!("Dimension {} out of range for tensor of rank {}", dim, self.rank())
+            ));
+        }
+        Ok(())
+    }
+
+    /// Reorders the dimensions; `dims` must be a permutation of `0..rank`
+    pub fn permute(&self, dims: &[usize]) -> Result<Self> {
+        let mut seen = vec![false; self.rank()];
+        if dims.len() != self.rank() || dims.iter().any(|&d| d >= self.rank() || std::mem::replace(&mut seen[d], true)) {
+            return Err(FerroFlowError::InvalidOperation(
+                format!("{:?} is not a permutation of the dimensions of a rank {} tensor", dims, self.rank())
+            ));
+        }
+
+        Ok(Self {
+            shape: Shape::new(dims.iter().map(|&d| self.shape.dims()[d]).collect()),
+            strides: dims.iter().map(|&d| self.strides[d]).collect(),
+            offset: self.offset,
+        })
+    }
+
+    /// Swaps two dimensions
+    pub fn transpose(&self, dim0: usize, dim1: usize) -> Result<Self> {
+        self.check_dim(dim0)?;
+        self.check_dim(dim1)?;
+
+        let mut dims: Vec<usize> = (0..self.rank()).collect();
+        dims.swap(dim0, dim1);
+        self.permute(&dims)
+    }
+
+    /// Restricts `dim` to `length` elements starting at `start`
+    pub fn narrow(&self, dim: usize, start: usize, length: usize) -> Result<Self> {
+        self.slice(dim, start, start + length, 1)
+    }
+
+    /// Restricts `dim` to every `step`-th element in `start..end`
+    pub fn slice(&self, dim: usize, start: usize, end: usize, step: usize) -> Result<Self> {
+        self.check_dim(dim)?;
+        let size = self.shape.dims()[dim];
+        if start > end || end > size || step == 0 {
+            return Err(FerroFlowError::ShapeMismatch(
+                format!("Invalid slice {}..{} (step {}) of dimension {} with size {}", start, end, step, dim, size)
+            ));
+        }
+
+        let mut dims = self.shape.dims().to_vec();
+        dims[dim] = (end - start).div_ceil(step);
+        let mut strides = self.strides.clone();
+        strides[dim] *= step;
+
+        Ok(Self {
+            shape: Shape::new(dims),
+            strides,
+            offset: self.offset + start * self.strides[dim],
+        })
+    }
+
+    /// Expands the layout to `shape` following NumPy broadcasting rules: missing leading
+    /// dimensions and dimensions of size 1 are repeated by giving them a stride of 0.
+    pub fn broadcast_to(&self, shape: &Shape) -> Result<Self> {
+        let target = shape.dims();
+        if target.len() < self.rank() {
+            return Err(FerroFlowError::ShapeMismatch(
+                format!("Cannot broadcast shape {:?} to {:?}", self.shape.dims(), target)
+            ));
+        }
+
+        let leading = target.len() - self.rank();
+        let mut strides = vec![0; target.len()];
+        for (i, (&dim, &stride)) in self.shape.dims().iter().zip(&self.strides).enumerate() {
+            match target[leading + i] {
+                size if size == dim => strides[leading + i] = stride,
+                _ if dim == 1 => {}
+                _ => return Err(FerroFlowError::ShapeMismatch(
+                    format!("Cannot broadcast shape {:?} to {:?}", self.shape.dims(), target)
+                )),
+            }
+        }
+
+        Ok(Self { shape: shape.clone(), strides, offset: self.offset })
+    }
+
+    /// Reinterprets the layout with a new shape of the same size without moving data.
+    /// Returns `None` when the current strides cannot express the new shape.
+    pub fn reshape(&self, shape: Shape) -> Option<Self> {
+        if shape.size() != self.shape.size() || !self.is_contiguous() {
+            return None;
+        }
+        let strides = Self::contiguous_strides(shape.dims());
+        Some(Self { shape, strides, offset: self.offset })
+    }
+}
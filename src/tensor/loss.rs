@@ -0,0 +1,81 @@
+use super::Tensor;
+use crate::autograd;
+use crate::compute::{ClassLossOp, ClassLossParams, ComputeBackend, PointwiseLossOp};
+use crate::dtype::DType;
+use crate::error::{Result, FerroFlowError};
+use std::sync::Arc;
+
+/// Tensor entry points of the fused loss kernels, wrapped with reductions by [`crate::loss`]
+impl<B: ComputeBackend> Tensor<B> {
+    /// Computes the unreduced loss of each row of a `[rows, classes]` tensor against
+    /// `[rows]` i64 class targets, and the number of rows not ignored. The targets are not
+    /// differentiated.
+    pub(crate) fn class_loss(&self, targets: &Self, op: ClassLossOp, ignore_index: Option<i64>) -> Result<(Self, usize)> {
+        self.check_f32(&[], "class loss")?;
+        if targets.dtype() != DType::I64 {
+            return Err(FerroFlowError::DTypeMismatch(format!(
+                "Class targets must be i64 indices, got {}", targets.dtype()
+            )));
+        }
+        let (rows, classes) = match self.shape().dims() {
+            &[rows, classes] if classes > 0 => (rows, classes),
+            dims => return Err(FerroFlowError::ShapeMismatch(format!(
+                "Class losses expect [rows, classes] scores, got shape {:?}", dims
+            ))),
+        };
+        if targets.shape().dims() != [rows] {
+            return Err(FerroFlowError::ShapeMismatch(format!(
+                "Expected [{}] targets, got shape {:?}", rows, targets.shape().dims()
+            )));
+        }
+
+        let params = ClassLossParams { op, rows, classes, ignore_index };
+        let (input, targets) = (self.materialize()?, targets.materialize()?);
+        let (buffer, counted) = B::class_loss(&self.ctx, &input.buffer(), &targets.buffer(), &params)?;
+        let output = Self::from_buffer(Arc::clone(&self.ctx), buffer, targets.shape().clone());
+
+        let saved = vec![input.saved_version(), targets.saved_version()];
+        let output = autograd::record("class_loss", output, &[self], saved, move |grad| {
+            let grad = grad.materialize()?;
+            let buffer = input.with_buffers(&grad, |input, grad_buffer| {
+                B::class_loss_backward(&grad.ctx, input, &targets.buffer(), grad_buffer, &params)
+            })?;
+            Ok(vec![Some(Self::from_buffer(Arc::clone(&grad.ctx), buffer, input.shape().clone()))])
+        });
+        Ok((output, counted))
+    }
+
+    /// Computes the unreduced element-wise loss between this tensor and a target of the same shape
+    pub(crate) fn pointwise_loss(&self, target: &Self, op: PointwiseLossOp) -> Result<Self> {
+        self.check_f32(&[target], "loss")?;
+        if self.shape() != target.shape() {
+            return Err(FerroFlowError::ShapeMismatch(format!(
+                "Loss input has shape {:?} but target has shape {:?}", self.shape().dims(), target.shape().dims()
+            )));
+        }
+
+        let (input, target_dense) = (self.materialize()?, target.materialize()?);
+        let size = self.shape().size();
+        let buffer = input.with_buffers(&target_dense, |input, target| B::pointwise_loss(&self.ctx, op, input, target, size))?;
+        let output = Self::from_buffer(Arc::clone(&self.ctx), buffer, self.shape().clone());
+
+        let (input_needs_grad, target_needs_grad) = (self.needs_grad(), target.needs_grad());
+        let saved = vec![input.saved_version(), target_dense.saved_version()];
+        Ok(autograd::record("pointwise_loss", output, &[self, target], saved, move |grad| {
+            let grad = grad.materialize()?;
+            let buffer = input.with_buffers(&target_dense, |input, target| {
+                B::pointwise_loss_backward(&grad.ctx, op, input, target, &grad.buffer(), size)
+            })?;
+            let grad_input = Self::from_buffer(Arc::clone(&grad.ctx), buffer, input.shape().clone());
+
+            // Regression losses depend on x - y only, so the target gradient is the negated
+            // input gradient; for BCE on logits d/dy = -x
+            let grad_target = match (target_needs_grad, op) {
+                (false, _) => None,
+                (true, PointwiseLossOp::BceWithLogits) => Some(input.multiply(&grad)?.scalar_multiply(-1.0)?),
+                (true, _) => Some(grad_input.scalar_multiply(-1.0)?),
+            };
+            Ok(vec![input_needs_grad.then_some(grad_input), grad_target])
+        }))
+    }
+}
@@ -1,8 +1,26 @@
-use std::sync::Arc;
-use crate::compute::ComputeBackend;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use crate::autograd::{self, Node, SavedVersion};
+use crate::compute::{strided, BinaryOp, BufferView, ComputeBackend, ReduceOp, SoftmaxOp, UnaryOp};
+use crate::dtype::{self, DType, Element};
 use crate::error::{Result, FerroFlowError};
+use crate::io::Storage;
 use tracing::{debug, error, instrument};
-use std::ops::{Add, Mul, Neg, BitAnd};
+use std::ops::{Add, Sub, Mul, Div, Neg, BitAnd, Range};
+
+mod conv;
+mod inplace;
+mod layout;
+mod loss;
+mod norm;
+mod optim;
+mod pool;
+mod quantized;
+
+pub use conv::Conv2dOptions;
+pub use layout::Layout;
+pub use pool::Pool2dOptions;
+pub use quantized::{QuantType, QuantizedTensor};
 
 /// Represents the shape of a tensor.
 /// Implements Clone to allow easy shape reuse and Debug for better error messages.
@@ -44,15 +62,44 @@ impl Shape {
     pub fn new_batched(batch: usize, rows: usize, cols: usize) -> Self {
         Self(vec![batch, rows, cols])
     }
+
+    /// Computes the shape two operands broadcast to under NumPy rules: shapes are aligned
+    /// from the trailing dimension and each pair of sizes must be equal or contain a 1.
+    pub fn broadcast_with(&self, other: &Shape) -> Result<Shape> {
+        let rank = self.0.len().max(other.0.len());
+        let mut dims = vec![0; rank];
+
+        for i in 0..rank {
+            let lhs = if i < self.0.len() { self.0[self.0.len() - 1 - i] } else { 1 };
+            let rhs = if i < other.0.len() { other.0[other.0.len() - 1 - i] } else { 1 };
+            dims[rank - 1 - i] = match (lhs, rhs) {
+                (l, r) if l == r => l,
+                (1, r) => r,
+                (l, 1) => l,
+                (l, r) => return Err(FerroFlowError::ShapeMismatch(format!(
+                    "Cannot broadcast shapes {:?} and {:?}: size {} does not match size {} at trailing dimension {}",
+                    self.0, other.0, l, r, i
+                ))),
+            };
+        }
+
+        Ok(Shape(dims))
+    }
 }
 
 /// A generic tensor implementation that works with any compute backend.
 /// B: ComputeBackend ensures the backend implements all required operations.
-/// Uses Arc for thread-safe sharing of the context.
+/// Uses Arc for thread-safe sharing of the context, and of the buffer between views.
+/// The buffer sits behind a lock so in-place updates (e.g. optimizer steps) are seen by
+/// every view and clone. Tensors that require gradients also hold their node in the autograd graph.
 pub struct Tensor<B: ComputeBackend> {
-    buffer: B::Buffer,
-    shape: Shape,
+    buffer: Arc<RwLock<B::Buffer>>,
+    /// Number of in-place writes to `buffer`, shared by every tensor viewing it
+    version: Arc<AtomicUsize>,
+    layout: Layout,
+    dtype: DType,
     ctx: Arc<B::Context>,
+    autograd: Option<Arc<Node<B>>>,
 }
 
 impl<B: ComputeBackend> Tensor<B> {
@@ -72,11 +119,7 @@ impl<B: ComputeBackend> Tensor<B> {
         let buffer = B::allocate_buffer(&ctx, shape.size(), Some(data))?;
         debug!("Successfully allocated buffer for tensor");
         
-        Ok(Self {
-            buffer,
-            shape,
-            ctx,
-        })
+        Ok(Self::from_buffer(ctx, buffer, shape))
     }
     
     /// Creates a new tensor filled with zeros.
@@ -84,152 +127,976 @@ impl<B: ComputeBackend> Tensor<B> {
     pub fn zeros(ctx: Arc<B::Context>, shape: Shape) -> Result<Self> {
         let buffer = B::allocate_buffer(&ctx, shape.size(), None)?;
         
-        Ok(Self {
-            buffer,
-            shape,
+        Ok(Self::from_buffer(ctx, buffer, shape))
+    }
+
+    /// Creates a tensor of any element type from a slice in row-major order.
+    pub fn from_slice<T: Element>(ctx: Arc<B::Context>, shape: Shape, data: &[T]) -> Result<Self> {
+        if data.len() != shape.size() {
+            return Err(FerroFlowError::ShapeMismatch(
+                format!("Data length {} doesn't match shape size {}", data.len(), shape.size())
+            ));
+        }
+
+        let buffer = B::allocate_typed_buffer(&ctx, T::DTYPE, shape.size(), Some(&dtype::to_bytes(data)))?;
+        Ok(Self::from_typed_buffer(ctx, buffer, shape, T::DTYPE))
+    }
+
+    /// Creates a tensor of `dtype` from the native-endian bytes of its elements in row-major order.
+    pub fn from_bytes(ctx: Arc<B::Context>, shape: Shape, dtype: DType, bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != shape.size() * dtype.size_in_bytes() {
+            return Err(FerroFlowError::ShapeMismatch(format!(
+                "{} bytes don't hold the {} {} elements of shape {:?}", bytes.len(), shape.size(), dtype, shape.dims()
+            )));
+        }
+        let buffer = B::allocate_typed_buffer(&ctx, dtype, shape.size(), Some(bytes))?;
+        Ok(Self::from_typed_buffer(ctx, buffer, shape, dtype))
+    }
+
+    /// Creates a tensor of `shape` over the native-endian `dtype` elements in `range` of a loaded
+    /// file, read in place where the backend supports it
+    pub(crate) fn from_storage(
+        ctx: Arc<B::Context>,
+        shape: Shape,
+        dtype: DType,
+        storage: &Arc<Storage>,
+        range: Range<usize>,
+    ) -> Result<Self> {
+        if range.len() != shape.size() * dtype.size_in_bytes() {
+            return Err(FerroFlowError::ShapeMismatch(format!(
+                "{} bytes don't hold the {} {} elements of shape {:?}", range.len(), shape.size(), dtype, shape.dims()
+            )));
+        }
+        let buffer = B::import_storage(&ctx, storage, range, dtype)?;
+        Ok(Self::from_typed_buffer(ctx, buffer, shape, dtype))
+    }
+
+    /// Creates a tensor of the given element type filled with zeros.
+    pub fn zeros_with_dtype(ctx: Arc<B::Context>, shape: Shape, dtype: DType) -> Result<Self> {
+        let buffer = B::allocate_typed_buffer(&ctx, dtype, shape.size(), None)?;
+        Ok(Self::from_typed_buffer(ctx, buffer, shape, dtype))
+    }
+
+    /// Wraps a freshly produced f32 backend buffer holding `shape` in row-major order.
+    fn from_buffer(ctx: Arc<B::Context>, buffer: B::Buffer, shape: Shape) -> Self {
+        Self::from_typed_buffer(ctx, buffer, shape, DType::F32)
+    }
+
+    fn from_typed_buffer(ctx: Arc<B::Context>, buffer: B::Buffer, shape: Shape, dtype: DType) -> Self {
+        Self {
+            buffer: Arc::new(RwLock::new(buffer)),
+            version: Arc::new(AtomicUsize::new(0)),
+            layout: Layout::contiguous(shape),
+            dtype,
             ctx,
-        })
+            autograd: None,
+        }
+    }
+
+    /// Returns an untracked view of the same buffer with a different layout.
+    fn with_layout(&self, layout: Layout) -> Self {
+        Self {
+            buffer: Arc::clone(&self.buffer),
+            version: Arc::clone(&self.version),
+            layout,
+            dtype: self.dtype,
+            ctx: Arc::clone(&self.ctx),
+            autograd: None,
+        }
+    }
+
+    pub(crate) fn autograd_node(&self) -> Option<&Arc<Node<B>>> {
+        self.autograd.as_ref()
+    }
+
+    pub(crate) fn with_autograd_node(mut self, node: Arc<Node<B>>) -> Self {
+        self.autograd = Some(node);
+        self
+    }
+
+    /// Marks this tensor as a leaf whose gradient is accumulated by `backward`.
+    pub fn requires_grad(mut self) -> Self {
+        if self.autograd.is_none() {
+            self.autograd = Some(Node::leaf());
+        }
+        self
+    }
+
+    /// Returns true if operations on this tensor are recorded for autograd.
+    pub fn needs_grad(&self) -> bool {
+        self.autograd.is_some()
+    }
+
+    /// Returns true unless this tensor was produced by a recorded operation.
+    pub fn is_leaf(&self) -> bool {
+        self.autograd.as_ref().is_none_or(|node| node.is_leaf())
+    }
+
+    /// Returns the gradient accumulated by `backward`, if any.
+    pub fn grad(&self) -> Option<Self> {
+        self.autograd.as_ref().and_then(|node| node.grad())
+    }
+
+    /// Clears the accumulated gradient.
+    pub fn zero_grad(&self) {
+        if let Some(node) = &self.autograd {
+            node.set_grad(None);
+        }
+    }
+
+    /// Returns a view of the same data that is not part of the autograd graph.
+    pub fn detach(&self) -> Self {
+        self.with_layout(self.layout.clone())
+    }
+
+    /// Back-propagates from this single-element tensor (typically a loss).
+    pub fn backward(&self) -> Result<()> {
+        if self.shape().size() != 1 {
+            return Err(FerroFlowError::InvalidOperation(format!(
+                "backward() requires a single-element tensor, got shape {:?}; use backward_with_grad",
+                self.shape()
+            )));
+        }
+        let seed = Self::full(Arc::clone(&self.ctx), self.shape().clone(), 1.0)?;
+        self.backward_with_grad(&seed)
+    }
+
+    /// Back-propagates `grad`, the gradient of some scalar with respect to this tensor.
+    pub fn backward_with_grad(&self, grad: &Self) -> Result<()> {
+        let node = self.autograd.as_ref().ok_or_else(|| FerroFlowError::InvalidOperation(
+            "Cannot call backward on a tensor that does not require grad".into()
+        ))?;
+        if grad.shape() != self.shape() {
+            return Err(FerroFlowError::ShapeMismatch(format!(
+                "Gradient shape {:?} doesn't match tensor shape {:?}", grad.shape(), self.shape()
+            )));
+        }
+        autograd::backward(node, grad.detach())
     }
 
     /// Returns the shape of the tensor.
     pub fn shape(&self) -> &Shape {
-        &self.shape
+        self.layout.shape()
+    }
+
+    /// Returns the element type of the tensor.
+    pub fn dtype(&self) -> DType {
+        self.dtype
+    }
+
+    /// Returns the compute context the tensor's buffer belongs to.
+    pub fn context(&self) -> &Arc<B::Context> {
+        &self.ctx
+    }
+
+    /// Returns the layout (shape, strides and offset) of the tensor.
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    /// Returns true if the elements are stored densely in row-major order.
+    pub fn is_contiguous(&self) -> bool {
+        self.layout.is_contiguous()
+    }
+
+    /// Locks the backend buffer for reading, e.g. to pass it to a kernel.
+    /// A panic while the lock was held cannot leave a buffer half-updated in a way that matters
+    /// more than the panic itself, so poisoning is ignored.
+    fn buffer(&self) -> RwLockReadGuard<'_, B::Buffer> {
+        self.buffer.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Calls `f` with the buffers of `self` and `other` locked for reading. A buffer both
+    /// tensors share is locked once, since a second read lock from the same thread can
+    /// deadlock behind a queued writer.
+    fn with_buffers<R>(&self, other: &Self, f: impl FnOnce(&B::Buffer, &B::Buffer) -> R) -> R {
+        if self.shares_buffer(other) {
+            let buffer = self.buffer();
+            f(&buffer, &buffer)
+        } else {
+            f(&self.buffer(), &other.buffer())
+        }
+    }
+
+    /// Locks the backend buffer for an in-place update, which invalidates recorded backward
+    /// rules that saved it
+    fn buffer_mut(&self) -> RwLockWriteGuard<'_, B::Buffer> {
+        self.version.fetch_add(1, Ordering::Relaxed);
+        self.buffer.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Snapshots the write count of this tensor's buffer for a backward rule that reads it
+    pub(crate) fn saved_version(&self) -> SavedVersion {
+        SavedVersion::new(Arc::clone(&self.version))
     }
 
-    /// Reads the tensor data into a Vec<f32>.
+    /// Returns true if this tensor covers its whole buffer in row-major order,
+    /// which is the form every backend kernel expects.
+    fn is_dense(&self) -> bool {
+        self.layout.is_contiguous()
+            && self.layout.offset() == 0
+            && B::buffer_len(&self.buffer(), self.dtype) == self.shape().size()
+    }
+
+    /// Reads the tensor data into a Vec<f32>, converting from other element types.
     /// Useful for debugging and verification.
     pub fn data(&self) -> Result<Vec<f32>> {
-        B::read_buffer(&self.ctx, &self.buffer)
+        B::read_buffer(&self.ctx, &self.cast_untracked(DType::F32)?.buffer())
     }
 
-    /// Element-wise addition of two tensors.
-    #[instrument(skip(self, other))]
-    pub fn add(&self, other: &Self) -> Result<Self> {
-        debug!("Adding tensors with shapes {:?} and {:?}", self.shape, other.shape);
-        
-        if self.shape != other.shape {
-            error!("Shape mismatch in add operation");
-            return Err(FerroFlowError::ShapeMismatch(
-                format!("Cannot add tensors with shapes {:?} and {:?}", self.shape, other.shape)
-            ));
+    /// Reads the tensor data as elements of type `T`, which must match the tensor's dtype.
+    pub fn to_vec<T: Element>(&self) -> Result<Vec<T>> {
+        if T::DTYPE != self.dtype {
+            return Err(FerroFlowError::DTypeMismatch(format!(
+                "Cannot read a {} tensor as {}", self.dtype, T::DTYPE
+            )));
+        }
+        let bytes = B::read_buffer_bytes(&self.ctx, &self.materialize()?.buffer(), self.dtype)?;
+        Ok(dtype::from_bytes(&bytes))
+    }
+
+    /// Reads the native-endian bytes of the elements in row-major order.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        B::read_buffer_bytes(&self.ctx, &self.materialize()?.buffer(), self.dtype)
+    }
+
+    /// Converts the elements to another type. Conversions between floating point
+    /// types are recorded for autograd; the gradient is converted back.
+    pub fn to_dtype(&self, dtype: DType) -> Result<Self> {
+        if dtype == self.dtype {
+            return Ok(self.clone());
+        }
+        let output = self.cast_untracked(dtype)?;
+        if !(dtype.is_float() && self.dtype.is_float()) {
+            return Ok(output);
         }
 
-        let result_buffer = B::element_wise_add(
+        let source = self.dtype;
+        Ok(autograd::record("to_dtype", output, &[self], Vec::new(), move |grad| {
+            Ok(vec![Some(grad.to_dtype(source)?)])
+        }))
+    }
+
+    fn cast_untracked(&self, dtype: DType) -> Result<Self> {
+        let input = self.materialize()?;
+        if dtype == self.dtype {
+            return Ok(input);
+        }
+        let buffer = B::cast(&self.ctx, &input.buffer(), self.dtype, dtype, self.shape().size())?;
+        Ok(Self::from_typed_buffer(Arc::clone(&self.ctx), buffer, self.shape().clone(), dtype))
+    }
+
+    /// Returns an error unless every operand holds f32 elements, which the compute kernels require.
+    fn check_f32(&self, others: &[&Self], operation: &str) -> Result<()> {
+        for tensor in std::iter::once(self).chain(others.iter().copied()) {
+            if tensor.dtype != DType::F32 {
+                return Err(FerroFlowError::DTypeMismatch(format!(
+                    "{} requires f32 tensors, got {}; convert with to_dtype(DType::F32)", operation, tensor.dtype
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns a tensor with the same data stored densely in row-major order.
+    /// This is free when the tensor is already dense, otherwise the view is copied.
+    pub fn contiguous(&self) -> Result<Self> {
+        if self.is_dense() {
+            return Ok(self.clone());
+        }
+        let output = self.materialize()?;
+        Ok(autograd::record("contiguous", output, &[self], Vec::new(), |grad| {
+            Ok(vec![Some(grad.clone())])
+        }))
+    }
+
+    /// Untracked version of `contiguous` used when handing buffers to backend kernels.
+    fn materialize(&self) -> Result<Self> {
+        if self.is_dense() {
+            return Ok(self.detach());
+        }
+
+        debug!("Materializing strided view with layout {:?}", self.layout);
+        self.copy_untracked()
+    }
+
+    /// Copies this view into a new dense buffer even when it is dense already, e.g. so it can be
+    /// read while its own buffer is being written.
+    fn copy_untracked(&self) -> Result<Self> {
+        let buffer = B::copy_strided(
             &self.ctx,
-            &self.buffer,
-            &other.buffer,
-            self.shape.size(),
+            &self.buffer(),
+            self.dtype,
+            self.shape().dims(),
+            self.layout.strides(),
+            self.layout.offset(),
         )?;
-        
-        debug!("Successfully completed add operation");
-        
-        Ok(Self {
-            buffer: result_buffer,
-            shape: self.shape.clone(),
-            ctx: Arc::clone(&self.ctx),
+        Ok(Self::from_typed_buffer(Arc::clone(&self.ctx), buffer, self.shape().clone(), self.dtype))
+    }
+
+    /// Returns true if both tensors are views of the same buffer
+    fn shares_buffer(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.buffer, &other.buffer)
+    }
+
+    /// Returns a tensor with the given shape and the same elements.
+    /// Shares the buffer when possible and copies otherwise.
+    pub fn reshape(&self, shape: Shape) -> Result<Self> {
+        self.check_reshape(&shape)?;
+        let output = match self.layout.reshape(shape.clone()) {
+            Some(layout) => self.with_layout(layout),
+            None => self.materialize()?.with_layout(Layout::contiguous(shape)),
+        };
+        Ok(self.record_reshape("reshape", output))
+    }
+
+    /// Returns a view with the given shape, failing if it would require a copy.
+    pub fn view(&self, shape: Shape) -> Result<Self> {
+        self.check_reshape(&shape)?;
+        match self.layout.reshape(shape) {
+            Some(layout) => Ok(self.record_reshape("view", self.with_layout(layout))),
+            None => Err(FerroFlowError::InvalidOperation(
+                "Cannot view a non-contiguous tensor with a new shape; use reshape or contiguous".into()
+            )),
+        }
+    }
+
+    fn record_reshape(&self, name: &'static str, output: Self) -> Self {
+        let input_shape = self.shape().clone();
+        autograd::record(name, output, &[self], Vec::new(), move |grad| {
+            Ok(vec![Some(grad.reshape(input_shape.clone())?)])
         })
     }
 
-    /// Element-wise multiplication of two tensors.
-    pub fn multiply(&self, other: &Self) -> Result<Self> {
-        if self.shape != other.shape {
+    fn check_reshape(&self, shape: &Shape) -> Result<()> {
+        if shape.size() != self.shape().size() {
             return Err(FerroFlowError::ShapeMismatch(
-                format!("Cannot multiply tensors with shapes {:?} and {:?}", self.shape, other.shape)
+                format!("Cannot reshape tensor of shape {:?} into {:?}", self.shape(), shape)
             ));
         }
+        Ok(())
+    }
+
+    /// Returns a view with the dimensions reordered according to `dims`.
+    pub fn permute(&self, dims: &[usize]) -> Result<Self> {
+        let output = self.with_layout(self.layout.permute(dims)?);
+        let mut inverse = vec![0; dims.len()];
+        for (i, &d) in dims.iter().enumerate() {
+            inverse[d] = i;
+        }
+        Ok(autograd::record("permute", output, &[self], Vec::new(), move |grad| {
+            Ok(vec![Some(grad.permute(&inverse)?)])
+        }))
+    }
+
+    /// Returns a view with dimensions `dim0` and `dim1` swapped.
+    pub fn transpose(&self, dim0: usize, dim1: usize) -> Result<Self> {
+        let output = self.with_layout(self.layout.transpose(dim0, dim1)?);
+        Ok(autograd::record("transpose", output, &[self], Vec::new(), move |grad| {
+            Ok(vec![Some(grad.transpose(dim0, dim1)?)])
+        }))
+    }
+
+    /// Returns a view of `length` elements along `dim` starting at `start`.
+    pub fn narrow(&self, dim: usize, start: usize, length: usize) -> Result<Self> {
+        let end = start.checked_add(length).ok_or_else(|| {
+            FerroFlowError::InvalidOperation(format!("Narrowing {} elements from {} overflows", length, start))
+        })?;
+        self.slice(dim, start, end, 1)
+    }
+
+    /// Returns a view of every `step`-th element along `dim` in `start..end`.
+    pub fn slice(&self, dim: usize, start: usize, end: usize, step: usize) -> Result<Self> {
+        let output = self.with_layout(self.layout.slice(dim, start, end, step)?);
+        let input_shape = self.shape().clone();
+        Ok(autograd::record("slice", output, &[self], Vec::new(), move |grad| {
+            // Scatter the gradient back into the selected positions of a zero tensor
+            let selected = Layout::contiguous(input_shape.clone()).slice(dim, start, end, step)?;
+            let grad_data = grad.data()?;
+            let mut data = vec![0.0; input_shape.size()];
+            let mut values = grad_data.iter();
+            strided::for_each_offset(selected.shape().dims(), selected.strides(), selected.offset(), |i| {
+                data[i] = *values.next().unwrap();
+            });
+            Ok(vec![Some(Self::new(Arc::clone(&grad.ctx), input_shape.clone(), &data)?)])
+        }))
+    }
+
+    /// Sums this tensor down to `shape`, undoing a broadcast from `shape` to `self.shape()`.
+    pub(crate) fn sum_to_shape(&self, shape: &Shape) -> Result<Self> {
+        if self.shape() == shape {
+            return Ok(self.clone());
+        }
+
+        let dims = self.shape().dims();
+        let leading = dims.len() - shape.dims().len();
+        let mut summed = self.clone();
+        // Reduce from the last dimension so the remaining indices stay valid
+        for d in (0..dims.len()).rev() {
+            if d < leading || (shape.dims()[d - leading] == 1 && dims[d] != 1) {
+                summed = summed.sum(d, true)?;
+            }
+        }
+        summed.reshape(shape.clone())
+    }
+
+    /// Expands dimensions of size 1 (and missing leading dimensions) to `shape` without copying.
+    pub fn broadcast_to(&self, shape: &Shape) -> Result<Self> {
+        let output = self.with_layout(self.layout.broadcast_to(shape)?);
+        let input_shape = self.shape().clone();
+        Ok(autograd::record("broadcast_to", output, &[self], Vec::new(), move |grad| {
+            Ok(vec![Some(grad.sum_to_shape(&input_shape)?)])
+        }))
+    }
+
+    /// Element-wise addition of two tensors, broadcasting as in NumPy.
+    #[instrument(skip(self, other))]
+    pub fn add(&self, other: &Self) -> Result<Self> {
+        debug!("Adding tensors with shapes {:?} and {:?}", self.shape(), other.shape());
+        self.binary_op(other, BinaryOp::Add)
+    }
+
+    /// Element-wise subtraction of two tensors, broadcasting as in NumPy.
+    pub fn sub(&self, other: &Self) -> Result<Self> {
+        self.binary_op(other, BinaryOp::Subtract)
+    }
+
+    /// Element-wise multiplication of two tensors, broadcasting as in NumPy.
+    pub fn multiply(&self, other: &Self) -> Result<Self> {
+        self.binary_op(other, BinaryOp::Multiply)
+    }
 
-        let result_buffer = B::element_wise_multiply(
+    /// Element-wise division of two tensors, broadcasting as in NumPy.
+    pub fn divide(&self, other: &Self) -> Result<Self> {
+        self.binary_op(other, BinaryOp::Divide)
+    }
+
+    /// Applies a binary operation after broadcasting both operands to a common shape.
+    /// Broadcasting only adjusts strides; the backend reads the original buffers.
+    fn binary_op(&self, other: &Self, op: BinaryOp) -> Result<Self> {
+        if self.dtype != other.dtype {
+            return Err(FerroFlowError::DTypeMismatch(format!(
+                "{:?} of {} and {} tensors", op, self.dtype, other.dtype
+            )));
+        }
+        self.check_f32(&[], &format!("{:?}", op))?;
+        let shape = self.shape().broadcast_with(other.shape()).inspect_err(|_| {
+            error!("Shape mismatch in {:?} operation", op);
+        })?;
+        let lhs = self.layout.broadcast_to(&shape)?;
+        let rhs = other.layout.broadcast_to(&shape)?;
+
+        let result_buffer = self.with_buffers(other, |lhs_buffer, rhs_buffer| B::binary_op(
             &self.ctx,
-            &self.buffer,
-            &other.buffer,
-            self.shape.size(),
-        )?;
+            op,
+            BufferView { buffer: lhs_buffer, strides: lhs.strides(), offset: lhs.offset() },
+            BufferView { buffer: rhs_buffer, strides: rhs.strides(), offset: rhs.offset() },
+            shape.dims(),
+        ))?;
 
-        Ok(Self {
-            buffer: result_buffer,
-            shape: self.shape.clone(),
-            ctx: Arc::clone(&self.ctx),
-        })
+        debug!("Successfully completed {:?} operation", op);
+
+        let output = Self::from_buffer(Arc::clone(&self.ctx), result_buffer, shape);
+        let (lhs, rhs) = (self.detach(), other.detach());
+        let (lhs_needs_grad, rhs_needs_grad) = (self.needs_grad(), other.needs_grad());
+        // Only the gradients of products and quotients read the operands
+        let saved = match op {
+            BinaryOp::Add | BinaryOp::Subtract => Vec::new(),
+            BinaryOp::Multiply | BinaryOp::Divide => vec![lhs.saved_version(), rhs.saved_version()],
+        };
+        Ok(autograd::record("binary_op", output, &[self, other], saved, move |grad| {
+            let grad_lhs = match op {
+                _ if !lhs_needs_grad => None,
+                BinaryOp::Add | BinaryOp::Subtract => Some(grad.clone()),
+                BinaryOp::Multiply => Some(grad.multiply(&rhs)?),
+                BinaryOp::Divide => Some(grad.divide(&rhs)?),
+            };
+            let grad_rhs = match op {
+                _ if !rhs_needs_grad => None,
+                BinaryOp::Add => Some(grad.clone()),
+                BinaryOp::Subtract => Some(grad.scalar_multiply(-1.0)?),
+                BinaryOp::Multiply => Some(grad.multiply(&lhs)?),
+                // d(a / b)/db = -a / b^2
+                BinaryOp::Divide => Some(grad.multiply(&lhs)?.divide(&rhs.multiply(&rhs)?)?.scalar_multiply(-1.0)?),
+            };
+            Ok(vec![
+                grad_lhs.map(|g| g.sum_to_shape(lhs.shape())).transpose()?,
+                grad_rhs.map(|g| g.sum_to_shape(rhs.shape())).transpose()?,
+            ])
+        }))
     }
 
     /// Multiplication by a scalar value.
     pub fn scalar_multiply(&self, scalar: f32) -> Result<Self> {
+        self.check_f32(&[], "scalar_multiply")?;
+        let input = self.materialize()?;
         let result_buffer = B::scalar_multiply(
             &self.ctx,
-            &self.buffer,
+            &input.buffer(),
             scalar,
-            self.shape.size(),
+            self.shape().size(),
         )?;
 
-        Ok(Self {
-            buffer: result_buffer,
-            shape: self.shape.clone(),
-            ctx: Arc::clone(&self.ctx),
-        })
+        let output = Self::from_buffer(Arc::clone(&self.ctx), result_buffer, self.shape().clone());
+        Ok(autograd::record("scalar_multiply", output, &[self], Vec::new(), move |grad| {
+            Ok(vec![Some(grad.scalar_multiply(scalar)?)])
+        }))
+    }
+
+    /// Applies an element-wise unary operation.
+    pub fn unary_op(&self, op: UnaryOp) -> Result<Self> {
+        self.check_f32(&[], &format!("{:?}", op))?;
+        if let UnaryOp::Clamp { min, max } = op {
+            if min > max || min.is_nan() || max.is_nan() {
+                return Err(FerroFlowError::InvalidOperation(format!("Invalid clamp range {}..={}", min, max)));
+            }
+        }
+        let input = self.materialize()?;
+        let result_buffer = B::unary_op(&self.ctx, op, &input.buffer(), self.shape().size())?;
+
+        let output = Self::from_buffer(Arc::clone(&self.ctx), result_buffer, self.shape().clone());
+        let saved = vec![input.saved_version()];
+        Ok(autograd::record("unary_op", output, &[self], saved, move |grad| {
+            let grad = grad.materialize()?;
+            let buffer = input.with_buffers(&grad, |input_buffer, grad_buffer| {
+                B::unary_op_backward(&grad.ctx, op, input_buffer, grad_buffer, input.shape().size())
+            })?;
+            Ok(vec![Some(Self::from_buffer(Arc::clone(&grad.ctx), buffer, input.shape().clone()))])
+        }))
+    }
+
+    pub fn exp(&self) -> Result<Self> {
+        self.unary_op(UnaryOp::Exp)
+    }
+
+    /// Natural logarithm
+    pub fn log(&self) -> Result<Self> {
+        self.unary_op(UnaryOp::Log)
+    }
+
+    pub fn sqrt(&self) -> Result<Self> {
+        self.unary_op(UnaryOp::Sqrt)
+    }
+
+    /// Reciprocal square root
+    pub fn rsqrt(&self) -> Result<Self> {
+        self.unary_op(UnaryOp::Rsqrt)
+    }
+
+    pub fn abs(&self) -> Result<Self> {
+        self.unary_op(UnaryOp::Abs)
+    }
+
+    pub fn sin(&self) -> Result<Self> {
+        self.unary_op(UnaryOp::Sin)
+    }
+
+    pub fn cos(&self) -> Result<Self> {
+        self.unary_op(UnaryOp::Cos)
+    }
+
+    pub fn tanh(&self) -> Result<Self> {
+        self.unary_op(UnaryOp::Tanh)
+    }
+
+    pub fn sigmoid(&self) -> Result<Self> {
+        self.unary_op(UnaryOp::Sigmoid)
+    }
+
+    pub fn relu(&self) -> Result<Self> {
+        self.unary_op(UnaryOp::Relu)
+    }
+
+    /// GELU with the exact normal CDF
+    pub fn gelu(&self) -> Result<Self> {
+        self.unary_op(UnaryOp::Gelu)
+    }
+
+    /// GELU with the tanh approximation
+    pub fn gelu_tanh(&self) -> Result<Self> {
+        self.unary_op(UnaryOp::GeluTanh)
+    }
+
+    pub fn silu(&self) -> Result<Self> {
+        self.unary_op(UnaryOp::Silu)
+    }
+
+    pub fn softplus(&self) -> Result<Self> {
+        self.unary_op(UnaryOp::Softplus)
+    }
+
+    /// Raises every element to a scalar power
+    pub fn pow(&self, exponent: f32) -> Result<Self> {
+        self.unary_op(UnaryOp::Pow(exponent))
+    }
+
+    /// Limits every element to `min..=max`
+    pub fn clamp(&self, min: f32, max: f32) -> Result<Self> {
+        self.unary_op(UnaryOp::Clamp { min, max })
+    }
+
+    /// Sums the elements along `dim`, keeping it with size 1 if `keepdim` is set.
+    pub fn sum(&self, dim: usize, keepdim: bool) -> Result<Self> {
+        let output = self.reduce(ReduceOp::Sum, dim, keepdim)?;
+        let input_shape = self.shape().clone();
+        Ok(autograd::record("sum", output, &[self], Vec::new(), move |grad| {
+            Ok(vec![Some(Self::expand_reduced(grad, dim, &input_shape)?)])
+        }))
+    }
+
+    /// Averages the elements along `dim`.
+    pub fn mean(&self, dim: usize, keepdim: bool) -> Result<Self> {
+        let output = self.reduce(ReduceOp::Mean, dim, keepdim)?;
+        let input_shape = self.shape().clone();
+        Ok(autograd::record("mean", output, &[self], Vec::new(), move |grad| {
+            let scale = 1.0 / input_shape.dims()[dim] as f32;
+            Ok(vec![Some(Self::expand_reduced(&grad.scalar_multiply(scale)?, dim, &input_shape)?)])
+        }))
+    }
+
+    /// Sums every element into a tensor of shape `[]`.
+    pub fn sum_all(&self) -> Result<Self> {
+        self.reshape(Shape::new(vec![self.shape().size()]))?.sum(0, false)
+    }
+
+    /// Averages every element into a tensor of shape `[]`.
+    pub fn mean_all(&self) -> Result<Self> {
+        self.reshape(Shape::new(vec![self.shape().size()]))?.mean(0, false)
+    }
+
+    /// Takes the maximum along `dim`. The gradient flows to the first maximal element.
+    pub fn max(&self, dim: usize, keepdim: bool) -> Result<Self> {
+        self.extreme(ReduceOp::Max, ReduceOp::ArgMax, dim, keepdim)
+    }
+
+    /// Takes the minimum along `dim`. The gradient flows to the first minimal element.
+    pub fn min(&self, dim: usize, keepdim: bool) -> Result<Self> {
+        self.extreme(ReduceOp::Min, ReduceOp::ArgMin, dim, keepdim)
+    }
+
+    /// Returns the i64 indices of the maxima along `dim`.
+    pub fn argmax(&self, dim: usize, keepdim: bool) -> Result<Self> {
+        self.reduce(ReduceOp::ArgMax, dim, keepdim)
+    }
+
+    /// Returns the i64 indices of the minima along `dim`.
+    pub fn argmin(&self, dim: usize, keepdim: bool) -> Result<Self> {
+        self.reduce(ReduceOp::ArgMin, dim, keepdim)
+    }
+
+    /// Multiplies the elements along `dim`.
+    pub fn prod(&self, dim: usize, keepdim: bool) -> Result<Self> {
+        let output = self.reduce(ReduceOp::Prod, dim, keepdim)?;
+        let input = self.detach();
+        let saved = vec![input.saved_version()];
+        Ok(autograd::record("prod", output, &[self], saved, move |grad| {
+            // Each input's gradient is the product of the other elements of its lane,
+            // built from prefix and suffix products so zeros are handled exactly
+            let (outer, size, inner) = input.reduction_extents(dim);
+            let data = input.data()?;
+            let grad_data = grad.data()?;
+            let mut result = vec![0.0; data.len()];
+            let mut suffix = vec![1.0f64; size + 1];
+            for o in 0..outer {
+                for i in 0..inner {
+                    let index = |r: usize| (o * size + r) * inner + i;
+                    for r in (0..size).rev() {
+                        suffix[r] = suffix[r + 1] * data[index(r)] as f64;
+                    }
+                    let mut prefix = 1.0f64;
+                    for r in 0..size {
+                        result[index(r)] = (prefix * suffix[r + 1]) as f32 * grad_data[o * inner + i];
+                        prefix *= data[index(r)] as f64;
+                    }
+                }
+            }
+            Ok(vec![Some(Self::new(Arc::clone(&grad.ctx), input.shape().clone(), &result)?)])
+        }))
+    }
+
+    /// Computes the variance along `dim` with `size - correction` in the denominator.
+    pub fn var(&self, dim: usize, correction: usize, keepdim: bool) -> Result<Self> {
+        self.check_correction(dim, correction)?;
+        let output = self.reduce(ReduceOp::Var { correction }, dim, keepdim)?;
+        let input = self.detach();
+        let saved = vec![input.saved_version()];
+        Ok(autograd::record("var", output, &[self], saved, move |grad| {
+            // d var / dx = 2 (x - mean) / (n - correction)
+            let n = input.shape().dims()[dim] as f32;
+            let centered = input.sub(&input.mean(dim, true)?)?;
+            let grad = Self::expand_reduced(grad, dim, input.shape())?;
+            Ok(vec![Some(grad.multiply(&centered)?.scalar_multiply(2.0 / (n - correction as f32))?)])
+        }))
+    }
+
+    /// Computes the standard deviation along `dim` with `size - correction` in the denominator.
+    pub fn std(&self, dim: usize, correction: usize, keepdim: bool) -> Result<Self> {
+        self.check_correction(dim, correction)?;
+        let output = self.reduce(ReduceOp::Std { correction }, dim, keepdim)?;
+        let (input, std) = (self.detach(), output.detach());
+        let saved = vec![input.saved_version(), std.saved_version()];
+        Ok(autograd::record("std", output, &[self], saved, move |grad| {
+            // d std / dx = (x - mean) / ((n - correction) std)
+            let n = input.shape().dims()[dim] as f32;
+            let centered = input.sub(&input.mean(dim, true)?)?;
+            let grad = Self::expand_reduced(&grad.divide(&std)?, dim, input.shape())?;
+            Ok(vec![Some(grad.multiply(&centered)?.scalar_multiply(1.0 / (n - correction as f32))?)])
+        }))
+    }
+
+    /// Rejects a correction that leaves no positive denominator for `var` and `std`
+    fn check_correction(&self, dim: usize, correction: usize) -> Result<()> {
+        match self.shape().dims().get(dim) {
+            Some(&size) if correction >= size => Err(FerroFlowError::InvalidOperation(format!(
+                "Correction {} must be less than the {} elements along dimension {}", correction, size, dim
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Normalizes `dim` into a probability distribution with a fused, numerically stable kernel.
+    pub fn softmax(&self, dim: usize) -> Result<Self> {
+        let output = self.softmax_forward(SoftmaxOp::Softmax, dim, false)?;
+        let probs = output.detach();
+        let saved = vec![probs.saved_version()];
+        Ok(autograd::record("softmax", output, &[self], saved, move |grad| {
+            // dx = y * (g - sum(g * y))
+            let dot = grad.multiply(&probs)?.sum(dim, true)?;
+            Ok(vec![Some(probs.multiply(&grad.sub(&dot)?)?)])
+        }))
+    }
+
+    /// Computes `log(softmax(x))` along `dim` without forming the probabilities.
+    pub fn log_softmax(&self, dim: usize) -> Result<Self> {
+        let output = self.softmax_forward(SoftmaxOp::LogSoftmax, dim, false)?;
+        let log_probs = output.detach();
+        let saved = vec![log_probs.saved_version()];
+        Ok(autograd::record("log_softmax", output, &[self], saved, move |grad| {
+            // dx = g - softmax(x) * sum(g)
+            let total = grad.sum(dim, true)?;
+            Ok(vec![Some(grad.sub(&log_probs.exp()?.multiply(&total)?)?)])
+        }))
+    }
+
+    /// Computes `log(sum(exp(x)))` along `dim`, shifted by the maximum to avoid overflow.
+    pub fn logsumexp(&self, dim: usize, keepdim: bool) -> Result<Self> {
+        let output = self.softmax_forward(SoftmaxOp::LogSumExp, dim, keepdim)?;
+        let (input, lse) = (self.detach(), output.detach());
+        let saved = vec![input.saved_version(), lse.saved_version()];
+        Ok(autograd::record("logsumexp", output, &[self], saved, move |grad| {
+            // dx = g * exp(x - logsumexp(x)) = g * softmax(x)
+            let input_shape = input.shape();
+            let lse = lse.reshape(Self::reduced_shape(input_shape, dim, true))?;
+            let probs = input.sub(&lse)?.exp()?;
+            Ok(vec![Some(Self::expand_reduced(grad, dim, input_shape)?.multiply(&probs)?)])
+        }))
+    }
+
+    /// Untracked forward pass of the softmax family.
+    fn softmax_forward(&self, op: SoftmaxOp, dim: usize, keepdim: bool) -> Result<Self> {
+        self.check_f32(&[], &format!("{:?}", op))?;
+        self.check_dim(dim)?;
+        let (outer, size, inner) = self.reduction_extents(dim);
+        let input = self.materialize()?;
+        let buffer = B::softmax(&self.ctx, op, &input.buffer(), outer, size, inner)?;
+
+        let shape = match op {
+            SoftmaxOp::LogSumExp => Self::reduced_shape(self.shape(), dim, keepdim),
+            _ => self.shape().clone(),
+        };
+        Ok(Self::from_buffer(Arc::clone(&self.ctx), buffer, shape))
+    }
+
+    /// Max or min along `dim`, with a backward pass that routes the gradient to the selected element.
+    fn extreme(&self, op: ReduceOp, arg_op: ReduceOp, dim: usize, keepdim: bool) -> Result<Self> {
+        let output = self.reduce(op, dim, keepdim)?;
+        let input = self.detach();
+        let saved = vec![input.saved_version()];
+        Ok(autograd::record("max_min", output, &[self], saved, move |grad| {
+            let (_, size, inner) = input.reduction_extents(dim);
+            let selected = input.reduce(arg_op, dim, false)?.to_vec::<i64>()?;
+            let grad_data = grad.data()?;
+            let mut result = vec![0.0; input.shape().size()];
+            for (lane, (&index, &g)) in selected.iter().zip(&grad_data).enumerate() {
+                let (o, i) = (lane / inner, lane % inner);
+                result[(o * size + index as usize) * inner + i] = g;
+            }
+            Ok(vec![Some(Self::new(Arc::clone(&grad.ctx), input.shape().clone(), &result)?)])
+        }))
+    }
+
+    /// Untracked reduction along `dim`, shared by the public reduction methods.
+    fn reduce(&self, op: ReduceOp, dim: usize, keepdim: bool) -> Result<Self> {
+        self.check_f32(&[], &format!("{:?}", op))?;
+        self.check_dim(dim)?;
+        let (outer, size, inner) = self.reduction_extents(dim);
+        if size == 0 && !matches!(op, ReduceOp::Sum | ReduceOp::Mean | ReduceOp::Prod) {
+            return Err(FerroFlowError::InvalidOperation(
+                format!("{:?} over dimension {} of size 0", op, dim)
+            ));
+        }
+
+        let input = self.materialize()?;
+        let buffer = B::reduce(&self.ctx, op, &input.buffer(), outer, size, inner)?;
+        let shape = Self::reduced_shape(self.shape(), dim, keepdim);
+        Ok(Self::from_typed_buffer(Arc::clone(&self.ctx), buffer, shape, op.output_dtype()))
+    }
+
+    fn check_dim(&self, dim: usize) -> Result<()> {
+        if dim >= self.layout.rank() {
+            return Err(FerroFlowError::InvalidOperation(
+                format!("Dimension {} out of range for tensor of rank {}", dim, self.layout.rank())
+            ));
+        }
+        Ok(())
+    }
+
+    /// Splits the shape around `dim` into `(outer, size, inner)` element counts.
+    fn reduction_extents(&self, dim: usize) -> (usize, usize, usize) {
+        let dims = self.shape().dims();
+        (dims[..dim].iter().product(), dims[dim], dims[dim + 1..].iter().product())
+    }
+
+    fn reduced_shape(shape: &Shape, dim: usize, keepdim: bool) -> Shape {
+        let mut dims = shape.dims().to_vec();
+        if keepdim {
+            dims[dim] = 1;
+        } else {
+            dims.remove(dim);
+        }
+        Shape::new(dims)
+    }
+
+    /// Broadcasts the gradient of a reduction along `dim` back to the input shape.
+    fn expand_reduced(grad: &Self, dim: usize, input_shape: &Shape) -> Result<Self> {
+        grad.reshape(Self::reduced_shape(input_shape, dim, true))?.broadcast_to(input_shape)
     }
 
     /// Performs matrix multiplication with another tensor
     #[instrument(skip(self, other))]
     pub fn matmul(&self, other: &Self) -> Result<Self> {
-        // Get dimensions
-        if self.shape.dims().len() < 2 || other.shape.dims().len() < 2 {
-            return Err(FerroFlowError::ShapeMismatch(
-                "Matmul requires at least 2D tensors".into()
-            ));
+        self.matmul_transposed(other, false, false)
+    }
+
+    /// Performs matrix multiplication with optional transposition of either operand.
+    /// The stored shapes are interpreted as (K x M) / (N x K) when the corresponding flag is set.
+    #[instrument(skip(self, other))]
+    pub fn matmul_transposed(&self, other: &Self, transpose_a: bool, transpose_b: bool) -> Result<Self> {
+        let output = self.matmul_transposed_forward(other, transpose_a, transpose_b)?;
+        let (a, b) = (self.detach(), other.detach());
+        let (a_needs_grad, b_needs_grad) = (self.needs_grad(), other.needs_grad());
+        let saved = vec![a.saved_version(), b.saved_version()];
+        Ok(autograd::record("matmul", output, &[self, other], saved, move |grad| {
+            // With C = op(A) op(B): dop(A) = G op(B)^T and dop(B) = op(A)^T G,
+            // transposed back when the operand itself was stored transposed
+            let grad_a = match (a_needs_grad, transpose_a) {
+                (false, _) => None,
+                (true, false) => Some(grad.matmul_transposed(&b, false, !transpose_b)?),
+                (true, true) => Some(b.matmul_transposed(grad, transpose_b, true)?),
+            };
+            let grad_b = match (b_needs_grad, transpose_b) {
+                (false, _) => None,
+                (true, false) => Some(a.matmul_transposed(grad, !transpose_a, false)?),
+                (true, true) => Some(grad.matmul_transposed(&a, true, transpose_a)?),
+            };
+            Ok(vec![grad_a, grad_b])
+        }))
+    }
+
+    fn matmul_transposed_forward(&self, other: &Self, transpose_a: bool, transpose_b: bool) -> Result<Self> {
+        let MatmulPlan { lhs, rhs, batch_size, m, n, k, transpose_a, transpose_b } =
+            self.plan_matmul(other, transpose_a, transpose_b)?;
+
+        match batch_size {
+            Some(batch_size) => {
+                debug!("Performing batched matmul with shapes {:?} x {:?}", self.shape(), other.shape());
+                let result_buffer = lhs.with_buffers(&rhs, |lhs, rhs| if transpose_a || transpose_b {
+                    B::matmul_transposed_batched(&self.ctx, lhs, rhs, batch_size, m, n, k, transpose_a, transpose_b)
+                } else {
+                    B::matmul_batched(&self.ctx, lhs, rhs, batch_size, m, n, k)
+                })?;
+                Ok(Self::from_buffer(Arc::clone(&self.ctx), result_buffer, Shape::new_batched(batch_size, m, n)))
+            },
+            None => {
+                debug!("Performing matmul with shapes {:?} x {:?}", self.shape(), other.shape());
+                let result_buffer = lhs.with_buffers(&rhs, |lhs, rhs| if transpose_a || transpose_b {
+                    B::matmul_transposed(&self.ctx, lhs, rhs, m, n, k, transpose_a, transpose_b)
+                } else {
+                    B::matmul(&self.ctx, lhs, rhs, m, n, k)
+                })?;
+                Ok(Self::from_buffer(Arc::clone(&self.ctx), result_buffer, Shape::new(vec![m, n])))
+            },
         }
+    }
+
+    /// Validates the operands of a matmul and works out the kernel's dimensions and operands
+    fn plan_matmul(&self, other: &Self, transpose_a: bool, transpose_b: bool) -> Result<MatmulPlan<B>> {
+        self.check_f32(&[other], "matmul")?;
+        let supported = |tensor: &Self| (2..=3).contains(&tensor.shape().dims().len());
+        if !supported(self) || !supported(other) {
+            return Err(FerroFlowError::ShapeMismatch(format!(
+                "Matmul supports matrices and batches of matrices, got shapes {:?} and {:?}",
+                self.shape().dims(), other.shape().dims()
+            )));
+        }
+
+        let (m, k1) = match self.shape().matrix_dims() {
+            (rows, cols) if transpose_a => (cols, rows),
+            dims => dims,
+        };
+        let (k2, n) = match other.shape().matrix_dims() {
+            (rows, cols) if transpose_b => (cols, rows),
+            dims => dims,
+        };
 
-        let (m, k1) = self.shape.matrix_dims();
-        let (k2, n) = other.shape.matrix_dims();
-        
         if k1 != k2 {
             return Err(FerroFlowError::ShapeMismatch(
-                format!("Incompatible dimensions for matmul: {:?} and {:?}", 
-                    self.shape.dims(), other.shape.dims())
+                format!("Incompatible dimensions for matmul: {:?} (transpose: {}) and {:?} (transpose: {})",
+                    self.shape().dims(), transpose_a, other.shape().dims(), transpose_b)
             ));
         }
 
-        // Check if this is a batched operation
-        match (self.shape.batch_size(), other.shape.batch_size()) {
-            (Some(b1), Some(b2)) if b1 == b2 => {
-                debug!("Performing batched matmul with shapes {:?} x {:?}", self.shape, other.shape);
-                let result_buffer = B::matmul_batched(
-                    &self.ctx,
-                    &self.buffer,
-                    &other.buffer,
-                    b1,
-                    m,
-                    n,
-                    k1
-                )?;
-                Ok(Self {
-                    buffer: result_buffer,
-                    shape: Shape::new_batched(b1, m, n),
-                    ctx: Arc::clone(&self.ctx),
-                })
-            },
-            (None, None) => {
-                debug!("Performing matmul with shapes {:?} x {:?}", self.shape, other.shape);
-                let result_buffer = B::matmul(
-                    &self.ctx,
-                    &self.buffer,
-                    &other.buffer,
-                    m,
-                    n,
-                    k1
-                )?;
-                Ok(Self {
-                    buffer: result_buffer,
-                    shape: Shape::new(vec![m, n]),
-                    ctx: Arc::clone(&self.ctx),
-                })
-            },
-            _ => Err(FerroFlowError::ShapeMismatch(
+        let batch_size = match (self.shape().batch_size(), other.shape().batch_size()) {
+            (Some(b1), Some(b2)) if b1 == b2 => Some(b1),
+            (None, None) => None,
+            _ => return Err(FerroFlowError::ShapeMismatch(
                 "Batch sizes must match for batched matmul".into()
-            ))
+            )),
+        };
+
+        // Transposed views are handed to the kernel as transposed operands instead of being copied
+        let (lhs, lhs_transposed) = self.matrix_operand()?;
+        let (rhs, rhs_transposed) = other.matrix_operand()?;
+        Ok(MatmulPlan {
+            lhs,
+            rhs,
+            batch_size,
+            m,
+            n,
+            k: k1,
+            transpose_a: transpose_a != lhs_transposed,
+            transpose_b: transpose_b != rhs_transposed,
+        })
+    }
+
+    /// Returns a dense tensor holding this matrix (or batch of matrices) and whether its
+    /// buffer stores the matrices transposed. A view produced by swapping the last two
+    /// dimensions of a dense tensor is returned as-is with the flag set.
+    fn matrix_operand(&self) -> Result<(Self, bool)> {
+        if self.is_dense() {
+            return Ok((self.detach(), false));
+        }
+
+        let rank = self.layout.rank();
+        let swapped = self.with_layout(self.layout.transpose(rank - 2, rank - 1)?);
+        if swapped.is_dense() {
+            return Ok((swapped, true));
         }
+
+        Ok((self.materialize()?, false))
     }
 
-    pub fn t(&self) -> TransposedTensor<B> {
+    pub fn t(&self) -> TransposedTensor<'_, B> {
         TransposedTensor { tensor: self, transpose: true }
     }
 
@@ -263,6 +1130,32 @@ impl<B: ComputeBackend> Tensor<B> {
     }
 }
 
+// Cloning is cheap: the clone shares the backend buffer and the autograd node
+impl<B: ComputeBackend> Clone for Tensor<B> {
+    fn clone(&self) -> Self {
+        Self {
+            buffer: Arc::clone(&self.buffer),
+            version: Arc::clone(&self.version),
+            layout: self.layout.clone(),
+            dtype: self.dtype,
+            ctx: Arc::clone(&self.ctx),
+            autograd: self.autograd.clone(),
+        }
+    }
+}
+
+/// Dense operands and dimensions of a matmul kernel call, see `Tensor::plan_matmul`
+struct MatmulPlan<B: ComputeBackend> {
+    lhs: Tensor<B>,
+    rhs: Tensor<B>,
+    batch_size: Option<usize>,
+    m: usize,
+    n: usize,
+    k: usize,
+    transpose_a: bool,
+    transpose_b: bool,
+}
+
 pub struct TransposedTensor<'a, B: ComputeBackend> {
     tensor: &'a Tensor<B>,
     transpose: bool,
@@ -276,10 +1169,12 @@ impl<'a, B: ComputeBackend> Mul for &TransposedTensor<'a, B> {
     }
 }
 
-// Implement Drop to ensure proper cleanup of backend resources
+// The last tensor referring to a buffer hands it back to the backend, which may cache it for reuse
 impl<B: ComputeBackend> Drop for Tensor<B> {
     fn drop(&mut self) {
-        // Backend-specific cleanup is handled by the backend's Buffer type
+        if let Some(buffer) = Arc::get_mut(&mut self.buffer) {
+            B::release_buffer(&self.ctx, buffer.get_mut().unwrap_or_else(PoisonError::into_inner));
+        }
     }
 }
 
@@ -291,6 +1186,22 @@ impl<B: ComputeBackend> Add for &Tensor<B> {
     }
 }
 
+impl<B: ComputeBackend> Sub for &Tensor<B> {
+    type Output = Result<Tensor<B>>;
+
+    fn sub(self, other: &Tensor<B>) -> Self::Output {
+        self.sub(other)
+    }
+}
+
+impl<B: ComputeBackend> Div for &Tensor<B> {
+    type Output = Result<Tensor<B>>;
+
+    fn div(self, other: &Tensor<B>) -> Self::Output {
+        self.divide(other)
+    }
+}
+
 impl<B: ComputeBackend> Mul for &Tensor<B> {
     type Output = Result<Tensor<B>>;
 
@@ -303,6 +1214,8 @@ pub struct TensorChain<B: ComputeBackend> {
     tensor: Result<Tensor<B>>
 }
 
+// Method names mirror `Tensor` rather than the operator traits
+#[allow(clippy::should_implement_trait)]
 impl<B: ComputeBackend> TensorChain<B> {
     pub fn matmul(self, other: &Tensor<B>) -> Self {
         TensorChain {
@@ -312,9 +1225,14 @@ impl<B: ComputeBackend> TensorChain<B> {
     
     pub fn transpose(self) -> Self {
         TensorChain {
-            tensor: self.tensor.map(|t| TransposedTensor { 
-                tensor: &t, 
-                transpose: true 
+            tensor: self.tensor.and_then(|t| {
+                let rank = t.shape().dims().len();
+                if rank < 2 {
+                    return Err(FerroFlowError::ShapeMismatch(
+                        "Transpose requires at least 2D tensors".into()
+                    ));
+                }
+                t.transpose(rank - 2, rank - 1)
             })
         }
     }
@@ -367,4 +1285,7 @@ impl<B: ComputeBackend> Neg for &Tensor<B> {
     fn neg(self) -> Self::Output {
         self.scalar_multiply(-1.0)
     }
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,127 @@
+use super::{Shape, Tensor};
+use crate::autograd;
+use crate::compute::{ComputeBackend, NormOp};
+use crate::error::{Result, FerroFlowError};
+use std::sync::Arc;
+
+impl<B: ComputeBackend> Tensor<B> {
+    /// Layer normalization over the trailing `normalized_shape` dimensions, followed by an
+    /// optional element-wise affine transform whose `weight` and `bias` have `normalized_shape`.
+    pub fn layer_norm(
+        &self,
+        normalized_shape: &[usize],
+        weight: Option<&Self>,
+        bias: Option<&Self>,
+        eps: f32
+    ) -> Result<Self> {
+        let size = self.trailing_size(normalized_shape, "layer_norm")?;
+        let output = self.normalize(NormOp::LayerNorm, size, eps)?;
+        output.affine(weight, bias, normalized_shape, normalized_shape)
+    }
+
+    /// RMS normalization over the trailing `normalized_shape` dimensions, with an optional
+    /// `weight` of `normalized_shape` as scale
+    pub fn rms_norm(&self, normalized_shape: &[usize], weight: Option<&Self>, eps: f32) -> Result<Self> {
+        let size = self.trailing_size(normalized_shape, "rms_norm")?;
+        let output = self.normalize(NormOp::RmsNorm, size, eps)?;
+        output.affine(weight, None, normalized_shape, normalized_shape)
+    }
+
+    /// Group normalization of an `[N, C, *]` tensor: the channels are split into `num_groups`
+    /// groups, each normalized over its channels and all spatial positions. `weight` and
+    /// `bias` are per-channel, `[C]`.
+    pub fn group_norm(&self, num_groups: usize, weight: Option<&Self>, bias: Option<&Self>, eps: f32) -> Result<Self> {
+        let dims = self.shape().dims();
+        if dims.len() < 2 {
+            return Err(FerroFlowError::ShapeMismatch(format!(
+                "group_norm expects an [N, C, *] tensor, got shape {:?}", dims
+            )));
+        }
+        let channels = dims[1];
+        if num_groups == 0 || !channels.is_multiple_of(num_groups) {
+            return Err(FerroFlowError::InvalidOperation(format!(
+                "{} groups do not evenly divide {} channels", num_groups, channels
+            )));
+        }
+
+        // In row-major NCHW each group of a sample is one contiguous row
+        let size = channels / num_groups * dims[2..].iter().product::<usize>();
+        let output = self.normalize(NormOp::LayerNorm, size, eps)?;
+        output.affine(weight, bias, &[channels], &Self::channel_shape(dims))
+    }
+
+    /// Returns `[1, C, 1, ...]` for an `[N, C, *]` shape, which broadcasts a per-channel
+    /// vector over the batch and spatial dimensions
+    pub(crate) fn channel_shape(dims: &[usize]) -> Vec<usize> {
+        let mut shape = vec![1; dims.len()];
+        shape[1] = dims[1];
+        shape
+    }
+
+    /// Normalizes every run of `size` consecutive elements of the row-major data
+    /// with the fused backend kernel.
+    pub(crate) fn normalize(&self, op: NormOp, size: usize, eps: f32) -> Result<Self> {
+        self.check_f32(&[], "normalization")?;
+        if size == 0 || eps.is_nan() || eps < 0.0 {
+            return Err(FerroFlowError::InvalidOperation(format!(
+                "Normalization needs a non-empty group and a non-negative eps, got size {} and eps {}", size, eps
+            )));
+        }
+
+        let rows = self.shape().size() / size;
+        let input = self.materialize()?;
+        let (buffer, rstd) = B::normalize(&self.ctx, op, &input.buffer(), rows, size, eps)?;
+        let output = Self::from_buffer(Arc::clone(&self.ctx), buffer, self.shape().clone());
+
+        let normalized = output.detach();
+        let saved = vec![normalized.saved_version()];
+        Ok(autograd::record("normalize", output, &[self], saved, move |grad| {
+            let grad = grad.materialize()?;
+            let buffer = normalized.with_buffers(&grad, |normalized, grad_buffer| {
+                B::normalize_backward(&grad.ctx, op, normalized, &rstd, grad_buffer, size)
+            })?;
+            Ok(vec![Some(Self::from_buffer(Arc::clone(&grad.ctx), buffer, grad.shape().clone()))])
+        }))
+    }
+
+    /// Returns the number of elements in the trailing `normalized_shape` dimensions,
+    /// which must match the end of this tensor's shape
+    fn trailing_size(&self, normalized_shape: &[usize], name: &str) -> Result<usize> {
+        let dims = self.shape().dims();
+        if normalized_shape.is_empty() || !dims.ends_with(normalized_shape) {
+            return Err(FerroFlowError::ShapeMismatch(format!(
+                "{} over {:?} requires a tensor ending in those dimensions, got shape {:?}",
+                name, normalized_shape, dims
+            )));
+        }
+        Ok(normalized_shape.iter().product())
+    }
+
+    /// Applies `self * weight + bias`, where `weight` and `bias` must have `param_shape`
+    /// and are reshaped to `broadcast_shape` first
+    pub(crate) fn affine(
+        self,
+        weight: Option<&Self>,
+        bias: Option<&Self>,
+        param_shape: &[usize],
+        broadcast_shape: &[usize]
+    ) -> Result<Self> {
+        let expand = |param: &Self, name: &str| -> Result<Self> {
+            if param.shape().dims() != param_shape {
+                return Err(FerroFlowError::ShapeMismatch(format!(
+                    "Normalization {} must have shape {:?}, got {:?}", name, param_shape, param.shape().dims()
+                )));
+            }
+            param.reshape(Shape::new(broadcast_shape.to_vec()))
+        };
+
+        let mut output = self;
+        if let Some(weight) = weight {
+            output = output.multiply(&expand(weight, "weight")?)?;
+        }
+        if let Some(bias) = bias {
+            output = output.add(&expand(bias, "bias")?)?;
+        }
+        Ok(output)
+    }
+}
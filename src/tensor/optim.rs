@@ -0,0 +1,40 @@
+use super::Tensor;
+use crate::compute::{ComputeBackend, OptimizerUpdate};
+use crate::error::{Result, FerroFlowError};
+
+impl<B: ComputeBackend> Tensor<B> {
+    /// Applies one optimizer update to this parameter in place, given its gradient and the
+    /// `update.state_len()` state tensors the optimizer keeps for it. The update is not recorded
+    /// for autograd and is visible through every tensor sharing the parameter's buffer. It bumps
+    /// the buffer's write count, so backward passes that saved the parameter fail.
+    pub(crate) fn apply_optimizer_update(&self, update: &OptimizerUpdate, grad: &Self, state: &[Self]) -> Result<()> {
+        self.check_f32(&[grad], "optimizer step")?;
+        if grad.shape() != self.shape() {
+            return Err(FerroFlowError::ShapeMismatch(format!(
+                "Gradient shape {:?} doesn't match parameter shape {:?}", grad.shape().dims(), self.shape().dims()
+            )));
+        }
+        if !self.is_dense() {
+            return Err(FerroFlowError::InvalidOperation(format!(
+                "Optimizers update parameters in place and need a dense tensor, got layout {:?}", self.layout
+            )));
+        }
+        let size = self.shape().size();
+        let valid_state = |s: &Self| s.shape().size() == size && s.is_dense() && !s.shares_buffer(self);
+        if state.len() != update.state_len() || !state.iter().all(valid_state) {
+            return Err(FerroFlowError::InvalidOperation(format!(
+                "{:?} needs {} dense state tensors of {} elements, separate from the parameter",
+                update, update.state_len(), size
+            )));
+        }
+
+        // Reading a gradient that shares the parameter's buffer while writing the parameter
+        // would deadlock, so such a gradient is copied first
+        let grad = if grad.shares_buffer(self) { grad.copy_untracked()? } else { grad.materialize()? };
+
+        let grad_buffer = grad.buffer();
+        let mut state_buffers: Vec<_> = state.iter().map(|s| s.buffer_mut()).collect();
+        let mut state_buffers: Vec<&mut B::Buffer> = state_buffers.iter_mut().map(|buffer| &mut **buffer).collect();
+        B::optimizer_step(&self.ctx, update, &mut self.buffer_mut(), &grad_buffer, &mut state_buffers, size)
+    }
+}
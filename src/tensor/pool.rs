@@ -0,0 +1,115 @@
+use super::{Shape, Tensor};
+use crate::autograd;
+use crate::compute::{ComputeBackend, Pool2dParams, PoolWindow};
+use crate::dtype::DType;
+use crate::error::{Result, FerroFlowError};
+use std::sync::Arc;
+
+/// Window configuration for [`Tensor::max_pool2d`] and [`Tensor::avg_pool2d`],
+/// given as `(height, width)` pairs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pool2dOptions {
+    pub kernel_size: (usize, usize),
+    pub stride: (usize, usize),
+    /// Implicit padding on each side, at most half the kernel size
+    pub padding: (usize, usize),
+    /// Whether padded positions count towards the divisor of `avg_pool2d`
+    pub count_include_pad: bool,
+}
+
+impl Pool2dOptions {
+    /// Non-overlapping windows of `kernel_size`, without padding
+    pub fn new(kernel_size: (usize, usize)) -> Self {
+        Self { kernel_size, stride: kernel_size, padding: (0, 0), count_include_pad: true }
+    }
+}
+
+impl<B: ComputeBackend> Tensor<B> {
+    /// 2D max pooling of an NCHW tensor. Returns the maxima and an i64 tensor of the same shape
+    /// holding the flat index of each maximum within its `H x W` input plane.
+    pub fn max_pool2d(&self, options: Pool2dOptions) -> Result<(Self, Self)> {
+        let params = self.pool_params(fixed_window(options)?, "max_pool2d")?;
+        self.max_pool2d_forward(params, "max_pool2d")
+    }
+
+    /// 2D average pooling of an NCHW tensor
+    pub fn avg_pool2d(&self, options: Pool2dOptions) -> Result<Self> {
+        let params = self.pool_params(fixed_window(options)?, "avg_pool2d")?;
+        self.avg_pool2d_forward(params, options.count_include_pad, "avg_pool2d")
+    }
+
+    /// Max pooling to a fixed `(H_out, W_out)` regardless of the input size.
+    /// Returns the maxima and their indices like [`Tensor::max_pool2d`].
+    pub fn adaptive_max_pool2d(&self, output_size: (usize, usize)) -> Result<(Self, Self)> {
+        let params = self.pool_params(PoolWindow::Adaptive { output_size }, "adaptive_max_pool2d")?;
+        self.max_pool2d_forward(params, "adaptive_max_pool2d")
+    }
+
+    /// Average pooling to a fixed `(H_out, W_out)` regardless of the input size
+    pub fn adaptive_avg_pool2d(&self, output_size: (usize, usize)) -> Result<Self> {
+        let params = self.pool_params(PoolWindow::Adaptive { output_size }, "adaptive_avg_pool2d")?;
+        self.avg_pool2d_forward(params, false, "adaptive_avg_pool2d")
+    }
+
+    fn pool_params(&self, window: PoolWindow, name: &str) -> Result<Pool2dParams> {
+        let [batch_size, channels, height, width] = self.nchw_dims(name)?;
+        let params = Pool2dParams { batch_size, channels, input_size: (height, width), window };
+        if height == 0 || width == 0 || matches!(params.output_size(), (0, _) | (_, 0)) {
+            return Err(FerroFlowError::ShapeMismatch(format!(
+                "{} of a {}x{} input with {:?} has an empty output", name, height, width, window
+            )));
+        }
+        Ok(params)
+    }
+
+    fn output_shape(params: &Pool2dParams) -> Shape {
+        let (out_h, out_w) = params.output_size();
+        Shape::new(vec![params.batch_size, params.channels, out_h, out_w])
+    }
+
+    fn max_pool2d_forward(&self, params: Pool2dParams, name: &'static str) -> Result<(Self, Self)> {
+        let input = self.materialize()?;
+        let (values, indices) = B::max_pool2d(&self.ctx, &input.buffer(), &params)?;
+        let shape = Self::output_shape(&params);
+        let values = Self::from_buffer(Arc::clone(&self.ctx), values, shape.clone());
+        let indices = Self::from_typed_buffer(Arc::clone(&self.ctx), indices, shape, DType::I64);
+
+        let (selected, input_shape) = (indices.clone(), self.shape().clone());
+        let saved = vec![selected.saved_version()];
+        let values = autograd::record(name, values, &[self], saved, move |grad| {
+            let grad = grad.materialize()?;
+            let buffer = grad.with_buffers(&selected, |grad_buffer, selected| B::max_pool2d_backward(&grad.ctx, grad_buffer, selected, &params))?;
+            Ok(vec![Some(Self::from_buffer(Arc::clone(&grad.ctx), buffer, input_shape.clone()))])
+        });
+        Ok((values, indices))
+    }
+
+    fn avg_pool2d_forward(&self, params: Pool2dParams, count_include_pad: bool, name: &'static str) -> Result<Self> {
+        let input = self.materialize()?;
+        let buffer = B::avg_pool2d(&self.ctx, &input.buffer(), &params, count_include_pad)?;
+        let output = Self::from_buffer(Arc::clone(&self.ctx), buffer, Self::output_shape(&params));
+
+        let input_shape = self.shape().clone();
+        Ok(autograd::record(name, output, &[self], Vec::new(), move |grad| {
+            let grad = grad.materialize()?;
+            let buffer = B::avg_pool2d_backward(&grad.ctx, &grad.buffer(), &params, count_include_pad)?;
+            Ok(vec![Some(Self::from_buffer(Arc::clone(&grad.ctx), buffer, input_shape.clone()))])
+        }))
+    }
+}
+
+fn fixed_window(options: Pool2dOptions) -> Result<PoolWindow> {
+    let Pool2dOptions { kernel_size, stride, padding, .. } = options;
+    if kernel_size.0 == 0 || kernel_size.1 == 0 || stride.0 == 0 || stride.1 == 0 {
+        return Err(FerroFlowError::InvalidOperation(format!(
+            "Pooling kernel size {:?} and stride {:?} must be positive", kernel_size, stride
+        )));
+    }
+    // Larger padding would allow windows that cover no input at all
+    if 2 * padding.0 > kernel_size.0 || 2 * padding.1 > kernel_size.1 {
+        return Err(FerroFlowError::InvalidOperation(format!(
+            "Pooling padding {:?} must be at most half the kernel size {:?}", padding, kernel_size
+        )));
+    }
+    Ok(PoolWindow::Fixed { kernel_size, stride, padding })
+}
@@ -0,0 +1,282 @@
+//! Block-quantized weights in the GGML formats used by GGUF files.
+//!
+//! Each format splits a row into fixed-length blocks that store low-bit integers with an f16
+//! scale, plus per-sub-block scales and minimums for the k-quants. Rows always hold whole blocks.
+
+use super::{Shape, Tensor};
+use crate::compute::ComputeBackend;
+use crate::error::{Result, FerroFlowError};
+use half::f16;
+use std::sync::Arc;
+
+/// Below this many multiply-adds the cost of spawning threads outweighs the speedup
+const PARALLEL_THRESHOLD: usize = 1 << 18;
+
+/// A GGML block quantization format
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QuantType {
+    /// Blocks of 32 4-bit values with an f16 scale, centered on 8
+    Q4_0,
+    /// Blocks of 32 8-bit values with an f16 scale
+    Q8_0,
+    /// Super-blocks of 256 4-bit values in 8 sub-blocks with 6-bit scales and minimums
+    Q4_K,
+    /// Super-blocks of 256 6-bit values in 16 sub-blocks with 8-bit scales
+    Q6_K,
+}
+
+impl QuantType {
+    /// Returns the number of values in one block
+    pub fn block_len(self) -> usize {
+        match self {
+            QuantType::Q4_0 | QuantType::Q8_0 => 32,
+            QuantType::Q4_K | QuantType::Q6_K => 256,
+        }
+    }
+
+    /// Returns the number of bytes one block occupies
+    pub fn block_bytes(self) -> usize {
+        match self {
+            QuantType::Q4_0 => 18,
+            QuantType::Q8_0 => 34,
+            QuantType::Q4_K => 144,
+            QuantType::Q6_K => 210,
+        }
+    }
+
+    /// Expands one block of `block_bytes()` bytes into `block_len()` values
+    fn dequantize_block(self, block: &[u8], out: &mut [f32]) {
+        let half = |i: usize| f16::from_le_bytes([block[i], block[i + 1]]).to_f32();
+        match self {
+            QuantType::Q4_0 => {
+                let d = half(0);
+                for (j, &q) in block[2..18].iter().enumerate() {
+                    out[j] = d * ((q & 0xF) as i32 - 8) as f32;
+                    out[j + 16] = d * ((q >> 4) as i32 - 8) as f32;
+                }
+            }
+            QuantType::Q8_0 => {
+                let d = half(0);
+                for (value, &q) in out.iter_mut().zip(&block[2..34]) {
+                    *value = d * q as i8 as f32;
+                }
+            }
+            QuantType::Q4_K => {
+                let (d, min) = (half(0), half(2));
+                let scales = &block[4..16];
+                // Each 32-byte chunk holds two sub-blocks, in its low and high nibbles
+                for (chunk, (qs, out)) in block[16..144].chunks_exact(32).zip(out.chunks_exact_mut(64)).enumerate() {
+                    let (low, high) = out.split_at_mut(32);
+                    for (sub_block, out, shift) in [(2 * chunk, low, 0), (2 * chunk + 1, high, 4)] {
+                        let (scale, offset) = k_scale_min(scales, sub_block);
+                        let (scale, offset) = (d * scale as f32, min * offset as f32);
+                        for (value, &q) in out.iter_mut().zip(qs) {
+                            *value = scale * ((q >> shift) & 0xF) as f32 - offset;
+                        }
+                    }
+                }
+            }
+            QuantType::Q6_K => {
+                let d = half(208);
+                for half_block in 0..2 {
+                    let ql = &block[64 * half_block..64 * half_block + 64];
+                    let qh = &block[128 + 32 * half_block..128 + 32 * half_block + 32];
+                    let scales = &block[192 + 8 * half_block..192 + 8 * half_block + 8];
+                    let out = &mut out[128 * half_block..128 * half_block + 128];
+                    for l in 0..32 {
+                        // The four quarters take their low bits from the nibbles of `ql` and
+                        // their high bits from successive bit pairs of `qh`
+                        let quarters = [ql[l] & 0xF, ql[l + 32] & 0xF, ql[l] >> 4, ql[l + 32] >> 4];
+                        for (quarter, low) in quarters.into_iter().enumerate() {
+                            let q = (low | (((qh[l] >> (2 * quarter)) & 3) << 4)) as i32 - 32;
+                            let scale = scales[l / 16 + 2 * quarter] as i8 as f32;
+                            out[l + 32 * quarter] = d * scale * q as f32;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Packs one block of `block_len()` values, for the formats [`QuantizedTensor::quantize`] supports
+    fn quantize_block(self, values: &[f32], out: &mut [u8]) {
+        match self {
+            QuantType::Q4_0 => {
+                // The value of largest magnitude maps to -8, so the scale takes its sign
+                let max = values.iter().copied().fold(0.0f32, |max, v| if v.abs() > max.abs() { v } else { max });
+                let d = max / -8.0;
+                let inverse = if d != 0.0 { 1.0 / d } else { 0.0 };
+                out[..2].copy_from_slice(&f16::from_f32(d).to_le_bytes());
+                let level = |v: f32| ((v * inverse + 8.5) as u8).min(15);
+                for j in 0..16 {
+                    out[2 + j] = level(values[j]) | (level(values[j + 16]) << 4);
+                }
+            }
+            QuantType::Q8_0 => {
+                let max = values.iter().fold(0.0f32, |max, v| max.max(v.abs()));
+                let d = max / 127.0;
+                let inverse = if d != 0.0 { 1.0 / d } else { 0.0 };
+                out[..2].copy_from_slice(&f16::from_f32(d).to_le_bytes());
+                for (q, &v) in out[2..].iter_mut().zip(values) {
+                    *q = (v * inverse).round() as i8 as u8;
+                }
+            }
+            QuantType::Q4_K | QuantType::Q6_K => unreachable!("k-quants are not quantized"),
+        }
+    }
+}
+
+/// Returns the 6-bit scale and minimum of sub-block `j` of a Q4_K block from its 12 packed bytes
+fn k_scale_min(scales: &[u8], j: usize) -> (u8, u8) {
+    if j < 4 {
+        (scales[j] & 63, scales[j + 4] & 63)
+    } else {
+        ((scales[j + 4] & 0xF) | ((scales[j - 4] >> 6) << 4), (scales[j + 4] >> 4) | ((scales[j] >> 6) << 4))
+    }
+}
+
+/// A tensor kept in a block-quantized format, such as weights loaded from a GGUF file. Its
+/// blocks live in host memory and are expanded to f32 by [`dequantize`](Self::dequantize) or
+/// row by row inside [`matmul`](Self::matmul), which never materializes the whole tensor.
+#[derive(Debug, Clone)]
+pub struct QuantizedTensor {
+    quant_type: QuantType,
+    shape: Shape,
+    data: Arc<[u8]>,
+}
+
+impl QuantizedTensor {
+    /// Wraps the blocks of a row-major tensor of `shape`, whose last dimension must be a
+    /// multiple of the block length
+    pub fn new(quant_type: QuantType, shape: Shape, data: &[u8]) -> Result<Self> {
+        let expected = Self::byte_len(quant_type, &shape)?;
+        if data.len() != expected {
+            return Err(FerroFlowError::ShapeMismatch(format!(
+                "{} bytes don't hold the {:?} blocks of shape {:?}, expected {}", data.len(), quant_type, shape.dims(), expected
+            )));
+        }
+        Ok(Self { quant_type, shape, data: data.into() })
+    }
+
+    /// Quantizes a tensor to Q4_0 or Q8_0
+    pub fn quantize<B: ComputeBackend>(tensor: &Tensor<B>, quant_type: QuantType) -> Result<Self> {
+        if matches!(quant_type, QuantType::Q4_K | QuantType::Q6_K) {
+            return Err(FerroFlowError::InvalidOperation(format!(
+                "Quantizing to {:?} is not supported, only Q4_0 and Q8_0", quant_type
+            )));
+        }
+        let mut data = vec![0; Self::byte_len(quant_type, tensor.shape())?];
+        let values = tensor.data()?;
+        for (block, out) in values.chunks_exact(quant_type.block_len()).zip(data.chunks_exact_mut(quant_type.block_bytes())) {
+            quant_type.quantize_block(block, out);
+        }
+        Self::new(quant_type, tensor.shape().clone(), &data)
+    }
+
+    /// Returns the size of the blocks holding `shape`, whose rows must hold whole blocks
+    pub(crate) fn byte_len(quant_type: QuantType, shape: &Shape) -> Result<usize> {
+        match shape.dims().last() {
+            Some(row_len) if row_len.is_multiple_of(quant_type.block_len()) => {
+                Ok(shape.size() / quant_type.block_len() * quant_type.block_bytes())
+            }
+            _ => Err(FerroFlowError::ShapeMismatch(format!(
+                "{:?} rows must hold whole blocks of {} values, got shape {:?}",
+                quant_type, quant_type.block_len(), shape.dims()
+            ))),
+        }
+    }
+
+    pub fn quant_type(&self) -> QuantType {
+        self.quant_type
+    }
+
+    pub fn shape(&self) -> &Shape {
+        &self.shape
+    }
+
+    /// Returns the quantized blocks
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Expands the tensor to f32 values in row-major order
+    pub fn dequantize_to_vec(&self) -> Vec<f32> {
+        let mut values = vec![0.0; self.shape.size()];
+        self.dequantize_rows(0, &mut values);
+        values
+    }
+
+    /// Expands the tensor into an f32 tensor of backend `B`
+    pub fn dequantize<B: ComputeBackend>(&self, ctx: Arc<B::Context>) -> Result<Tensor<B>> {
+        Tensor::new(ctx, self.shape.clone(), &self.dequantize_to_vec())
+    }
+
+    /// Computes `input @ self^T` for a 2D `[out, in]` weight, like [`Linear`](crate::nn::Linear).
+    /// Leading dimensions of `input` are kept and its last one must be `in`. Weight rows are
+    /// dequantized one at a time on the host, spread over threads for large problems; the
+    /// result is not recorded for autograd.
+    pub fn matmul<B: ComputeBackend>(&self, input: &Tensor<B>) -> Result<Tensor<B>> {
+        let &[out_features, in_features] = self.shape.dims() else {
+            return Err(FerroFlowError::ShapeMismatch(format!(
+                "Quantized matmul needs a 2D [out, in] weight, got shape {:?}", self.shape.dims()
+            )));
+        };
+        let dims = input.shape().dims();
+        if dims.last() != Some(&in_features) {
+            return Err(FerroFlowError::ShapeMismatch(format!(
+                "Quantized matmul expects inputs with {} features in the last dimension, got shape {:?}",
+                in_features, dims
+            )));
+        }
+        let mut output_dims = dims[..dims.len() - 1].to_vec();
+        output_dims.push(out_features);
+        let rows: usize = dims[..dims.len() - 1].iter().product();
+        if rows == 0 || out_features == 0 || in_features == 0 {
+            return Tensor::zeros(Arc::clone(input.context()), Shape::new(output_dims));
+        }
+        let input_values = input.data()?;
+
+        // Threads fill disjoint ranges of weight rows of the transposed `[out, rows]` output
+        let threads = if out_features * in_features * rows < PARALLEL_THRESHOLD {
+            1
+        } else {
+            std::thread::available_parallelism().map(|threads| threads.get()).unwrap_or(1).min(out_features)
+        };
+        let rows_per_thread = out_features.div_ceil(threads);
+        let mut transposed = vec![0.0; out_features * rows];
+        std::thread::scope(|scope| {
+            for (t, chunk) in transposed.chunks_mut(rows_per_thread * rows).enumerate() {
+                let input_values = &input_values;
+                scope.spawn(move || {
+                    let mut weights = vec![0.0; in_features];
+                    for (i, outputs) in chunk.chunks_mut(rows).enumerate() {
+                        self.dequantize_rows(t * rows_per_thread + i, &mut weights);
+                        for (output, x) in outputs.iter_mut().zip(input_values.chunks_exact(in_features)) {
+                            *output = weights.iter().zip(x).map(|(w, x)| w * x).sum();
+                        }
+                    }
+                });
+            }
+        });
+
+        let mut output = vec![0.0; rows * out_features];
+        for (o, outputs) in transposed.chunks_exact(rows).enumerate() {
+            for (r, &value) in outputs.iter().enumerate() {
+                output[r * out_features + o] = value;
+            }
+        }
+        Tensor::new(Arc::clone(input.context()), Shape::new(output_dims), &output)
+    }
+
+    /// Dequantizes rows starting at `first_row` into `out`, which holds whole rows
+    fn dequantize_rows(&self, first_row: usize, out: &mut [f32]) {
+        let (block_len, block_bytes) = (self.quant_type.block_len(), self.quant_type.block_bytes());
+        let row_len = self.shape.dims().last().copied().unwrap_or(0);
+        let start = first_row * row_len / block_len * block_bytes;
+        let blocks = self.data[start..].chunks_exact(block_bytes);
+        for (block, values) in blocks.zip(out.chunks_exact_mut(block_len)) {
+            self.quant_type.dequantize_block(block, values);
+        }
+    }
+}
@@ -1,45 +1,849 @@
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::compute::{CPUBackend, MetalBackend};
-
-    #[test]
-    fn test_cpu_operations() -> Result<()> {
-        let ctx = CPUBackend::new()?;
-        test_backend_operations::<CPUBackend>(ctx)
-    }
-
-    #[test]
-    fn test_metal_operations() -> Result<()> {
-        let ctx = MetalBackend::new()?;
-        test_backend_operations::<MetalBackend>(ctx)
-    }
-
-    fn test_backend_operations<B: ComputeBackend>(ctx: Arc<B::Context>) -> Result<()> {
-        let a = Tensor::new(
-            Arc::clone(&ctx),
-            Shape::new(vec![2, 2]),
-            &[1.0, 2.0, 3.0, 4.0],
-        )?;
-        
-        let b = Tensor::new(
-            Arc::clone(&ctx),
-            Shape::new(vec![2, 2]),
-            &[5.0, 6.0, 7.0, 8.0],
-        )?;
-
-        // Test addition
-        let c = a.add(&b)?;
-        assert_eq!(c.data()?, vec![6.0, 8.0, 10.0, 12.0]);
-
-        // Test multiplication
-        let d = a.multiply(&b)?;
-        assert_eq!(d.data()?, vec![5.0, 12.0, 21.0, 32.0]);
-
-        // Test scalar multiplication
-        let e = a.scalar_multiply(2.0)?;
-        assert_eq!(e.data()?, vec![2.0, 4.0, 6.0, 8.0]);
-
-        Ok(())
-    }
-}
\ No newline at end of file
+use super::*;
+use crate::compute::{CPUBackend, CPUBuffer};
+#[cfg(all(feature = "metal", target_os = "macos"))]
+use crate::compute::MetalBackend;
+
+#[test]
+fn test_cpu_operations() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    test_backend_operations::<CPUBackend>(ctx)
+}
+
+#[cfg(all(feature = "metal", target_os = "macos"))]
+#[test]
+fn test_metal_operations() -> Result<()> {
+    let ctx = MetalBackend::new()?;
+    test_backend_operations::<MetalBackend>(ctx)
+}
+
+fn test_backend_operations<B: ComputeBackend>(ctx: Arc<B::Context>) -> Result<()> {
+    let a = Tensor::<B>::new(
+        Arc::clone(&ctx),
+        Shape::new(vec![2, 2]),
+        &[1.0, 2.0, 3.0, 4.0],
+    )?;
+    
+    let b = Tensor::<B>::new(
+        Arc::clone(&ctx),
+        Shape::new(vec![2, 2]),
+        &[5.0, 6.0, 7.0, 8.0],
+    )?;
+
+    // Test addition
+    let c = a.add(&b)?;
+    assert_eq!(c.data()?, vec![6.0, 8.0, 10.0, 12.0]);
+
+    // Test multiplication
+    let d = a.multiply(&b)?;
+    assert_eq!(d.data()?, vec![5.0, 12.0, 21.0, 32.0]);
+
+    // Test scalar multiplication
+    let e = a.scalar_multiply(2.0)?;
+    assert_eq!(e.data()?, vec![2.0, 4.0, 6.0, 8.0]);
+
+    Ok(())
+}
+
+#[test]
+fn test_cpu_transposed_matmul() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    test_transposed_matmul::<CPUBackend>(ctx)
+}
+
+#[cfg(all(feature = "metal", target_os = "macos"))]
+#[test]
+fn test_metal_transposed_matmul() -> Result<()> {
+    let ctx = MetalBackend::new()?;
+    test_transposed_matmul::<MetalBackend>(ctx)
+}
+
+fn test_transposed_matmul<B: ComputeBackend>(ctx: Arc<B::Context>) -> Result<()> {
+    // A stored as 3x2, interpreted as [[1, 2, 3], [4, 5, 6]]
+    let a = Tensor::<B>::new(
+        Arc::clone(&ctx),
+        Shape::new(vec![3, 2]),
+        &[1.0, 4.0, 2.0, 5.0, 3.0, 6.0],
+    )?;
+
+    // B stored as 2x3, interpreted as [[7, 8], [9, 10], [11, 12]]
+    let b = Tensor::<B>::new(
+        Arc::clone(&ctx),
+        Shape::new(vec![2, 3]),
+        &[7.0, 9.0, 11.0, 8.0, 10.0, 12.0],
+    )?;
+
+    let c = a.matmul_transposed(&b, true, true)?;
+    assert_eq!(c.shape().dims(), &[2, 2]);
+    assert_eq!(c.data()?, vec![58.0, 64.0, 139.0, 154.0]);
+
+    // Batched: the second batch is the first scaled by 2
+    let a_batched = Tensor::<B>::new(
+        Arc::clone(&ctx),
+        Shape::new_batched(2, 3, 2),
+        &[1.0, 4.0, 2.0, 5.0, 3.0, 6.0, 2.0, 8.0, 4.0, 10.0, 6.0, 12.0],
+    )?;
+    let b_batched = Tensor::<B>::new(
+        Arc::clone(&ctx),
+        Shape::new_batched(2, 3, 2),
+        &[7.0, 8.0, 9.0, 10.0, 11.0, 12.0, 7.0, 8.0, 9.0, 10.0, 11.0, 12.0],
+    )?;
+
+    let c = a_batched.matmul_transposed(&b_batched, true, false)?;
+    assert_eq!(c.shape().dims(), &[2, 2, 2]);
+    assert_eq!(c.data()?, vec![58.0, 64.0, 139.0, 154.0, 116.0, 128.0, 278.0, 308.0]);
+
+    Ok(())
+}
+
+#[test]
+fn test_cpu_matmul_rejects_mismatched_buffers() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let a = CPUBuffer::from(vec![1.0f32; 6]);
+    let b = CPUBuffer::from(vec![1.0f32; 5]);
+
+    let result = CPUBackend::matmul_transposed(&ctx, &a, &b, 2, 2, 3, false, true);
+    assert!(matches!(result, Err(FerroFlowError::ShapeMismatch(_))));
+
+    let result = CPUBackend::matmul_batched(&ctx, &a, &b, 2, 1, 1, 3);
+    assert!(matches!(result, Err(FerroFlowError::ShapeMismatch(_))));
+
+    // Only matrices and batches of matrices are multiplied
+    let vector = Tensor::<CPUBackend>::zeros(Arc::clone(&ctx), Shape::new(vec![3]))?;
+    let matrix = Tensor::<CPUBackend>::zeros(Arc::clone(&ctx), Shape::new(vec![3, 3]))?;
+    let batches = Tensor::<CPUBackend>::zeros(Arc::clone(&ctx), Shape::new(vec![2, 2, 3, 3]))?;
+    assert!(matches!(vector.matmul(&matrix), Err(FerroFlowError::ShapeMismatch(_))));
+    assert!(matches!(batches.matmul(&batches), Err(FerroFlowError::ShapeMismatch(_))));
+    assert!(matches!(matrix.matmul_transposed(&batches, false, true), Err(FerroFlowError::ShapeMismatch(_))));
+
+    Ok(())
+}
+
+#[test]
+fn test_strided_views_share_buffer() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let data: Vec<f32> = (0..24).map(|x| x as f32).collect();
+    let t = Tensor::<CPUBackend>::new(Arc::clone(&ctx), Shape::new(vec![2, 3, 4]), &data)?;
+
+    let reshaped = t.view(Shape::new(vec![6, 4]))?;
+    assert!(Arc::ptr_eq(&reshaped.buffer, &t.buffer));
+    assert_eq!(reshaped.data()?, data);
+
+    let permuted = t.permute(&[2, 0, 1])?;
+    assert_eq!(permuted.shape().dims(), &[4, 2, 3]);
+    assert_eq!(permuted.layout().strides(), &[1, 12, 4]);
+    assert!(!permuted.is_contiguous());
+    assert!(Arc::ptr_eq(&permuted.buffer, &t.buffer));
+    assert_eq!(&permuted.data()?[..6], &[0.0, 4.0, 8.0, 12.0, 16.0, 20.0]);
+
+    // A non-contiguous view cannot be viewed with a new shape, but can be reshaped by copying
+    assert!(permuted.view(Shape::new(vec![24])).is_err());
+    let flat = permuted.reshape(Shape::new(vec![24]))?;
+    assert!(flat.is_contiguous());
+    assert!(!Arc::ptr_eq(&flat.buffer, &t.buffer));
+
+    let narrowed = t.narrow(2, 1, 2)?;
+    assert_eq!(narrowed.shape().dims(), &[2, 3, 2]);
+    assert_eq!(&narrowed.data()?[..4], &[1.0, 2.0, 5.0, 6.0]);
+
+    let sliced = t.slice(1, 0, 3, 2)?;
+    assert_eq!(sliced.shape().dims(), &[2, 2, 4]);
+    assert_eq!(sliced.data()?[4..8], [8.0, 9.0, 10.0, 11.0]);
+
+    assert!(t.slice(1, 0, 4, 1).is_err());
+    assert!(t.narrow(2, 1, usize::MAX).is_err());
+    assert!(t.permute(&[0, 0, 1]).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_ops_on_views() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let a = Tensor::<CPUBackend>::new(
+        Arc::clone(&ctx),
+        Shape::new(vec![2, 3]),
+        &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0],
+    )?;
+
+    // The transposed view is passed to the kernel as a transposed operand without a copy
+    let gram = a.transpose(0, 1)?.matmul(&a)?;
+    assert_eq!(gram.shape().dims(), &[3, 3]);
+    assert_eq!(gram.data()?, vec![17.0, 22.0, 27.0, 22.0, 29.0, 36.0, 27.0, 36.0, 45.0]);
+
+    let column = a.narrow(1, 1, 1)?;
+    let doubled = column.add(&column)?;
+    assert_eq!(doubled.data()?, vec![4.0, 10.0]);
+
+    let chained = a.clone().chain().transpose().scalar_multiply(2.0).finish()?;
+    assert_eq!(chained.shape().dims(), &[3, 2]);
+    assert_eq!(chained.data()?, vec![2.0, 8.0, 4.0, 10.0, 6.0, 12.0]);
+
+    Ok(())
+}
+
+#[test]
+fn test_broadcast_shapes() {
+    let shape = Shape::new(vec![8, 1, 6, 1]).broadcast_with(&Shape::new(vec![7, 1, 5])).unwrap();
+    assert_eq!(shape.dims(), &[8, 7, 6, 5]);
+
+    let scalar = Shape::new(vec![]).broadcast_with(&Shape::new(vec![3, 2])).unwrap();
+    assert_eq!(scalar.dims(), &[3, 2]);
+
+    let err = Shape::new(vec![2, 3]).broadcast_with(&Shape::new(vec![4])).unwrap_err();
+    assert!(matches!(err, FerroFlowError::ShapeMismatch(ref msg) if msg.contains("[2, 3]") && msg.contains("[4]")));
+}
+
+#[test]
+fn test_cpu_broadcasting() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    test_broadcasting::<CPUBackend>(ctx)
+}
+
+#[cfg(all(feature = "metal", target_os = "macos"))]
+#[test]
+fn test_metal_broadcasting() -> Result<()> {
+    let ctx = MetalBackend::new()?;
+    test_broadcasting::<MetalBackend>(ctx)
+}
+
+fn test_broadcasting<B: ComputeBackend>(ctx: Arc<B::Context>) -> Result<()> {
+    let activations = Tensor::<B>::new(
+        Arc::clone(&ctx),
+        Shape::new(vec![2, 3]),
+        &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0],
+    )?;
+    let bias = Tensor::<B>::new(Arc::clone(&ctx), Shape::new(vec![3]), &[10.0, 20.0, 30.0])?;
+    let column = Tensor::<B>::new(Arc::clone(&ctx), Shape::new(vec![2, 1]), &[2.0, -1.0])?;
+
+    assert_eq!(activations.add(&bias)?.data()?, vec![11.0, 22.0, 33.0, 14.0, 25.0, 36.0]);
+    assert_eq!(bias.add(&activations)?.data()?, vec![11.0, 22.0, 33.0, 14.0, 25.0, 36.0]);
+    assert_eq!(activations.multiply(&column)?.data()?, vec![2.0, 4.0, 6.0, -4.0, -5.0, -6.0]);
+    assert_eq!((&activations - &bias)?.data()?, vec![-9.0, -18.0, -27.0, -6.0, -15.0, -24.0]);
+    assert_eq!((&activations / &column)?.data()?, vec![0.5, 1.0, 1.5, -4.0, -5.0, -6.0]);
+
+    // Outer product through two broadcast operands
+    let outer = column.multiply(&bias)?;
+    assert_eq!(outer.shape().dims(), &[2, 3]);
+    assert_eq!(outer.data()?, vec![20.0, 40.0, 60.0, -10.0, -20.0, -30.0]);
+
+    // Broadcasting composes with strided views
+    let transposed = activations.transpose(0, 1)?;
+    assert_eq!(transposed.add(&column.reshape(Shape::new(vec![2]))?)?.data()?, vec![3.0, 3.0, 4.0, 4.0, 5.0, 5.0]);
+
+    assert!(activations.add(&column.reshape(Shape::new(vec![2]))?).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_cpu_dtypes() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    test_dtypes::<CPUBackend>(ctx)
+}
+
+#[cfg(all(feature = "metal", target_os = "macos"))]
+#[test]
+fn test_metal_dtypes() -> Result<()> {
+    let ctx = MetalBackend::new()?;
+    test_dtypes::<MetalBackend>(ctx)
+}
+
+fn test_dtypes<B: ComputeBackend>(ctx: Arc<B::Context>) -> Result<()> {
+    use half::{bf16, f16};
+    let shape = Shape::new(vec![2, 2]);
+
+    let ints = Tensor::<B>::from_slice(Arc::clone(&ctx), shape.clone(), &[1i32, -2, 3, 4])?;
+    assert_eq!(ints.dtype(), DType::I32);
+    assert_eq!(ints.to_vec::<i32>()?, vec![1, -2, 3, 4]);
+    assert_eq!(ints.data()?, vec![1.0, -2.0, 3.0, 4.0]);
+    // Strided views of non-f32 tensors are materialized in their own type
+    assert_eq!(ints.transpose(0, 1)?.to_vec::<i32>()?, vec![1, 3, -2, 4]);
+
+    let values = [1.5f32, -2.75, 0.0, 3.0];
+    let floats = Tensor::<B>::new(Arc::clone(&ctx), shape.clone(), &values)?;
+    for dtype in DType::ALL {
+        let converted = floats.to_dtype(dtype)?;
+        assert_eq!(converted.dtype(), dtype);
+        assert_eq!(converted.shape(), &shape);
+        let expected: Vec<f32> = match dtype {
+            DType::I32 | DType::I64 => vec![1.0, -2.0, 0.0, 3.0],
+            DType::U8 => vec![1.0, 0.0, 0.0, 3.0],
+            DType::Bool => vec![1.0, 1.0, 0.0, 1.0],
+            _ => values.to_vec(),
+        };
+        assert_eq!(converted.data()?, expected, "{}", dtype);
+    }
+
+    assert_eq!(floats.to_dtype(DType::F16)?.to_vec::<f16>()?[1], f16::from_f32(-2.75));
+    assert_eq!(floats.to_dtype(DType::BF16)?.to_vec::<bf16>()?[0], bf16::from_f32(1.5));
+    assert_eq!(floats.to_dtype(DType::F64)?.to_vec::<f64>()?, vec![1.5, -2.75, 0.0, 3.0]);
+    assert_eq!(
+        Tensor::<B>::from_slice(Arc::clone(&ctx), shape.clone(), &[true, false, false, true])?.to_vec::<u8>().unwrap_err().to_string(),
+        "DType mismatch: Cannot read a bool tensor as u8"
+    );
+
+    let zeros = Tensor::<B>::zeros_with_dtype(Arc::clone(&ctx), shape.clone(), DType::I64)?;
+    assert_eq!(zeros.to_vec::<i64>()?, vec![0; 4]);
+
+    assert!(matches!(floats.add(&ints), Err(FerroFlowError::DTypeMismatch(_))));
+    assert!(matches!(ints.add(&ints), Err(FerroFlowError::DTypeMismatch(_))));
+    assert!(matches!(ints.matmul(&ints), Err(FerroFlowError::DTypeMismatch(_))));
+    assert!(matches!(ints.scalar_multiply(2.0), Err(FerroFlowError::DTypeMismatch(_))));
+
+    Ok(())
+}
+
+#[test]
+fn test_to_dtype_gradient() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let x = Tensor::<CPUBackend>::new(Arc::clone(&ctx), Shape::new(vec![2]), &[1.0, 2.0])?.requires_grad();
+
+    // Round-tripping through half precision keeps the graph connected
+    let y = x.to_dtype(DType::F16)?.to_dtype(DType::F32)?.multiply(&x)?;
+    y.backward_with_grad(&Tensor::full(Arc::clone(&ctx), Shape::new(vec![2]), 1.0)?)?;
+    assert_eq!(x.grad().unwrap().data()?, vec![2.0, 4.0]);
+
+    // Integer conversions are not differentiable
+    assert!(!x.to_dtype(DType::I32)?.needs_grad());
+
+    Ok(())
+}
+
+#[test]
+fn test_cpu_reductions() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    test_reductions::<CPUBackend>(ctx)
+}
+
+#[cfg(all(feature = "metal", target_os = "macos"))]
+#[test]
+fn test_metal_reductions() -> Result<()> {
+    let ctx = MetalBackend::new()?;
+    test_reductions::<MetalBackend>(ctx)
+}
+
+fn test_reductions<B: ComputeBackend>(ctx: Arc<B::Context>) -> Result<()> {
+    let a = Tensor::<B>::new(
+        Arc::clone(&ctx),
+        Shape::new(vec![2, 3]),
+        &[1.0, 5.0, 3.0, 4.0, 2.0, 6.0],
+    )?;
+
+    let rows = a.sum(1, false)?;
+    assert_eq!(rows.shape().dims(), &[2]);
+    assert_eq!(rows.data()?, vec![9.0, 12.0]);
+    let columns = a.sum(0, true)?;
+    assert_eq!(columns.shape().dims(), &[1, 3]);
+    assert_eq!(columns.data()?, vec![5.0, 7.0, 9.0]);
+
+    assert_eq!(a.mean(0, false)?.data()?, vec![2.5, 3.5, 4.5]);
+    assert_eq!(a.max(1, false)?.data()?, vec![5.0, 6.0]);
+    assert_eq!(a.min(0, false)?.data()?, vec![1.0, 2.0, 3.0]);
+    assert_eq!(a.prod(1, false)?.data()?, vec![15.0, 48.0]);
+    assert_eq!(a.var(1, 1, false)?.data()?, vec![4.0, 4.0]);
+    assert_eq!(a.std(0, 0, false)?.data()?, vec![1.5, 1.5, 1.5]);
+    assert!(matches!(a.var(0, 2, false), Err(FerroFlowError::InvalidOperation(_))));
+    assert!(matches!(a.std(1, 4, true), Err(FerroFlowError::InvalidOperation(_))));
+
+    let indices = a.argmax(1, true)?;
+    assert_eq!(indices.dtype(), DType::I64);
+    assert_eq!(indices.shape().dims(), &[2, 1]);
+    assert_eq!(indices.to_vec::<i64>()?, vec![1, 2]);
+    assert_eq!(a.argmin(0, false)?.to_vec::<i64>()?, vec![0, 1, 0]);
+
+    let total = a.sum_all()?;
+    assert_eq!(total.shape().dims(), &[] as &[usize]);
+    assert_eq!(total.data()?, vec![21.0]);
+    assert_eq!(a.mean_all()?.data()?, vec![3.5]);
+
+    // Reductions read strided views
+    assert_eq!(a.transpose(0, 1)?.sum(1, false)?.data()?, vec![5.0, 7.0, 9.0]);
+
+    assert!(matches!(a.sum(2, false), Err(FerroFlowError::InvalidOperation(_))));
+    let empty = Tensor::<B>::zeros(Arc::clone(&ctx), Shape::new(vec![2, 0]))?;
+    assert_eq!(empty.sum(1, false)?.data()?, vec![0.0, 0.0]);
+    assert!(matches!(empty.max(1, false), Err(FerroFlowError::InvalidOperation(_))));
+
+    Ok(())
+}
+
+#[test]
+fn test_cpu_unary_ops() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    test_unary_ops::<CPUBackend>(ctx)
+}
+
+#[cfg(all(feature = "metal", target_os = "macos"))]
+#[test]
+fn test_metal_unary_ops() -> Result<()> {
+    let ctx = MetalBackend::new()?;
+    test_unary_ops::<MetalBackend>(ctx)
+}
+
+fn test_unary_ops<B: ComputeBackend>(ctx: Arc<B::Context>) -> Result<()> {
+    let a = Tensor::<B>::new(Arc::clone(&ctx), Shape::new(vec![2, 2]), &[-2.0, -0.5, 1.0, 4.0])?;
+
+    assert_eq!(a.relu()?.data()?, vec![0.0, 0.0, 1.0, 4.0]);
+    assert_eq!(a.abs()?.data()?, vec![2.0, 0.5, 1.0, 4.0]);
+    assert_eq!(a.clamp(-1.0, 2.0)?.data()?, vec![-1.0, -0.5, 1.0, 2.0]);
+    assert_eq!(a.abs()?.sqrt()?.data()?, vec![2.0f32.sqrt(), 0.5f32.sqrt(), 1.0, 2.0]);
+    assert_eq!(a.abs()?.rsqrt()?.data()?[3], 0.5);
+    assert_eq!(a.pow(2.0)?.data()?, vec![4.0, 0.25, 1.0, 16.0]);
+    for (actual, expected) in a.exp()?.log()?.data()?.iter().zip([-2.0, -0.5, 1.0, 4.0]) {
+        assert!((actual - expected).abs() < 1e-6, "{} vs {}", actual, expected);
+    }
+    assert!((a.sigmoid()?.data()?[2] - 0.731_058_6).abs() < 1e-6);
+    assert!((a.tanh()?.data()?[0] + 0.964_027_6).abs() < 1e-6);
+
+    // Unary ops read strided views and keep their shape
+    let transposed = a.transpose(0, 1)?.relu()?;
+    assert_eq!(transposed.shape().dims(), &[2, 2]);
+    assert_eq!(transposed.data()?, vec![0.0, 1.0, 0.0, 4.0]);
+
+    assert!(matches!(a.clamp(1.0, -1.0), Err(FerroFlowError::InvalidOperation(_))));
+
+    Ok(())
+}
+
+#[test]
+fn test_cpu_softmax() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    test_softmax::<CPUBackend>(ctx)
+}
+
+#[cfg(all(feature = "metal", target_os = "macos"))]
+#[test]
+fn test_metal_softmax() -> Result<()> {
+    let ctx = MetalBackend::new()?;
+    test_softmax::<MetalBackend>(ctx)
+}
+
+fn test_softmax<B: ComputeBackend>(ctx: Arc<B::Context>) -> Result<()> {
+    let logits = Tensor::<B>::new(
+        Arc::clone(&ctx),
+        Shape::new(vec![2, 3]),
+        &[1.0, 2.0, 3.0, 1000.0, 1000.0, 1000.0],
+    )?;
+
+    let probs = logits.softmax(1)?.data()?;
+    let expected = [0.090_030_57, 0.244_728_48, 0.665_240_9, 1.0 / 3.0, 1.0 / 3.0, 1.0 / 3.0];
+    for (p, e) in probs.iter().zip(expected) {
+        assert!((p - e).abs() < 1e-6, "{} vs {}", p, e);
+    }
+
+    let log_probs = logits.log_softmax(1)?.data()?;
+    for (lp, p) in log_probs.iter().zip(&probs) {
+        assert!((lp - p.ln()).abs() < 1e-5);
+    }
+
+    let lse = logits.logsumexp(1, true)?;
+    assert_eq!(lse.shape().dims(), &[2, 1]);
+    let lse = lse.data()?;
+    assert!((lse[0] - 3.407_606).abs() < 1e-5);
+    assert!((lse[1] - (1000.0 + 3.0f32.ln())).abs() < 1e-3);
+
+    // Normalizing along dim 0 of the transpose matches dim 1 of the original
+    assert_eq!(logits.transpose(0, 1)?.softmax(0)?.transpose(0, 1)?.data()?, probs);
+    assert!(logits.softmax(2).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_cpu_conv2d() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    test_conv2d::<CPUBackend>(ctx)
+}
+
+#[cfg(all(feature = "metal", target_os = "macos"))]
+#[test]
+fn test_metal_conv2d() -> Result<()> {
+    let ctx = MetalBackend::new()?;
+    test_conv2d::<MetalBackend>(ctx)
+}
+
+fn test_conv2d<B: ComputeBackend>(ctx: Arc<B::Context>) -> Result<()> {
+    let data: Vec<f32> = (1..=16).map(|v| v as f32).collect();
+    let input = Tensor::<B>::new(Arc::clone(&ctx), Shape::new(vec![1, 1, 4, 4]), &data)?;
+    let weight = Tensor::<B>::new(Arc::clone(&ctx), Shape::new(vec![2, 1, 2, 2]), &[1.0, 0.0, 0.0, 1.0, 0.25, 0.25, 0.25, 0.25])?;
+    let bias = Tensor::<B>::new(Arc::clone(&ctx), Shape::new(vec![2]), &[0.0, 100.0])?;
+
+    // Channel 0 adds the diagonal neighbours, channel 1 averages 2x2 windows
+    let output = input.conv2d(&weight, Some(&bias), Conv2dOptions { stride: (2, 2), ..Default::default() })?;
+    assert_eq!(output.shape().dims(), &[1, 2, 2, 2]);
+    assert_eq!(output.data()?, vec![7.0, 11.0, 23.0, 27.0, 103.5, 105.5, 111.5, 113.5]);
+
+    let padded = input.conv2d(&weight, None, Conv2dOptions { padding: (1, 1), ..Default::default() })?;
+    assert_eq!(padded.shape().dims(), &[1, 2, 5, 5]);
+    assert_eq!(padded.data()?[0], 1.0);
+
+    // The transposed convolution restores the input shape of a strided convolution
+    let options = Conv2dOptions { stride: (2, 2), padding: (1, 1), ..Default::default() };
+    let down = input.conv2d(&weight, None, options)?;
+    assert_eq!(down.shape().dims(), &[1, 2, 3, 3]);
+    let up = down.conv_transpose2d(&weight, None, options)?;
+    assert_eq!(up.shape().dims(), &[1, 1, 4, 4]);
+    let wider = down.conv_transpose2d(&weight, None, Conv2dOptions { output_padding: (1, 0), ..options })?;
+    assert_eq!(wider.shape().dims(), &[1, 1, 5, 4]);
+
+    // <conv(x), y> == <x, conv_transpose(y)> for the same weight
+    let y = Tensor::<B>::new(Arc::clone(&ctx), Shape::new(vec![1, 2, 3, 3]), &(0..18).map(|v| (v % 5) as f32 - 2.0).collect::<Vec<_>>())?;
+    let lhs = down.multiply(&y)?.sum_all()?.data()?[0];
+    let rhs = input.multiply(&y.conv_transpose2d(&weight, None, options)?)?.sum_all()?.data()?[0];
+    assert!((lhs - rhs).abs() < 1e-3, "{} vs {}", lhs, rhs);
+
+    assert!(input.conv2d(&weight, None, Conv2dOptions { groups: 2, ..Default::default() }).is_err());
+    assert!(input.conv2d(&weight, None, Conv2dOptions { stride: (0, 1), ..Default::default() }).is_err());
+    assert!(input.conv2d(&weight, Some(&input), Conv2dOptions::default()).is_err());
+    assert!(input.reshape(Shape::new(vec![4, 4]))?.conv2d(&weight, None, Conv2dOptions::default()).is_err());
+    assert!(down.conv_transpose2d(&weight, None, Conv2dOptions { output_padding: (2, 0), ..options }).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_cpu_pooling() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    test_pooling::<CPUBackend>(ctx)
+}
+
+#[cfg(all(feature = "metal", target_os = "macos"))]
+#[test]
+fn test_metal_pooling() -> Result<()> {
+    let ctx = MetalBackend::new()?;
+    test_pooling::<MetalBackend>(ctx)
+}
+
+fn test_pooling<B: ComputeBackend>(ctx: Arc<B::Context>) -> Result<()> {
+    let data: Vec<f32> = (1..=16).map(|v| v as f32).collect();
+    let input = Tensor::<B>::new(Arc::clone(&ctx), Shape::new(vec![1, 1, 4, 4]), &data)?;
+
+    let (values, indices) = input.max_pool2d(Pool2dOptions::new((2, 2)))?;
+    assert_eq!(values.shape().dims(), &[1, 1, 2, 2]);
+    assert_eq!(values.data()?, vec![6.0, 8.0, 14.0, 16.0]);
+    assert_eq!(indices.dtype(), DType::I64);
+    assert_eq!(indices.to_vec::<i64>()?, vec![5, 7, 13, 15]);
+
+    assert_eq!(input.avg_pool2d(Pool2dOptions::new((2, 2)))?.data()?, vec![3.5, 5.5, 11.5, 13.5]);
+
+    // The corner window covers one real element and three padded ones
+    let padded = Pool2dOptions { stride: (3, 3), padding: (1, 1), ..Pool2dOptions::new((2, 2)) };
+    assert_eq!(input.avg_pool2d(padded)?.data()?[0], 0.25);
+    let excluded = Pool2dOptions { count_include_pad: false, ..padded };
+    assert_eq!(input.avg_pool2d(excluded)?.data()?[0], 1.0);
+
+    let (values, _) = input.adaptive_max_pool2d((1, 2))?;
+    assert_eq!(values.data()?, vec![14.0, 16.0]);
+    let averages = input.adaptive_avg_pool2d((3, 1))?;
+    assert_eq!(averages.shape().dims(), &[1, 1, 3, 1]);
+    assert_eq!(averages.data()?, vec![4.5, 8.5, 12.5]);
+    // Global average pooling of a permuted view
+    assert_eq!(input.transpose(2, 3)?.adaptive_avg_pool2d((1, 1))?.data()?, vec![8.5]);
+
+    assert!(input.max_pool2d(Pool2dOptions::new((5, 5))).is_err());
+    assert!(input.max_pool2d(Pool2dOptions::new((0, 2))).is_err());
+    assert!(input.avg_pool2d(Pool2dOptions { padding: (2, 0), ..Pool2dOptions::new((3, 3)) }).is_err());
+    assert!(input.adaptive_avg_pool2d((0, 1)).is_err());
+    assert!(input.reshape(Shape::new(vec![4, 4]))?.max_pool2d(Pool2dOptions::new((2, 2))).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_cpu_normalization() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    test_normalization::<CPUBackend>(ctx)
+}
+
+#[cfg(all(feature = "metal", target_os = "macos"))]
+#[test]
+fn test_metal_normalization() -> Result<()> {
+    let ctx = MetalBackend::new()?;
+    test_normalization::<MetalBackend>(ctx)
+}
+
+fn test_normalization<B: ComputeBackend>(ctx: Arc<B::Context>) -> Result<()> {
+    let x = Tensor::<B>::new(Arc::clone(&ctx), Shape::new(vec![2, 4]), &[1.0, 2.0, 3.0, 4.0, -2.0, 0.0, 0.0, 2.0])?;
+    let weight = Tensor::<B>::new(Arc::clone(&ctx), Shape::new(vec![4]), &[1.0, 2.0, 1.0, 1.0])?;
+    let bias = Tensor::<B>::new(Arc::clone(&ctx), Shape::new(vec![4]), &[0.0, 0.0, 0.0, 10.0])?;
+
+    let normalized = x.layer_norm(&[4], None, None, 0.0)?.data()?;
+    let (inv, root2) = (1.0 / 1.25f32.sqrt(), 2f32.sqrt());
+    let expected = [-1.5 * inv, -0.5 * inv, 0.5 * inv, 1.5 * inv, -root2, 0.0, 0.0, root2];
+    for (n, e) in normalized.iter().zip(expected) {
+        assert!((n - e).abs() < 1e-5, "{} vs {}", n, e);
+    }
+
+    let affine = x.layer_norm(&[4], Some(&weight), Some(&bias), 0.0)?.data()?;
+    assert!((affine[1] - 2.0 * normalized[1]).abs() < 1e-6);
+    assert!((affine[7] - (normalized[7] + 10.0)).abs() < 1e-5);
+
+    // RMS of the second row is sqrt(2)
+    let rms = x.rms_norm(&[4], Some(&weight), 0.0)?.data()?;
+    for (r, e) in rms[4..].iter().zip([-root2, 0.0, 0.0, root2]) {
+        assert!((r - e).abs() < 1e-6, "{} vs {}", r, e);
+    }
+
+    // Group norm with one group per sample is layer norm over everything but the batch
+    let images = Tensor::<B>::new(Arc::clone(&ctx), Shape::new(vec![2, 4, 1, 1]), &x.data()?)?;
+    assert_eq!(images.group_norm(1, None, None, 0.0)?.data()?, normalized);
+    // ... and with one group per channel pair, layer norm over each pair
+    let pairs = x.reshape(Shape::new(vec![4, 2]))?.layer_norm(&[2], None, None, 1e-5)?.data()?;
+    let grouped = images.group_norm(2, Some(&weight), None, 1e-5)?.data()?;
+    let channel_scale = [1.0, 2.0, 1.0, 1.0];
+    for (i, (g, p)) in grouped.iter().zip(&pairs).enumerate() {
+        assert!((g - p * channel_scale[i % 4]).abs() < 1e-5, "{} vs {}", g, p);
+    }
+
+    // Normalizing a transposed view reads it in logical order
+    let columns = x.transpose(0, 1)?.layer_norm(&[2], None, None, 0.0)?.data()?;
+    for (i, c) in columns.iter().enumerate() {
+        assert!((c - if i % 2 == 0 { 1.0 } else { -1.0 }).abs() < 1e-6);
+    }
+
+    assert!(x.layer_norm(&[2, 4, 1], None, None, 1e-5).is_err());
+    assert!(x.layer_norm(&[3], None, None, 1e-5).is_err());
+    assert!(x.layer_norm(&[4], Some(&bias.reshape(Shape::new(vec![1, 4]))?), None, 1e-5).is_err());
+    assert!(images.group_norm(3, None, None, 1e-5).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_cpu_inplace_ops() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    test_inplace_ops::<CPUBackend>(ctx)
+}
+
+#[cfg(all(feature = "metal", target_os = "macos"))]
+#[test]
+fn test_metal_inplace_ops() -> Result<()> {
+    let ctx = MetalBackend::new()?;
+    test_inplace_ops::<MetalBackend>(ctx)
+}
+
+fn test_inplace_ops<B: ComputeBackend>(ctx: Arc<B::Context>) -> Result<()> {
+    let x = Tensor::<B>::new(Arc::clone(&ctx), Shape::new(vec![2, 3]), &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0])?;
+    let row = Tensor::<B>::new(Arc::clone(&ctx), Shape::new(vec![3]), &[10.0, 20.0, 30.0])?;
+    let buffer = Arc::clone(&x.buffer);
+
+    // In-place ops broadcast the operand and keep the buffer
+    x.add_(&row)?;
+    assert_eq!(x.data()?, vec![11.0, 22.0, 33.0, 14.0, 25.0, 36.0]);
+    x.sub_(&row)?;
+    x.mul_(&row)?;
+    x.div_(&Tensor::full(Arc::clone(&ctx), Shape::new(vec![2, 1]), 10.0)?)?;
+    assert_eq!(x.data()?, vec![1.0, 4.0, 9.0, 4.0, 10.0, 18.0]);
+    x.scale_(0.5)?;
+    assert_eq!(x.data()?, vec![0.5, 2.0, 4.5, 2.0, 5.0, 9.0]);
+    assert!(Arc::ptr_eq(&x.buffer, &buffer));
+
+    // Writes are visible through views sharing the buffer, and operands may alias the target
+    let view = x.reshape(Shape::new(vec![6]))?;
+    x.add_(&x)?;
+    assert_eq!(view.data()?, vec![1.0, 4.0, 9.0, 4.0, 10.0, 18.0]);
+    x.copy_from(&x.transpose(0, 1)?.reshape(Shape::new(vec![2, 3]))?)?;
+    assert_eq!(x.data()?, vec![1.0, 4.0, 4.0, 10.0, 9.0, 18.0]);
+    x.copy_from(&row)?;
+    assert_eq!(x.data()?, vec![10.0, 20.0, 30.0, 10.0, 20.0, 30.0]);
+
+    // `_into` variants write into an existing output, which may also be an input
+    let a = Tensor::<B>::new(Arc::clone(&ctx), Shape::new(vec![2, 2]), &[1.0, 2.0, 3.0, 4.0])?;
+    let b = Tensor::<B>::new(Arc::clone(&ctx), Shape::new(vec![2, 2]), &[5.0, 6.0, 7.0, 8.0])?;
+    let out = Tensor::<B>::zeros(Arc::clone(&ctx), Shape::new(vec![2, 2]))?;
+    let buffer = Arc::clone(&out.buffer);
+    a.add_into(&b, &out)?;
+    assert_eq!(out.data()?, vec![6.0, 8.0, 10.0, 12.0]);
+    a.transpose(0, 1)?.sub_into(&b, &out)?;
+    assert_eq!(out.data()?, vec![-4.0, -3.0, -5.0, -4.0]);
+    a.mul_into(&b, &out)?;
+    out.div_into(&a, &out)?;
+    assert_eq!(out.data()?, b.data()?);
+    a.scale_into(2.0, &out)?;
+    assert_eq!(out.data()?, vec![2.0, 4.0, 6.0, 8.0]);
+    a.matmul_into(&b, &out)?;
+    assert_eq!(out.data()?, a.matmul(&b)?.data()?);
+    a.transpose(0, 1)?.matmul_into(&b.transpose(0, 1)?, &out)?;
+    assert_eq!(out.data()?, vec![23.0, 31.0, 34.0, 46.0]);
+    out.matmul_into(&out, &out)?;
+    assert_eq!(out.data()?, vec![1583.0, 2139.0, 2346.0, 3170.0]);
+    assert!(Arc::ptr_eq(&out.buffer, &buffer));
+    assert_eq!(a.multiply(&a)?.data()?, vec![1.0, 4.0, 9.0, 16.0]);
+    assert_eq!(a.matmul(&a)?.data()?, vec![7.0, 10.0, 15.0, 22.0]);
+
+    let batched = Tensor::<B>::new(Arc::clone(&ctx), Shape::new_batched(2, 2, 2), &[1.0, 0.0, 0.0, 1.0, 2.0, 0.0, 0.0, 2.0])?;
+    let batched_out = Tensor::<B>::zeros(Arc::clone(&ctx), Shape::new_batched(2, 2, 2))?;
+    let batched_b = Tensor::<B>::new(Arc::clone(&ctx), Shape::new_batched(2, 2, 2), &[5.0, 6.0, 7.0, 8.0, 5.0, 6.0, 7.0, 8.0])?;
+    batched.matmul_into(&batched_b, &batched_out)?;
+    assert_eq!(batched_out.data()?, vec![5.0, 6.0, 7.0, 8.0, 10.0, 12.0, 14.0, 16.0]);
+
+    // Outputs must have the result's shape and be dense
+    assert!(a.add_into(&row, &out).is_err());
+    assert!(a.matmul_into(&b, &view).is_err());
+    assert!(a.add_into(&b, &out.transpose(0, 1)?).is_err());
+    assert!(a.transpose(0, 1)?.add_(&b).is_err());
+    assert!(row.add_(&a).is_err());
+    assert!(a.copy_from(&a.to_dtype(DType::F16)?).is_err());
+
+    // Untracked writes to tensors that need grad are only allowed under no_grad
+    let param = Tensor::<B>::new(Arc::clone(&ctx), Shape::new(vec![2, 2]), &[1.0, 2.0, 3.0, 4.0])?.requires_grad();
+    assert!(param.scale_(2.0).is_err());
+    assert!(out.add_(&param).is_err());
+    crate::autograd::no_grad(|| param.sub_(&a))?;
+    assert_eq!(param.data()?, vec![0.0; 4]);
+
+    // Backward rules reject tensors they saved that were written in place since, while rules
+    // that saved nothing still run
+    let product = a.multiply(&param)?.sum_all()?;
+    let sum = a.add(&param)?.sum_all()?;
+    let matmul = b.matmul(&param)?.sum_all()?;
+    a.scale_(2.0)?;
+    a.add_into(&a, &b)?;
+    assert!(matches!(product.backward(), Err(FerroFlowError::InvalidOperation(_))));
+    assert!(matches!(matmul.backward(), Err(FerroFlowError::InvalidOperation(_))));
+    sum.backward()?;
+    assert_eq!(param.grad().unwrap().data()?, vec![1.0; 4]);
+
+    Ok(())
+}
+
+#[test]
+fn test_cpu_buffer_reuse() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    test_buffer_reuse::<CPUBackend>(ctx)
+}
+
+#[cfg(all(feature = "metal", target_os = "macos"))]
+#[test]
+fn test_metal_buffer_reuse() -> Result<()> {
+    let ctx = MetalBackend::new()?;
+    test_buffer_reuse::<MetalBackend>(ctx)
+}
+
+#[test]
+fn test_cpu_kernel_buffer_reuse() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    let logits = Tensor::<CPUBackend>::new(Arc::clone(&ctx), Shape::new(vec![32, 32]), &[1.0; 1024])?;
+    let input = Tensor::<CPUBackend>::new(Arc::clone(&ctx), Shape::new(vec![1, 2, 8, 8]), &[1.0; 128])?;
+    let weight = Tensor::<CPUBackend>::new(Arc::clone(&ctx), Shape::new(vec![4, 2, 3, 3]), &[0.5; 72])?;
+    let inputs = ctx.memory_stats();
+
+    // Kernel results come from the cache and go back to it once dropped
+    assert_eq!(logits.softmax(1)?.data()?, vec![1.0 / 32.0; 1024]);
+    let first = ctx.memory_stats();
+    assert_eq!(first.allocations, inputs.allocations + 1);
+    assert_eq!(first.releases, inputs.releases + 1);
+    logits.softmax(1)?;
+    let second = ctx.memory_stats();
+    assert_eq!(second.allocations, first.allocations + 1);
+    assert_eq!(second.reuses, first.reuses + 1);
+
+    assert_eq!(input.conv2d(&weight, None, Conv2dOptions::default())?.data()?, vec![9.0; 144]);
+    let first = ctx.memory_stats();
+    assert!(first.allocations > second.allocations);
+    input.conv2d(&weight, None, Conv2dOptions::default())?;
+    let reused = ctx.memory_stats();
+    assert_eq!(reused.allocations - first.allocations, first.allocations - second.allocations);
+    assert_eq!(reused.reuses - first.reuses, reused.allocations - first.allocations);
+    assert_eq!(reused.live_bytes, inputs.live_bytes);
+
+    Ok(())
+}
+
+fn test_buffer_reuse<B: ComputeBackend>(ctx: Arc<B::Context>) -> Result<()> {
+    let a = Tensor::<B>::new(Arc::clone(&ctx), Shape::new(vec![32, 32]), &[1.0; 1024])?;
+    let b = Tensor::<B>::new(Arc::clone(&ctx), Shape::new(vec![32, 32]), &[2.0; 1024])?;
+    let inputs = B::memory_stats(&ctx);
+    assert_eq!(inputs.allocations, 2);
+
+    // Each result is dropped before the next op, which then reuses its buffer
+    for _ in 0..10 {
+        assert_eq!(a.add(&b)?.scalar_multiply(2.0)?.data()?[0], 6.0);
+    }
+    let stats = B::memory_stats(&ctx);
+    assert_eq!(stats.allocations, inputs.allocations + 20);
+    assert_eq!(stats.reuses, 18);
+    assert_eq!(stats.live_bytes, inputs.live_bytes);
+    assert_eq!(stats.peak_bytes, 2 * inputs.live_bytes);
+
+    // A buffer is released once the last view of it is dropped
+    let sum = a.add(&b)?;
+    let view = sum.reshape(Shape::new(vec![1024]))?;
+    drop(sum);
+    assert_eq!(B::memory_stats(&ctx).live_bytes, 3 * inputs.live_bytes / 2);
+    drop(view);
+    assert_eq!(B::memory_stats(&ctx).live_bytes, inputs.live_bytes);
+
+    // After emptying the cache, results are newly allocated again
+    B::empty_cache(&ctx);
+    let emptied = B::memory_stats(&ctx);
+    assert_eq!(emptied.cached_bytes, 0);
+    assert_eq!(a.add(&b)?.data()?, vec![3.0; 1024]);
+    assert_eq!(B::memory_stats(&ctx).reuses, emptied.reuses);
+
+    Ok(())
+}
+
+#[test]
+fn test_cpu_quantized_matmul() -> Result<()> {
+    let ctx = CPUBackend::new()?;
+    test_quantized_matmul::<CPUBackend>(ctx)
+}
+
+#[cfg(all(feature = "metal", target_os = "macos"))]
+#[test]
+fn test_metal_quantized_matmul() -> Result<()> {
+    let ctx = MetalBackend::new()?;
+    test_quantized_matmul::<MetalBackend>(ctx)
+}
+
+fn test_quantized_matmul<B: ComputeBackend>(ctx: Arc<B::Context>) -> Result<()> {
+    let values: Vec<f32> = (0..64).map(|i| (i as f32 - 20.0) / 8.0).collect();
+    let weight = Tensor::<B>::new(Arc::clone(&ctx), Shape::new(vec![2, 32]), &values)?;
+
+    // Rounding errs by at most half a step, the largest magnitude of a block over 127 or 8
+    for (quant_type, tolerance) in [(QuantType::Q8_0, 0.03), (QuantType::Q4_0, 0.35)] {
+        let quantized = QuantizedTensor::quantize(&weight, quant_type)?;
+        assert_eq!(quantized.as_bytes().len(), 2 * quant_type.block_bytes());
+        let restored = quantized.dequantize::<B>(Arc::clone(&ctx))?;
+        assert_eq!(restored.shape(), weight.shape());
+        for (r, v) in restored.data()?.iter().zip(&values) {
+            assert!((r - v).abs() <= tolerance, "{:?}: {} vs {}", quant_type, r, v);
+        }
+    }
+    // Q4_0 maps the value of largest magnitude exactly
+    let q4 = QuantizedTensor::quantize(&weight, QuantType::Q4_0)?.dequantize_to_vec();
+    assert_eq!((q4[0], q4[63]), (-2.5, 5.375));
+
+    // Leading input dimensions are kept, threads split larger problems
+    for (out_features, in_features, batch) in [(3, 32, vec![2, 2]), (64, 256, vec![32])] {
+        let count = out_features * in_features;
+        let weight: Vec<f32> = (0..count).map(|i| ((i * 37 % 101) as f32 - 50.0) / 25.0).collect();
+        let weight = Tensor::<B>::new(Arc::clone(&ctx), Shape::new(vec![out_features, in_features]), &weight)?;
+        let quantized = QuantizedTensor::quantize(&weight, QuantType::Q8_0)?;
+        let mut input_dims = batch.clone();
+        input_dims.push(in_features);
+        let input = Tensor::<B>::rand(Arc::clone(&ctx), Shape::new(input_dims))?;
+
+        let output = quantized.matmul(&input)?;
+        let rows = input.shape().size() / in_features;
+        let expected = input
+            .reshape(Shape::new(vec![rows, in_features]))?
+            .matmul_transposed(&quantized.dequantize(Arc::clone(&ctx))?, false, true)?;
+        let mut output_dims = batch;
+        output_dims.push(out_features);
+        assert_eq!(output.shape().dims(), &output_dims[..]);
+        for (o, e) in output.data()?.iter().zip(expected.data()?) {
+            assert!((o - e).abs() < 1e-3, "{} vs {}", o, e);
+        }
+    }
+
+    let input = Tensor::<B>::zeros(Arc::clone(&ctx), Shape::new(vec![0, 32]))?;
+    let quantized = QuantizedTensor::quantize(&weight, QuantType::Q8_0)?;
+    assert_eq!(quantized.matmul(&input)?.shape().dims(), &[0, 2]);
+    assert!(quantized.matmul(&Tensor::<B>::zeros(Arc::clone(&ctx), Shape::new(vec![1, 16]))?).is_err());
+    assert!(QuantizedTensor::quantize(&weight, QuantType::Q4_K).is_err());
+    assert!(QuantizedTensor::new(QuantType::Q4_0, Shape::new(vec![16]), &[0; 9]).is_err());
+    assert!(QuantizedTensor::new(QuantType::Q4_0, Shape::new(vec![32]), &[0; 17]).is_err());
+    Ok(())
+}
@@ -0,0 +1,2 @@
+JR?@?fX?V/?YK?CX?L=-^5>+̍?2?BJ?7>Gf?#7#>W~ʽ]>~?RyU?.?5Uf>#޼XE>0?֐>D?{]!?,}L5,j>hG>Q?+=\>Trk=>Y>8>^	Gv?^mtKl?\K!А@Q/u?l_?Ut'U*Lws>L_#oR;kTLy?
+֐(>Bx
\ No newline at end of file
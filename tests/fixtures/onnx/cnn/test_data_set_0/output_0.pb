@@ -0,0 +1 @@
+J}=j=Blogits
\ No newline at end of file
@@ -0,0 +1,372 @@
+#!/usr/bin/env python3
+"""Writes the ONNX fixture models used by the importer tests in src/onnx/tests.rs.
+
+Each model directory follows the layout of the ONNX backend test data: `model.onnx` plus
+`test_data_set_0/input_N.pb` and `output_N.pb` serialized TensorProtos. The protobuf is encoded
+by hand so the models don't depend on the exporter's defaults.
+
+With the `onnx` package installed, every model is validated with `onnx.checker` and the expected
+outputs are computed by `onnx.reference.ReferenceEvaluator`, after checking that they agree with
+the plain reference implementations below. Without it, those implementations produce the
+outputs on their own and a warning is printed; regenerate with `onnx` before committing.
+
+Run from the repository root: python3 tests/fixtures/onnx/generate.py
+"""
+
+import math
+import os
+import random
+import struct
+import sys
+
+try:
+    import numpy as np
+    import onnx
+    from onnx.reference import ReferenceEvaluator
+except ImportError:
+    onnx = None
+
+ROOT = os.path.dirname(os.path.abspath(__file__))
+
+FLOAT, INT64 = 1, 7
+
+
+# Protobuf wire format
+
+def varint(value):
+    value &= (1 << 64) - 1
+    out = bytearray()
+    while True:
+        byte = value & 0x7F
+        value >>= 7
+        if value:
+            out.append(byte | 0x80)
+        else:
+            out.append(byte)
+            return bytes(out)
+
+
+def key(number, wire_type):
+    return varint((number << 3) | wire_type)
+
+
+def int_field(number, value):
+    return key(number, 0) + varint(value)
+
+
+def bytes_field(number, value):
+    if isinstance(value, str):
+        value = value.encode()
+    return key(number, 2) + varint(len(value)) + value
+
+
+def float_field(number, value):
+    return key(number, 5) + struct.pack("<f", value)
+
+
+def packed_floats(number, values):
+    return bytes_field(number, struct.pack("<%df" % len(values), *values))
+
+
+# ONNX messages
+
+def tensor_proto(name, dims, values, data_type=FLOAT, raw=True):
+    out = b"".join(int_field(1, d) for d in dims) + int_field(2, data_type)
+    if raw:
+        fmt = "<%d%s" % (len(values), "f" if data_type == FLOAT else "q")
+        out += bytes_field(9, struct.pack(fmt, *values))
+    elif data_type == FLOAT:
+        out += packed_floats(4, values)
+    else:
+        out += b"".join(int_field(7, v) for v in values)
+    return out + bytes_field(8, name)
+
+
+def attribute(name, value):
+    out = bytes_field(1, name)
+    if isinstance(value, float):
+        return out + float_field(2, value) + int_field(20, 1)
+    if isinstance(value, int):
+        return out + int_field(3, value) + int_field(20, 2)
+    if isinstance(value, str):
+        return out + bytes_field(4, value) + int_field(20, 3)
+    if isinstance(value, bytes):
+        return out + bytes_field(5, value) + int_field(20, 4)
+    if all(isinstance(v, float) for v in value):
+        return out + b"".join(float_field(7, v) for v in value) + int_field(20, 6)
+    return out + b"".join(int_field(8, v) for v in value) + int_field(20, 7)
+
+
+def node(op_type, inputs, outputs, name, domain="", **attributes):
+    out = b"".join(bytes_field(1, i) for i in inputs)
+    out += b"".join(bytes_field(2, o) for o in outputs)
+    out += bytes_field(3, name) + bytes_field(4, op_type)
+    out += b"".join(bytes_field(5, attribute(k, v)) for k, v in sorted(attributes.items()))
+    if domain:
+        out += bytes_field(7, domain)
+    return out
+
+
+def value_info(name, dims):
+    shape = b"".join(bytes_field(1, int_field(1, d)) for d in dims)
+    tensor_type = int_field(1, FLOAT) + bytes_field(2, shape)
+    return bytes_field(1, name) + bytes_field(2, bytes_field(1, tensor_type))
+
+
+def model(name, opset, nodes, initializers, inputs, outputs, extra_opsets=()):
+    graph = b"".join(bytes_field(1, n) for n in nodes) + bytes_field(2, name)
+    graph += b"".join(bytes_field(5, t) for t in initializers)
+    graph += b"".join(bytes_field(11, value_info(n, d)) for n, d in inputs)
+    graph += b"".join(bytes_field(12, value_info(n, d)) for n, d in outputs)
+    out = int_field(1, 8) + bytes_field(2, "ferroflow-fixtures") + bytes_field(7, graph)
+    for domain, version in ((("", opset),) + tuple(extra_opsets)):
+        out += bytes_field(8, bytes_field(1, domain) + int_field(2, version))
+    return out
+
+
+# Reference operators on (shape, flat values) pairs
+
+def f32(x):
+    return struct.unpack("<f", struct.pack("<f", x))[0]
+
+
+def rand(rng, shape, scale=1.0):
+    return (list(shape), [f32(rng.uniform(-scale, scale)) for _ in range(math.prod(shape))])
+
+
+def strides(shape):
+    out, step = [], 1
+    for d in reversed(shape):
+        out.insert(0, step)
+        step *= d
+    return out
+
+
+def index(shape, flat):
+    out = []
+    for s in strides(shape):
+        out.append(flat // s)
+        flat %= s
+    return out
+
+
+def broadcast(a, b, fn):
+    (sa, va), (sb, vb) = a, b
+    rank = max(len(sa), len(sb))
+    sa, sb = [1] * (rank - len(sa)) + sa, [1] * (rank - len(sb)) + sb
+    shape = [max(x, y) for x, y in zip(sa, sb)]
+    out = []
+    for flat in range(math.prod(shape)):
+        i = index(shape, flat)
+        ia = sum((0 if d == 1 else x) * s for x, d, s in zip(i, sa, strides(sa)))
+        ib = sum((0 if d == 1 else x) * s for x, d, s in zip(i, sb, strides(sb)))
+        out.append(fn(va[ia], vb[ib]))
+    return (shape, out)
+
+
+def unary(a, fn):
+    return (a[0], [fn(v) for v in a[1]])
+
+
+def transpose(a, perm):
+    shape = [a[0][p] for p in perm]
+    out = []
+    for flat in range(math.prod(shape)):
+        i = index(shape, flat)
+        source = [0] * len(perm)
+        for axis, p in enumerate(perm):
+            source[p] = i[axis]
+        out.append(a[1][sum(x * s for x, s in zip(source, strides(a[0])))])
+    return (shape, out)
+
+
+def matmul(a, b):
+    (m, k), (_, n) = a[0], b[0]
+    return ([m, n], [sum(a[1][i * k + j] * b[1][j * n + c] for j in range(k)) for i in range(m) for c in range(n)])
+
+
+def softmax_rows(a, log=False):
+    rows, cols = math.prod(a[0][:-1]), a[0][-1]
+    out = []
+    for r in range(rows):
+        row = a[1][r * cols:(r + 1) * cols]
+        top = max(row)
+        total = sum(math.exp(v - top) for v in row)
+        out += [(v - top - math.log(total)) if log else math.exp(v - top) / total for v in row]
+    return (a[0], out)
+
+
+def conv2d(x, w, bias, pad):
+    (n, c, h, wd), (co, _, kh, kw) = x[0], w[0]
+    out = []
+    for b in range(n):
+        for o in range(co):
+            for i in range(h + 2 * pad - kh + 1):
+                for j in range(wd + 2 * pad - kw + 1):
+                    total = bias[1][o]
+                    for ci in range(c):
+                        for di in range(kh):
+                            for dj in range(kw):
+                                y, z = i + di - pad, j + dj - pad
+                                if 0 <= y < h and 0 <= z < wd:
+                                    total += x[1][((b * c + ci) * h + y) * wd + z] * w[1][((o * c + ci) * kh + di) * kw + dj]
+                    out.append(total)
+    return ([n, co, h + 2 * pad - kh + 1, wd + 2 * pad - kw + 1], out)
+
+
+def max_pool2(x):
+    n, c, h, w = x[0]
+    out = [max(x[1][((b * c + ch) * h + 2 * i + di) * w + 2 * j + dj] for di in range(2) for dj in range(2))
+           for b in range(n) for ch in range(c) for i in range(h // 2) for j in range(w // 2)]
+    return ([n, c, h // 2, w // 2], out)
+
+
+# Fixtures
+
+def evaluate(model_bytes, inputs, outputs):
+    """Checks the model and returns its outputs computed by the ONNX reference evaluator"""
+    proto = onnx.load_from_string(model_bytes)
+    onnx.checker.check_model(proto, full_check=True)
+    if not inputs:
+        return outputs
+    feeds = {n: np.array(values, dtype=np.float32).reshape(shape) for n, (shape, values) in inputs}
+    results = ReferenceEvaluator(proto).run(None, feeds)
+    for (n, (shape, values)), result in zip(outputs, results):
+        expected = np.array(values, dtype=np.float32).reshape(shape)
+        np.testing.assert_allclose(result, expected, rtol=1e-4, atol=1e-5, err_msg=n)
+    return [(n, (list(result.shape), result.ravel().tolist())) for (n, _), result in zip(outputs, results)]
+
+
+def write(name, model_bytes, inputs, outputs):
+    if onnx is not None:
+        outputs = evaluate(model_bytes, inputs, outputs)
+    directory = os.path.join(ROOT, name)
+    os.makedirs(directory, exist_ok=True)
+    if inputs or outputs:
+        os.makedirs(os.path.join(directory, "test_data_set_0"), exist_ok=True)
+    with open(os.path.join(directory, "model.onnx"), "wb") as f:
+        f.write(model_bytes)
+    for kind, tensors in (("input", inputs), ("output", outputs)):
+        for i, (tensor_name, (shape, values)) in enumerate(tensors):
+            path = os.path.join(directory, "test_data_set_0", "%s_%d.pb" % (kind, i))
+            with open(path, "wb") as f:
+                f.write(tensor_proto(tensor_name, shape, [f32(v) for v in values]))
+
+
+def mlp(rng):
+    x, w1, b1 = rand(rng, [2, 4]), rand(rng, [8, 4]), rand(rng, [8])
+    w2, b2 = rand(rng, [8, 3]), rand(rng, [3])
+    hidden = unary(broadcast(matmul(x, transpose(w1, [1, 0])), b1, lambda a, b: a + b), lambda v: max(v, 0.0))
+    probs = softmax_rows(broadcast(matmul(hidden, w2), b2, lambda a, b: a + b))
+    nodes = [
+        node("Gemm", ["x", "w1", "b1"], ["gemm"], "fc1", transB=1),
+        node("Relu", ["gemm"], ["hidden"], "relu"),
+        node("MatMul", ["hidden", "w2"], ["logits"], "fc2"),
+        node("Add", ["logits", "b2"], ["biased"], "bias"),
+        node("Softmax", ["biased"], ["probs"], "softmax", axis=-1),
+    ]
+    initializers = [tensor_proto(n, *t) for n, t in (("w1", w1), ("b1", b1), ("w2", w2), ("b2", b2))]
+    write("mlp", model("mlp", 13, nodes, initializers, [("x", [2, 4])], [("probs", [2, 3])]),
+          [("x", x)], [("probs", probs)])
+
+
+def cnn(rng):
+    x, w, b = rand(rng, [1, 2, 6, 6]), rand(rng, [3, 2, 3, 3], 0.5), rand(rng, [3])
+    scale, shift, mean = rand(rng, [3]), rand(rng, [3]), rand(rng, [3])
+    var = ([3], [f32(rng.uniform(0.5, 1.5)) for _ in range(3)])
+    fc = rand(rng, [27, 2])
+
+    conv = conv2d(x, w, b, 1)
+    plane = 36
+    normed = ([1, 3, 6, 6], [
+        (v - mean[1][i // plane]) / math.sqrt(var[1][i // plane] + 1e-5) * scale[1][i // plane] + shift[1][i // plane]
+        for i, v in enumerate(conv[1])
+    ])
+    relu = unary(normed, lambda v: max(v, 0.0))
+    pooled = max_pool2(relu)
+    flat = ([1, 27], transpose(pooled, [0, 2, 3, 1])[1])
+    logits = matmul(unary(flat, lambda v: v * 0.5), fc)
+    features = ([1, 3], [sum(relu[1][c * plane:(c + 1) * plane]) / plane for c in range(3)])
+
+    nodes = [
+        node("Conv", ["x", "w", "b"], ["conv"], "conv", kernel_shape=[3, 3], pads=[1, 1, 1, 1]),
+        node("BatchNormalization", ["conv", "scale", "shift", "mean", "var"], ["bn"], "bn"),
+        node("Relu", ["bn"], ["relu"], "relu"),
+        node("MaxPool", ["relu"], ["pool"], "pool", kernel_shape=[2, 2], strides=[2, 2]),
+        node("Transpose", ["pool"], ["nhwc"], "nhwc", perm=[0, 2, 3, 1]),
+        node("Constant", [], ["shape"], "shape", value=tensor_proto("shape", [2], [1, -1], INT64, raw=False)),
+        node("Reshape", ["nhwc", "shape"], ["flat"], "flatten"),
+        node("Constant", [], ["half"], "half", value_float=0.5),
+        node("Mul", ["flat", "half"], ["scaled"], "scale"),
+        node("MatMul", ["scaled", "fc"], ["logits"], "fc"),
+        node("GlobalAveragePool", ["relu"], ["gap"], "gap"),
+        node("Flatten", ["gap"], ["features"], "features"),
+    ]
+    initializers = [tensor_proto(n, *t) for n, t in (
+        ("w", w), ("b", b), ("scale", scale), ("shift", shift), ("mean", mean), ("var", var), ("fc", fc)
+    )]
+    write("cnn", model("cnn", 13, nodes, initializers, [("x", [1, 2, 6, 6])], [("logits", [1, 2]), ("features", [1, 3])]),
+          [("x", x)], [("logits", logits), ("features", features)])
+
+
+def ops(rng):
+    """Operators at opset 11, where Squeeze and Unsqueeze take attributes and Softmax flattens"""
+    x, y, w, c = rand(rng, [2, 3]), rand(rng, [3]), rand(rng, [2, 4]), rand(rng, [4])
+    divisor = ([1], [2.0])
+    bounds = (([], [-0.5]), ([], [0.5]))
+
+    diff = broadcast(x, y, lambda a, b: a - b)
+    gate = unary(broadcast(diff, divisor, lambda a, b: a / b), lambda v: 1.0 / (1.0 + math.exp(-v)))
+    clipped = unary(x, lambda v: min(max(math.tanh(v), -0.5), 0.5))
+    # Softmax over axis 1 of [1, 2, 3] at opset 11 normalizes over the trailing 6 values
+    spread = ([1, 2, 3], softmax_rows(([1, 6], clipped[1]))[1])
+    gemm = broadcast(matmul(transpose(x, [1, 0]), w), c, lambda a, b: 0.5 * a + 2.0 * b)
+    log_probs = softmax_rows(gemm, log=True)
+    leaky = unary(diff, lambda v: v if v > 0 else 0.1 * v)
+    # A negative axis counts back from the rank: axis -1 of [1, 2, 3] gives [2, 3]
+    rows = ([2, 3], clipped[1])
+
+    nodes = [
+        node("Sub", ["x", "y"], ["diff"], "sub"),
+        node("Div", ["diff", "divisor"], ["ratio"], "div"),
+        node("Sigmoid", ["ratio"], ["gate"], "sigmoid"),
+        node("Tanh", ["x"], ["tanh"], "tanh"),
+        node("Clip", ["tanh", "low", "high"], ["clipped"], "clip"),
+        node("Unsqueeze", ["clipped"], ["expanded"], "unsqueeze", axes=[0]),
+        node("Softmax", ["expanded"], ["spread"], "softmax", axis=1),
+        node("Squeeze", ["expanded"], ["squeezed"], "squeeze", axes=[0]),
+        node("Identity", ["squeezed"], ["same"], "identity"),
+        node("Gemm", ["x", "w", "c"], ["gemm"], "gemm", alpha=0.5, beta=2.0, transA=1),
+        node("LogSoftmax", ["gemm"], ["log_probs"], "log_softmax", axis=1),
+        node("LeakyRelu", ["diff"], ["leaky"], "leaky_relu", alpha=0.1),
+        node("Flatten", ["expanded"], ["rows"], "flatten", axis=-1),
+    ]
+    initializers = [
+        tensor_proto("w", *w), tensor_proto("c", *c),
+        tensor_proto("divisor", *divisor, raw=False),
+        tensor_proto("low", *bounds[0]), tensor_proto("high", *bounds[1]),
+    ]
+    outputs = [("gate", gate), ("same", clipped), ("spread", spread), ("log_probs", log_probs), ("leaky", leaky), ("rows", rows)]
+    write("ops", model("ops", 11, nodes, initializers, [("x", [2, 3]), ("y", [3])], [(n, t[0]) for n, t in outputs]),
+          [("x", x), ("y", y)], outputs)
+
+
+def unsupported():
+    nodes = [
+        node("Relu", ["x"], ["relu"], "relu"),
+        node("Einsum", ["relu", "relu"], ["outer"], "outer", equation="i,j->ij"),
+        node("FusedGemm", ["outer", "outer"], ["y"], "fused", domain="com.microsoft"),
+    ]
+    write("unsupported", model("unsupported", 13, nodes, [], [("x", [3])], [("y", [3, 3])], [("com.microsoft", 1)]),
+          [], [])
+
+
+if __name__ == "__main__":
+    if onnx is None:
+        print("onnx is not installed: models are not checked and expected outputs come from "
+              "the reference implementations in this script", file=sys.stderr)
+    rng = random.Random(0)
+    mlp(rng)
+    cnn(rng)
+    ops(rng)
+    unsupported()
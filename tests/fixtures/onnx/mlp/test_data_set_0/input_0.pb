@@ -0,0 +1 @@
+J X0??^"<BN?dhɾBx
\ No newline at end of file
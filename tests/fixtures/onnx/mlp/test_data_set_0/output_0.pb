@@ -0,0 +1 @@
+JM/>5?a%=kֶ>IT?	>Bprobs
\ No newline at end of file
@@ -0,0 +1 @@
+JP>:v>rjx,qv?Bx
\ No newline at end of file
@@ -0,0 +1 @@
+J9׾MwWx=By
\ No newline at end of file
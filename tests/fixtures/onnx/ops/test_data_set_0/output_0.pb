@@ -0,0 +1,2 @@
+Je
+?>?Z`>J>1P?Bgate
\ No newline at end of file
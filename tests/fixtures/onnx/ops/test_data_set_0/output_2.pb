@@ -0,0 +1 @@
+Jr>`;=bK>z=z=>Bspread
\ No newline at end of file
@@ -0,0 +1 @@
+Jġ[?A9>"nK-=]?Bleaky
\ No newline at end of file